@@ -1,36 +1,399 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
+#![deny(clippy::arithmetic_side_effects)]
+
+/// Oracle contract the betting contract can pull results from.
+#[ink::trait_definition]
+pub trait ResultOracle {
+    /// Final result of a fixture, if known.
+    #[ink(message)]
+    fn get_result(&self, fixture_id: betting::FixtureId) -> Option<betting::MatchResult>;
+}
+
+/// Randomness beacon the betting contract draws its instant games from.
+#[ink::trait_definition]
+pub trait RandomnessOracle {
+    /// Random seed published for a block, if known yet.
+    #[ink(message)]
+    fn get_randomness(&self, block: u32) -> Option<[u8; 32]>;
+}
+
+/// Contract the protocol fees are pushed to, such as a revenue-sharing or buyback
+/// contract.
+#[ink::trait_definition]
+pub trait FeeRouter {
+    /// Receive the transferred value as protocol fees.
+    #[ink(message, payable)]
+    fn receive_fees(&mut self);
+}
+
+/// DEX router the winnings can be swapped through when they are claimed.
+#[ink::trait_definition]
+pub trait SwapRouter {
+    /// Swap the transferred value for at least `min_out` of the token `asset`, sent to
+    /// `to`. Returns the amount of `asset` sent, or None if the swap can't be made.
+    #[ink(message, payable)]
+    fn swap_native_for(
+        &mut self,
+        asset: ink::primitives::AccountId,
+        min_out: u128,
+        to: ink::primitives::AccountId,
+    ) -> Option<u128>;
+}
+
+/// Mintable PSP22 platform token the reward points are converted into.
+#[ink::trait_definition]
+pub trait PSP22Mintable {
+    /// Mint `amount` tokens to `account`.
+    #[ink(message)]
+    fn mint(&mut self, account: ink::primitives::AccountId, amount: u128) -> Result<(), ()>;
+}
+
+/// Staking contract of the platform token, whose stakers get fee discounts.
+#[ink::trait_definition]
+pub trait Staking {
+    /// Platform tokens staked by an account.
+    #[ink(message)]
+    fn staked_of(&self, account: ink::primitives::AccountId) -> u128;
+}
+
+/// Stable interface of a betting market, for aggregator contracts and routers.
+#[ink::trait_definition]
+pub trait BettingMarket {
+    /// Bet the transferred value on a result of a match.
+    #[ink(message, payable, selector = 0x6B8C21BA)]
+    fn bet(
+        &mut self,
+        match_id: betting::MatchId,
+        result: betting::MatchResult,
+    ) -> Result<(), betting::Error>;
+
+    /// Implied decimal odds of team1, team2 and the draw in hundredths (`250` is 2.50),
+    /// `0` for an outcome nobody bet on. `None` for LMSR markets, priced per share, and
+    /// markets with more outcomes.
+    #[ink(message, selector = 0xE045E413)]
+    fn get_odds(&self, match_id: betting::MatchId) -> Option<[u32; 3]>;
+
+    /// Current stage of a match.
+    #[ink(message, selector = 0x43C3B22F)]
+    fn get_status(&self, match_id: betting::MatchId) -> Option<betting::MatchStatus>;
+
+    /// Withdraw the winnings paid to the caller that are waiting to be claimed.
+    #[ink(message, selector = 0xDD2DC950)]
+    fn claim_winnings(&mut self) -> Result<(), betting::Error>;
+}
 
 #[ink::contract]
 mod betting {
-    use ink::storage::Mapping;
+    use crate::{
+        BettingMarket, FeeRouter, PSP22Mintable, RandomnessOracle, ResultOracle, Staking,
+        SwapRouter,
+    };
+    use ink::codegen::TraitCallBuilder;
+    use ink::env::CallFlags;
+    use ink::storage::{Lazy, Mapping};
 
     // Use BoundedVec?
     pub type TeamName = Vec<u8>;
+    /// Matches are identified by the account of their creator.
+    pub type MatchId = AccountId;
+    pub type ParlayId = u32;
+    /// Bets are identified by their position in the bets of their match.
+    pub type BetId = u32;
+    pub type OrderId = u32;
+    pub type MatchedBetId = u32;
+    /// Id of a fixture in the result oracle.
+    pub type FixtureId = u64;
+    pub type ContestId = u32;
+    pub type SurvivorId = u32;
+    pub type BracketId = u32;
+    pub type OutrightId = u32;
+    pub type TeamId = u32;
+    /// Sport or league of a match, leagues nested in their sport with a `/`, e.g.
+    /// `football/premier-league`.
+    pub type Sport = Vec<u8>;
+    pub type CoinFlipId = u32;
+    pub type ActionId = u32;
+    /// A payout root and an account claiming from it.
+    pub type RootClaim = ([u8; 32], AccountId);
+    /// A bettor, the result they voted for and the weight of their stakes.
+    pub type ResultVote = (AccountId, MatchResult, Balance);
+    /// A score oracle and the goals of team1 and team2 it reported.
+    pub type ScoreReport = (AccountId, (u32, u32));
+    /// A score oracle and the placings it reported for an outright market.
+    pub type PlacingsReport = (AccountId, Vec<OutcomeId>);
+
+    /// Flags of every call to another contract. Reentry is not allowed, so an oracle, a
+    /// router or a token can't call back into the contract in the middle of a message.
+    /// Plain balance transfers run no code of the recipient.
+    const NO_REENTRY: CallFlags = CallFlags::empty();
+
+    /// Minimum number of legs of a parlay.
+    const MIN_PARLAY_LEGS: usize = 2;
+    /// Maximum number of matches of a prediction contest.
+    const MAX_CONTEST_MATCHES: usize = 32;
+    /// Maximum number of entries of a prediction contest.
+    const MAX_CONTEST_ENTRIES: usize = 250;
+    /// Maximum number of entries of a survivor pool.
+    const MAX_SURVIVOR_ENTRIES: usize = 100;
+    /// Maximum number of teams of a bracket challenge.
+    const MAX_BRACKET_TEAMS: usize = 32;
+    /// Maximum number of entries of a bracket challenge.
+    const MAX_BRACKET_ENTRIES: usize = 100;
+    /// Maximum number of outcomes of a match.
+    const MAX_MATCH_OUTCOMES: usize = 32;
+    /// Maximum number of runners of an outright market.
+    const MAX_OUTRIGHT_RUNNERS: usize = 32;
+    /// Maximum number of bets of an outright market.
+    const MAX_OUTRIGHT_BETS: usize = 250;
+    /// Blocks after an update during which the prices of an outright market value its
+    /// cashouts, a day with 6s blocks.
+    const MAX_OUTRIGHT_PRICE_AGE: BlockNumber = 14_400;
+    /// Maximum number of sponsors of a match.
+    const MAX_MATCH_SPONSORS: usize = 16;
+    /// Maximum length of the evidence attached to a result.
+    const MAX_EVIDENCE_LEN: usize = 256;
+
+    /// Payout of a won coin flip, in basis points of the stake, before the protocol fee.
+    /// Even odds less the 2% house edge of the bankroll.
+    const COIN_FLIP_PAYOUT_BPS: u32 = 19_600;
+    /// Blocks after the draw block of a coin flip without randomness before its stake is
+    /// refunded, a day with 6s blocks.
+    const COIN_FLIP_TIMEOUT: BlockNumber = 14_400;
+
+    /// Maximum number of accounts returned by `get_leaderboard`.
+    const MAX_LEADERBOARD_PAGE: u32 = 50;
+    /// Number of privileged calls kept in the admin log, the oldest are overwritten.
+    const ADMIN_LOG_CAPACITY: u32 = 1_000;
+
+    /// Maximum number of entries returned by `get_admin_log`.
+    const MAX_ADMIN_LOG_PAGE: u32 = 50;
+    /// Maximum number of prizes paid at the end of a season.
+    const MAX_SEASON_PRIZES: usize = 10;
+    /// Maximum number of bets placed with `bet_many`.
+    const MAX_BATCH_BETS: usize = 16;
+    /// Maximum number of matches created with `create_matches`.
+    const MAX_BATCH_MATCHES: usize = 16;
+    /// Maximum number of matches settled with `settle_matches`.
+    const MAX_BATCH_SETTLEMENTS: usize = 8;
+    /// Maximum number of bets of the matches settled with `settle_matches`, bounding the
+    /// payouts per call.
+    const MAX_BATCH_SETTLEMENT_BETS: usize = 400;
+
+    /// Default minimum deposit to create a match.
+    const DEFAULT_MIN_DEPOSIT: Balance = 1_000_000_000_000;
+
+    /// Default blocks after the result before anyone can settle a match (~1 day).
+    const DEFAULT_SETTLEMENT_GRACE_PERIOD: BlockNumber = 14_400;
+    /// Percentage of the creator deposit paid to whoever settles a match for them.
+    const SETTLEMENT_INCENTIVE_PERCENT: Balance = 10;
+
+    /// Default part of the deposit, in basis points, slashed per block of late settlement.
+    const DEFAULT_SLASH_PER_BLOCK_BPS: u32 = 10;
+
+    /// Default blocks before a proposed result left unchallenged becomes final (~1 day).
+    const DEFAULT_RESULT_CONFIRMATION_TIMEOUT: BlockNumber = 14_400;
+
+    /// Fee on cashouts, in basis points.
+    const CASHOUT_FEE_BPS: Balance = 500;
+    /// Default share of the fees, in basis points, earned by referrers.
+    const DEFAULT_REFERRAL_SHARE_BPS: u32 = 2_000;
+    /// Default premium of an insured parlay, in basis points of its stake.
+    const DEFAULT_PARLAY_INSURANCE_PREMIUM_BPS: u32 = 1_000;
+
+    /// Default maximum number of bets per match, keeping the encoded match below
+    /// the size of a storage cell.
+    const DEFAULT_MAX_BETS_PER_MATCH: u32 = 250;
+
+    /// Blocks between scheduling an emergency withdrawal and executing it (~1 week),
+    /// giving users time to exit.
+    const EMERGENCY_WITHDRAW_TIMELOCK: BlockNumber = 100_000;
+
+    /// Length of the rolling window of personal stake limits (~1 day), which is also
+    /// the delay before a higher limit applies.
+    const PERSONAL_LIMIT_PERIOD: BlockNumber = 14_400;
+
+    /// Stakes counted by personal limits are grouped in buckets of this many blocks
+    /// (~1 hour), bounding the stakes stored per account.
+    const PERSONAL_LIMIT_BUCKET: BlockNumber = 600;
+
+    /// Default blocks after a payout root is posted during which the payouts can be
+    /// claimed (~70 days).
+    const DEFAULT_CLAIM_PERIOD: BlockNumber = 1_000_000;
 
-    const MIN_DEPOSIT: Balance = 1_000_000_000_000;
+    /// Default blocks after the end of a match without a result before its bettors can
+    /// vote on it (~3 days).
+    const DEFAULT_ORACLE_TIMEOUT: BlockNumber = 43_200;
+    /// Default blocks the bettors of a match can vote on its result for (~1 day).
+    const DEFAULT_VOTE_PERIOD: BlockNumber = 14_400;
+    /// Default length of a reward emission epoch, about a week with 6s blocks.
+    const DEFAULT_EPOCH_LENGTH: BlockNumber = 100_800;
+    /// Default longest match, about a week with 6s blocks.
+    const DEFAULT_MAX_MATCH_LENGTH: BlockNumber = 100_800;
+    /// Percentage of the creator deposit shared among the voters on the winning result.
+    const VOTER_REWARD_PERCENT: Balance = 10;
 
+    /// Default minimum bond of a proposed result, challenges double the previous bond.
+    const DEFAULT_MIN_CHALLENGE_BOND: Balance = 100_000_000_000;
+
+    /// Default number of score oracles that have to report the score of a match.
+    const DEFAULT_SCORE_QUORUM: u32 = 3;
+
+    /// Default highest fee, in basis points, creators can choose for their match.
+    const DEFAULT_MAX_MATCH_FEE_BPS: u32 = 500;
+
+    /// Cut of the minimum deposit, in basis points, for each match a creator settled on
+    /// time beyond the disputes they lost.
+    const REPUTATION_DISCOUNT_BPS: u32 = 500;
+
+    /// Maximum cut of the minimum deposit earned with reputation.
+    const MAX_REPUTATION_DISCOUNT_BPS: u32 = 5_000;
+
+    /// Number of fee discount tiers of the stakers of the platform token.
+    const MAX_FEE_TIERS: usize = 4;
+
+    /// Maximum number of pool milestones stepping up the fee share of creators.
+    const MAX_CREATOR_MILESTONES: usize = 4;
+
+    /// Exchange odds are decimal odds in hundredths (`250` is 2.50).
+    const ODDS_UNIT: u32 = 100;
+
+    /// Weight of a bet placed before the start of its match, in basis points. Live bets
+    /// weigh less the later they are placed.
+    const FULL_BET_WEIGHT_BPS: u32 = 10_000;
+
+    /// Version of the message API returned by `api_version`. The selectors of the messages
+    /// are fixed, the version is raised when a message changes incompatibly.
+    const API_VERSION: u32 = 2;
+
+    /// Outcome of a match, identified by its index. The first ones keep the index, and so
+    /// the SCALE encoding, of the variants of the former `MatchResult` enum.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct OutcomeId(pub u8);
+
+    #[allow(non_upper_case_globals)]
+    impl OutcomeId {
+        pub const Team1Victory: Self = Self(0);
+        pub const Team2Victory: Self = Self(1);
+        pub const Draw: Self = Self(2);
+        /// The match was abandoned or postponed indefinitely, every stake is refunded.
+        /// It can't be bet on.
+        pub const Voided: Self = Self(3);
+
+        /// Outcome labelled by the label at `index` of a match, `Voided` is skipped.
+        pub fn nth(index: usize) -> Option<Self> {
+            let id = u8::try_from(index).ok()?;
+            if id < Self::Voided.0 {
+                Some(Self(id))
+            } else {
+                id.checked_add(1).map(Self)
+            }
+        }
+
+        /// Index of the label of the outcome, the inverse of `nth`. `Voided` has none.
+        pub fn index(self) -> Option<usize> {
+            if self.0 < Self::Voided.0 {
+                Some(usize::from(self.0))
+            } else if self == Self::Voided {
+                None
+            } else {
+                Some(usize::from(self.0.saturating_sub(1)))
+            }
+        }
+
+        /// Outcome labels of a 1X2 market: team1, team2 and the draw if it is allowed.
+        pub fn one_x_two(team1: &TeamName, team2: &TeamName, allow_draw: bool) -> Vec<TeamName> {
+            let mut labels = Vec::from([team1.clone(), team2.clone()]);
+            if allow_draw {
+                labels.push(b"Draw".to_vec());
+            }
+            labels
+        }
+    }
+
+    /// Outcomes of 1X2 markets, named like the variants of the former enum.
+    pub type MatchResult = OutcomeId;
+    /// Stage of the lifecycle of a match.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum MatchStatus {
+        /// Open to bets until the match starts.
+        Upcoming,
+        /// The match has started, bets are closed.
+        BettingClosed,
+        /// The match is over and waits for its result.
+        AwaitingResult,
+        /// A result has been proposed and can still be rejected.
+        Disputed,
+        /// The result is final, winnings can be distributed.
+        Settled,
+        /// The match has been cancelled.
+        Cancelled,
+    }
+    /// Market offered on a match.
     #[derive(scale::Decode, scale::Encode, PartialEq, Clone, Copy)]
     #[cfg_attr(
         feature = "std",
         derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
     )]
-    pub enum MatchResult {
-        Team1Victory,
-        Team2Victory,
-        Draw,
+    pub enum Market {
+        /// Plain market settled on the match result.
+        Winner,
+        /// Market settled on the match result where a draw refunds all the stakes.
+        /// The draw can't be bet on.
+        DrawNoBet,
+        /// Asian handicap added to team1's goals, in quarter goals (e.g. `-3` is
+        /// a -0.75 line). Quarter lines split the stake across the two adjacent lines.
+        Handicap(i32),
+        /// LMSR market maker with the given liquidity parameter: shares are bought
+        /// at a price moving with volume and winning shares are redeemed 1:1.
+        Lmsr(Balance),
+        /// Fixed decimal odds of team1, team2 and the draw in hundredths, `0` if the
+        /// draw is not offered. The creator deposit, then the bankroll, back the bets
+        /// as counterparty.
+        FixedOdds([u32; 3]),
+    }
+
+    impl Market {
+        /// Kind of the market, leaving out its parameters.
+        pub fn kind(&self) -> u8 {
+            match self {
+                Market::Winner => 0,
+                Market::DrawNoBet => 1,
+                Market::Handicap(_) => 2,
+                Market::Lmsr(_) => 3,
+                Market::FixedOdds(_) => 4,
+            }
+        }
     }
-    #[derive(scale::Decode, scale::Encode, PartialEq)]
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq)]
     #[cfg_attr(
         feature = "std",
         derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
     )]
     pub struct Bet {
-        /// Account of the better.
+        /// Sequential id of the bet in its match.
+        pub id: BetId,
+        /// Account of the better, the current holder of the position.
         pub bettor: AccountId,
         /// Bet amount.
         pub amount: Balance,
         /// Result predicted.
         pub result: MatchResult,
+        /// Part of the amount funded by free-bet credits, which is not paid out.
+        pub credit: Balance,
+        /// Account that placed and funded the bet on behalf of the bettor, `None` if the
+        /// bettor placed it.
+        pub placed_by: Option<AccountId>,
+        /// Weight of the bet in the share of the lost stakes, in basis points, decaying
+        /// with the blocks elapsed since the start for live bets.
+        pub weight_bps: u32,
     }
     #[derive(scale::Decode, scale::Encode)]
     #[cfg_attr(
@@ -42,695 +405,18709 @@ mod betting {
         start: BlockNumber,
         /// Length of the match (start + length = end).
         length: BlockNumber,
+        /// Blocks before the start at which betting closes.
+        betting_cutoff: BlockNumber,
+        /// Blocks after the start until which bets stay open in live mode, `None` if
+        /// betting closes before the start.
+        live_cutoff: Option<BlockNumber>,
+        /// Blocks after the end before the result can be set.
+        result_delay: BlockNumber,
         /// Team1 name.
         team1: TeamName,
         /// Team2 name.
         team2: TeamName,
+        /// Label of each outcome, in the order of their ids.
+        outcome_labels: Vec<TeamName>,
         /// Result.
         result: Option<MatchResult>,
-        /// List of bets.
+        /// Hash or URI of the data source the result was set from, for disputes.
+        evidence: Option<Vec<u8>>,
+        /// Stage of the match set by the messages; `get_status` derives the stages
+        /// depending on the block number.
+        status: MatchStatus,
+        /// Block at which the result was set.
+        result_block: BlockNumber,
+        /// Block at which the result became or becomes final: the end of the dispute or
+        /// correction window, or the start of the distribution if earlier.
+        final_block: BlockNumber,
+        /// Block at which the distribution completed.
+        settled_block: BlockNumber,
+        /// Result proposed by the creator, waiting for confirmation, and the block it was proposed.
+        proposed_result: Option<(MatchResult, BlockNumber)>,
+        /// Fixture of the match in the result oracle.
+        fixture_id: Option<FixtureId>,
+        /// Sport or league of the match, whose oracles can set its result.
+        sport: Option<Sport>,
+        /// Registered ids of team1 and team2, if the match was created from the registry.
+        team_ids: Option<(TeamId, TeamId)>,
+        /// Market offered on the match.
+        market: Market,
+        /// Whether a draw can be bet on and set as the result, false in 2-way markets.
+        allow_draw: bool,
+        /// Accounts allowed to bet on a private match, `None` if anyone can bet.
+        allowed_bettors: Option<Vec<AccountId>>,
+        /// Final score (team1 goals, team2 goals).
+        score: Option<(u32, u32)>,
+        /// Running score updated by the score oracles during play, and the block of its
+        /// last update.
+        live_score: Option<((u32, u32), BlockNumber)>,
+        /// Blocks bets are suspended for after the running score changes, so bettors
+        /// can't beat the oracle to a goal.
+        suspension_blocks: BlockNumber,
+        /// Last block of the current suspension of the bets.
+        suspended_until: Option<BlockNumber>,
+        /// List of bets, or of shares held in LMSR markets.
         pub bets: Vec<Bet>,
+        /// Id of the next bet placed on the match.
+        next_bet_id: BetId,
+        /// Maximum number of bets on the match, below the global cap.
+        max_bets: Option<u32>,
+        /// Most that can be staked on each outcome, in the order of their labels, set by
+        /// the creator to keep the pool balanced or cap the liability of fixed odds.
+        outcome_caps: Vec<Option<Balance>>,
+        /// Amount paid for shares in LMSR markets.
+        collected: Balance,
+        /// Bankroll funds backing the bets of fixed-odds markets, the worst-case loss
+        /// of the market.
+        backed: Balance,
+        /// Funds added to the pot on top of the bets, shared among the winners.
+        pot_bonus: Balance,
+        /// Boost, in basis points, of the net winnings paid from the boost budget.
+        boost_bps: u32,
+        /// Funds escrowed by the creator to boost the winnings, paid in the order of the
+        /// payouts until spent.
+        boost_budget: Balance,
+        /// Fee, in basis points, chosen by the creator instead of the protocol fee.
+        fee_bps: Option<u32>,
+        /// Whether the shares bought in the same block of an LMSR market all get the same
+        /// price, whatever their order in the block.
+        uniform_price: bool,
+        /// Funds added to the pot by each sponsor, refunded if the match is voided.
+        sponsors: Vec<(AccountId, Balance)>,
+        /// Part of the jackpot attached to a featured match, shared by the bettors
+        /// predicting the exact score.
+        jackpot: Balance,
+        /// Exact scores predicted by the bettors of a featured match.
+        score_predictions: Vec<(AccountId, (u32, u32))>,
         /// The amount held in reserve of the `depositor`,
         /// To be returned once this recovery process is closed.
         deposit: Balance,
     }
 
-    #[ink(storage)]
-    pub struct Betting {
-        /// Mapping of open matches.
-        matches: Mapping<AccountId, Match>,
-        // Mapping of all match hashes. (hash -> owner)
-        matches_hashes: Mapping<[u8; 32], AccountId>,
-        /// Owner of the Smart Contract (sudo)
-        owner: AccountId,
+    /// Result of a match together with its pool, recorded when the result is set.
+    #[derive(scale::Decode, scale::Encode, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ResultOdds {
+        /// Result of the match.
+        pub result: MatchResult,
+        /// Total amount bet in the match.
+        pub total_pool: Balance,
+        /// Amount bet on the result.
+        pub winning_pool: Balance,
+        /// Block from which the result can't be corrected anymore.
+        pub final_block: BlockNumber,
+    }
+    /// A leg of a parlay.
+    #[derive(scale::Decode, scale::Encode, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ParlayLeg {
+        /// Match of the leg.
+        pub match_id: MatchId,
+        /// Hash of the match, so a later match of the same creator is not mistaken for it.
+        pub match_hash: [u8; 32],
+        /// Result predicted.
+        pub result: MatchResult,
+        /// Odds of the result implied by the pools when the parlay was placed, in
+        /// hundredths.
+        pub odds: u32,
+    }
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Parlay {
+        /// Account of the better.
+        pub bettor: AccountId,
+        /// Stake of the whole parlay.
+        pub amount: Balance,
+        /// Legs, all of them must be won.
+        pub legs: Vec<ParlayLeg>,
+        /// Premium paid to refund the stake if a single leg is lost, `0` if not insured.
+        pub premium: Balance,
+        /// Winnings beyond the stake locked by the bankroll until the parlay is settled.
+        pub backed: Balance,
     }
 
-    /// A new match has been created. [who, team1, team2, start, length]
-    #[ink(event)]
-    pub struct MatchCreated {
-        #[ink(topic)]
-        who: AccountId,
-        team1: TeamName,
-        team2: TeamName,
-        start: BlockNumber,
-        length: BlockNumber,
+    /// Prediction contest: entrants pay a fixed fee and predict the results of a set
+    /// of matches, and the top scorers share the prize pool.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Contest {
+        /// Fee paid by each entrant into the prize pool.
+        pub entry_fee: Balance,
+        /// Hashes of the matches to predict, so a later match of the same creator is
+        /// not mistaken for one of them.
+        pub match_hashes: Vec<[u8; 32]>,
+        /// Entries close when the first match starts.
+        pub deadline: BlockNumber,
+        /// End of the last match, the entries are refunded if a match has no result
+        /// the oracle timeout after it.
+        pub end: BlockNumber,
+        /// Entrants and their predicted results, in the order of the matches.
+        pub entries: Vec<(AccountId, Vec<MatchResult>)>,
     }
-    /// A new bet has been created. [matchId, who, amount, result]
-    #[ink(event)]
-    pub struct BetPlaced {
-        #[ink(topic)]
-        match_id: AccountId,
-        #[ink(topic)]
-        who: AccountId,
-        amount: Balance,
-        result: MatchResult,
+
+    /// Side of an exchange order.
+    #[derive(scale::Decode, scale::Encode, PartialEq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum OrderSide {
+        /// Bets for the outcome, risking the stake.
+        Back,
+        /// Bets against the outcome, risking the liability `stake * (odds - 1)`.
+        Lay,
     }
-    /// A match result has been set. [matchId, result]
-    #[ink(event)]
-    pub struct MatchResultSet {
-        #[ink(topic)]
-        match_id: AccountId,
-        result: MatchResult,
+    /// An exchange order, waiting to be matched.
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Order {
+        /// Account that posted the order.
+        pub owner: AccountId,
+        /// Match of the order.
+        pub match_id: MatchId,
+        /// Hash of the match.
+        pub match_hash: [u8; 32],
+        /// Outcome backed or laid.
+        pub outcome: MatchResult,
+        /// Back or lay.
+        pub side: OrderSide,
+        /// Odds asked, in hundredths.
+        pub odds: u32,
+        /// Backer stake not matched yet.
+        pub stake: Balance,
+    }
+    /// A back order matched against a lay order.
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct MatchedBet {
+        /// Account backing the outcome.
+        pub backer: AccountId,
+        /// Account laying the outcome.
+        pub layer: AccountId,
+        /// Hash of the match.
+        pub match_hash: [u8; 32],
+        /// Outcome of the bet.
+        pub outcome: MatchResult,
+        /// Backer stake.
+        pub stake: Balance,
+        /// Agreed odds, in hundredths.
+        pub odds: u32,
+        /// Whether a draw refunds both sides, as in draw-no-bet markets.
+        pub draw_no_bet: bool,
     }
 
-    /// The Betting error types.
-    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub enum Error {
-        /// The match to be created already exist.
-        MatchAlreadyExists,
-        /// Each account can only have one match open.
-        OriginHasAlreadyOpenMatch,
-        /// The time of the match is over.
-        TimeMatchOver,
-        /// Not enough deposit to create the Match.
-        NotEnoughDeposit,
-        /// The match where the bet is placed does not exist
-        MatchDoesNotExist,
-        /// No allowing betting if the match has started
-        MatchHasStarted,
-        /// You already place the same bet in that match
-        AlreadyBet,
-        /// Only owner of the smart contract can make this call
-        BadOrigin,
-        /// No allowing set the result if the match not over
-        TimeMatchNotOver,
-        /// The match still has not a result set
-        MatchNotResult,
-        /// Returned if the requested transfer failed. This can be the case if the
-        /// contract does not have sufficient free funds or if the transfer would
-        /// have brought the contract's balance below minimum balance.
-        TransferFailed,
+    /// Cumulative statistics of the contract, saturating instead of overflowing.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Stats {
+        /// Matches created.
+        pub matches_created: u64,
+        /// Bets placed, including shares bought, parlays and matched exchange bets.
+        pub bets: u64,
+        /// Amount bet.
+        pub volume: Balance,
+        /// Amount paid to bettors.
+        pub paid_out: Balance,
+        /// Fees collected.
+        pub fees: Balance,
     }
 
-    impl Betting {
-        #[ink(constructor, payable)]
-        pub fn new() -> Self {
-            let owner = Self::env().caller();
-            Self {
-                matches: Default::default(),
-                matches_hashes: Default::default(),
-                owner,
-            }
-        }
+    /// Record of a registered team, saturating instead of overflowing.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct TeamStats {
+        /// Matches created with the team.
+        pub matches: u32,
+        /// Settled matches won, lost and drawn by the team.
+        pub wins: u32,
+        pub losses: u32,
+        pub draws: u32,
+    }
 
-        // payable accepts a payment (deposit).
-        #[ink(message, payable)]
-        pub fn create_match_to_bet(
-            &mut self,
-            team1: Vec<u8>,
-            team2: Vec<u8>,
-            start: BlockNumber,
-            length: BlockNumber,
-        ) -> Result<(), Error> {
-            let caller = Self::env().caller();
-            // Check account has no open match
-            if self.exists_match(caller) {
-                return Err(Error::OriginHasAlreadyOpenMatch);
-            }
-            // Check if start and length are valid
-            let current_block_number = self.env().block_number();
-            if current_block_number > (start + length) {
-                return Err(Error::TimeMatchOver);
-            }
-            // Check the deposit.
-            // Assert or Error?
-            let deposit = Self::env().transferred_value();
-            if deposit < MIN_DEPOSIT {
-                return Err(Error::NotEnoughDeposit);
-            }
-            // Create the betting match
-            let betting_match = Match {
-                start,
-                length,
-                team1,
-                team2,
-                result: None,
-                bets: Default::default(),
-                deposit,
-            };
-            // Hash the match object.
-            let match_hash = Self::get_match_hash(&betting_match);
-            // Check if match already exists by checking its specs hash.
-            if self.matches_hashes.contains(match_hash) {
-                return Err(Error::MatchAlreadyExists);
-            }
-            // Store the match hash with its creator account.
-            self.matches_hashes.insert(match_hash, &caller);
-            // Store the betting match in the list of open matches
-            self.matches.insert(caller, &betting_match);
-            // Emit an event.
-            self.env().emit_event(MatchCreated {
-                who: caller,
-                team1: betting_match.team1,
-                team2: betting_match.team2,
-                start,
-                length,
-            });
+    /// Recipient of the remainder of the pro-rata shares of a pool at settlement.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum DustPolicy {
+        /// The last winner paid.
+        LastWinner,
+        /// The creator of the match, with the deposit.
+        Creator,
+        #[default]
+        Treasury,
+    }
 
-            Ok(())
-        }
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum CoinSide {
+        Heads,
+        Tails,
+    }
 
-        // payable accepts a payment (amount_to_bet).
-        #[ink(message, payable)]
-        pub fn bet(&mut self, match_id: AccountId, result: MatchResult) -> Result<(), Error> {
-            let caller = Self::env().caller();
-            // Find the match that user wants to place the bet
-            let mut match_to_bet = match self.matches.take(&match_id) {
-                Some(match_from_storage) => match_from_storage,
-                None => return Err(Error::MatchDoesNotExist),
-            };
+    /// Fee discount of the bettors staking at least `min_staked` platform tokens.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct FeeTier {
+        pub min_staked: Balance,
+        /// Cut of the protocol fee, in basis points.
+        pub discount_bps: u32,
+    }
 
-            // Check if the Match Has Started (can't bet in a started match)
+    /// Fee share of the creators of the matches whose total pool reaches `min_pool`.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct CreatorMilestone {
+        pub min_pool: Balance,
+        /// Share of the fees of the match, in basis points.
+        pub share_bps: u32,
+    }
+
+    /// Coin flip against the bankroll, decided by the randomness of a later block.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct CoinFlip {
+        pub player: AccountId,
+        pub amount: Balance,
+        pub side: CoinSide,
+        /// Funds of the bankroll locked to pay the flip if it is won.
+        pub backed: Balance,
+        /// Block whose randomness decides the flip, after the one it was placed in.
+        pub draw_block: BlockNumber,
+    }
+
+    /// Result proposed by the creator of a match, or answered to the proposed result,
+    /// with a bond.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Challenge {
+        pub challenger: AccountId,
+        pub result: MatchResult,
+        pub bond: Balance,
+    }
+
+    /// Sensitive admin operation, scheduled to be executed after the admin delay.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum AdminAction {
+        SetProtocolFee(u32),
+        SetMatchFeeBounds(u32, u32),
+        SetFeeRouter(Option<AccountId>),
+        SetResultOracle(Option<AccountId>),
+        SetRandomnessOracle(Option<AccountId>),
+        AuthorizeOracle(AccountId, Sport),
+        RevokeOracle(AccountId, Sport),
+        AddScoreOracle(AccountId),
+        RemoveScoreOracle(AccountId),
+        /// Replace the code of the contract, keeping its storage.
+        Upgrade(Hash),
+        SetAdminDelay(BlockNumber),
+    }
+
+    /// Privileged call recorded in the admin log.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct AdminLogEntry {
+        pub who: AccountId,
+        /// Selector of the message called.
+        pub selector: [u8; 4],
+        pub block: BlockNumber,
+        /// Blake2x256 hash of the SCALE encoded parameters of the call.
+        pub params_hash: [u8; 32],
+    }
+
+    /// Admin action waiting for its delay to be over.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ScheduledAction {
+        pub action: AdminAction,
+        /// Block from which the action can be executed.
+        pub executable_at: BlockNumber,
+    }
+
+    /// Order for shares of a uniform-price LMSR market, waiting for its block to be over.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ShareOrder {
+        pub buyer: AccountId,
+        pub outcome: MatchResult,
+        pub amount: Balance,
+        /// Most the buyer pays for the shares, the order is refunded above it.
+        pub max_cost: Balance,
+    }
+
+    /// Share orders of a uniform-price LMSR market placed in the same block.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ShareBatch {
+        pub block: BlockNumber,
+        pub orders: Vec<ShareOrder>,
+    }
+
+    /// Track record of a match creator, saturating instead of overflowing.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct CreatorReputation {
+        pub matches_created: u32,
+        /// Matches whose winnings the creator distributed within the settlement grace period.
+        pub settled_on_time: u32,
+        /// Matches voided.
+        pub cancelled: u32,
+        /// Proposed results rejected by the owner.
+        pub disputes_lost: u32,
+    }
+
+    /// What still keeps the contract from being terminated, all zero once it is wound down.
+    #[derive(scale::Decode, scale::Encode, Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TerminationBlockers {
+        /// Matches not settled yet.
+        pub open_matches: u32,
+        /// Parlays, exchange orders and matched bets not settled yet.
+        pub open_positions: u32,
+        /// Funds held in escrow, including unclaimed payout roots.
+        pub escrow: Balance,
+        pub balances: Balance,
+        pub free_bets: Balance,
+        pub prize_pools: Balance,
+        /// Free and locked funds of the bankroll.
+        pub bankroll: Balance,
+        pub insurance_fund: Balance,
+        /// Premiums of the insured parlays not paid out as refunds.
+        pub parlay_insurance: Balance,
+        pub treasury: Balance,
+        pub jackpot: Balance,
+        pub pending_claims: Balance,
+        /// Creator and referrer fees not claimed yet.
+        pub accrued_fees: Balance,
+    }
+
+    /// Tunables of the contract set by the owner.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Config {
+        /// Minimum deposit to create a match, on top of the subsidy of LMSR markets.
+        pub min_deposit: Balance,
+        /// Minimum amount of a bet.
+        pub min_bet: Balance,
+        /// Maximum amount of a bet.
+        pub max_bet: Balance,
+        /// Protocol fee, in basis points, taken on the winnings paid at settlement.
+        pub fee_bps: u32,
+        /// Blocks before a proposed result left unchallenged becomes final.
+        pub dispute_window: BlockNumber,
+        /// Blocks after the result before anyone can settle a match.
+        pub settlement_grace_period: BlockNumber,
+        /// Blocks after the result during which it can be corrected, until a payout.
+        pub correction_window: BlockNumber,
+        /// Blocks after a payout root is posted during which the payouts can be claimed.
+        pub claim_period: BlockNumber,
+        /// Recipient of the rounding dust of the distribution.
+        pub dust_policy: DustPolicy,
+        /// Lowest fee, in basis points, creators can choose for their match.
+        pub min_match_fee_bps: u32,
+        /// Highest fee, in basis points, creators can choose for their match.
+        pub max_match_fee_bps: u32,
+        /// Number of score oracles that have to report the score of a match to set it.
+        pub score_quorum: u32,
+        /// Minimum bond of a proposed result, each challenge doubles the previous bond.
+        pub min_challenge_bond: Balance,
+        /// Blocks after the end of a match without a result before its bettors can vote,
+        /// and after the deadline of an outright market without a result before its stakes
+        /// are refunded.
+        pub oracle_timeout: BlockNumber,
+        /// Blocks the bettors of a match can vote on its result for.
+        pub vote_period: BlockNumber,
+        /// Reward points accrued per settled bet, in basis points of its stake.
+        pub reward_points_bps: u32,
+        /// Blocks of a reward emission epoch.
+        pub epoch_length: BlockNumber,
+        /// Most platform tokens minted from reward points per epoch.
+        pub epoch_emission_cap: Balance,
+        /// Fee discounts of the stakers of the platform token, the tier with the highest
+        /// discount a bettor stakes enough for applies.
+        pub fee_tiers: [FeeTier; MAX_FEE_TIERS],
+        /// Most bets an account can place per rate limit window, `0` for no limit.
+        pub max_bets_per_window: u32,
+        /// Blocks of a rate limit window.
+        pub rate_limit_window: BlockNumber,
+        /// Blocks from now a new match has to start after.
+        pub min_lead_time: BlockNumber,
+        /// Shortest length of a new match.
+        pub min_match_length: BlockNumber,
+        /// Longest length of a new match.
+        pub max_match_length: BlockNumber,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                min_deposit: DEFAULT_MIN_DEPOSIT,
+                min_bet: 0,
+                max_bet: Balance::MAX,
+                fee_bps: 0,
+                dispute_window: DEFAULT_RESULT_CONFIRMATION_TIMEOUT,
+                settlement_grace_period: DEFAULT_SETTLEMENT_GRACE_PERIOD,
+                correction_window: 0,
+                claim_period: DEFAULT_CLAIM_PERIOD,
+                dust_policy: DustPolicy::Treasury,
+                min_match_fee_bps: 0,
+                max_match_fee_bps: DEFAULT_MAX_MATCH_FEE_BPS,
+                score_quorum: DEFAULT_SCORE_QUORUM,
+                min_challenge_bond: DEFAULT_MIN_CHALLENGE_BOND,
+                oracle_timeout: DEFAULT_ORACLE_TIMEOUT,
+                vote_period: DEFAULT_VOTE_PERIOD,
+                reward_points_bps: 0,
+                epoch_length: DEFAULT_EPOCH_LENGTH,
+                epoch_emission_cap: 0,
+                fee_tiers: [FeeTier::default(); MAX_FEE_TIERS],
+                max_bets_per_window: 0,
+                rate_limit_window: 1,
+                min_lead_time: 0,
+                min_match_length: 1,
+                max_match_length: DEFAULT_MAX_MATCH_LENGTH,
+            }
+        }
+    }
+
+    /// A fixture created with `create_matches`, with the parameters of `create_match_to_bet`.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct MatchSpec {
+        /// Account the match is opened for, which can have no other open match.
+        /// The owner, who paid the deposit, gets it back at settlement.
+        pub creator: AccountId,
+        pub team1: TeamName,
+        pub team2: TeamName,
+        pub start: BlockNumber,
+        pub length: BlockNumber,
+        pub allow_draw: bool,
+        pub draw_no_bet: bool,
+    }
+
+    /// Summary of a match without its bets.
+    #[derive(scale::Decode, scale::Encode, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct MatchInfo {
+        pub team1: TeamName,
+        pub team2: TeamName,
+        /// Starting block of the match.
+        pub start: BlockNumber,
+        /// Length of the match (start + length = end).
+        pub length: BlockNumber,
+        pub status: MatchStatus,
+        pub market: Market,
+        pub result: Option<MatchResult>,
+        /// Amount bet, with the funds added to the pot, or paid for shares in LMSR markets.
+        pub total_pool: Balance,
+        /// Amount bet, or shares held, on each outcome, in the order of their labels.
+        pub outcome_pools: Vec<Balance>,
+        /// Number of bets.
+        pub bet_count: u32,
+        /// Fee, in basis points, taken on the winnings.
+        pub fee_bps: u32,
+        /// Running score during play (team1 goals, team2 goals).
+        pub live_score: Option<(u32, u32)>,
+    }
+
+    /// Progress of a match settled over several calls.
+    #[derive(scale::Decode, scale::Encode, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Settlement {
+        /// Account that started the settlement, earning the incentive.
+        settler: AccountId,
+        /// Number of payouts already made.
+        cursor: u32,
+        /// Total of the payouts, fixed when the settlement started.
+        paid: Balance,
+        /// Part of the total already paid.
+        made: Balance,
+        /// Part of the deposit slashed, fixed when the settlement started.
+        slashed: Balance,
+        /// Part of the slashed deposit already shared among the winners.
+        shared: Balance,
+        /// Winners paid so far.
+        winners_count: u32,
+        /// Fees taken on the winnings paid so far.
+        fees: Balance,
+        /// Part of the boost budget paid so far.
+        boosted: Balance,
+    }
+
+    /// How the pool of a settled match was distributed, to verify it without replaying
+    /// the bets.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SettlementReport {
+        /// Amount bet, with the funds added to the pot, or paid for shares in LMSR markets.
+        pub total_pool: Balance,
+        /// Number of bettors paid winnings.
+        pub winners: u32,
+        /// Number of bettors who lost all their bets.
+        pub losers: u32,
+        /// Fees taken on the winnings.
+        pub fees: Balance,
+        /// Rounding of the shares of the pool and of the slashed deposit, routed by the
+        /// dust policy.
+        pub dust: Balance,
+        /// Boosts paid to the winners from the budget of the creator.
+        pub boosted: Balance,
+    }
+
+    /// Stake committed to a hidden outcome, placed as a bet when revealed.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SealedBet {
+        /// Hash of the bettor, the outcome and the salt (see `sealed_bet_hash`).
+        pub commitment: [u8; 32],
+        pub amount: Balance,
+        /// Hash of the match, so a later match of the same creator is not mistaken for it.
+        pub match_hash: [u8; 32],
+    }
+
+    /// Merkle root of the payouts of a match computed off-chain, claimed with proofs.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PayoutRoot {
+        /// Root of the tree of the `(account, amount)` payouts.
+        pub root: [u8; 32],
+        /// Funds left to claim.
+        pub remaining: Balance,
+        /// Block after which the funds left can be swept.
+        pub deadline: BlockNumber,
+    }
+
+    /// Stake limit an account set on itself, and what it staked in the rolling window.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PersonalLimit {
+        /// Maximum amount staked per period.
+        pub amount_per_period: Balance,
+        /// Higher limit requested and the block it applies from.
+        pub pending_increase: Option<(Balance, BlockNumber)>,
+        /// Amounts staked in the rolling window, by first block of their bucket.
+        pub stakes: Vec<(BlockNumber, Balance)>,
+    }
+
+    /// Survivor pool: each round entrants pick a team to win, never the same team twice,
+    /// and are eliminated on a wrong pick. The entrants left share the pot.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SurvivorPool {
+        /// Fee paid by each entrant into the pot.
+        pub entry_fee: Balance,
+        /// Hashes of the matches of the current round.
+        pub round: Vec<[u8; 32]>,
+        /// Number of rounds added.
+        pub rounds: u32,
+        /// Entries close when the first match of the first round starts.
+        pub deadline: BlockNumber,
+        /// End of the last match of the current round, the entries are refunded if a
+        /// match of the round has no result the oracle timeout after it.
+        pub round_end: BlockNumber,
+        pub entries: Vec<SurvivorEntry>,
+    }
+
+    /// An entrant of a survivor pool.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SurvivorEntry {
+        pub entrant: AccountId,
+        pub eliminated: bool,
+        /// Teams picked in the previous rounds, which can't be picked again.
+        pub used_teams: Vec<TeamName>,
+        /// Hash of the match and winner picked in the current round.
+        pub pick: Option<([u8; 32], MatchResult)>,
+    }
+
+    /// Bracket challenge on a knockout tournament: entrants predict the winner of every
+    /// game before the first round, and the best brackets share the prize pool.
+    ///
+    /// Games are numbered round after round. Listing the teams followed by the winners
+    /// of the games, game `g` is played between the entries `2g` and `2g + 1`.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Bracket {
+        /// Fee paid by each entrant into the prize pool.
+        pub entry_fee: Balance,
+        /// Teams of the first round, paired in order.
+        pub teams: Vec<TeamName>,
+        /// Winners of the games scored so far.
+        pub winners: Vec<TeamName>,
+        /// Hashes of the matches of the current round, in the order of its games.
+        pub round: Vec<[u8; 32]>,
+        /// Entries close when the first match of the first round starts.
+        pub deadline: BlockNumber,
+        /// End of the last match of the current round, the entries are refunded if a
+        /// game of the round has no winner the oracle timeout after it.
+        pub round_end: BlockNumber,
+        pub entries: Vec<BracketEntry>,
+    }
+
+    /// An entrant of a bracket challenge.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct BracketEntry {
+        pub entrant: AccountId,
+        /// Predicted winner of every game.
+        pub picks: Vec<TeamName>,
+        pub points: u32,
+    }
+
+    /// Outright market on a competition between many runners, e.g. a league or a race,
+    /// with a pool on the winner and a pool on the runners finishing in the paid places.
+    /// Both pools are pari-mutuel and settled independently. Runners are outcomes, like
+    /// those of a match.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Outright {
+        /// Labels of the runners, identified by `OutcomeId::nth` of their position.
+        pub outcome_labels: Vec<TeamName>,
+        /// Number of places paid by the place pool.
+        pub places: u32,
+        /// Betting closes after this block.
+        pub deadline: BlockNumber,
+        pub bets: Vec<OutrightBet>,
+        /// Id of the next bet placed on the market.
+        pub next_bet_id: BetId,
+        /// Funds of the win pool, the stakes on the winner net of the cashouts.
+        pub win_pool: Balance,
+        /// Funds of the place pool, the stakes on the places net of the cashouts.
+        pub place_pool: Balance,
+        /// Probabilities, in basis points, of each runner winning and finishing in the
+        /// paid places, last updated by a score oracle at `priced_block`.
+        pub prices: Vec<(u32, u32)>,
+        pub priced_block: BlockNumber,
+    }
+
+    /// A bet on a runner of an outright market. An each-way bet splits its stake evenly
+    /// between the win and the place pools.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct OutrightBet {
+        /// Sequential id of the bet in its market.
+        pub id: BetId,
+        pub bettor: AccountId,
+        pub runner: OutcomeId,
+        /// Part of the stake on the runner winning.
+        pub win_stake: Balance,
+        /// Part of the stake on the runner finishing in the paid places.
+        pub place_stake: Balance,
+    }
+
+    /// A team of the registry.
+    #[derive(scale::Decode, scale::Encode, Debug, PartialEq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Team {
+        /// Canonical name of the team.
+        pub name: TeamName,
+        /// Free-form metadata, e.g. the league or a logo URI.
+        pub metadata: Vec<u8>,
+    }
+
+    /// Amounts paid to a bettor at settlement.
+    struct Payout {
+        bettor: AccountId,
+        /// Winnings, stake included.
+        won: Balance,
+        /// Stake refunded.
+        refunded: Balance,
+        /// Part of the stake funded by free-bet credits.
+        credit: Balance,
+        /// Part of the stake backing the result, which scores season points.
+        stake: Balance,
+    }
+
+    /// Payouts of all the bets of a bettor on a match, paid in a single transfer.
+    #[derive(scale::Decode, scale::Encode, Clone, Copy)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    struct BettorPayout {
+        bettor: AccountId,
+        /// Winnings, stakes included.
+        won: Balance,
+        /// Stakes refunded.
+        refunded: Balance,
+        /// Part of the refunded stakes funded by free-bet credits, refunded as credits.
+        refunded_credit: Balance,
+        /// Part of the winnings funded by free-bet credits, which is not paid out.
+        withheld: Balance,
+        /// Part of the stakes backing the result, which scores season points.
+        stake: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct Betting {
+        /// Mapping of open matches.
+        matches: Mapping<MatchId, Match>,
+        // Mapping of all match hashes. (hash -> owner)
+        matches_hashes: Mapping<[u8; 32], MatchId>,
+        /// Owner of the Smart Contract (sudo)
+        owner: AccountId,
+        /// Results of the matches by match hash, used to settle parlays.
+        results: Mapping<[u8; 32], ResultOdds>,
+        /// Mapping of unclaimed parlays.
+        parlays: Mapping<ParlayId, Parlay>,
+        /// Id of the next parlay.
+        next_parlay_id: ParlayId,
+        /// Mapping of open exchange orders.
+        orders: Mapping<OrderId, Order>,
+        /// Id of the next order.
+        next_order_id: OrderId,
+        /// Mapping of unsettled matched bets.
+        matched_bets: Mapping<MatchedBetId, MatchedBet>,
+        /// Id of the next matched bet.
+        next_matched_bet_id: MatchedBetId,
+        /// Tunables of the contract.
+        config: Lazy<Config>,
+        /// Part of the deposit, in basis points, slashed per block of late settlement.
+        slash_per_block_bps: u32,
+        /// Funds owned by the protocol.
+        treasury: Balance,
+        /// Oracle keys allowed to sign results.
+        oracle_keys: Mapping<[u8; 33], ()>,
+        /// Oracle contract results can be pulled from.
+        result_oracle: Option<AccountId>,
+        /// Referrer of each referred account.
+        referrers: Mapping<AccountId, AccountId>,
+        /// Creator and referrer fees accrued by each beneficiary and not claimed yet.
+        accrued_fees: Mapping<AccountId, Balance>,
+        /// Share of the fees, in basis points, earned by referrers.
+        referral_share_bps: u32,
+        /// Share of the fees of a match, in basis points, earned by its creator.
+        creator_fee_share_bps: u32,
+        /// Pool milestones stepping up the fee share of creators, by increasing pool.
+        creator_milestones: Vec<CreatorMilestone>,
+        /// Cumulative statistics.
+        stats: Stats,
+        /// Payments that failed at settlement, to be withdrawn by their recipient.
+        pending_claims: Mapping<AccountId, Balance>,
+        /// Block of the last payment deferred to each account with a pending claim.
+        pending_claim_blocks: Mapping<AccountId, BlockNumber>,
+        /// Progress of the settlements spanning several calls.
+        settlements: Mapping<MatchId, Settlement>,
+        /// Payouts of the settlements spanning several calls, computed when they started.
+        settlement_payouts: Mapping<MatchId, Vec<BettorPayout>>,
+        /// Report of the last settlement of each match.
+        settlement_reports: Mapping<MatchId, SettlementReport>,
+        /// Final status of the last match of each creator, once deleted.
+        closed_statuses: Mapping<MatchId, MatchStatus>,
+        /// Account that paid the deposit of an open match for its creator, which gets it
+        /// back at settlement.
+        deposit_payers: Mapping<MatchId, AccountId>,
+        /// Funds held for each open match: deposit, bets and shares sold.
+        escrows: Mapping<MatchId, Balance>,
+        /// Sum of the escrows of all the open matches, and the funds of the open parlays,
+        /// orders, matched bets and coin flips.
+        total_escrow: Balance,
+        /// Internal balances users can bet from.
+        balances: Mapping<AccountId, Balance>,
+        /// Sum of the internal balances.
+        total_balances: Balance,
+        /// Maximum number of bets on any match.
+        max_bets_per_match: u32,
+        /// Emergency withdrawal scheduled by the owner and the block it becomes executable.
+        emergency_withdrawal: Option<(Balance, BlockNumber)>,
+        /// Accounts that can't create matches nor bet.
+        blacklist: Mapping<AccountId, ()>,
+        /// Block until which an account excluded itself from betting.
+        self_exclusions: Mapping<AccountId, BlockNumber>,
+        /// Stake limits accounts set on themselves.
+        personal_limits: Mapping<AccountId, PersonalLimit>,
+        /// Nonce of the next signed bet of each account.
+        bet_nonces: Mapping<AccountId, u64>,
+        /// Payout roots of the matches settled off-chain.
+        payout_roots: Mapping<MatchId, PayoutRoot>,
+        /// Payouts already claimed, by payout root and account.
+        root_claims: Mapping<RootClaim, ()>,
+        /// Sealed bets not revealed yet, by match and bettor.
+        sealed_bets: Mapping<(MatchId, AccountId), SealedBet>,
+        /// Stakes of the sealed bets of each match not revealed yet.
+        sealed_stakes: Mapping<MatchId, Balance>,
+        /// Jackpot funded by fees and pools nobody won, attached to featured matches.
+        jackpot: Balance,
+        /// Share of the fees, in basis points, added to the jackpot.
+        jackpot_share_bps: u32,
+        /// Free-bet credits granted by the owner, which can only be bet.
+        free_bets: Mapping<AccountId, Balance>,
+        /// Sum of the free-bet credits, taken from the treasury.
+        total_free_bets: Balance,
+        /// Mapping of open prediction contests.
+        contests: Mapping<ContestId, Contest>,
+        /// Id of the next prediction contest.
+        next_contest_id: ContestId,
+        /// Sum of the prize pools of the open games.
+        total_prize_pools: Balance,
+        /// Mapping of open survivor pools.
+        survivor_pools: Mapping<SurvivorId, SurvivorPool>,
+        /// Id of the next survivor pool.
+        next_survivor_id: SurvivorId,
+        /// Mapping of open bracket challenges.
+        brackets: Mapping<BracketId, Bracket>,
+        /// Id of the next bracket challenge.
+        next_bracket_id: BracketId,
+        /// Mapping of open outright markets.
+        outrights: Mapping<OutrightId, Outright>,
+        /// Id of the next outright market.
+        next_outright_id: OutrightId,
+        /// Placings reported by the score oracles for each outright market, until the
+        /// quorum agrees.
+        outright_reports: Mapping<OutrightId, Vec<PlacingsReport>>,
+        /// Current season of the leaderboard.
+        season: u32,
+        /// Points scored by each account in a season.
+        season_points: Mapping<(u32, AccountId), Balance>,
+        /// Free funds of the bankroll backing fixed-odds markets.
+        bankroll: Balance,
+        /// Bankroll funds locked in the escrow of fixed-odds matches.
+        bankroll_locked: Balance,
+        /// Liquidity pool shares of the bankroll held by each provider.
+        lp_shares: Mapping<AccountId, Balance>,
+        /// Total liquidity pool shares of the bankroll.
+        total_lp_shares: Balance,
+        /// Maximum worst-case loss of a fixed-odds match, in basis points of the bankroll.
+        max_exposure_bps: u32,
+        /// Fixed-odds matches with a result, not settled yet.
+        unsettled_fixed_odds: u32,
+        /// Funds covering a shortfall of the escrow of a match at settlement.
+        insurance_fund: Balance,
+        /// Total drawn from the insurance fund.
+        insurance_drawn: Balance,
+        /// Share of the protocol fees, in basis points, added to the insurance fund.
+        insurance_share_bps: u32,
+        /// Premiums of the insured parlays, refunding the stakes of those losing a single
+        /// leg.
+        parlay_insurance: Balance,
+        /// Total refunded from the parlay insurance.
+        parlay_insurance_paid: Balance,
+        /// Premium of an insured parlay, in basis points of its stake.
+        parlay_insurance_premium_bps: u32,
+        /// Accounts that scored in a season, by order of their first points.
+        season_accounts: Mapping<(u32, u32), AccountId>,
+        /// Number of accounts that scored in a season.
+        season_accounts_count: Mapping<u32, u32>,
+        /// Registry of the teams matches can be created with.
+        teams: Mapping<TeamId, Team>,
+        /// Id of each registered team name.
+        team_names: Mapping<TeamName, TeamId>,
+        /// Id of the next registered team.
+        next_team_id: TeamId,
+        /// Record of each registered team.
+        team_stats: Mapping<TeamId, TeamStats>,
+        /// Amount of its internal balance a principal lets a delegate bet, by
+        /// `(principal, delegate)`.
+        allowances: Mapping<(AccountId, AccountId), Balance>,
+        /// Address the winnings, claims and refunds of an account are paid to, if not
+        /// the account itself.
+        payout_addresses: Mapping<AccountId, AccountId>,
+        /// Number of matches not settled yet.
+        open_matches: u32,
+        /// Number of parlays, exchange orders and matched bets not settled yet.
+        open_positions: u32,
+        /// Sum of the pending claims.
+        total_pending_claims: Balance,
+        /// Sum of the accrued fees not claimed yet.
+        total_accrued_fees: Balance,
+        /// Track record of each match creator.
+        creator_reputations: Mapping<AccountId, CreatorReputation>,
+        /// Randomness beacon contract coin flips are decided by.
+        randomness_oracle: Option<AccountId>,
+        /// Contract the protocol fees are pushed to instead of the treasury.
+        fee_router: Option<AccountId>,
+        /// DEX router the winnings can be swapped through.
+        dex_router: Option<AccountId>,
+        /// Platform token the reward points are converted into.
+        reward_token: Option<AccountId>,
+        /// Staking contract of the platform token, queried for the fee discounts.
+        staking_contract: Option<AccountId>,
+        /// Governance contract changing the configuration instead of the owner.
+        governance: Option<AccountId>,
+        /// Account allowed to pause the contract besides the owner.
+        guardian: Option<AccountId>,
+        /// Whether new matches and positions are refused.
+        paused: bool,
+        /// Blocks between scheduling a sensitive admin action and executing it. While it
+        /// is not zero, those actions can only be made through `schedule_action`.
+        admin_delay: BlockNumber,
+        /// Admin actions scheduled and not executed or cancelled yet.
+        scheduled_actions: Mapping<ActionId, ScheduledAction>,
+        next_action_id: ActionId,
+        /// Ring buffer of the last privileged calls, by position modulo its capacity.
+        admin_log: Mapping<u32, AdminLogEntry>,
+        /// Number of privileged calls recorded since the deployment.
+        admin_log_count: u32,
+        /// Share orders of the last block of each uniform-price LMSR market, not priced yet.
+        share_batches: Mapping<MatchId, ShareBatch>,
+        /// Start of the current rate limit window of each account and the bets it placed
+        /// in it.
+        bet_rates: Mapping<AccountId, (BlockNumber, u32)>,
+        /// Reward points of each account not converted yet.
+        reward_points: Mapping<AccountId, Balance>,
+        /// Platform tokens minted from reward points in each epoch.
+        epoch_emissions: Mapping<BlockNumber, Balance>,
+        /// Challenges of the proposed result of each match, in order, until it is final.
+        challenges: Mapping<MatchId, Vec<Challenge>>,
+        /// Oracles allowed to set the results of the matches of a sport or league, by
+        /// `(oracle, sport)`.
+        sport_oracles: Mapping<(AccountId, Sport), ()>,
+        /// Votes of the bettors of each match left without a result.
+        result_votes: Mapping<MatchId, Vec<ResultVote>>,
+        /// Bets of each match whose stake has voted, so a transferred bet can't vote again.
+        voted_bets: Mapping<MatchId, Vec<BetId>>,
+        /// Accounts allowed to report the score of matches.
+        score_oracles: Mapping<AccountId, ()>,
+        /// Scores reported by the score oracles for each match, until the quorum is met.
+        score_reports: Mapping<MatchId, Vec<ScoreReport>>,
+        /// Mapping of open coin flips.
+        coin_flips: Mapping<CoinFlipId, CoinFlip>,
+        /// Id of the next coin flip.
+        next_coin_flip_id: CoinFlipId,
+    }
+
+    /// A new match has been created. [who, team1, team2, start, length]
+    #[ink(event)]
+    pub struct MatchCreated {
+        #[ink(topic)]
+        who: AccountId,
+        team1: TeamName,
+        team2: TeamName,
+        start: BlockNumber,
+        length: BlockNumber,
+    }
+    /// A new bet has been created. [matchId, who, amount, result]
+    #[ink(event)]
+    pub struct BetPlaced {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        bet_id: BetId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        result: MatchResult,
+    }
+    /// A bet hit the cap of its outcome or of the liability and was partially filled, the
+    /// rest of the stake was refunded. [matchId, betId, who, filled, refunded]
+    #[ink(event)]
+    pub struct BetPartiallyFilled {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        bet_id: BetId,
+        #[ink(topic)]
+        who: AccountId,
+        filled: Balance,
+        refunded: Balance,
+    }
+    /// A prediction contest has been created. [contestId, entryFee, matches]
+    #[ink(event)]
+    pub struct ContestCreated {
+        #[ink(topic)]
+        contest_id: ContestId,
+        entry_fee: Balance,
+        matches: u32,
+    }
+    /// An account has entered a prediction contest. [contestId, who]
+    #[ink(event)]
+    pub struct ContestEntered {
+        #[ink(topic)]
+        contest_id: ContestId,
+        #[ink(topic)]
+        who: AccountId,
+    }
+    /// A prediction contest has been settled. [contestId, topScore, winnersCount, prize]
+    #[ink(event)]
+    pub struct ContestSettled {
+        #[ink(topic)]
+        contest_id: ContestId,
+        top_score: u32,
+        winners_count: u32,
+        prize: Balance,
+    }
+    /// The entries of a prediction contest have been refunded. [contestId, entries]
+    #[ink(event)]
+    pub struct ContestRefunded {
+        #[ink(topic)]
+        contest_id: ContestId,
+        entries: u32,
+    }
+    /// A survivor pool has been created. [survivorId, entryFee]
+    #[ink(event)]
+    pub struct SurvivorPoolCreated {
+        #[ink(topic)]
+        survivor_id: SurvivorId,
+        entry_fee: Balance,
+    }
+    /// An entrant of a survivor pool has been eliminated. [survivorId, who, round]
+    #[ink(event)]
+    pub struct SurvivorEliminated {
+        #[ink(topic)]
+        survivor_id: SurvivorId,
+        #[ink(topic)]
+        who: AccountId,
+        round: u32,
+    }
+    /// A survivor pool has ended. [survivorId, survivorsCount, prize]
+    #[ink(event)]
+    pub struct SurvivorPoolEnded {
+        #[ink(topic)]
+        survivor_id: SurvivorId,
+        survivors_count: u32,
+        prize: Balance,
+    }
+    /// The entries of a survivor pool have been refunded. [survivorId, entries]
+    #[ink(event)]
+    pub struct SurvivorPoolRefunded {
+        #[ink(topic)]
+        survivor_id: SurvivorId,
+        entries: u32,
+    }
+    /// A bracket challenge has been created. [bracketId, entryFee, teams]
+    #[ink(event)]
+    pub struct BracketCreated {
+        #[ink(topic)]
+        bracket_id: BracketId,
+        entry_fee: Balance,
+        teams: u32,
+    }
+    /// A round of a bracket challenge has been scored. [bracketId, winners]
+    #[ink(event)]
+    pub struct BracketRoundScored {
+        #[ink(topic)]
+        bracket_id: BracketId,
+        winners: Vec<TeamName>,
+    }
+    /// A bracket challenge has been settled. [bracketId, topScore, winnersCount, prize]
+    #[ink(event)]
+    pub struct BracketSettled {
+        #[ink(topic)]
+        bracket_id: BracketId,
+        top_score: u32,
+        winners_count: u32,
+        prize: Balance,
+    }
+    /// The entries of a bracket challenge have been refunded. [bracketId, entries]
+    #[ink(event)]
+    pub struct BracketRefunded {
+        #[ink(topic)]
+        bracket_id: BracketId,
+        entries: u32,
+    }
+    /// An outright market has been created. [outrightId, runners, places]
+    #[ink(event)]
+    pub struct OutrightCreated {
+        #[ink(topic)]
+        outright_id: OutrightId,
+        runners: u32,
+        places: u32,
+    }
+    /// A bet has been placed on an outright market. [outrightId, who, runner, winStake, placeStake]
+    #[ink(event)]
+    pub struct OutrightBetPlaced {
+        #[ink(topic)]
+        outright_id: OutrightId,
+        #[ink(topic)]
+        who: AccountId,
+        runner: OutcomeId,
+        win_stake: Balance,
+        place_stake: Balance,
+    }
+    /// A score oracle has updated the prices of an outright market. [outrightId]
+    #[ink(event)]
+    pub struct OutrightPricesUpdated {
+        #[ink(topic)]
+        outright_id: OutrightId,
+    }
+    /// A bet on an outright market has been cashed out. [outrightId, betId, who, amount, fee]
+    #[ink(event)]
+    pub struct OutrightCashout {
+        #[ink(topic)]
+        outright_id: OutrightId,
+        bet_id: BetId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+    /// A score oracle has reported the placings of an outright market. [outrightId, oracle]
+    #[ink(event)]
+    pub struct OutrightResultReported {
+        #[ink(topic)]
+        outright_id: OutrightId,
+        #[ink(topic)]
+        oracle: AccountId,
+    }
+    /// An outright market has been settled, or refunded with a `Voided` winner.
+    /// [outrightId, winner, winPool, placePool]
+    #[ink(event)]
+    pub struct OutrightSettled {
+        #[ink(topic)]
+        outright_id: OutrightId,
+        winner: OutcomeId,
+        win_pool: Balance,
+        place_pool: Balance,
+    }
+    /// Liquidity has been provided to the bankroll. [who, amount, shares]
+    #[ink(event)]
+    pub struct LiquidityProvided {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+    /// Liquidity has been withdrawn from the bankroll. [who, amount, shares]
+    #[ink(event)]
+    pub struct LiquidityWithdrawn {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+    /// The insurance fund has been topped up. [amount]
+    #[ink(event)]
+    pub struct InsuranceToppedUp {
+        amount: Balance,
+    }
+    /// The insurance fund has covered a shortfall of the escrow of a match. [matchId, amount]
+    #[ink(event)]
+    pub struct InsuranceDrawn {
+        #[ink(topic)]
+        match_id: MatchId,
+        amount: Balance,
+    }
+    /// The parlay insurance has refunded the stake of a parlay losing a single leg.
+    /// [parlayId, who, amount]
+    #[ink(event)]
+    pub struct ParlayInsurancePaid {
+        #[ink(topic)]
+        parlay_id: ParlayId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A team has been registered or updated. [teamId, name]
+    #[ink(event)]
+    pub struct TeamRegistered {
+        #[ink(topic)]
+        team_id: TeamId,
+        name: TeamName,
+    }
+    /// A principal has set the allowance of a delegate. [principal, delegate, cap]
+    #[ink(event)]
+    pub struct DelegateApproved {
+        #[ink(topic)]
+        principal: AccountId,
+        #[ink(topic)]
+        delegate: AccountId,
+        cap: Balance,
+    }
+    /// An account has set the address it is paid to. [who, payoutAddress]
+    #[ink(event)]
+    pub struct PayoutAddressSet {
+        #[ink(topic)]
+        who: AccountId,
+        payout_address: AccountId,
+    }
+    /// A season of the leaderboard has ended. [season, prizes]
+    #[ink(event)]
+    pub struct SeasonEnded {
+        #[ink(topic)]
+        season: u32,
+        prizes: Balance,
+    }
+    /// Free-bet credits have been granted. [who, amount]
+    #[ink(event)]
+    pub struct FreeBetGranted {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A sponsor has added funds to the pot of a match. [matchId, sponsor, amount]
+    #[ink(event)]
+    pub struct MatchSponsored {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        sponsor: AccountId,
+        amount: Balance,
+    }
+    /// The creator has funded the boost of the winnings of a match. [matchId, amount, boostBps]
+    #[ink(event)]
+    pub struct BoostFunded {
+        #[ink(topic)]
+        match_id: MatchId,
+        amount: Balance,
+        boost_bps: u32,
+    }
+    /// Winnings have been boosted from the budget of the creator. [matchId, who, amount]
+    #[ink(event)]
+    pub struct WinningsBoosted {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A coin flip has been placed against the bankroll. [coinFlipId, who, amount, side]
+    #[ink(event)]
+    pub struct CoinFlipPlaced {
+        #[ink(topic)]
+        coin_flip_id: CoinFlipId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        side: CoinSide,
+    }
+    /// A coin flip has been decided. [coinFlipId, who, side, payout]
+    #[ink(event)]
+    pub struct CoinFlipResolved {
+        #[ink(topic)]
+        coin_flip_id: CoinFlipId,
+        #[ink(topic)]
+        who: AccountId,
+        side: CoinSide,
+        payout: Balance,
+    }
+    /// A coin flip never decided has been refunded. [coinFlipId, who, amount]
+    #[ink(event)]
+    pub struct CoinFlipRefunded {
+        #[ink(topic)]
+        coin_flip_id: CoinFlipId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// Part of the jackpot has been attached to a featured match. [matchId, amount]
+    #[ink(event)]
+    pub struct JackpotAttached {
+        #[ink(topic)]
+        match_id: MatchId,
+        amount: Balance,
+    }
+    /// A share of the jackpot has been won with an exact score. [matchId, who, amount]
+    #[ink(event)]
+    pub struct JackpotWon {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A sealed bet has been committed. [matchId, who, amount]
+    #[ink(event)]
+    pub struct BetCommitted {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A match result has been set. [matchId, result, evidence]
+    #[ink(event)]
+    pub struct MatchResultSet {
+        #[ink(topic)]
+        match_id: MatchId,
+        result: MatchResult,
+        evidence: Option<Vec<u8>>,
+    }
+    /// The winnings of a match have been distributed. [matchId, result, finalBlock, settledBlock]
+    #[ink(event)]
+    pub struct MatchSettled {
+        #[ink(topic)]
+        match_id: MatchId,
+        result: MatchResult,
+        final_block: BlockNumber,
+        settled_block: BlockNumber,
+    }
+    /// The result of a match has been corrected. [matchId, previous, result, evidence]
+    #[ink(event)]
+    pub struct MatchResultCorrected {
+        #[ink(topic)]
+        match_id: MatchId,
+        previous: MatchResult,
+        result: MatchResult,
+        evidence: Option<Vec<u8>>,
+    }
+    /// A match has been voided. [matchId, reason]
+    #[ink(event)]
+    pub struct MatchVoided {
+        #[ink(topic)]
+        match_id: MatchId,
+        reason: u8,
+    }
+    /// A match final score has been set. [matchId, team1Goals, team2Goals]
+    #[ink(event)]
+    pub struct MatchScoreSet {
+        #[ink(topic)]
+        match_id: MatchId,
+        team1_goals: u32,
+        team2_goals: u32,
+    }
+    /// A score oracle has reported the score of a match. [matchId, oracle, team1Goals, team2Goals]
+    #[ink(event)]
+    pub struct ScoreReported {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        oracle: AccountId,
+        team1_goals: u32,
+        team2_goals: u32,
+    }
+    /// A score oracle has updated the running score of a match in play.
+    /// [matchId, oracle, team1Goals, team2Goals]
+    #[ink(event)]
+    pub struct ScoreUpdated {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        oracle: AccountId,
+        team1_goals: u32,
+        team2_goals: u32,
+    }
+    /// Bets on a match are suspended after a change of its score. [matchId, until]
+    #[ink(event)]
+    pub struct MarketSuspended {
+        #[ink(topic)]
+        match_id: MatchId,
+        until: BlockNumber,
+    }
+    /// A new parlay has been placed. [parlayId, who, amount, legs]
+    #[ink(event)]
+    pub struct ParlayPlaced {
+        #[ink(topic)]
+        parlay_id: ParlayId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        legs: u32,
+    }
+    /// A parlay has been settled. [parlayId, who, amount]
+    #[ink(event)]
+    pub struct ParlaySettled {
+        #[ink(topic)]
+        parlay_id: ParlayId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+
+    /// Shares have been bought in an LMSR market. [matchId, who, outcome, amount, cost]
+    #[ink(event)]
+    pub struct SharesBought {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        outcome: MatchResult,
+        amount: Balance,
+        cost: Balance,
+    }
+
+    /// Shares have been ordered in a uniform-price LMSR market. [matchId, who, outcome, amount, maxCost]
+    #[ink(event)]
+    pub struct SharesOrdered {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        outcome: MatchResult,
+        amount: Balance,
+        max_cost: Balance,
+    }
+
+    /// An exchange order has been posted. [orderId, matchId, who, side, outcome, odds, stake]
+    #[ink(event)]
+    pub struct OrderPosted {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        side: OrderSide,
+        outcome: MatchResult,
+        odds: u32,
+        stake: Balance,
+    }
+    /// A back and a lay order have been matched. [matchedBetId, backOrderId, layOrderId, stake, odds]
+    #[ink(event)]
+    pub struct OrdersMatched {
+        #[ink(topic)]
+        matched_bet_id: MatchedBetId,
+        back_order_id: OrderId,
+        lay_order_id: OrderId,
+        stake: Balance,
+        odds: u32,
+    }
+    /// An exchange order has been cancelled. [orderId, refund]
+    #[ink(event)]
+    pub struct OrderCancelled {
+        #[ink(topic)]
+        order_id: OrderId,
+        refund: Balance,
+    }
+    /// A matched bet has been settled. [matchedBetId, winner, amount]
+    #[ink(event)]
+    pub struct MatchedBetSettled {
+        #[ink(topic)]
+        matched_bet_id: MatchedBetId,
+        #[ink(topic)]
+        winner: AccountId,
+        amount: Balance,
+    }
+
+    /// A creator deposit has been slashed for late settlement. [matchId, amount]
+    #[ink(event)]
+    pub struct DepositSlashed {
+        #[ink(topic)]
+        match_id: MatchId,
+        amount: Balance,
+    }
+
+    /// The creator of a match has proposed its result. [matchId, result]
+    #[ink(event)]
+    pub struct ResultProposed {
+        #[ink(topic)]
+        match_id: MatchId,
+        result: MatchResult,
+    }
+    /// The result proposed for a match has been challenged. [matchId, who, result, bond]
+    #[ink(event)]
+    pub struct ResultChallenged {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        result: MatchResult,
+        bond: Balance,
+    }
+    /// A bettor has voted on the result of a match left without one. [matchId, who, result, weight]
+    #[ink(event)]
+    pub struct ResultVoted {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        result: MatchResult,
+        weight: Balance,
+    }
+    /// An oracle has been authorized for a sport or league, or revoked. [oracle, sport, authorized]
+    #[ink(event)]
+    pub struct OracleAuthorized {
+        #[ink(topic)]
+        oracle: AccountId,
+        sport: Sport,
+        authorized: bool,
+    }
+    /// A proposed result has been rejected. [matchId]
+    #[ink(event)]
+    pub struct ResultRejected {
+        #[ink(topic)]
+        match_id: MatchId,
+    }
+
+    /// A bet has been transferred to a new holder. [matchId, betId, from, to]
+    #[ink(event)]
+    pub struct BetTransferred {
+        #[ink(topic)]
+        match_id: MatchId,
+        bet_id: BetId,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+    }
+
+    /// A bet has been cashed out before the match. [matchId, betId, who, amount, fee]
+    #[ink(event)]
+    pub struct Cashout {
+        #[ink(topic)]
+        match_id: MatchId,
+        bet_id: BetId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
+
+    /// Accrued fees have been claimed. [who, amount]
+    #[ink(event)]
+    pub struct FeesClaimed {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+
+    /// Accrued fees have been claimed with `claim_referral_rewards`. [who, amount]
+    #[ink(event)]
+    pub struct ReferralRewardsClaimed {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+
+    /// Funds have been deposited to an internal balance. [who, amount]
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+
+    /// Funds have been withdrawn from an internal balance. [who, amount]
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+
+    /// The configuration has been updated. [config]
+    #[ink(event)]
+    pub struct ConfigUpdated {
+        config: Config,
+    }
+
+    /// An emergency withdrawal has been scheduled. [amount, executableAt]
+    #[ink(event)]
+    pub struct EmergencyWithdrawalScheduled {
+        amount: Balance,
+        executable_at: BlockNumber,
+    }
+
+    /// An emergency withdrawal has been cancelled. [amount]
+    #[ink(event)]
+    pub struct EmergencyWithdrawalCancelled {
+        amount: Balance,
+    }
+
+    /// An emergency withdrawal has been executed. [amount]
+    #[ink(event)]
+    pub struct EmergencyWithdrawalExecuted {
+        amount: Balance,
+    }
+
+    /// An account has been blacklisted. [account]
+    #[ink(event)]
+    pub struct Blacklisted {
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    /// An account has been removed from the blacklist. [account]
+    #[ink(event)]
+    pub struct Unblacklisted {
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    /// The governance contract changing the configuration has been set. [governance]
+    #[ink(event)]
+    pub struct GovernanceSet {
+        governance: Option<AccountId>,
+    }
+
+    /// The contract has been paused. [by]
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    /// The contract has been unpaused. [by]
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    /// An admin action has been scheduled. [actionId, action, executableAt]
+    #[ink(event)]
+    pub struct ActionScheduled {
+        #[ink(topic)]
+        action_id: ActionId,
+        action: AdminAction,
+        executable_at: BlockNumber,
+    }
+
+    /// A scheduled admin action has been executed. [actionId]
+    #[ink(event)]
+    pub struct ActionExecuted {
+        #[ink(topic)]
+        action_id: ActionId,
+    }
+
+    /// A scheduled admin action has been cancelled. [actionId]
+    #[ink(event)]
+    pub struct ActionCancelled {
+        #[ink(topic)]
+        action_id: ActionId,
+    }
+
+    /// An account excluded itself from betting. [who, until]
+    #[ink(event)]
+    pub struct SelfExclusionSet {
+        #[ink(topic)]
+        who: AccountId,
+        until: BlockNumber,
+    }
+
+    /// An account set a limit on its stakes, applying from a block. [who, amountPerPeriod,
+    /// appliesFrom]
+    #[ink(event)]
+    pub struct PersonalLimitSet {
+        #[ink(topic)]
+        who: AccountId,
+        amount_per_period: Balance,
+        applies_from: BlockNumber,
+    }
+
+    /// A payment failed and has been recorded as a pending claim. [who, amount]
+    #[ink(event)]
+    pub struct PaymentDeferred {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+
+    /// The winnings of a match have been distributed. [matchId, totalPool, winnersCount]
+    #[ink(event)]
+    pub struct WinningsDistributed {
+        #[ink(topic)]
+        match_id: MatchId,
+        total_pool: Balance,
+        winners_count: u32,
+    }
+    /// The payouts of a match have been posted as a Merkle root. [matchId, root, total]
+    #[ink(event)]
+    pub struct PayoutRootPosted {
+        #[ink(topic)]
+        match_id: MatchId,
+        root: [u8; 32],
+        total: Balance,
+    }
+    /// The payouts of a match left unclaimed after the deadline have been swept.
+    /// [matchId, amount, toJackpot]
+    #[ink(event)]
+    pub struct UnclaimedSwept {
+        #[ink(topic)]
+        match_id: MatchId,
+        amount: Balance,
+        to_jackpot: bool,
+    }
+    /// A pending claim left unclaimed after the claim period has been swept.
+    /// [who, amount, toJackpot]
+    #[ink(event)]
+    pub struct PendingClaimSwept {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+        to_jackpot: bool,
+    }
+    /// Winnings have been claimed. [who, amount]
+    #[ink(event)]
+    pub struct WinningsClaimed {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// Winnings have been claimed swapped to another asset. [who, asset, amount, amountOut]
+    #[ink(event)]
+    pub struct WinningsSwapped {
+        #[ink(topic)]
+        who: AccountId,
+        #[ink(topic)]
+        asset: AccountId,
+        amount: Balance,
+        amount_out: Balance,
+    }
+    /// Reward points have been converted into platform tokens. [who, amount]
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A stake has been refunded. [matchId, who, amount]
+    #[ink(event)]
+    pub struct BetRefunded {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// The deposit of a match has been returned. [matchId, who, amount]
+    #[ink(event)]
+    pub struct DepositReturned {
+        #[ink(topic)]
+        match_id: MatchId,
+        #[ink(topic)]
+        who: AccountId,
+        amount: Balance,
+    }
+    /// A match has been closed and deleted. [matchId]
+    #[ink(event)]
+    pub struct MatchClosed {
+        #[ink(topic)]
+        match_id: MatchId,
+    }
+
+    /// The Betting error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// The match to be created already exist.
+        MatchAlreadyExists,
+        /// Each account can only have one match open.
+        OriginHasAlreadyOpenMatch,
+        /// The time of the match is over.
+        TimeMatchOver,
+        /// The match starts before the minimum lead time from now is over
+        MatchStartTooSoon,
+        /// The length of the match is outside the bounds of the config
+        InvalidMatchLength,
+        /// Not enough deposit to create the Match.
+        NotEnoughDeposit,
+        /// The match where the bet is placed does not exist
+        MatchDoesNotExist,
+        /// No allowing betting if the match has started or betting on it has closed
+        MatchHasStarted,
+        /// You already place the same bet in that match
+        AlreadyBet,
+        /// Only owner of the smart contract can make this call
+        BadOrigin,
+        /// No allowing set the result if the match not over
+        TimeMatchNotOver,
+        /// The match still has not a result set
+        MatchNotResult,
+        /// The outcome can not be bet on or set in this market
+        OutcomeNotAllowed,
+        /// Handicap markets must be settled with `set_score`
+        ScoreRequired,
+        /// Parlays need at least two legs on different matches
+        InvalidParlay,
+        /// The parlay does not exist
+        ParlayDoesNotExist,
+        /// Only the bettor can make this call
+        NotBettor,
+        /// The call is not supported by the market of the match
+        WrongMarket,
+        /// The LMSR liquidity parameter, or the liquidity provided, must be positive
+        InvalidLiquidity,
+        /// The transferred value does not cover the cost
+        InsufficientPayment,
+        /// Odds must be higher than 1.00
+        InvalidOdds,
+        /// The order does not exist
+        OrderDoesNotExist,
+        /// The orders are not a back and a lay of the same outcome at compatible odds
+        OrdersDoNotMatch,
+        /// The matched bet does not exist
+        MatchedBetDoesNotExist,
+        /// Only the creator can settle the match until the grace period is over
+        SettlementGracePeriodNotOver,
+        /// The winnings of the match are being distributed
+        SettlementInProgress,
+        /// The payouts of the match exceed its escrow
+        EscrowExceeded,
+        /// The internal balance of the caller is too low
+        NotEnoughBalance,
+        /// The match has reached its maximum number of bets
+        MatchFull,
+        /// The configuration is not valid
+        InvalidConfig,
+        /// The amount of the bet is above the maximum bet
+        InvalidBetAmount,
+        /// No emergency withdrawal has been scheduled
+        NoEmergencyWithdrawal,
+        /// The timelock of the emergency withdrawal is not over
+        TimelockNotOver,
+        /// An arithmetic operation overflowed
+        ArithmeticOverflow,
+        /// The caller is not on the allowlist of the private match
+        NotAllowedBettor,
+        /// The match has no allowlist
+        MatchNotPrivate,
+        /// The account is blacklisted
+        AccountBlacklisted,
+        /// The account excluded itself from betting
+        SelfExcluded,
+        /// The stake would exceed the personal limit of the account for the period
+        PersonalLimitExceeded,
+        /// The batch is empty, too large or its amounts do not add up to the transferred value
+        InvalidBatch,
+        /// The nonce of the signed bet is not the next one of the bettor
+        InvalidNonce,
+        /// The match has no payout root
+        NoPayoutRoot,
+        /// The proof does not link the payout to the payout root
+        InvalidProof,
+        /// The payout has already been claimed
+        AlreadyClaimed,
+        /// The payouts of the previous root of the match are not all claimed
+        ClaimsOutstanding,
+        /// The caller has no sealed bet on the match
+        NoSealedBet,
+        /// The outcome and salt do not match the commitment
+        InvalidReveal,
+        /// The jackpot does not hold enough funds
+        NotEnoughJackpot,
+        /// No jackpot is attached to the match
+        MatchNotFeatured,
+        /// The contest does not exist
+        ContestDoesNotExist,
+        /// Contests need between one and 32 different matches not started yet
+        InvalidContest,
+        /// The entries of the contest are closed
+        EntriesClosed,
+        /// The game has reached its maximum number of entries
+        ContestFull,
+        /// The account has already entered
+        AlreadyEntered,
+        /// The transferred value is not the entry fee
+        WrongEntryFee,
+        /// The predictions are not one valid result for each match
+        InvalidPredictions,
+        /// The survivor pool does not exist
+        SurvivorPoolDoesNotExist,
+        /// The caller has not entered or has been eliminated
+        NotSurvivor,
+        /// The match is not in the current round
+        NotInRound,
+        /// The entrant has already picked a team this round
+        AlreadyPicked,
+        /// The entrant has already picked the team in a previous round
+        TeamAlreadyUsed,
+        /// The matches don't form a valid round or have started
+        InvalidRound,
+        /// The bracket challenge does not exist
+        BracketDoesNotExist,
+        /// Brackets need a power of two of different teams, at most 32
+        InvalidBracket,
+        /// A knockout match ended without a winner
+        NoWinner,
+        /// The outright market does not exist
+        OutrightDoesNotExist,
+        /// Outrights need between two and 32 different runners and fewer places than
+        /// runners, and placings of different runners for each place
+        InvalidOutright,
+        /// The outright market has no prices updated recently enough to value a cashout
+        StalePrices,
+        /// The bankroll does not hold enough free funds
+        NotEnoughBankroll,
+        /// The caller does not hold enough liquidity pool shares
+        NotEnoughShares,
+        /// The worst-case loss of the match would exceed the creator bond or the share of
+        /// the bankroll it can risk
+        ExposureExceeded,
+        /// The claim deadline of the payouts is over
+        ClaimPeriodOver,
+        /// The claim deadline of the payouts is not over yet
+        ClaimPeriodNotOver,
+        /// The team is not in the registry
+        TeamDoesNotExist,
+        /// A team with the name is already registered
+        TeamAlreadyExists,
+        /// The bet exceeds the allowance of the delegate
+        AllowanceExceeded,
+        /// Accepting the stake would leave the balance of the contract below its liabilities
+        Insolvent,
+        /// Matches, positions or funds still keep the contract from being terminated
+        TerminationBlocked,
+        /// The sponsorship is empty or the match has reached its maximum number of sponsors
+        InvalidSponsorship,
+        /// The coin flip does not exist
+        CoinFlipDoesNotExist,
+        /// No randomness oracle contract is registered
+        NoRandomnessOracle,
+        /// The randomness deciding the coin flip is not published yet
+        RandomnessNotReady,
+        /// The evidence of the result is too long
+        InvalidEvidence,
+        /// The fee is out of the bounds set by the owner, or the match already has bets
+        InvalidMatchFee,
+        /// The caller is not a registered score oracle
+        NotScoreOracle,
+        /// The oracle has already reported the score of the match
+        AlreadyReported,
+        /// The bond is below the minimum bond or twice the bond of the previous challenge
+        BondTooLow,
+        /// The bettors of the match can't vote on its result
+        VotingNotOpen,
+        /// The vote on the result of the match is not over yet
+        VotingNotOver,
+        /// The caller has already voted
+        AlreadyVoted,
+        /// No DEX router is registered
+        NoDexRouter,
+        /// The DEX router failed to make the swap
+        SwapFailed,
+        /// The swap returned less than the minimum amount
+        SlippageExceeded,
+        /// The contract is paused
+        ContractPaused,
+        /// The action has to be scheduled while the admin delay is set
+        ActionTimelocked,
+        /// The scheduled action does not exist
+        ActionDoesNotExist,
+        /// The admin delay of the scheduled action is not over
+        ActionNotReady,
+        /// The code of the contract could not be replaced
+        UpgradeFailed,
+        /// No platform token is registered
+        NoRewardToken,
+        /// The platform token failed to mint the rewards
+        MintFailed,
+        /// The emission cap of the epoch has been reached
+        EmissionCapReached,
+        /// The match already has bets
+        MatchHasBets,
+        /// The match has no share orders to price
+        NoShareOrders,
+        /// The block of the share orders is not over yet
+        ShareOrdersOpen,
+        /// The account has placed the most bets allowed in the rate limit window
+        RateLimited,
+        /// The stake would exceed the cap of the outcome
+        OutcomeCapExceeded,
+        /// The match has not started yet or is over
+        MatchNotInPlay,
+        /// The bet has no stake
+        ZeroBetAmount,
+        /// The stake is below the minimum bet
+        BetTooSmall,
+        /// Bets are suspended after a change of the score
+        MarketSuspended,
+        /// The live betting window does not close before the end of the match
+        InvalidLiveCutoff,
+        /// The boost is zero or above 100%
+        InvalidBoost,
+        /// The treasury does not hold enough funds
+        NotEnoughTreasury,
+        /// Only the creator of the match can make this call
+        NotCreator,
+        /// The match already has a result
+        ResultAlreadySet,
+        /// No result has been proposed for the match
+        NoResultProposed,
+        /// The proposed result is final, the confirmation timeout is over
+        ResultFinal,
+        /// The result is not signed by a registered oracle key
+        InvalidSignature,
+        /// No result oracle contract is registered
+        NoResultOracle,
+        /// The match is not linked to a fixture of the result oracle
+        NoFixture,
+        /// The call to the result oracle failed
+        OracleCallFailed,
+        /// The bet does not exist
+        BetDoesNotExist,
+        /// There is nothing to claim
+        NothingToClaim,
+        /// No fee router is registered
+        NoFeeRouter,
+        /// No other bet backs the result of the bet, its cashout can't be priced
+        NoCashoutLiquidity,
+        /// A fixed-odds match backed by the bankroll has a result waiting for its settlement
+        BankrollSettlementPending,
+        /// Matches need between two and 32 different outcome labels
+        InvalidOutcomeLabels,
+        /// The match already has a proposed result, which can be challenged
+        ResultAlreadyProposed,
+        /// The result can still be set, the stakes are refunded after the timeout
+        RefundNotOpen,
+        /// Returned if the requested transfer failed. This can be the case if the
+        /// contract does not have sufficient free funds or if the transfer would
+        /// have brought the contract's balance below minimum balance.
+        TransferFailed,
+    }
+
+    impl Betting {
+        #[ink(constructor, payable, selector = 0x9BAE9D5E)]
+        pub fn new() -> Self {
+            let owner = Self::env().caller();
+            let mut config = Lazy::new();
+            config.set(&Config::default());
+            Self {
+                matches: Default::default(),
+                matches_hashes: Default::default(),
+                owner,
+                results: Default::default(),
+                parlays: Default::default(),
+                next_parlay_id: 0,
+                orders: Default::default(),
+                next_order_id: 0,
+                matched_bets: Default::default(),
+                next_matched_bet_id: 0,
+                config,
+                slash_per_block_bps: DEFAULT_SLASH_PER_BLOCK_BPS,
+                treasury: 0,
+                oracle_keys: Default::default(),
+                result_oracle: None,
+                referrers: Default::default(),
+                accrued_fees: Default::default(),
+                referral_share_bps: DEFAULT_REFERRAL_SHARE_BPS,
+                creator_fee_share_bps: 0,
+                creator_milestones: Vec::new(),
+                stats: Default::default(),
+                pending_claims: Mapping::default(),
+                pending_claim_blocks: Mapping::default(),
+                settlements: Mapping::default(),
+                settlement_payouts: Mapping::default(),
+                settlement_reports: Mapping::default(),
+                closed_statuses: Mapping::default(),
+                deposit_payers: Mapping::default(),
+                escrows: Mapping::default(),
+                total_escrow: 0,
+                balances: Mapping::default(),
+                total_balances: 0,
+                max_bets_per_match: DEFAULT_MAX_BETS_PER_MATCH,
+                emergency_withdrawal: None,
+                blacklist: Mapping::default(),
+                self_exclusions: Mapping::default(),
+                personal_limits: Mapping::default(),
+                bet_nonces: Mapping::default(),
+                payout_roots: Mapping::default(),
+                root_claims: Mapping::default(),
+                sealed_bets: Mapping::default(),
+                sealed_stakes: Mapping::default(),
+                jackpot: 0,
+                jackpot_share_bps: 0,
+                free_bets: Mapping::default(),
+                total_free_bets: 0,
+                contests: Mapping::default(),
+                next_contest_id: 0,
+                total_prize_pools: 0,
+                survivor_pools: Mapping::default(),
+                next_survivor_id: 0,
+                brackets: Mapping::default(),
+                next_bracket_id: 0,
+                outrights: Mapping::default(),
+                next_outright_id: 0,
+                outright_reports: Mapping::default(),
+                season: 0,
+                season_points: Mapping::default(),
+                season_accounts: Mapping::default(),
+                season_accounts_count: Mapping::default(),
+                bankroll: 0,
+                bankroll_locked: 0,
+                lp_shares: Mapping::default(),
+                total_lp_shares: 0,
+                max_exposure_bps: 10_000,
+                unsettled_fixed_odds: 0,
+                insurance_fund: 0,
+                insurance_drawn: 0,
+                insurance_share_bps: 0,
+                parlay_insurance: 0,
+                parlay_insurance_paid: 0,
+                parlay_insurance_premium_bps: DEFAULT_PARLAY_INSURANCE_PREMIUM_BPS,
+                teams: Mapping::default(),
+                team_names: Mapping::default(),
+                next_team_id: 0,
+                team_stats: Mapping::default(),
+                allowances: Mapping::default(),
+                payout_addresses: Mapping::default(),
+                open_matches: 0,
+                open_positions: 0,
+                total_pending_claims: 0,
+                total_accrued_fees: 0,
+                creator_reputations: Mapping::default(),
+                randomness_oracle: None,
+                fee_router: None,
+                dex_router: None,
+                reward_token: None,
+                staking_contract: None,
+                governance: None,
+                guardian: None,
+                paused: false,
+                admin_delay: 0,
+                scheduled_actions: Mapping::default(),
+                next_action_id: 0,
+                admin_log: Mapping::default(),
+                admin_log_count: 0,
+                share_batches: Mapping::default(),
+                bet_rates: Mapping::default(),
+                reward_points: Mapping::default(),
+                epoch_emissions: Mapping::default(),
+                challenges: Mapping::default(),
+                sport_oracles: Mapping::default(),
+                result_votes: Mapping::default(),
+                voted_bets: Mapping::default(),
+                score_oracles: Mapping::default(),
+                score_reports: Mapping::default(),
+                coin_flips: Mapping::default(),
+                next_coin_flip_id: 0,
+            }
+        }
+
+        // payable accepts a payment (deposit).
+        // `allow_draw` is false for 2-way markets of sports without draws.
+        // `draw_no_bet` refunds all the stakes if the result is a draw.
+        // `allowed_bettors` makes the match private to the accounts on the list.
+        #[ink(message, payable, selector = 0x041D2437)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn create_match_to_bet(
+            &mut self,
+            team1: Vec<u8>,
+            team2: Vec<u8>,
+            start: BlockNumber,
+            length: BlockNumber,
+            allow_draw: bool,
+            draw_no_bet: bool,
+            allowed_bettors: Option<Vec<AccountId>>,
+        ) -> Result<(), Error> {
+            let market = if draw_no_bet {
+                Market::DrawNoBet
+            } else {
+                Market::Winner
+            };
+            self.create_match(
+                Self::env().caller(),
+                Self::env().transferred_value(),
+                team1,
+                team2,
+                start,
+                length,
+                market,
+                allow_draw,
+                allowed_bettors,
+            )
+        }
+
+        /// Creates a match with an Asian handicap market, `handicap` being the
+        /// line added to team1's goals in quarter goals.
+        #[ink(message, payable, selector = 0xAC0E5026)]
+        pub fn create_handicap_match_to_bet(
+            &mut self,
+            team1: Vec<u8>,
+            team2: Vec<u8>,
+            start: BlockNumber,
+            length: BlockNumber,
+            handicap: i32,
+        ) -> Result<(), Error> {
+            // A draw on the handicap line is a push
+            self.create_match(
+                Self::env().caller(),
+                Self::env().transferred_value(),
+                team1,
+                team2,
+                start,
+                length,
+                Market::Handicap(handicap),
+                true,
+                None,
+            )
+        }
+
+        /// Creates a match with an LMSR market maker. The deposit must also fund
+        /// the maximum loss of the market maker (`liquidity * ln(3)`).
+        #[ink(message, payable, selector = 0x6C2FA1A0)]
+        pub fn create_lmsr_match_to_bet(
+            &mut self,
+            team1: Vec<u8>,
+            team2: Vec<u8>,
+            start: BlockNumber,
+            length: BlockNumber,
+            liquidity: Balance,
+        ) -> Result<(), Error> {
+            if liquidity == 0 {
+                return Err(Error::InvalidLiquidity);
+            }
+            self.create_match(
+                Self::env().caller(),
+                Self::env().transferred_value(),
+                team1,
+                team2,
+                start,
+                length,
+                Market::Lmsr(liquidity),
+                true,
+                None,
+            )
+        }
+
+        /// Creates a match with a fixed-odds market backed by the deposit, then the
+        /// bankroll. `odds` are the decimal odds of team1, team2 and the draw in hundredths
+        /// (`250` is 2.50), the draw being `0` if it can't be bet on. They must have an
+        /// overround.
+        #[ink(message, payable, selector = 0x3584989C)]
+        pub fn create_fixed_odds_match_to_bet(
+            &mut self,
+            team1: Vec<u8>,
+            team2: Vec<u8>,
+            start: BlockNumber,
+            length: BlockNumber,
+            odds: [u32; 3],
+        ) -> Result<(), Error> {
+            let allow_draw = odds[2] != 0;
+            if odds[0] <= ODDS_UNIT || odds[1] <= ODDS_UNIT || allow_draw && odds[2] <= ODDS_UNIT {
+                return Err(Error::InvalidOdds);
+            }
+            if !Self::has_overround(odds) {
+                return Err(Error::InvalidOdds);
+            }
+            self.create_match(
+                Self::env().caller(),
+                Self::env().transferred_value(),
+                team1,
+                team2,
+                start,
+                length,
+                Market::FixedOdds(odds),
+                allow_draw,
+                None,
+            )
+        }
+
+        /// Whether fixed odds leave the book a margin: the probabilities they imply for
+        /// the outcomes offered, one over the odds, add up to at least one.
+        fn has_overround(odds: [u32; 3]) -> bool {
+            let offered: Vec<u128> = odds
+                .iter()
+                .filter(|odds| **odds != 0)
+                .map(|odds| u128::from(*odds))
+                .collect();
+            // Over the common denominator, the product of the odds
+            let product = offered
+                .iter()
+                .fold(1, |product: u128, odds| product.saturating_mul(*odds));
+            let implied = offered.iter().fold(0, |implied: u128, odds| {
+                implied.saturating_add(
+                    product
+                        .checked_div(*odds)
+                        .unwrap_or(0)
+                        .saturating_mul(u128::from(ODDS_UNIT)),
+                )
+            });
+            implied >= product
+        }
+
+        /// Creates a pari-mutuel match between team1 and team2 on `outcome_labels`, e.g.
+        /// the winner of a race or the first scorer, instead of the 1X2 outcomes. The
+        /// outcome at position `i` of the labels is `OutcomeId::nth(i)`.
+        #[ink(message, payable, selector = 0xB961968E)]
+        pub fn create_multi_outcome_match_to_bet(
+            &mut self,
+            team1: Vec<u8>,
+            team2: Vec<u8>,
+            start: BlockNumber,
+            length: BlockNumber,
+            outcome_labels: Vec<TeamName>,
+        ) -> Result<(), Error> {
+            if outcome_labels.len() < 2 || outcome_labels.len() > MAX_MATCH_OUTCOMES {
+                return Err(Error::InvalidOutcomeLabels);
+            }
+            for (i, label) in outcome_labels.iter().enumerate() {
+                if outcome_labels[..i].contains(label) {
+                    return Err(Error::InvalidOutcomeLabels);
+                }
+            }
+            let caller = Self::env().caller();
+            self.create_match(
+                caller,
+                Self::env().transferred_value(),
+                team1,
+                team2,
+                start,
+                length,
+                Market::Winner,
+                false,
+                None,
+            )?;
+            let mut betting_match = match self.matches.get(caller) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            betting_match.outcome_labels = outcome_labels;
+            self.matches.insert(caller, &betting_match);
+            Ok(())
+        }
+
+        /// Creates a match between two teams of the registry, as `create_match_to_bet`.
+        #[ink(message, payable, selector = 0x6E241F68)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn create_registered_match_to_bet(
+            &mut self,
+            team1: TeamId,
+            team2: TeamId,
+            start: BlockNumber,
+            length: BlockNumber,
+            allow_draw: bool,
+            draw_no_bet: bool,
+            allowed_bettors: Option<Vec<AccountId>>,
+        ) -> Result<(), Error> {
+            let (name1, name2) = match (self.teams.get(team1), self.teams.get(team2)) {
+                (Some(team1), Some(team2)) => (team1.name, team2.name),
+                _ => return Err(Error::TeamDoesNotExist),
+            };
+            let caller = Self::env().caller();
+            self.create_match_to_bet(
+                name1,
+                name2,
+                start,
+                length,
+                allow_draw,
+                draw_no_bet,
+                allowed_bettors,
+            )?;
+            let mut betting_match = match self.matches.get(caller) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            betting_match.team_ids = Some((team1, team2));
+            self.matches.insert(caller, &betting_match);
+            for team_id in [team1, team2] {
+                let mut stats = self.team_stats.get(team_id).unwrap_or_default();
+                stats.matches = stats.matches.saturating_add(1);
+                self.team_stats.insert(team_id, &stats);
+            }
+            Ok(())
+        }
+
+        /// Create many fixtures in one call, e.g. a round of a league, each opened for the
+        /// creator in its spec. The transferred value is the deposit of all the matches,
+        /// split evenly among them, at least the configured minimum deposit each as the owner
+        /// pays it, and returned to the owner at their settlement. If any match can't be
+        /// created, none is.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, payable, selector = 0x08F9C112)]
+        pub fn create_matches(&mut self, specs: Vec<MatchSpec>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("create_matches"), &specs);
+            if specs.is_empty() || specs.len() > MAX_BATCH_MATCHES {
+                return Err(Error::InvalidBatch);
+            }
+            // The batch is short, the count fits in a Balance
+            let count = specs.len() as Balance;
+            let total_deposit = Self::env().transferred_value();
+            let deposit = total_deposit.checked_div(count).unwrap_or(0);
+            // The first match gets the rounding of the split
+            let mut remainder = total_deposit.checked_rem(count).unwrap_or(0);
+            // The reputation of the creators doesn't discount a deposit they don't pay
+            if deposit < self.get_config().min_deposit {
+                return Err(Error::NotEnoughDeposit);
+            }
+            for spec in specs {
+                let market = if spec.draw_no_bet {
+                    Market::DrawNoBet
+                } else {
+                    Market::Winner
+                };
+                let deposit = deposit
+                    .checked_add(core::mem::take(&mut remainder))
+                    .ok_or(Error::ArithmeticOverflow)?;
+                self.create_match(
+                    spec.creator,
+                    deposit,
+                    spec.team1,
+                    spec.team2,
+                    spec.start,
+                    spec.length,
+                    market,
+                    spec.allow_draw,
+                    None,
+                )?;
+                if spec.creator != self.owner {
+                    self.deposit_payers.insert(spec.creator, &self.owner);
+                }
+            }
+            Ok(())
+        }
+
+        /// Amount the creator has to fund on top of the deposit.
+        fn market_subsidy(market: Market) -> Result<Balance, Error> {
+            match market {
+                Market::Lmsr(liquidity) => lmsr::cost(&[0; 3], liquidity)
+                    .and_then(|cost| cost.checked_add(1))
+                    .ok_or(Error::ArithmeticOverflow),
+                _ => Ok(0),
+            }
+        }
+
+        /// Sum of `amounts`, failing on overflow.
+        fn checked_sum(amounts: impl IntoIterator<Item = Balance>) -> Result<Balance, Error> {
+            amounts.into_iter().try_fold(0, |sum: Balance, amount| {
+                sum.checked_add(amount).ok_or(Error::ArithmeticOverflow)
+            })
+        }
+
+        /// `amount * numerator / denominator` rounded down, with a 256-bit product so
+        /// large pools can't overflow. `None` if the denominator is zero or the result
+        /// doesn't fit.
+        #[allow(clippy::arithmetic_side_effects)]
+        fn mul_div(amount: Balance, numerator: Balance, denominator: Balance) -> Option<Balance> {
+            if denominator == 0 {
+                return None;
+            }
+            if let Some(product) = amount.checked_mul(numerator) {
+                return Some(product / denominator);
+            }
+            // Product of the 64-bit halves, which can't overflow
+            let (a1, a0) = (amount >> 64, amount & u128::from(u64::MAX));
+            let (b1, b0) = (numerator >> 64, numerator & u128::from(u64::MAX));
+            let (low, middle1, middle2, high) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+            let (low, carry1) = low.overflowing_add(middle1 << 64);
+            let (low, carry2) = low.overflowing_add(middle2 << 64);
+            let high =
+                high + (middle1 >> 64) + (middle2 >> 64) + u128::from(carry1) + u128::from(carry2);
+            if high >= denominator {
+                return None;
+            }
+            // Long division of the 256-bit product
+            let mut remainder = high;
+            let mut quotient: Balance = 0;
+            for bit in (0..128).rev() {
+                let overflow = remainder >> 127 == 1;
+                remainder = (remainder << 1) | ((low >> bit) & 1);
+                if overflow || remainder >= denominator {
+                    remainder = remainder.wrapping_sub(denominator);
+                    quotient |= 1 << bit;
+                }
+            }
+            Some(quotient)
+        }
+
+        /// Block after which the result of a match can be set: its end plus its result delay.
+        fn result_opens(betting_match: &Match) -> Result<BlockNumber, Error> {
+            betting_match
+                .start
+                .checked_add(betting_match.length)
+                .and_then(|end| end.checked_add(betting_match.result_delay))
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Last block bets can be placed on a match, its betting cutoff before the start.
+        fn betting_closes(betting_match: &Match) -> BlockNumber {
+            betting_match
+                .start
+                .saturating_sub(betting_match.betting_cutoff)
+        }
+
+        /// Block after which single bets close, after the start in live mode.
+        fn bets_close(betting_match: &Match) -> BlockNumber {
+            match betting_match.live_cutoff {
+                Some(live_cutoff) => betting_match.start.saturating_add(live_cutoff),
+                None => Self::betting_closes(betting_match),
+            }
+        }
+
+        /// Weight of a bet placed at `block`: full before the start, then decaying
+        /// linearly over the live window, never down to zero.
+        fn bet_weight(betting_match: &Match, block: BlockNumber) -> u32 {
+            let elapsed = block.saturating_sub(betting_match.start);
+            if elapsed == 0 {
+                return FULL_BET_WEIGHT_BPS;
+            }
+            let window = Balance::from(betting_match.live_cutoff.unwrap_or(0)).saturating_add(1);
+            let remaining = window.saturating_sub(Balance::from(elapsed));
+            Self::mul_div(Balance::from(FULL_BET_WEIGHT_BPS), remaining, window)
+                .and_then(|weight| u32::try_from(weight).ok())
+                .unwrap_or(FULL_BET_WEIGHT_BPS)
+                .max(1)
+        }
+
+        /// Opens a match for `creator`, funded with `deposit`.
+        #[allow(clippy::too_many_arguments)]
+        fn create_match(
+            &mut self,
+            creator: AccountId,
+            deposit: Balance,
+            team1: Vec<u8>,
+            team2: Vec<u8>,
+            start: BlockNumber,
+            length: BlockNumber,
+            market: Market,
+            allow_draw: bool,
+            allowed_bettors: Option<Vec<AccountId>>,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(creator)?;
+            // Check account has no open match
+            if self.exists_match(creator) {
+                return Err(Error::OriginHasAlreadyOpenMatch);
+            }
+            // Check if start and length are valid
+            let current_block_number = self.env().block_number();
+            let end = start.checked_add(length).ok_or(Error::ArithmeticOverflow)?;
+            if current_block_number > end {
+                return Err(Error::TimeMatchOver);
+            }
+            let config = self.get_config();
+            let earliest_start = current_block_number
+                .checked_add(config.min_lead_time)
+                .ok_or(Error::ArithmeticOverflow)?;
+            if start <= earliest_start {
+                return Err(Error::MatchStartTooSoon);
+            }
+            if length < config.min_match_length || length > config.max_match_length {
+                return Err(Error::InvalidMatchLength);
+            }
+            // Check the deposit.
+            // Assert or Error?
+            let min_deposit = self
+                .get_min_deposit_for(creator)?
+                .checked_add(Self::market_subsidy(market)?)
+                .ok_or(Error::ArithmeticOverflow)?;
+            if deposit < min_deposit {
+                return Err(Error::NotEnoughDeposit);
+            }
+            // Create the betting match
+            let betting_match = Match {
+                start,
+                length,
+                betting_cutoff: 0,
+                live_cutoff: None,
+                result_delay: 0,
+                outcome_labels: OutcomeId::one_x_two(&team1, &team2, allow_draw),
+                team1,
+                team2,
+                result: None,
+                evidence: None,
+                status: MatchStatus::Upcoming,
+                result_block: 0,
+                final_block: 0,
+                settled_block: 0,
+                proposed_result: None,
+                fixture_id: None,
+                sport: None,
+                team_ids: None,
+                market,
+                allow_draw,
+                allowed_bettors,
+                score: None,
+                live_score: None,
+                suspension_blocks: 0,
+                suspended_until: None,
+                bets: Default::default(),
+                collected: 0,
+                backed: 0,
+                pot_bonus: 0,
+                boost_bps: 0,
+                boost_budget: 0,
+                fee_bps: None,
+                uniform_price: false,
+                sponsors: Vec::new(),
+                jackpot: 0,
+                score_predictions: Vec::new(),
+                next_bet_id: 0,
+                max_bets: None,
+                outcome_caps: Vec::new(),
+                deposit,
+            };
+            // Hash the match object.
+            let match_hash = Self::get_match_hash(&betting_match);
+            // Check if match already exists by checking its specs hash.
+            if self.matches_hashes.contains(match_hash) {
+                return Err(Error::MatchAlreadyExists);
+            }
+            // Store the match hash with its creator account.
+            self.matches_hashes.insert(match_hash, &creator);
+            // Store the betting match in the list of open matches
+            self.matches.insert(creator, &betting_match);
+            self.escrow_in(creator, deposit)?;
+            self.stats.matches_created = self.stats.matches_created.saturating_add(1);
+            self.open_matches = self.open_matches.saturating_add(1);
+            self.update_reputation(creator, |reputation| {
+                reputation.matches_created = reputation.matches_created.saturating_add(1)
+            });
+            // Emit an event.
+            self.env().emit_event(MatchCreated {
+                who: creator,
+                team1: betting_match.team1,
+                team2: betting_match.team2,
+                start,
+                length,
+            });
+
+            Ok(())
+        }
+
+        // payable accepts a payment (amount_to_bet).
+        // The free-bet credits of the caller are bet with the transferred value, and are
+        // used first if the stake is not filled in full.
+        #[ink(message, payable, selector = 0x610E57B8)]
+        pub fn bet(&mut self, match_id: MatchId, result: MatchResult) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let transferred = Self::env().transferred_value();
+            self.ensure_solvent(transferred)?;
+            let free_bet = self.get_free_bet(caller);
+            let amount = transferred
+                .checked_add(free_bet)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let filled = self.place_bet(caller, match_id, result, amount, free_bet, None, false)?;
+            let credit = free_bet.min(filled);
+            if credit > 0 {
+                self.free_bets
+                    .insert(caller, &free_bet.saturating_sub(credit));
+                // The total is at least any credit
+                self.total_free_bets = self.total_free_bets.saturating_sub(credit);
+            }
+            // The transferred value not filled is returned
+            let refund = transferred.saturating_sub(filled.saturating_sub(credit));
+            if refund > 0 {
+                self.env()
+                    .transfer(caller, refund)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            Ok(())
+        }
+
+        /// Place a bet on behalf of `beneficiary`, e.g. for custody or as a gift. The
+        /// transferred value funds the bet, which belongs to the beneficiary and pays them.
+        #[ink(message, payable, selector = 0xF3DE9010)]
+        pub fn bet_for(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            beneficiary: AccountId,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let amount = Self::env().transferred_value();
+            self.ensure_solvent(amount)?;
+            let filled = self.place_bet(
+                beneficiary,
+                match_id,
+                result,
+                amount,
+                0,
+                Some(caller),
+                false,
+            )?;
+            self.refund_unfilled(caller, amount, filled)
+        }
+
+        /// Place bets on several matches in one call, each with its own amount.
+        /// The transferred value must be the sum of the amounts. If any bet fails, none is placed.
+        #[ink(message, payable, selector = 0xE5F8C8E5)]
+        pub fn bet_many(
+            &mut self,
+            bets: Vec<(MatchId, MatchResult, Balance)>,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if bets.is_empty() || bets.len() > MAX_BATCH_BETS {
+                return Err(Error::InvalidBatch);
+            }
+            let total = Self::checked_sum(bets.iter().map(|(_, _, amount)| *amount))?;
+            if total != Self::env().transferred_value() {
+                return Err(Error::InvalidBatch);
+            }
+            self.ensure_solvent(total)?;
+            let mut filled: Balance = 0;
+            for (match_id, result, amount) in bets {
+                filled = filled.saturating_add(
+                    self.place_bet(caller, match_id, result, amount, 0, None, false)?,
+                );
+            }
+            self.refund_unfilled(caller, total, filled)
+        }
+
+        /// Place a bet of `amount` funded from the internal balance of the caller.
+        #[ink(message, selector = 0xA7AA8610)]
+        pub fn bet_from_balance(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.place_bet_from_balance(caller, match_id, result, amount, None)?;
+            Ok(())
+        }
+
+        /// Let `delegate` place bets funded from the internal balance of the caller, up to
+        /// `cap` in total. A cap of zero revokes the delegate.
+        #[ink(message, selector = 0xAC60C632)]
+        pub fn approve_delegate(&mut self, delegate: AccountId, cap: Balance) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if cap == 0 {
+                self.allowances.remove((caller, delegate));
+            } else {
+                self.allowances.insert((caller, delegate), &cap);
+            }
+            self.env().emit_event(DelegateApproved {
+                principal: caller,
+                delegate,
+                cap,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xB3BAC9BB)]
+        pub fn get_allowance(&self, principal: AccountId, delegate: AccountId) -> Balance {
+            self.allowances.get((principal, delegate)).unwrap_or(0)
+        }
+
+        /// Place a bet for `principal` funded from their internal balance, within the
+        /// allowance they approved for the caller. The bet belongs to the principal.
+        #[ink(message, selector = 0x16F589C9)]
+        pub fn bet_as_delegate(
+            &mut self,
+            principal: AccountId,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let allowance = self.get_allowance(principal, caller);
+            if amount > allowance {
+                return Err(Error::AllowanceExceeded);
+            }
+            let filled =
+                self.place_bet_from_balance(principal, match_id, result, amount, Some(caller))?;
+            self.allowances
+                .insert((principal, caller), &allowance.saturating_sub(filled));
+            Ok(())
+        }
+
+        /// Place a bet signed off-chain by the bettor, funded from their internal balance,
+        /// so anyone can relay it and pay the fees. The signed payload is `bet_payload_hash`
+        /// and the bettor is the account of the ECDSA key (the hash of the compressed key).
+        #[ink(message, selector = 0x299C49AE)]
+        pub fn bet_with_signature(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+            nonce: u64,
+            signature: [u8; 65],
+        ) -> Result<(), Error> {
+            let message_hash = self.signed_bet_hash(match_id, result, amount, nonce);
+            let mut bettor_key = [0u8; 33];
+            ink::env::ecdsa_recover(&signature, &message_hash, &mut bettor_key)
+                .map_err(|_| Error::InvalidSignature)?;
+            let mut bettor = [0u8; 32];
+            ink::env::hash_bytes::<ink::env::hash::Blake2x256>(&bettor_key, &mut bettor);
+            let bettor = AccountId::from(bettor);
+            if nonce != self.get_bet_nonce(bettor) {
+                return Err(Error::InvalidNonce);
+            }
+            self.place_bet_from_balance(bettor, match_id, result, amount, None)?;
+            self.bet_nonces.insert(
+                bettor,
+                &nonce.checked_add(1).ok_or(Error::ArithmeticOverflow)?,
+            );
+            Ok(())
+        }
+
+        /// Hash a bettor has to sign to place a bet with `bet_with_signature`.
+        #[ink(message, selector = 0x8D778D19)]
+        pub fn bet_payload_hash(
+            &self,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+            nonce: u64,
+        ) -> [u8; 32] {
+            self.signed_bet_hash(match_id, result, amount, nonce)
+        }
+
+        /// Nonce of the next signed bet of an account.
+        #[ink(message, selector = 0xC049A25E)]
+        pub fn get_bet_nonce(&self, account: AccountId) -> u64 {
+            self.bet_nonces.get(account).unwrap_or(0)
+        }
+
+        /// The payload covers the contract so a signed bet can't be replayed on another one.
+        fn signed_bet_hash(
+            &self,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+            nonce: u64,
+        ) -> [u8; 32] {
+            let payload = (self.env().account_id(), match_id, result, amount, nonce);
+            let mut message =
+                <ink::env::hash::Blake2x256 as ink::env::hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(&payload, &mut message);
+            message
+        }
+
+        /// Lock the transferred value as a sealed bet on a hidden outcome, revealed with
+        /// `reveal_bet` before the match starts. Picks stay hidden from copy-traders and
+        /// front-runners until then. The stake counts against the personal limit and the
+        /// rate limit when committed. A stake not revealed in time goes to the treasury
+        /// at settlement, unless the match is voided, see `refund_sealed_bet`.
+        #[ink(message, payable, selector = 0x24FD4AF0)]
+        pub fn commit_bet(&mut self, match_id: MatchId, commitment: [u8; 32]) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let amount = Self::env().transferred_value();
+            let config = self.get_config();
+            Self::check_bet_amount(&config, amount)?;
+            let match_to_bet = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let current_block_number = self.env().block_number();
+            if current_block_number > Self::betting_closes(&match_to_bet) {
+                return Err(Error::MatchHasStarted);
+            }
+            if match_to_bet
+                .suspended_until
+                .is_some_and(|until| current_block_number <= until)
+            {
+                return Err(Error::MarketSuspended);
+            }
+            if matches!(match_to_bet.market, Market::Lmsr(_)) {
+                return Err(Error::WrongMarket);
+            }
+            if !Self::is_allowed_bettor(&match_to_bet, &caller) {
+                return Err(Error::NotAllowedBettor);
+            }
+            if self.is_full(&match_to_bet) {
+                return Err(Error::MatchFull);
+            }
+            let match_hash = Self::get_match_hash(&match_to_bet);
+            // One sealed bet per match, a stale one of a previous match is replaced unless
+            // it is still to be refunded
+            if self
+                .sealed_bets
+                .get((match_id, caller))
+                .is_some_and(|sealed_bet| {
+                    sealed_bet.match_hash == match_hash
+                        || self.is_voided_hash(sealed_bet.match_hash)
+                })
+            {
+                return Err(Error::AlreadyBet);
+            }
+            // The reveal places the bet without counting it again
+            self.record_stake(caller, amount)?;
+            self.record_bet_rate(caller)?;
+            self.escrow_in(match_id, amount)?;
+            let sealed_stake = self.sealed_stakes.get(match_id).unwrap_or(0);
+            self.sealed_stakes.insert(
+                match_id,
+                &sealed_stake
+                    .checked_add(amount)
+                    .ok_or(Error::ArithmeticOverflow)?,
+            );
+            self.sealed_bets.insert(
+                (match_id, caller),
+                &SealedBet {
+                    commitment,
+                    amount,
+                    match_hash,
+                },
+            );
+            self.env().emit_event(BetCommitted {
+                match_id,
+                who: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Reveal the outcome of a sealed bet before the match starts, placing the bet.
+        #[ink(message, selector = 0x4B9B2DF4)]
+        pub fn reveal_bet(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            salt: [u8; 32],
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let sealed_bet = match self.sealed_bets.get((match_id, caller)) {
+                Some(sealed_bet) => sealed_bet,
+                None => return Err(Error::NoSealedBet),
+            };
+            let match_to_bet = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if sealed_bet.match_hash != Self::get_match_hash(&match_to_bet) {
+                return Err(Error::NoSealedBet);
+            }
+            if self.sealed_bet_hash(caller, result, salt) != sealed_bet.commitment {
+                return Err(Error::InvalidReveal);
+            }
+            // The stake already is in the escrow of the match
+            let filled =
+                self.place_bet(caller, match_id, result, sealed_bet.amount, 0, None, true)?;
+            self.escrow_out(match_id, sealed_bet.amount)?;
+            self.sealed_bets.remove((match_id, caller));
+            self.release_sealed_stake(match_id, sealed_bet.amount);
+            self.refund_unfilled(caller, sealed_bet.amount, filled)
+        }
+
+        /// Refund a sealed bet of a voided match, revealed or not in time.
+        #[ink(message, selector = 0x9D74C7D5)]
+        pub fn refund_sealed_bet(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let sealed_bet = match self.sealed_bets.get((match_id, caller)) {
+                Some(sealed_bet) => sealed_bet,
+                None => return Err(Error::NoSealedBet),
+            };
+            if !self.is_voided_hash(sealed_bet.match_hash) {
+                return Err(Error::RefundNotOpen);
+            }
+            self.sealed_bets.remove((match_id, caller));
+            let unsettled = self.matches.get(match_id).is_some_and(|betting_match| {
+                Self::get_match_hash(&betting_match) == sealed_bet.match_hash
+            });
+            if unsettled {
+                self.escrow_out(match_id, sealed_bet.amount)?;
+                self.release_sealed_stake(match_id, sealed_bet.amount);
+            } else {
+                // The settlement left the stake in the total escrow
+                self.total_escrow = self.total_escrow.saturating_sub(sealed_bet.amount);
+            }
+            self.pay_or_defer(caller, sealed_bet.amount)?;
+            self.env().emit_event(BetRefunded {
+                match_id,
+                who: caller,
+                amount: sealed_bet.amount,
+            });
+            Ok(())
+        }
+
+        /// Whether the match recorded under `match_hash` was voided.
+        fn is_voided_hash(&self, match_hash: [u8; 32]) -> bool {
+            self.results
+                .get(match_hash)
+                .is_some_and(|result_odds| result_odds.result == MatchResult::Voided)
+        }
+
+        /// Removes a stake revealed or refunded from the sealed stakes of a match.
+        fn release_sealed_stake(&mut self, match_id: MatchId, amount: Balance) {
+            let sealed_stake = self.sealed_stakes.get(match_id).unwrap_or(0);
+            self.sealed_stakes
+                .insert(match_id, &sealed_stake.saturating_sub(amount));
+        }
+
+        /// Commitment of a sealed bet. It covers the bettor so nobody else can commit
+        /// the same hash and reveal it after them.
+        #[ink(message, selector = 0xEFBCB8D5)]
+        pub fn sealed_bet_hash(
+            &self,
+            bettor: AccountId,
+            result: MatchResult,
+            salt: [u8; 32],
+        ) -> [u8; 32] {
+            let mut commitment = [0u8; 32];
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(
+                &(bettor, result, salt),
+                &mut commitment,
+            );
+            commitment
+        }
+
+        #[ink(message, selector = 0x6B2415D9)]
+        pub fn get_sealed_bet(&self, match_id: MatchId, bettor: AccountId) -> Option<SealedBet> {
+            self.sealed_bets.get((match_id, bettor))
+        }
+
+        /// Places a bet of `amount` for `account`, funded from its internal balance.
+        /// Returns the amount filled, only that is debited.
+        fn place_bet_from_balance(
+            &mut self,
+            account: AccountId,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+            placed_by: Option<AccountId>,
+        ) -> Result<Balance, Error> {
+            let balance = self.balances.get(account).unwrap_or(0);
+            if amount > balance {
+                return Err(Error::NotEnoughBalance);
+            }
+            // The stake moves from the internal balance to the escrow
+            self.ensure_solvent(0)?;
+            let filled = self.place_bet(account, match_id, result, amount, 0, placed_by, false)?;
+            self.debit_balance(account, balance, filled);
+            Ok(filled)
+        }
+
+        /// Fund the internal balance of the caller, to bet from it without a transfer per bet.
+        #[ink(message, payable, selector = 0x2D10C9BD)]
+        pub fn deposit(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let amount = Self::env().transferred_value();
+            let balance = self.balances.get(caller).unwrap_or(0);
+            let new_balance = balance
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_balances = self
+                .total_balances
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.balances.insert(caller, &new_balance);
+            self.env().emit_event(Deposited {
+                who: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Withdraw funds from the internal balance of the caller.
+        #[ink(message, selector = 0x410FCC9D)]
+        pub fn withdraw(&mut self, amount: Balance) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let balance = self.balances.get(caller).unwrap_or(0);
+            if amount > balance {
+                return Err(Error::NotEnoughBalance);
+            }
+            self.debit_balance(caller, balance, amount);
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(Withdrawn {
+                who: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Takes `amount`, checked to be at most `balance`, from the internal balance of `account`.
+        fn debit_balance(&mut self, account: AccountId, balance: Balance, amount: Balance) {
+            self.balances
+                .insert(account, &balance.saturating_sub(amount));
+            // The total is at least any balance
+            self.total_balances = self.total_balances.saturating_sub(amount);
+        }
+
+        #[ink(message, selector = 0xEA817E65)]
+        pub fn get_balance(&self, account: AccountId) -> Balance {
+            self.balances.get(account).unwrap_or(0)
+        }
+
+        /// Set the maximum number of bets on any match.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x6B519FC0)]
+        pub fn set_max_bets_per_match(&mut self, max_bets: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_max_bets_per_match"), &max_bets);
+            self.max_bets_per_match = max_bets;
+            Ok(())
+        }
+
+        /// Set the maximum number of bets on a match, below the global cap.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xEC54D3E4)]
+        pub fn set_match_max_bets(
+            &mut self,
+            match_id: MatchId,
+            max_bets: Option<u32>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("set_match_max_bets"),
+                &(match_id, max_bets),
+            );
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            betting_match.max_bets = max_bets;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Cap the total stake on an outcome of a match, or remove the cap. Bets that would
+        /// take the outcome above its cap are rejected. Only the creator can change it.
+        #[ink(message, selector = 0x0C594EB7)]
+        pub fn set_outcome_cap(
+            &mut self,
+            match_id: MatchId,
+            outcome: MatchResult,
+            cap: Option<Balance>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            // LMSR markets price their shares instead
+            if matches!(betting_match.market, Market::Lmsr(_)) {
+                return Err(Error::WrongMarket);
+            }
+            if !Self::is_outcome(&betting_match, outcome) {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            // The outcome is labelled, so it has an index
+            let index = outcome.index().unwrap_or_default();
+            if betting_match.outcome_caps.len() <= index {
+                betting_match
+                    .outcome_caps
+                    .resize(index.saturating_add(1), None);
+            }
+            betting_match.outcome_caps[index] = cap;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Most that can be staked on an outcome of a match, `None` if there is no cap.
+        #[ink(message, selector = 0x34D6F1D0)]
+        pub fn get_outcome_cap(&self, match_id: MatchId, outcome: MatchResult) -> Option<Balance> {
+            let betting_match = self.matches.get(match_id)?;
+            betting_match
+                .outcome_caps
+                .get(outcome.index()?)
+                .copied()
+                .flatten()
+        }
+
+        /// Add an account to the allowlist of a private match.
+        /// The dispatch origin for this call must be the creator of the match.
+        #[ink(message, selector = 0xCB374E17)]
+        pub fn add_allowed_bettor(
+            &mut self,
+            match_id: MatchId,
+            bettor: AccountId,
+        ) -> Result<(), Error> {
+            self.update_allowed_bettors(match_id, |allowed_bettors| {
+                if !allowed_bettors.contains(&bettor) {
+                    allowed_bettors.push(bettor);
+                }
+            })
+        }
+
+        /// Remove an account from the allowlist of a private match. Its bets stay.
+        /// The dispatch origin for this call must be the creator of the match.
+        #[ink(message, selector = 0x946D1FF3)]
+        pub fn remove_allowed_bettor(
+            &mut self,
+            match_id: MatchId,
+            bettor: AccountId,
+        ) -> Result<(), Error> {
+            self.update_allowed_bettors(match_id, |allowed_bettors| {
+                allowed_bettors.retain(|allowed| *allowed != bettor)
+            })
+        }
+
+        /// Applies `update` to the allowlist of a private match after checking the
+        /// caller is its creator.
+        fn update_allowed_bettors(
+            &mut self,
+            match_id: MatchId,
+            update: impl FnOnce(&mut Vec<AccountId>),
+        ) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            match betting_match.allowed_bettors.as_mut() {
+                Some(allowed_bettors) => update(allowed_bettors),
+                None => return Err(Error::MatchNotPrivate),
+            }
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Whether `account` can bet on a match.
+        fn is_allowed_bettor(betting_match: &Match, account: &AccountId) -> bool {
+            betting_match
+                .allowed_bettors
+                .as_ref()
+                .is_none_or(|allowed_bettors| allowed_bettors.contains(account))
+        }
+
+        /// Whether a match has reached its maximum number of bets.
+        fn is_full(&self, betting_match: &Match) -> bool {
+            betting_match.bets.len() >= self.max_bets_of(betting_match)
+        }
+
+        fn max_bets_of(&self, betting_match: &Match) -> usize {
+            betting_match
+                .max_bets
+                .map_or(self.max_bets_per_match, |max_bets| {
+                    max_bets.min(self.max_bets_per_match)
+                }) as usize
+        }
+
+        /// Places a bet of `amount` for `caller`, already paid by them, `credit` of it
+        /// with free-bet credits. A stake above the cap of the outcome or of the liability
+        /// is filled up to the cap, returns the amount filled for the caller to refund
+        /// the rest. A `committed` stake already counted against the limits when sealed.
+        #[allow(clippy::too_many_arguments)]
+        fn place_bet(
+            &mut self,
+            caller: AccountId,
+            match_id: MatchId,
+            result: MatchResult,
+            amount: Balance,
+            credit: Balance,
+            placed_by: Option<AccountId>,
+            committed: bool,
+        ) -> Result<Balance, Error> {
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let config = self.get_config();
+            Self::check_bet_amount(&config, amount)?;
+            // Find the match that user wants to place the bet
+            let mut match_to_bet = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+
+            // Check if the Match Has Started (can't bet in a started match, unless live)
+            let current_block_number = self.env().block_number();
+            if current_block_number > Self::bets_close(&match_to_bet) {
+                return Err(Error::MatchHasStarted);
+            }
+            if match_to_bet
+                .suspended_until
+                .is_some_and(|until| current_block_number <= until)
+            {
+                return Err(Error::MarketSuspended);
+            }
+            // LMSR markets only sell shares
+            if matches!(match_to_bet.market, Market::Lmsr(_)) {
+                return Err(Error::WrongMarket);
+            }
+            if !Self::is_allowed_bettor(&match_to_bet, &caller) {
+                return Err(Error::NotAllowedBettor);
+            }
+            if !Self::is_outcome(&match_to_bet, result) {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            // Handicap and draw no bet markets have no draw
+            if result == MatchResult::Draw
+                && matches!(match_to_bet.market, Market::Handicap(_) | Market::DrawNoBet)
+            {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            if self.is_full(&match_to_bet) {
+                return Err(Error::MatchFull);
+            }
+            let requested = amount;
+            let cap = result
+                .index()
+                .and_then(|index| match_to_bet.outcome_caps.get(index).copied().flatten());
+            let amount = match cap {
+                Some(cap) => {
+                    let staked = Self::checked_sum(
+                        match_to_bet
+                            .bets
+                            .iter()
+                            .filter(|bet| bet.result == result)
+                            .map(|bet| bet.amount),
+                    )?;
+                    amount.min(cap.saturating_sub(staked))
+                }
+                None => amount,
+            };
+            if amount == 0 || amount < config.min_bet {
+                return Err(Error::OutcomeCapExceeded);
+            }
+            let max_exposure =
+                Self::bps_of(self.bankroll_value()?, Balance::from(self.max_exposure_bps))?;
+            let amount = Self::fixed_odds_fillable(
+                &match_to_bet,
+                result,
+                amount,
+                match_to_bet.deposit.saturating_add(max_exposure),
+            )?;
+            if amount == 0 || amount < config.min_bet {
+                return Err(Error::ExposureExceeded);
+            }
+            let credit = credit.min(amount);
+            // The creator deposit takes the first losses, the bankroll locks the increase
+            // of the rest of the liability, or is released the decrease
+            let backed = Self::fixed_odds_liability(&match_to_bet, result, amount)?
+                .saturating_sub(match_to_bet.deposit);
+            let backing = backed.saturating_sub(match_to_bet.backed);
+            let released = match_to_bet.backed.saturating_sub(backed);
+            if backing > self.bankroll {
+                return Err(Error::NotEnoughBankroll);
+            }
+            // Check if the bet already exists
+            if match_to_bet
+                .bets
+                .iter()
+                .any(|bet| bet.bettor == caller && bet.amount == amount && bet.result == result)
+            {
+                return Err(Error::AlreadyBet);
+            } else {
+                if !committed {
+                    self.record_stake(caller, amount)?;
+                    self.record_bet_rate(placed_by.unwrap_or(caller))?;
+                }
+                // Create the bet to be placed
+                let bet_id = match_to_bet.next_bet_id;
+                match_to_bet.next_bet_id =
+                    bet_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+                match_to_bet.bets.push(Bet {
+                    id: bet_id,
+                    bettor: caller,
+                    amount,
+                    result,
+                    credit,
+                    placed_by,
+                    weight_bps: Self::bet_weight(&match_to_bet, current_block_number),
+                });
+                self.stats.bets = self.stats.bets.saturating_add(1);
+                self.stats.volume = self.stats.volume.saturating_add(amount);
+                // The escrow of a fixed-odds match holds the liability of the bankroll
+                self.bankroll = self
+                    .bankroll
+                    .saturating_sub(backing)
+                    .checked_add(released)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                self.bankroll_locked = self
+                    .bankroll_locked
+                    .saturating_sub(released)
+                    .checked_add(backing)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                match_to_bet.backed = backed;
+                // Store the betting match in the list of open matches
+                self.escrow_in(
+                    match_id,
+                    amount
+                        .checked_add(backing)
+                        .ok_or(Error::ArithmeticOverflow)?,
+                )?;
+                self.escrow_out(match_id, released)?;
+                self.matches.insert(match_id, &match_to_bet);
+                // Emit an event.
+                self.env().emit_event(BetPlaced {
+                    match_id,
+                    bet_id,
+                    who: caller,
+                    amount,
+                    result,
+                });
+                if amount < requested {
+                    self.env().emit_event(BetPartiallyFilled {
+                        match_id,
+                        bet_id,
+                        who: caller,
+                        filled: amount,
+                        refunded: requested.saturating_sub(amount),
+                    });
+                }
+            }
+            Ok(amount)
+        }
+
+        /// Rejects stakes of nothing, and dust below the minimum bet that would only
+        /// bloat the storage and the payouts, or above the maximum bet.
+        fn check_bet_amount(config: &Config, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Err(Error::ZeroBetAmount);
+            }
+            if amount < config.min_bet {
+                return Err(Error::BetTooSmall);
+            }
+            if amount > config.max_bet {
+                return Err(Error::InvalidBetAmount);
+            }
+            Ok(())
+        }
+
+        /// Returns to `payer` what was paid for bets beyond the amount filled.
+        fn refund_unfilled(
+            &mut self,
+            payer: AccountId,
+            paid: Balance,
+            filled: Balance,
+        ) -> Result<(), Error> {
+            let refund = paid.saturating_sub(filled);
+            if refund > 0 {
+                self.env()
+                    .transfer(payer, refund)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            Ok(())
+        }
+
+        /// Transfer a bet to a new holder before the match has a result, e.g. to sell the
+        /// position. Settlement pays the holder of the bet at that time. The new holder
+        /// must be allowed to bet on the match.
+        #[ink(message, selector = 0x699D0EDB)]
+        pub fn transfer_bet(
+            &mut self,
+            match_id: MatchId,
+            bet_id: BetId,
+            new_owner: AccountId,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(new_owner)?;
+            self.ensure_not_self_excluded(new_owner)?;
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.settlements.contains(match_id) {
+                return Err(Error::SettlementInProgress);
+            }
+            if betting_match.result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            if !Self::is_allowed_bettor(&betting_match, &new_owner) {
+                return Err(Error::NotAllowedBettor);
+            }
+            let bet = match betting_match.bets.iter_mut().find(|bet| bet.id == bet_id) {
+                Some(bet) => bet,
+                None => return Err(Error::BetDoesNotExist),
+            };
+            if bet.bettor != caller {
+                return Err(Error::NotBettor);
+            }
+            bet.bettor = new_owner;
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(BetTransferred {
+                match_id,
+                bet_id,
+                from: caller,
+                to: new_owner,
+            });
+
+            Ok(())
+        }
+
+        /// Exit a bet before the match starts, selling it back to the pool for its value at
+        /// the current implied odds minus a fee. The part of the stake not paid back stays
+        /// in the pot for the winners.
+        #[ink(message, selector = 0xE03BB8F6)]
+        pub fn cashout(&mut self, match_id: MatchId, bet_id: BetId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            if betting_match.market != Market::Winner {
+                return Err(Error::WrongMarket);
+            }
+            let index = match betting_match.bets.iter().position(|bet| bet.id == bet_id) {
+                Some(index) => index,
+                None => return Err(Error::BetDoesNotExist),
+            };
+            if betting_match.bets[index].bettor != caller {
+                return Err(Error::NotBettor);
+            }
+            let value = Self::cashout_value(&betting_match, &betting_match.bets[index])?;
+            let Bet {
+                amount: stake,
+                credit,
+                ..
+            } = betting_match.bets.remove(index);
+            // The value of a bet is at most its stake
+            betting_match.pot_bonus = betting_match
+                .pot_bonus
+                .checked_add(stake.saturating_sub(value))
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.escrow_out(match_id, value)?;
+            let fee = Self::bps_of(value, CASHOUT_FEE_BPS)?;
+            let amount = value.saturating_sub(fee);
+            let total_pool = Self::total_pool(&betting_match)?;
+            self.collect_fee(caller, fee, Some((match_id, total_pool)))?;
+            // Free-bet credits are not paid out
+            let withheld = credit.min(amount);
+            self.treasury = self
+                .treasury
+                .checked_add(withheld)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let amount = amount.saturating_sub(withheld);
+            self.matches.insert(match_id, &betting_match);
+            self.stats.paid_out = self.stats.paid_out.saturating_add(amount);
+            self.env()
+                .transfer(self.get_payout_address(caller), amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(Cashout {
+                match_id,
+                bet_id,
+                who: caller,
+                amount,
+                fee,
+            });
+
+            Ok(())
+        }
+
+        /// Value of a bet before fees: what it would win at the current implied odds, times
+        /// the probability the rest of the pool implies for its result.
+        fn cashout_value(betting_match: &Match, bet: &Bet) -> Result<Balance, Error> {
+            let total_pool = Self::total_pool(betting_match)?;
+            let result_pool = Self::checked_sum(
+                betting_match
+                    .bets
+                    .iter()
+                    .filter(|other_bet| other_bet.result == bet.result)
+                    .map(|other_bet| other_bet.amount),
+            )?;
+            // The only bet of the pool is worth its stake
+            if total_pool == bet.amount {
+                return Ok(bet.amount);
+            }
+            // The rest of the pool implies no chance for a result only the bet backs
+            if result_pool == bet.amount {
+                return Err(Error::NoCashoutLiquidity);
+            }
+            // The bet is part of both pools
+            let amount_if_won = bet
+                .amount
+                .checked_mul(total_pool)
+                .and_then(|amount| amount.checked_div(result_pool))
+                .ok_or(Error::ArithmeticOverflow)?;
+            let probability_ppm = result_pool
+                .saturating_sub(bet.amount)
+                .checked_mul(1_000_000)
+                .and_then(|ppm| ppm.checked_div(total_pool.saturating_sub(bet.amount)))
+                .ok_or(Error::ArithmeticOverflow)?;
+            amount_if_won
+                .checked_mul(probability_ppm)
+                .map(|value| value / 1_000_000)
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// `bps` basis points of `amount`, failing on overflow.
+        fn bps_of(amount: Balance, bps: Balance) -> Result<Balance, Error> {
+            amount
+                .checked_mul(bps)
+                .map(|part| part / 10_000)
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Set the result of an existing match, with the hash or URI of the data source
+        /// it was set from as evidence, if any. A result proposed by the creator is
+        /// confirmed or rejected instead.
+        /// The dispatch origin for this call must be the owner.
+        /// Get root of the node?? like ensure_root(origin)?;
+        #[ink(message, selector = 0xFCB1EF09)]
+        pub fn set_result(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            evidence: Option<Vec<u8>>,
+        ) -> Result<(), Error> {
+            let match_to_set_result = self.match_to_set_directly(match_id)?;
+            self.apply_result(match_id, match_to_set_result, result, evidence)
+        }
+
+        /// Set the result of an existing match with a payload signed by a registered
+        /// oracle key, so anyone can relay it. The signed payload is `result_payload_hash`.
+        #[ink(message, selector = 0x9978789A)]
+        pub fn submit_signed_result(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            signature: [u8; 65],
+        ) -> Result<(), Error> {
+            let match_to_set_result = self.ended_match(match_id)?;
+            let message_hash = self.signed_result_hash(&match_to_set_result, result);
+            let mut oracle_key = [0u8; 33];
+            ink::env::ecdsa_recover(&signature, &message_hash, &mut oracle_key)
+                .map_err(|_| Error::InvalidSignature)?;
+            if !self.oracle_keys.contains(oracle_key) {
+                return Err(Error::InvalidSignature);
+            }
+            self.apply_result(match_id, match_to_set_result, result, None)
+        }
+
+        /// Hash an oracle has to sign to submit the result of a match.
+        #[ink(message, selector = 0xDB96B514)]
+        pub fn result_payload_hash(
+            &self,
+            match_id: MatchId,
+            result: MatchResult,
+        ) -> Result<[u8; 32], Error> {
+            match self.matches.get(match_id) {
+                Some(betting_match) => Ok(self.signed_result_hash(&betting_match, result)),
+                None => Err(Error::MatchDoesNotExist),
+            }
+        }
+
+        /// Register an ECDSA oracle key (compressed) allowed to sign results.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x7A84BACB)]
+        pub fn add_oracle_key(&mut self, oracle_key: [u8; 33]) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("add_oracle_key"), &oracle_key);
+            self.oracle_keys.insert(oracle_key, &());
+            Ok(())
+        }
+
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xA59ED916)]
+        pub fn remove_oracle_key(&mut self, oracle_key: [u8; 33]) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("remove_oracle_key"), &oracle_key);
+            self.oracle_keys.remove(oracle_key);
+            Ok(())
+        }
+
+        /// Pull the result of a match over from the result oracle contract, using the
+        /// fixture linked to the match. Anyone can call it.
+        #[ink(message, selector = 0x3F99824E)]
+        pub fn pull_result(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let match_to_set_result = self.ended_match(match_id)?;
+            let oracle_address = match self.result_oracle {
+                Some(oracle_address) => oracle_address,
+                None => return Err(Error::NoResultOracle),
+            };
+            let fixture_id = match match_to_set_result.fixture_id {
+                Some(fixture_id) => fixture_id,
+                None => return Err(Error::NoFixture),
+            };
+            let mut oracle: ink::contract_ref!(ResultOracle) = oracle_address.into();
+            let result = match oracle
+                .call_mut()
+                .get_result(fixture_id)
+                .call_flags(NO_REENTRY)
+                .try_invoke()
+            {
+                Ok(Ok(Some(result))) => result,
+                Ok(Ok(None)) => return Err(Error::MatchNotResult),
+                _ => return Err(Error::OracleCallFailed),
+            };
+            self.apply_result(match_id, match_to_set_result, result, None)
+        }
+
+        /// Register the oracle contract results can be pulled from, or none.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x484BFAA1)]
+        pub fn set_result_oracle(&mut self, oracle: Option<AccountId>) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_result_oracle"), &oracle);
+            self.apply_action_now(AdminAction::SetResultOracle(oracle))
+        }
+
+        /// Set how many blocks before the start of their match betting closes, and how
+        /// many blocks after its end the result has to wait. Both are zero by default.
+        /// Only the creator can change them, while betting is open.
+        #[ink(message, selector = 0x3A4B8E15)]
+        pub fn set_match_timing(
+            &mut self,
+            match_id: MatchId,
+            betting_cutoff: BlockNumber,
+            result_delay: BlockNumber,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            betting_match.betting_cutoff = betting_cutoff;
+            betting_match.result_delay = result_delay;
+            // The result can only be set after the end
+            Self::result_opens(&betting_match)?;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Keep single bets open for `live_cutoff` blocks after the start of a pari-mutuel
+        /// match, or close them before the start with `None`. Live bets share the lost
+        /// stakes with a weight decaying with the blocks elapsed since the start, so late
+        /// bets earn less. Only the creator can change it, while betting is open.
+        #[ink(message, selector = 0xC9DF72CB)]
+        pub fn set_live_betting(
+            &mut self,
+            match_id: MatchId,
+            live_cutoff: Option<BlockNumber>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            // Odds and prices are set before the bets, only pools can weigh them
+            if !matches!(betting_match.market, Market::Winner | Market::DrawNoBet) {
+                return Err(Error::WrongMarket);
+            }
+            if live_cutoff.is_some_and(|live_cutoff| live_cutoff >= betting_match.length) {
+                return Err(Error::InvalidLiveCutoff);
+            }
+            betting_match.live_cutoff = live_cutoff;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Blocks after the start until which single bets stay open, `None` if the match
+        /// has no live betting.
+        #[ink(message, selector = 0xE8EB17B6)]
+        pub fn get_live_cutoff(&self, match_id: MatchId) -> Option<BlockNumber> {
+            self.matches.get(match_id)?.live_cutoff
+        }
+
+        /// Link a match to its fixture in the result oracle.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x44B46A58)]
+        pub fn set_fixture_id(
+            &mut self,
+            match_id: MatchId,
+            fixture_id: FixtureId,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("set_fixture_id"),
+                &(match_id, fixture_id),
+            );
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            betting_match.fixture_id = Some(fixture_id);
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Set the sport or league of a match, whose oracles can set its result.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x6F3386DD)]
+        pub fn set_match_sport(&mut self, match_id: MatchId, sport: Sport) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_match_sport"), &(match_id, &sport));
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            betting_match.sport = Some(sport);
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Allow an oracle to set the results of the matches of a sport, its leagues
+        /// included, or of a single league.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xB8695942)]
+        pub fn authorize_oracle(&mut self, oracle: AccountId, sport: Sport) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("authorize_oracle"), &(oracle, &sport));
+            self.apply_action_now(AdminAction::AuthorizeOracle(oracle, sport))
+        }
+
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x01C01E56)]
+        pub fn revoke_oracle(&mut self, oracle: AccountId, sport: Sport) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("revoke_oracle"), &(oracle, &sport));
+            self.apply_action_now(AdminAction::RevokeOracle(oracle, sport))
+        }
+
+        /// Whether an oracle can set the results of the matches of a sport or league,
+        /// being authorized for it or for a sport it is nested in.
+        #[ink(message, selector = 0xDF8FED9E)]
+        pub fn is_oracle_for(&self, oracle: AccountId, sport: Sport) -> bool {
+            sport
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'/')
+                .map(|(index, _)| index)
+                .chain([sport.len()])
+                .any(|end| {
+                    self.sport_oracles
+                        .contains((oracle, sport.get(..end).unwrap_or_default().to_vec()))
+                })
+        }
+
+        /// The payload binds the contract and the match specs, so a signature can't
+        /// be replayed on another contract or on a later match of the same creator.
+        fn signed_result_hash(&self, betting_match: &Match, result: MatchResult) -> [u8; 32] {
+            let payload = (
+                self.env().account_id(),
+                Self::get_match_hash(betting_match),
+                result,
+            );
+            let mut message =
+                <ink::env::hash::Blake2x256 as ink::env::hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(&payload, &mut message);
+            message
+        }
+
+        /// Sets the result of a match over, storing it.
+        fn apply_result(
+            &mut self,
+            match_id: MatchId,
+            mut match_to_set_result: Match,
+            result: MatchResult,
+            evidence: Option<Vec<u8>>,
+        ) -> Result<(), Error> {
+            // Handicap markets are settled from the final score
+            if matches!(match_to_set_result.market, Market::Handicap(_)) {
+                return Err(Error::ScoreRequired);
+            }
+            // Matches are voided with a reason by `void_match`
+            if result == MatchResult::Voided {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            if evidence
+                .as_ref()
+                .is_some_and(|evidence| evidence.len() > MAX_EVIDENCE_LEN)
+            {
+                return Err(Error::InvalidEvidence);
+            }
+            let previous = self.correctable_result(&match_to_set_result)?;
+            //set the result
+            match_to_set_result.result = Some(result);
+            match_to_set_result.evidence = evidence.clone();
+            if previous.is_none() {
+                match_to_set_result.result_block = self.env().block_number();
+            }
+            self.record_result(match_id, &mut match_to_set_result, result)?;
+            // Store the betting match in the list of open matches
+            self.matches.insert(match_id, &match_to_set_result);
+            // Emit an event.
+            self.emit_result(match_id, previous, result, evidence);
+
+            Ok(())
+        }
+
+        /// Returns the result a new one would correct, if any. A result can be corrected
+        /// within the correction window, which closes early when a payout of the match
+        /// starts, and a void is final.
+        fn correctable_result(&self, betting_match: &Match) -> Result<Option<MatchResult>, Error> {
+            let previous = match betting_match.result {
+                Some(previous) => previous,
+                None => return Ok(None),
+            };
+            if previous == MatchResult::Voided
+                || self.env().block_number() >= betting_match.final_block
+            {
+                return Err(Error::ResultAlreadySet);
+            }
+            Ok(Some(previous))
+        }
+
+        /// Emits `MatchResultSet`, or `MatchResultCorrected` for a correction.
+        fn emit_result(
+            &self,
+            match_id: MatchId,
+            previous: Option<MatchResult>,
+            result: MatchResult,
+            evidence: Option<Vec<u8>>,
+        ) {
+            match previous {
+                Some(previous) => self.env().emit_event(MatchResultCorrected {
+                    match_id,
+                    previous,
+                    result,
+                    evidence,
+                }),
+                None => self.env().emit_event(MatchResultSet {
+                    match_id,
+                    result,
+                    evidence,
+                }),
+            }
+        }
+
+        /// Result of a match recorded under `match_hash`, once it can't be corrected.
+        fn final_result(&self, match_hash: [u8; 32]) -> Option<ResultOdds> {
+            self.results
+                .get(match_hash)
+                .filter(|result_odds| self.env().block_number() >= result_odds.final_block)
+        }
+
+        /// Void a match that was abandoned or postponed indefinitely, at any time before
+        /// its result is set. Its settlement refunds every stake and returns the deposit.
+        /// `reason` is a code for the cause, emitted with `MatchVoided`.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x55AECDCF)]
+        pub fn void_match(&mut self, match_id: MatchId, reason: u8) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("void_match"), &(match_id, reason));
+            let mut match_to_void = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            // Shares of LMSR markets have no stake to refund
+            if matches!(match_to_void.market, Market::Lmsr(_)) {
+                return Err(Error::WrongMarket);
+            }
+            if match_to_void.result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            let result = MatchResult::Voided;
+            match_to_void.result = Some(result);
+            match_to_void.proposed_result = None;
+            match_to_void.result_block = self.env().block_number();
+            self.record_result(match_id, &mut match_to_void, result)?;
+            self.matches.insert(match_id, &match_to_void);
+            self.update_reputation(match_id, |reputation| {
+                reputation.cancelled = reputation.cancelled.saturating_add(1)
+            });
+            self.env().emit_event(MatchResultSet {
+                match_id,
+                result,
+                evidence: None,
+            });
+            self.env().emit_event(MatchVoided { match_id, reason });
+
+            Ok(())
+        }
+
+        /// Set the final score of an existing match, deriving its result.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x9703FC58)]
+        pub fn set_score(
+            &mut self,
+            match_id: MatchId,
+            team1_goals: u32,
+            team2_goals: u32,
+        ) -> Result<(), Error> {
+            let match_to_set_result = self.match_to_set_directly(match_id)?;
+            self.apply_score(match_id, match_to_set_result, team1_goals, team2_goals)
+        }
+
+        /// Report the final score of a match over as a registered score oracle. Once the
+        /// quorum of oracles has reported, the median of their goals for each team is set
+        /// as the score, which is the score reported by a majority if there is one.
+        #[ink(message, selector = 0xAD7235B2)]
+        pub fn report_score(
+            &mut self,
+            match_id: MatchId,
+            team1_goals: u32,
+            team2_goals: u32,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if !self.score_oracles.contains(caller) {
+                return Err(Error::NotScoreOracle);
+            }
+            let match_to_set_result = self.ended_match(match_id)?;
+            if match_to_set_result.result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            let mut reports = self.score_reports.get(match_id).unwrap_or_default();
+            if reports.iter().any(|(oracle, _)| *oracle == caller) {
+                return Err(Error::AlreadyReported);
+            }
+            reports.push((caller, (team1_goals, team2_goals)));
+            self.env().emit_event(ScoreReported {
+                match_id,
+                oracle: caller,
+                team1_goals,
+                team2_goals,
+            });
+            // There are fewer reports than oracles
+            if (reports.len() as u32) < self.get_config().score_quorum {
+                self.score_reports.insert(match_id, &reports);
+                return Ok(());
+            }
+            self.score_reports.remove(match_id);
+            let (team1_goals, team2_goals) = Self::median_score(&reports);
+            self.apply_score(match_id, match_to_set_result, team1_goals, team2_goals)
+        }
+
+        /// Median of the goals reported for each team, the lower one for an even number of
+        /// reports.
+        fn median_score(reports: &[ScoreReport]) -> (u32, u32) {
+            let mut team1_goals: Vec<u32> = reports.iter().map(|(_, score)| score.0).collect();
+            let mut team2_goals: Vec<u32> = reports.iter().map(|(_, score)| score.1).collect();
+            team1_goals.sort_unstable();
+            team2_goals.sort_unstable();
+            // The index is below the length
+            let middle = reports.len().saturating_sub(1) / 2;
+            (
+                team1_goals.get(middle).copied().unwrap_or_default(),
+                team2_goals.get(middle).copied().unwrap_or_default(),
+            )
+        }
+
+        /// Update the running score of a match in play as a registered score oracle, e.g.
+        /// after each goal. Each update is emitted, to check live bets against it later.
+        /// The final score is still reported with `report_score`.
+        #[ink(message, selector = 0x8125892B)]
+        pub fn update_score(&mut self, match_id: MatchId, score: (u32, u32)) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if !self.score_oracles.contains(caller) {
+                return Err(Error::NotScoreOracle);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let current_block_number = self.env().block_number();
+            if current_block_number < betting_match.start
+                || current_block_number > betting_match.start.saturating_add(betting_match.length)
+            {
+                return Err(Error::MatchNotInPlay);
+            }
+            if betting_match.result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            let changed = betting_match
+                .live_score
+                .map_or(score != (0, 0), |(previous, _)| previous != score);
+            betting_match.live_score = Some((score, current_block_number));
+            if changed && betting_match.suspension_blocks > 0 {
+                let until = current_block_number.saturating_add(betting_match.suspension_blocks);
+                betting_match.suspended_until = Some(until);
+                self.env().emit_event(MarketSuspended { match_id, until });
+            }
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(ScoreUpdated {
+                match_id,
+                oracle: caller,
+                team1_goals: score.0,
+                team2_goals: score.1,
+            });
+            Ok(())
+        }
+
+        /// Set how many blocks bets on a match are suspended for after its running score
+        /// changes, zero by default. Only the creator can change it, while betting is open.
+        #[ink(message, selector = 0x82839E44)]
+        pub fn set_score_suspension(
+            &mut self,
+            match_id: MatchId,
+            suspension_blocks: BlockNumber,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            betting_match.suspension_blocks = suspension_blocks;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Blocks bets on a match are suspended for after a change of its score, and the
+        /// last block of the current suspension, if any.
+        #[ink(message, selector = 0x81B509F9)]
+        pub fn get_score_suspension(
+            &self,
+            match_id: MatchId,
+        ) -> Option<(BlockNumber, Option<BlockNumber>)> {
+            let betting_match = self.matches.get(match_id)?;
+            Some((
+                betting_match.suspension_blocks,
+                betting_match.suspended_until,
+            ))
+        }
+
+        /// Running score of a match in play and the block of its last update, `None`
+        /// before the first update.
+        #[ink(message, selector = 0xDB4FC8E3)]
+        pub fn get_live_score(&self, match_id: MatchId) -> Option<((u32, u32), BlockNumber)> {
+            self.matches.get(match_id)?.live_score
+        }
+
+        /// Scores reported for a match that hasn't met the quorum yet.
+        #[ink(message, selector = 0xB16DBF51)]
+        pub fn get_score_reports(&self, match_id: MatchId) -> Vec<ScoreReport> {
+            self.score_reports.get(match_id).unwrap_or_default()
+        }
+
+        /// Register an account allowed to report the score of matches.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xEEE99BA4)]
+        pub fn add_score_oracle(&mut self, oracle: AccountId) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("add_score_oracle"), &oracle);
+            self.apply_action_now(AdminAction::AddScoreOracle(oracle))
+        }
+
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xDBBBB6A8)]
+        pub fn remove_score_oracle(&mut self, oracle: AccountId) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("remove_score_oracle"), &oracle);
+            self.apply_action_now(AdminAction::RemoveScoreOracle(oracle))
+        }
+
+        /// Set the number of score oracles that have to report the score of a match.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0xEDC5B9DC)]
+        pub fn set_score_quorum(&mut self, quorum: u32) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_score_quorum"), &quorum);
+            self.update_config(|config| config.score_quorum = quorum)
+        }
+
+        /// Sets the final score of a match over, deriving its result.
+        fn apply_score(
+            &mut self,
+            match_id: MatchId,
+            mut match_to_set_result: Match,
+            team1_goals: u32,
+            team2_goals: u32,
+        ) -> Result<(), Error> {
+            // The outcomes of other markets are not derived from the score
+            if !Self::is_one_x_two(&match_to_set_result) {
+                return Err(Error::WrongMarket);
+            }
+            let previous = self.correctable_result(&match_to_set_result)?;
+            let result = match team1_goals.cmp(&team2_goals) {
+                core::cmp::Ordering::Greater => MatchResult::Team1Victory,
+                core::cmp::Ordering::Less => MatchResult::Team2Victory,
+                core::cmp::Ordering::Equal => MatchResult::Draw,
+            };
+            //set the score and the result
+            match_to_set_result.score = Some((team1_goals, team2_goals));
+            match_to_set_result.result = Some(result);
+            match_to_set_result.evidence = None;
+            if previous.is_none() {
+                match_to_set_result.result_block = self.env().block_number();
+            }
+            self.record_result(match_id, &mut match_to_set_result, result)?;
+            // Store the betting match in the list of open matches
+            self.matches.insert(match_id, &match_to_set_result);
+            // Emit the events.
+            self.env().emit_event(MatchScoreSet {
+                match_id,
+                team1_goals,
+                team2_goals,
+            });
+            self.emit_result(match_id, previous, result, None);
+
+            Ok(())
+        }
+
+        /// The creator proposes the result of their match, bonding the transferred value,
+        /// at least the minimum bond. It becomes final when the owner confirms it, or when
+        /// the confirmation timeout is over without a rejection. The bond is the first of
+        /// the challenges of the match.
+        #[ink(message, payable, selector = 0x5999D38D)]
+        pub fn propose_result(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let current_block_number = self.env().block_number();
+            if current_block_number <= Self::result_opens(&betting_match)? {
+                return Err(Error::TimeMatchNotOver);
+            }
+            if matches!(betting_match.market, Market::Handicap(_)) {
+                return Err(Error::ScoreRequired);
+            }
+            if !Self::is_outcome(&betting_match, result) {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            if betting_match.result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            if betting_match.proposed_result.is_some() {
+                return Err(Error::ResultAlreadyProposed);
+            }
+            let bond = Self::env().transferred_value();
+            if bond < self.get_config().min_challenge_bond {
+                return Err(Error::BondTooLow);
+            }
+            self.escrow_in(match_id, bond)?;
+            self.challenges.insert(
+                match_id,
+                &vec![Challenge {
+                    challenger: match_id,
+                    result,
+                    bond,
+                }],
+            );
+            betting_match.proposed_result = Some((result, current_block_number));
+            betting_match.status = MatchStatus::Disputed;
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(ResultProposed { match_id, result });
+
+            Ok(())
+        }
+
+        /// Challenge the proposed result of a match with another result, bonding the
+        /// transferred value, at least twice the previous bond. The challenge becomes the
+        /// proposed result, final if left unchallenged for the confirmation timeout. Once
+        /// the result is final the bonds of the challenges on other results are shared
+        /// among those on the final result, in proportion to their bonds.
+        #[ink(message, payable, selector = 0x9E1FFFB4)]
+        pub fn challenge_result(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let bond = Self::env().transferred_value();
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.settlements.contains(match_id) {
+                return Err(Error::SettlementInProgress);
+            }
+            let proposed = match (betting_match.result, betting_match.proposed_result) {
+                (None, Some((proposed, proposed_block))) => {
+                    let final_block =
+                        proposed_block.saturating_add(self.get_config().dispute_window);
+                    if self.env().block_number() > final_block {
+                        return Err(Error::ResultFinal);
+                    }
+                    proposed
+                }
+                _ => return Err(Error::NoResultProposed),
+            };
+            if result == proposed || !Self::is_outcome(&betting_match, result) {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            // The proposal of the creator is the first challenge
+            let mut challenges = self.challenges.get(match_id).unwrap_or_default();
+            let min_bond = match challenges.last() {
+                Some(previous) => previous
+                    .bond
+                    .checked_mul(2)
+                    .ok_or(Error::ArithmeticOverflow)?,
+                None => self.get_config().min_challenge_bond,
+            };
+            if bond < min_bond {
+                return Err(Error::BondTooLow);
+            }
+            challenges.push(Challenge {
+                challenger: caller,
+                result,
+                bond,
+            });
+            self.escrow_in(match_id, bond)?;
+            self.challenges.insert(match_id, &challenges);
+            betting_match.proposed_result = Some((result, self.env().block_number()));
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(ResultChallenged {
+                match_id,
+                who: caller,
+                result,
+                bond,
+            });
+            Ok(())
+        }
+
+        /// Challenges of the proposed result of a match, in order, starting with the
+        /// proposal.
+        #[ink(message, selector = 0x881276BC)]
+        pub fn get_challenges(&self, match_id: MatchId) -> Vec<Challenge> {
+            self.challenges.get(match_id).unwrap_or_default()
+        }
+
+        /// Pays out the bonds of the challenges of a match once its result is set. The
+        /// challenges on the result get their bonds back and share the bonds of the others,
+        /// which go to the treasury if none is on the result. A void refunds every bond.
+        fn close_challenges(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+        ) -> Result<(), Error> {
+            let challenges = match self.challenges.take(match_id) {
+                Some(challenges) => challenges,
+                None => return Ok(()),
+            };
+            let total = Self::checked_sum(challenges.iter().map(|challenge| challenge.bond))?;
+            self.escrow_out(match_id, total)?;
+            if result == MatchResult::Voided {
+                for challenge in challenges {
+                    self.pay_or_defer(challenge.challenger, challenge.bond)?;
+                }
+                return Ok(());
+            }
+            self.share_forfeited_bonds(&challenges, total, |challenge| challenge.result == result)
+        }
+
+        /// Returns the bonds of the winning `challenges` with a share of the bonds of the
+        /// others in proportion to their bonds, the forfeited bonds go to the treasury if
+        /// none won. `total` is the sum of the bonds, already out of the escrow.
+        fn share_forfeited_bonds(
+            &mut self,
+            challenges: &[Challenge],
+            total: Balance,
+            wins: impl Fn(&Challenge) -> bool,
+        ) -> Result<(), Error> {
+            let winning = Self::checked_sum(
+                challenges
+                    .iter()
+                    .filter(|challenge| wins(challenge))
+                    .map(|challenge| challenge.bond),
+            )?;
+            // The winning bonds are part of the total
+            let forfeited = total.saturating_sub(winning);
+            let mut payments = Vec::new();
+            for challenge in challenges.iter().filter(|challenge| wins(challenge)) {
+                let reward = Self::mul_div(forfeited, challenge.bond, winning)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                let amount = challenge
+                    .bond
+                    .checked_add(reward)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                payments.push((challenge.challenger, amount));
+            }
+            let paid = Self::checked_sum(payments.iter().map(|(_, amount)| *amount))?;
+            // The rewards add up to at most the forfeited bonds
+            self.treasury = self
+                .treasury
+                .checked_add(total.saturating_sub(paid))
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.pay_all(payments)
+        }
+
+        /// Set the minimum bond of a proposed result.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x8BAE0E74)]
+        pub fn set_min_challenge_bond(&mut self, bond: Balance) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_min_challenge_bond"), &bond);
+            self.update_config(|config| config.min_challenge_bond = bond)
+        }
+
+        /// Vote on the result of a match left without a result or proposal for the oracle
+        /// timeout after its end, weighted by the stakes of the caller on the match. Each
+        /// bet votes once, whoever holds it. The vote lasts for the vote period, then
+        /// `close_vote` sets the result.
+        #[ink(message, selector = 0xC56199D6)]
+        pub fn vote_result(&mut self, match_id: MatchId, result: MatchResult) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let (opens, closes) = self.voting_period(&betting_match)?;
+            let current_block_number = self.env().block_number();
+            if current_block_number <= opens
+                || current_block_number > closes
+                || betting_match.result.is_some()
+                || betting_match.proposed_result.is_some()
+            {
+                return Err(Error::VotingNotOpen);
+            }
+            if !Self::is_outcome(&betting_match, result) {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            let bets: Vec<&Bet> = betting_match
+                .bets
+                .iter()
+                .filter(|bet| bet.bettor == caller)
+                .collect();
+            if bets.is_empty() {
+                return Err(Error::NotBettor);
+            }
+            // Each bet votes once, whoever holds it
+            let mut voted_bets = self.voted_bets.get(match_id).unwrap_or_default();
+            let bets: Vec<&Bet> = bets
+                .into_iter()
+                .filter(|bet| !voted_bets.contains(&bet.id))
+                .collect();
+            let weight = Self::checked_sum(bets.iter().map(|bet| bet.amount))?;
+            let mut votes = self.result_votes.get(match_id).unwrap_or_default();
+            if weight == 0 || votes.iter().any(|(voter, _, _)| *voter == caller) {
+                return Err(Error::AlreadyVoted);
+            }
+            voted_bets.extend(bets.iter().map(|bet| bet.id));
+            self.voted_bets.insert(match_id, &voted_bets);
+            votes.push((caller, result, weight));
+            self.result_votes.insert(match_id, &votes);
+            self.env().emit_event(ResultVoted {
+                match_id,
+                who: caller,
+                result,
+                weight,
+            });
+            Ok(())
+        }
+
+        /// Set the result of a match voted on by its bettors once the vote is over: the
+        /// result with the most weight, or a void on a tie or without votes. The voters on
+        /// the result share a part of the creator deposit, in proportion to their weight.
+        /// Anyone can call it.
+        #[ink(message, selector = 0x7C628067)]
+        pub fn close_vote(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let (_, closes) = self.voting_period(&betting_match)?;
+            if betting_match.result.is_some() || betting_match.proposed_result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            if self.env().block_number() <= closes {
+                return Err(Error::VotingNotOver);
+            }
+            let votes = self.result_votes.take(match_id).unwrap_or_default();
+            self.voted_bets.remove(match_id);
+            let mut tallies: Vec<(MatchResult, Balance)> = Vec::new();
+            for (_, result, weight) in &votes {
+                match tallies.iter_mut().find(|(tallied, _)| tallied == result) {
+                    Some((_, tally)) => {
+                        *tally = tally
+                            .checked_add(*weight)
+                            .ok_or(Error::ArithmeticOverflow)?
+                    }
+                    None => tallies.push((*result, *weight)),
+                }
+            }
+            let top = tallies.iter().map(|(_, tally)| *tally).max().unwrap_or(0);
+            let mut leaders = tallies.iter().filter(|(_, tally)| *tally == top);
+            let result = match (leaders.next(), leaders.next()) {
+                (Some((result, _)), None) => *result,
+                _ => MatchResult::Voided,
+            };
+            let mut voter_rewards = Vec::new();
+            if result != MatchResult::Voided {
+                let reward = betting_match
+                    .deposit
+                    .checked_mul(VOTER_REWARD_PERCENT)
+                    .ok_or(Error::ArithmeticOverflow)?
+                    / 100;
+                for (voter, _, weight) in votes.iter().filter(|(_, voted, _)| *voted == result) {
+                    let share =
+                        Self::mul_div(reward, *weight, top).ok_or(Error::ArithmeticOverflow)?;
+                    self.escrow_out(match_id, share)?;
+                    voter_rewards.push((*voter, share));
+                }
+                let paid = Self::checked_sum(voter_rewards.iter().map(|(_, share)| *share))?;
+                // The shares add up to at most the reward, a part of the deposit
+                betting_match.deposit = betting_match.deposit.saturating_sub(paid);
+            }
+            betting_match.result = Some(result);
+            betting_match.result_block = self.env().block_number();
+            self.record_result(match_id, &mut betting_match, result)?;
+            self.matches.insert(match_id, &betting_match);
+            self.pay_all(voter_rewards)?;
+            self.env().emit_event(MatchResultSet {
+                match_id,
+                result,
+                evidence: None,
+            });
+            Ok(())
+        }
+
+        /// Votes on the result of a match, while the vote is open.
+        #[ink(message, selector = 0xBB837A1B)]
+        pub fn get_result_votes(&self, match_id: MatchId) -> Vec<ResultVote> {
+            self.result_votes.get(match_id).unwrap_or_default()
+        }
+
+        /// Blocks after which the bettors of a match can vote on its result, and until
+        /// which they can. Handicap and LMSR markets can't be voted on.
+        fn voting_period(
+            &self,
+            betting_match: &Match,
+        ) -> Result<(BlockNumber, BlockNumber), Error> {
+            match betting_match.market {
+                Market::Handicap(_) => return Err(Error::ScoreRequired),
+                Market::Lmsr(_) => return Err(Error::WrongMarket),
+                _ => {}
+            }
+            let config = self.get_config();
+            let opens = Self::result_opens(betting_match)?
+                .checked_add(config.oracle_timeout)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let closes = opens
+                .checked_add(config.vote_period)
+                .ok_or(Error::ArithmeticOverflow)?;
+            Ok((opens, closes))
+        }
+
+        /// Set the blocks after the end of a match without a result before its bettors can
+        /// vote on it, and the blocks the vote lasts.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x6B0CC7FB)]
+        pub fn set_vote_timing(
+            &mut self,
+            oracle_timeout: BlockNumber,
+            vote_period: BlockNumber,
+        ) -> Result<(), Error> {
+            self.log_admin(
+                ink::selector_bytes!("set_vote_timing"),
+                &(oracle_timeout, vote_period),
+            );
+            self.update_config(|config| {
+                config.oracle_timeout = oracle_timeout;
+                config.vote_period = vote_period;
+            })
+        }
+
+        /// Confirm the result proposed by the creator of a match.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xD9C55904)]
+        pub fn confirm_result(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let mut betting_match = self.match_to_set_result(match_id)?;
+            let result = match betting_match.proposed_result.take() {
+                Some((result, _)) => result,
+                None => return Err(Error::NoResultProposed),
+            };
+            betting_match.result = Some(result);
+            betting_match.result_block = self.env().block_number();
+            self.record_result(match_id, &mut betting_match, result)?;
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(MatchResultSet {
+                match_id,
+                result,
+                evidence: None,
+            });
+
+            Ok(())
+        }
+
+        /// Reject the result proposed for a match before it is final. The bonds on the
+        /// rejected result are shared among the challenges on other results, the creator
+        /// can then propose again.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x7FDE3954)]
+        pub fn reject_result(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let mut betting_match = self.match_to_set_result(match_id)?;
+            let (rejected, proposed_block) = match betting_match.proposed_result.take() {
+                Some(proposed_result) => proposed_result,
+                None => return Err(Error::NoResultProposed),
+            };
+            let final_block = proposed_block.saturating_add(self.get_config().dispute_window);
+            if self.env().block_number() > final_block {
+                return Err(Error::ResultFinal);
+            }
+            betting_match.status = MatchStatus::Upcoming;
+            self.matches.insert(match_id, &betting_match);
+            self.update_reputation(match_id, |reputation| {
+                reputation.disputes_lost = reputation.disputes_lost.saturating_add(1)
+            });
+            // The owner settles the dispute against the bonds on the rejected result
+            let challenges = self.challenges.take(match_id).unwrap_or_default();
+            let total = Self::checked_sum(challenges.iter().map(|challenge| challenge.bond))?;
+            self.escrow_out(match_id, total)?;
+            self.share_forfeited_bonds(&challenges, total, |challenge| {
+                challenge.result != rejected
+            })?;
+            self.env().emit_event(ResultRejected { match_id });
+
+            Ok(())
+        }
+
+        /// Make final a proposed result left unchallenged for the confirmation timeout,
+        /// so parlays and matched bets can be settled. Anyone can call it.
+        #[ink(message, selector = 0xA8A5B0AE)]
+        pub fn finalize_result(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if !self.finalize_proposed_result(match_id, &mut betting_match)? {
+                return Err(Error::MatchNotResult);
+            }
+            self.matches.insert(match_id, &betting_match);
+
+            Ok(())
+        }
+
+        /// Set the blocks before a proposed result left unchallenged becomes final.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x9E2D7B3B)]
+        pub fn set_result_confirmation_timeout(
+            &mut self,
+            blocks: BlockNumber,
+        ) -> Result<(), Error> {
+            self.log_admin(
+                ink::selector_bytes!("set_result_confirmation_timeout"),
+                &blocks,
+            );
+            self.update_config(|config| config.dispute_window = blocks)
+        }
+
+        /// Makes final the proposed result of a match if its confirmation timeout is over,
+        /// returning whether it did. The result counts as set when the timeout ended.
+        fn finalize_proposed_result(
+            &mut self,
+            match_id: MatchId,
+            betting_match: &mut Match,
+        ) -> Result<bool, Error> {
+            let (result, proposed_block) =
+                match (betting_match.result, betting_match.proposed_result) {
+                    (None, Some(proposal)) => proposal,
+                    _ => return Ok(false),
+                };
+            let final_block = proposed_block.saturating_add(self.get_config().dispute_window);
+            if self.env().block_number() <= final_block {
+                return Ok(false);
+            }
+            betting_match.proposed_result = None;
+            betting_match.result = Some(result);
+            betting_match.result_block = final_block;
+            self.record_result(match_id, betting_match, result)?;
+            self.env().emit_event(MatchResultSet {
+                match_id,
+                result,
+                evidence: None,
+            });
+            Ok(true)
+        }
+
+        /// Checks the caller can set the result of the match and returns it: the owner,
+        /// or an oracle of the sport of the match.
+        fn match_to_set_result(&self, match_id: MatchId) -> Result<Match, Error> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                let authorized = self
+                    .matches
+                    .get(match_id)
+                    .and_then(|betting_match| betting_match.sport)
+                    .is_some_and(|sport| self.is_oracle_for(caller, sport));
+                if !authorized {
+                    return Err(Error::BadOrigin);
+                }
+            }
+            self.ended_match(match_id)
+        }
+
+        /// Like `match_to_set_result`, for a result set without going through the proposal
+        /// of the creator, which must then be confirmed or rejected instead.
+        fn match_to_set_directly(&self, match_id: MatchId) -> Result<Match, Error> {
+            let betting_match = self.match_to_set_result(match_id)?;
+            if betting_match.result.is_none() && betting_match.proposed_result.is_some() {
+                return Err(Error::ResultAlreadyProposed);
+            }
+            Ok(betting_match)
+        }
+
+        /// Returns the match if it is over.
+        fn ended_match(&self, match_id: MatchId) -> Result<Match, Error> {
+            //Find the match where owner wants to set the result
+            let match_to_set_result = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            // The result can't change while the winnings are being distributed
+            if self.settlements.contains(match_id) {
+                return Err(Error::SettlementInProgress);
+            }
+            // Check if start and length are valid
+            let current_block_number = self.env().block_number();
+            if current_block_number <= Self::result_opens(&match_to_set_result)? {
+                return Err(Error::TimeMatchNotOver);
+            }
+            Ok(match_to_set_result)
+        }
+
+        /// Current stage of a match, `Settled` or `Cancelled` once it was deleted.
+        #[ink(message, selector = 0x07E3B8DF)]
+        pub fn get_status(&self, match_id: MatchId) -> Option<MatchStatus> {
+            let betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return self.closed_statuses.get(match_id),
+            };
+            let current_block_number = self.env().block_number();
+            let status = match betting_match.status {
+                MatchStatus::Upcoming
+                    if current_block_number
+                        > betting_match.start.saturating_add(betting_match.length) =>
+                {
+                    MatchStatus::AwaitingResult
+                }
+                MatchStatus::Upcoming
+                    if current_block_number > Self::bets_close(&betting_match) =>
+                {
+                    MatchStatus::BettingClosed
+                }
+                // A proposed result left unchallenged is final
+                MatchStatus::Disputed
+                    if betting_match
+                        .proposed_result
+                        .is_some_and(|(_, proposed_block)| {
+                            current_block_number
+                                > proposed_block.saturating_add(self.get_config().dispute_window)
+                        }) =>
+                {
+                    MatchStatus::Settled
+                }
+                status => status,
+            };
+            Some(status)
+        }
+
+        /// Records the result of a match with its pool, so parlays can be settled
+        /// once the match has been distributed, and pays out the bonds of the challenges
+        /// of its proposed result. Outcomes the match doesn't offer, e.g. a draw in 2-way
+        /// markets, are rejected.
+        fn record_result(
+            &mut self,
+            match_id: MatchId,
+            betting_match: &mut Match,
+            result: MatchResult,
+        ) -> Result<(), Error> {
+            if result != MatchResult::Voided && !Self::is_outcome(betting_match, result) {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            self.result_votes.remove(match_id);
+            self.voted_bets.remove(match_id);
+            // The first result of a fixed-odds match holds the bankroll until it is settled
+            if matches!(betting_match.market, Market::FixedOdds(_))
+                && betting_match.status != MatchStatus::Settled
+                && betting_match.status != MatchStatus::Cancelled
+            {
+                self.unsettled_fixed_odds = self.unsettled_fixed_odds.saturating_add(1);
+            }
+            // A void cancels the match, refunding every stake
+            betting_match.status = if result == MatchResult::Voided {
+                MatchStatus::Cancelled
+            } else {
+                MatchStatus::Settled
+            };
+            // Voids and results without a correction window are final at once
+            betting_match.final_block = betting_match.result_block;
+            if result != MatchResult::Voided {
+                betting_match.final_block = betting_match
+                    .final_block
+                    .saturating_add(self.get_config().correction_window);
+            }
+            let bets = &betting_match.bets;
+            let result_odds = ResultOdds {
+                result,
+                total_pool: Self::checked_sum(bets.iter().map(|bet| bet.amount))?,
+                winning_pool: Self::checked_sum(
+                    bets.iter()
+                        .filter(|bet| bet.result == result)
+                        .map(|bet| bet.amount),
+                )?,
+                final_block: betting_match.final_block,
+            };
+            self.results
+                .insert(Self::get_match_hash(betting_match), &result_odds);
+            self.close_challenges(match_id, result)
+        }
+
+        /// Place a parlay on several matches with a single stake.
+        /// It is won only if every leg is won, paying the stake times the
+        /// implied odds (total pool / pool on the result) of every leg when placed.
+        /// The bankroll backs the winnings, locking them until the parlay is settled.
+        #[ink(message, payable, selector = 0xBEB99A2F)]
+        pub fn place_parlay(
+            &mut self,
+            legs: Vec<(MatchId, MatchResult)>,
+        ) -> Result<ParlayId, Error> {
+            self.new_parlay(legs, false)
+        }
+
+        /// Place a parlay insured against losing a single leg. The transferred value pays
+        /// the stake and the premium on top of it, which goes to the parlay insurance. If
+        /// exactly one leg is lost, the stake is refunded from the parlay insurance, as far
+        /// as it goes.
+        #[ink(message, payable, selector = 0x8F6F0CC1)]
+        pub fn place_insured_parlay(
+            &mut self,
+            legs: Vec<(MatchId, MatchResult)>,
+        ) -> Result<ParlayId, Error> {
+            self.new_parlay(legs, true)
+        }
+
+        /// Places a parlay paid with the transferred value, less the premium if `insured`.
+        fn new_parlay(
+            &mut self,
+            legs: Vec<(MatchId, MatchResult)>,
+            insured: bool,
+        ) -> Result<ParlayId, Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            if legs.len() < MIN_PARLAY_LEGS {
+                return Err(Error::InvalidParlay);
+            }
+            let current_block_number = self.env().block_number();
+            let mut parlay_legs: Vec<ParlayLeg> = Vec::new();
+            for (match_id, result) in legs {
+                // Each match can only be a leg once
+                if parlay_legs.iter().any(|leg| leg.match_id == match_id) {
+                    return Err(Error::InvalidParlay);
+                }
+                let match_to_bet = match self.matches.get(match_id) {
+                    Some(match_from_storage) => match_from_storage,
+                    None => return Err(Error::MatchDoesNotExist),
+                };
+                if current_block_number > Self::betting_closes(&match_to_bet) {
+                    return Err(Error::MatchHasStarted);
+                }
+                if !Self::is_allowed_bettor(&match_to_bet, &caller) {
+                    return Err(Error::NotAllowedBettor);
+                }
+                // Only result pools can price a leg
+                if match_to_bet.market != Market::Winner || !Self::is_outcome(&match_to_bet, result)
+                {
+                    return Err(Error::OutcomeNotAllowed);
+                }
+                parlay_legs.push(ParlayLeg {
+                    match_id,
+                    match_hash: Self::get_match_hash(&match_to_bet),
+                    result,
+                    odds: Self::pool_odds(&match_to_bet.bets, result)?,
+                });
+            }
+            let paid = Self::env().transferred_value();
+            // The premium is on top of the stake
+            let mut amount = paid;
+            if insured {
+                let premium_bps = Balance::from(self.parlay_insurance_premium_bps);
+                amount = Self::mul_div(paid, 10_000, premium_bps.saturating_add(10_000))
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            // The stake is at most what was paid
+            let premium = paid.saturating_sub(amount);
+            Self::check_bet_amount(&self.get_config(), amount)?;
+            let payout = Self::parlay_payout(amount, parlay_legs.iter().map(|leg| leg.odds))?;
+            let backed = payout.saturating_sub(amount);
+            let max_exposure =
+                Self::bps_of(self.bankroll_value()?, Balance::from(self.max_exposure_bps))?;
+            if backed > max_exposure {
+                return Err(Error::ExposureExceeded);
+            }
+            if backed > self.bankroll {
+                return Err(Error::NotEnoughBankroll);
+            }
+            self.record_stake(caller, paid)?;
+            // The stake and the backing are held in escrow until the parlay is settled
+            self.bankroll = self.bankroll.saturating_sub(backed);
+            self.bankroll_locked = self
+                .bankroll_locked
+                .checked_add(backed)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_escrow = self
+                .total_escrow
+                .checked_add(payout)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.parlay_insurance = self
+                .parlay_insurance
+                .checked_add(premium)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let parlay_id = self.next_parlay_id;
+            self.next_parlay_id = parlay_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            let legs_count = parlay_legs.len() as u32;
+            self.stats.bets = self.stats.bets.saturating_add(1);
+            self.stats.volume = self.stats.volume.saturating_add(amount);
+            self.open_positions = self.open_positions.saturating_add(1);
+            self.parlays.insert(
+                parlay_id,
+                &Parlay {
+                    bettor: caller,
+                    amount,
+                    legs: parlay_legs,
+                    premium,
+                    backed,
+                },
+            );
+            self.env().emit_event(ParlayPlaced {
+                parlay_id,
+                who: caller,
+                amount,
+                legs: legs_count,
+            });
+
+            Ok(parlay_id)
+        }
+
+        /// Settle a parlay once all its legs have a result, paying the bettor if every leg won.
+        #[ink(message, selector = 0x12C40EF6)]
+        pub fn claim_parlay(&mut self, parlay_id: ParlayId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let parlay = match self.parlays.get(parlay_id) {
+                Some(parlay_from_storage) => parlay_from_storage,
+                None => return Err(Error::ParlayDoesNotExist),
+            };
+            if parlay.bettor != caller {
+                return Err(Error::NotBettor);
+            }
+            let mut won_odds = Vec::new();
+            let mut lost_legs: u32 = 0;
+            for leg in &parlay.legs {
+                let result_odds = match self.final_result(leg.match_hash) {
+                    Some(result_odds) => result_odds,
+                    None => return Err(Error::MatchNotResult),
+                };
+                // A voided leg is left out
+                if result_odds.result == MatchResult::Voided {
+                    continue;
+                }
+                if result_odds.result != leg.result {
+                    lost_legs = lost_legs.saturating_add(1);
+                } else {
+                    won_odds.push(leg.odds);
+                }
+            }
+            let mut amount_won = 0;
+            if lost_legs == 0 {
+                amount_won = Self::parlay_payout(parlay.amount, won_odds)?;
+            }
+            let held = parlay
+                .amount
+                .checked_add(parlay.backed)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.parlays.remove(parlay_id);
+            // Positions are counted when opened, and their funds held in escrow
+            self.open_positions = self.open_positions.saturating_sub(1);
+            self.total_escrow = self.total_escrow.saturating_sub(held);
+            self.bankroll_locked = self.bankroll_locked.saturating_sub(parlay.backed);
+            // The bankroll gets back what the parlay doesn't win, voided legs paying less
+            self.bankroll = self
+                .bankroll
+                .checked_add(held.saturating_sub(amount_won))
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.stats.paid_out = self.stats.paid_out.saturating_add(amount_won);
+            // An insured parlay losing a single leg gets its stake back
+            let mut refunded = 0;
+            if parlay.premium > 0 && lost_legs == 1 {
+                refunded = parlay.amount.min(self.parlay_insurance);
+                self.parlay_insurance = self.parlay_insurance.saturating_sub(refunded);
+                self.parlay_insurance_paid = self.parlay_insurance_paid.saturating_add(refunded);
+                self.stats.paid_out = self.stats.paid_out.saturating_add(refunded);
+            }
+            if amount_won > 0 {
+                self.env()
+                    .transfer(self.get_payout_address(caller), amount_won)
+                    .map_err(|_| Error::TransferFailed)?;
+                self.env().emit_event(WinningsClaimed {
+                    who: caller,
+                    amount: amount_won,
+                });
+            }
+            if refunded > 0 {
+                self.env()
+                    .transfer(self.get_payout_address(caller), refunded)
+                    .map_err(|_| Error::TransferFailed)?;
+                self.env().emit_event(ParlayInsurancePaid {
+                    parlay_id,
+                    who: caller,
+                    amount: refunded,
+                });
+            }
+            self.env().emit_event(ParlaySettled {
+                parlay_id,
+                who: caller,
+                amount: amount_won,
+            });
+
+            Ok(())
+        }
+
+        /// Odds a pool implies for `result`, in hundredths: the total pool over the pool on
+        /// the result. Even if nobody bet on the result, as it has no odds.
+        fn pool_odds(bets: &[Bet], result: MatchResult) -> Result<u32, Error> {
+            let total_pool = Self::checked_sum(bets.iter().map(|bet| bet.amount))?;
+            let winning_pool = Self::checked_sum(
+                bets.iter()
+                    .filter(|bet| bet.result == result)
+                    .map(|bet| bet.amount),
+            )?;
+            if winning_pool == 0 {
+                return Ok(ODDS_UNIT);
+            }
+            let odds = Self::mul_div(total_pool, Balance::from(ODDS_UNIT), winning_pool)
+                .ok_or(Error::ArithmeticOverflow)?;
+            u32::try_from(odds).map_err(|_| Error::ArithmeticOverflow)
+        }
+
+        /// What a parlay staking `amount` pays if the legs with `odds` are won.
+        fn parlay_payout(
+            amount: Balance,
+            odds: impl IntoIterator<Item = u32>,
+        ) -> Result<Balance, Error> {
+            odds.into_iter().try_fold(amount, |payout, odds| {
+                Self::mul_div(payout, Balance::from(odds), Balance::from(ODDS_UNIT))
+                    .ok_or(Error::ArithmeticOverflow)
+            })
+        }
+
+        #[ink(message, selector = 0xBBE96477)]
+        pub fn get_parlay(&self, parlay_id: ParlayId) -> Option<Parlay> {
+            self.parlays.get(parlay_id)
+        }
+
+        /// Create a prediction contest on a set of matches with a fixed entry fee. Entries
+        /// close when the first match starts.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xCF981A64)]
+        pub fn create_contest(
+            &mut self,
+            entry_fee: Balance,
+            match_ids: Vec<MatchId>,
+        ) -> Result<ContestId, Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("create_contest"),
+                &(entry_fee, &match_ids),
+            );
+            if match_ids.is_empty() || match_ids.len() > MAX_CONTEST_MATCHES {
+                return Err(Error::InvalidContest);
+            }
+            let current_block_number = self.env().block_number();
+            let mut match_hashes = Vec::new();
+            let mut deadline = BlockNumber::MAX;
+            let mut end = 0;
+            for match_id in &match_ids {
+                let betting_match = match self.matches.get(match_id) {
+                    Some(match_from_storage) => match_from_storage,
+                    None => return Err(Error::MatchDoesNotExist),
+                };
+                let match_hash = Self::get_match_hash(&betting_match);
+                if current_block_number > betting_match.start || match_hashes.contains(&match_hash)
+                {
+                    return Err(Error::InvalidContest);
+                }
+                match_hashes.push(match_hash);
+                deadline = deadline.min(betting_match.start);
+                end = end.max(betting_match.start.saturating_add(betting_match.length));
+            }
+            let contest_id = self.next_contest_id;
+            self.next_contest_id = contest_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            self.contests.insert(
+                contest_id,
+                &Contest {
+                    entry_fee,
+                    match_hashes,
+                    deadline,
+                    end,
+                    entries: Vec::new(),
+                },
+            );
+            self.env().emit_event(ContestCreated {
+                contest_id,
+                entry_fee,
+                // The contest is short, the count fits in a u32
+                matches: match_ids.len() as u32,
+            });
+            Ok(contest_id)
+        }
+
+        /// Enter a prediction contest, paying its entry fee, with a predicted result for
+        /// each of its matches.
+        #[ink(message, payable, selector = 0xD01B3B8A)]
+        pub fn enter_contest(
+            &mut self,
+            contest_id: ContestId,
+            predictions: Vec<MatchResult>,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let mut contest = match self.contests.get(contest_id) {
+                Some(contest) => contest,
+                None => return Err(Error::ContestDoesNotExist),
+            };
+            if self.env().block_number() > contest.deadline {
+                return Err(Error::EntriesClosed);
+            }
+            if contest.entries.len() >= MAX_CONTEST_ENTRIES {
+                return Err(Error::ContestFull);
+            }
+            if contest
+                .entries
+                .iter()
+                .any(|(entrant, _)| *entrant == caller)
+            {
+                return Err(Error::AlreadyEntered);
+            }
+            if Self::env().transferred_value() != contest.entry_fee {
+                return Err(Error::WrongEntryFee);
+            }
+            if predictions.len() != contest.match_hashes.len()
+                || predictions.contains(&MatchResult::Voided)
+            {
+                return Err(Error::InvalidPredictions);
+            }
+            self.record_stake(caller, contest.entry_fee)?;
+            self.total_prize_pools = self
+                .total_prize_pools
+                .checked_add(contest.entry_fee)
+                .ok_or(Error::ArithmeticOverflow)?;
+            contest.entries.push((caller, predictions));
+            self.contests.insert(contest_id, &contest);
+            self.env().emit_event(ContestEntered {
+                contest_id,
+                who: caller,
+            });
+            Ok(())
+        }
+
+        /// Settle a prediction contest once all its matches have a result, scoring a
+        /// point per correct prediction. The entrants with the top score share the prize
+        /// pool, what can't be split evenly goes to the treasury. Anyone can call it.
+        #[ink(message, selector = 0x5AA7EF38)]
+        pub fn settle_contest(&mut self, contest_id: ContestId) -> Result<(), Error> {
+            let contest = match self.contests.get(contest_id) {
+                Some(contest) => contest,
+                None => return Err(Error::ContestDoesNotExist),
+            };
+            let mut results = Vec::new();
+            for match_hash in &contest.match_hashes {
+                match self.final_result(*match_hash) {
+                    Some(result_odds) => results.push(result_odds.result),
+                    None => return Err(Error::MatchNotResult),
+                }
+            }
+            // Predictions are never voided, a voided match scores for nobody
+            let scores: Vec<u32> = contest
+                .entries
+                .iter()
+                .map(|(_, predictions)| {
+                    predictions
+                        .iter()
+                        .zip(&results)
+                        .filter(|(prediction, result)| prediction == result)
+                        .count() as u32
+                })
+                .collect();
+            let top_score = scores.iter().copied().max().unwrap_or(0);
+            let winners: Vec<AccountId> = contest
+                .entries
+                .iter()
+                .zip(&scores)
+                .filter(|(_, score)| **score == top_score)
+                .map(|((entrant, _), _)| *entrant)
+                .collect();
+            // There are at most MAX_CONTEST_ENTRIES entries
+            let prize_pool = contest
+                .entry_fee
+                .checked_mul(contest.entries.len() as Balance)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.contests.remove(contest_id);
+            let prize = self.share_prize_pool(&winners, prize_pool)?;
+            self.env().emit_event(ContestSettled {
+                contest_id,
+                top_score,
+                winners_count: winners.len() as u32,
+                prize,
+            });
+            Ok(())
+        }
+
+        /// Refund the entries of a prediction contest with a match still without a result
+        /// once the oracle timeout after its last match is over. Anyone can call it.
+        #[ink(message, selector = 0x4862BD71)]
+        pub fn refund_contest(&mut self, contest_id: ContestId) -> Result<(), Error> {
+            let contest = match self.contests.get(contest_id) {
+                Some(contest) => contest,
+                None => return Err(Error::ContestDoesNotExist),
+            };
+            if !self.refund_open(contest.end)
+                || contest
+                    .match_hashes
+                    .iter()
+                    .all(|match_hash| self.final_result(*match_hash).is_some())
+            {
+                return Err(Error::RefundNotOpen);
+            }
+            self.contests.remove(contest_id);
+            let entrants: Vec<AccountId> = contest
+                .entries
+                .iter()
+                .map(|(entrant, _)| *entrant)
+                .collect();
+            self.refund_entries(&entrants, contest.entry_fee)?;
+            self.env().emit_event(ContestRefunded {
+                contest_id,
+                entries: entrants.len() as u32,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x3A4CF5F9)]
+        pub fn get_contest(&self, contest_id: ContestId) -> Option<Contest> {
+            self.contests.get(contest_id)
+        }
+
+        /// Create a survivor pool with a fixed entry fee. The owner then adds its rounds.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xB4836720)]
+        pub fn create_survivor_pool(&mut self, entry_fee: Balance) -> Result<SurvivorId, Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("create_survivor_pool"), &entry_fee);
+            let survivor_id = self.next_survivor_id;
+            self.next_survivor_id = survivor_id
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.survivor_pools.insert(
+                survivor_id,
+                &SurvivorPool {
+                    entry_fee,
+                    round: Vec::new(),
+                    rounds: 0,
+                    deadline: BlockNumber::MAX,
+                    round_end: 0,
+                    entries: Vec::new(),
+                },
+            );
+            self.env().emit_event(SurvivorPoolCreated {
+                survivor_id,
+                entry_fee,
+            });
+            Ok(survivor_id)
+        }
+
+        /// Start the next round of a survivor pool on a set of matches, once every match
+        /// of the current round has a result. Entrants with a wrong pick, or no pick, in
+        /// the current round are eliminated.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xCA6E24BD)]
+        pub fn add_survivor_round(
+            &mut self,
+            survivor_id: SurvivorId,
+            match_ids: Vec<MatchId>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("add_survivor_round"),
+                &(survivor_id, &match_ids),
+            );
+            let mut pool = match self.survivor_pools.get(survivor_id) {
+                Some(pool) => pool,
+                None => return Err(Error::SurvivorPoolDoesNotExist),
+            };
+            if match_ids.is_empty() || match_ids.len() > MAX_CONTEST_MATCHES {
+                return Err(Error::InvalidRound);
+            }
+            let current_block_number = self.env().block_number();
+            let mut round = Vec::new();
+            let mut round_start = BlockNumber::MAX;
+            let mut round_end = 0;
+            for match_id in &match_ids {
+                let betting_match = match self.matches.get(match_id) {
+                    Some(match_from_storage) => match_from_storage,
+                    None => return Err(Error::MatchDoesNotExist),
+                };
+                let match_hash = Self::get_match_hash(&betting_match);
+                if current_block_number > betting_match.start || round.contains(&match_hash) {
+                    return Err(Error::InvalidRound);
+                }
+                round.push(match_hash);
+                round_start = round_start.min(betting_match.start);
+                round_end = round_end.max(betting_match.start.saturating_add(betting_match.length));
+            }
+            self.eliminate_survivors(survivor_id, &mut pool)?;
+            if pool.rounds == 0 {
+                pool.deadline = round_start;
+            }
+            pool.rounds = pool
+                .rounds
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            pool.round = round;
+            pool.round_end = round_end;
+            self.survivor_pools.insert(survivor_id, &pool);
+            Ok(())
+        }
+
+        /// Enter a survivor pool, paying its entry fee, before its first round starts.
+        #[ink(message, payable, selector = 0x19BD388F)]
+        pub fn enter_survivor_pool(&mut self, survivor_id: SurvivorId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let mut pool = match self.survivor_pools.get(survivor_id) {
+                Some(pool) => pool,
+                None => return Err(Error::SurvivorPoolDoesNotExist),
+            };
+            if self.env().block_number() > pool.deadline {
+                return Err(Error::EntriesClosed);
+            }
+            if pool.entries.len() >= MAX_SURVIVOR_ENTRIES {
+                return Err(Error::ContestFull);
+            }
+            if pool.entries.iter().any(|entry| entry.entrant == caller) {
+                return Err(Error::AlreadyEntered);
+            }
+            if Self::env().transferred_value() != pool.entry_fee {
+                return Err(Error::WrongEntryFee);
+            }
+            self.record_stake(caller, pool.entry_fee)?;
+            self.total_prize_pools = self
+                .total_prize_pools
+                .checked_add(pool.entry_fee)
+                .ok_or(Error::ArithmeticOverflow)?;
+            pool.entries.push(SurvivorEntry {
+                entrant: caller,
+                eliminated: false,
+                used_teams: Vec::new(),
+                pick: None,
+            });
+            self.survivor_pools.insert(survivor_id, &pool);
+            Ok(())
+        }
+
+        /// Pick the winner of a match of the current round before it starts. A team can
+        /// only be picked once per survivor pool.
+        #[ink(message, selector = 0xD8E5BFEE)]
+        pub fn pick_survivor(
+            &mut self,
+            survivor_id: SurvivorId,
+            match_id: MatchId,
+            winner: MatchResult,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let mut pool = match self.survivor_pools.get(survivor_id) {
+                Some(pool) => pool,
+                None => return Err(Error::SurvivorPoolDoesNotExist),
+            };
+            let betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let match_hash = Self::get_match_hash(&betting_match);
+            if !pool.round.contains(&match_hash) {
+                return Err(Error::NotInRound);
+            }
+            if self.env().block_number() > betting_match.start {
+                return Err(Error::MatchHasStarted);
+            }
+            let team = match winner {
+                MatchResult::Team1Victory => betting_match.team1,
+                MatchResult::Team2Victory => betting_match.team2,
+                _ => return Err(Error::OutcomeNotAllowed),
+            };
+            let entry = match pool
+                .entries
+                .iter_mut()
+                .find(|entry| entry.entrant == caller && !entry.eliminated)
+            {
+                Some(entry) => entry,
+                None => return Err(Error::NotSurvivor),
+            };
+            if entry.pick.is_some() {
+                return Err(Error::AlreadyPicked);
+            }
+            if entry.used_teams.contains(&team) {
+                return Err(Error::TeamAlreadyUsed);
+            }
+            entry.used_teams.push(team);
+            entry.pick = Some((match_hash, winner));
+            self.survivor_pools.insert(survivor_id, &pool);
+            Ok(())
+        }
+
+        /// End a survivor pool once every match of its last round has a result. The
+        /// entrants left share the pot, what can't be split evenly goes to the treasury.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x0E0CEC9E)]
+        pub fn end_survivor_pool(&mut self, survivor_id: SurvivorId) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("end_survivor_pool"), &survivor_id);
+            let mut pool = match self.survivor_pools.get(survivor_id) {
+                Some(pool) => pool,
+                None => return Err(Error::SurvivorPoolDoesNotExist),
+            };
+            self.eliminate_survivors(survivor_id, &mut pool)?;
+            let survivors: Vec<AccountId> = pool
+                .entries
+                .iter()
+                .filter(|entry| !entry.eliminated)
+                .map(|entry| entry.entrant)
+                .collect();
+            // There are at most MAX_SURVIVOR_ENTRIES entries
+            let pot = pool
+                .entry_fee
+                .checked_mul(pool.entries.len() as Balance)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.survivor_pools.remove(survivor_id);
+            let prize = self.share_prize_pool(&survivors, pot)?;
+            self.env().emit_event(SurvivorPoolEnded {
+                survivor_id,
+                survivors_count: survivors.len() as u32,
+                prize,
+            });
+            Ok(())
+        }
+
+        /// Refund the entries of a survivor pool, eliminated or not, if a match of the
+        /// current round is still without a result once the oracle timeout after the
+        /// round is over. Anyone can call it.
+        #[ink(message, selector = 0x2701E2D9)]
+        pub fn refund_survivor_pool(&mut self, survivor_id: SurvivorId) -> Result<(), Error> {
+            let pool = match self.survivor_pools.get(survivor_id) {
+                Some(pool) => pool,
+                None => return Err(Error::SurvivorPoolDoesNotExist),
+            };
+            if !self.refund_open(pool.round_end)
+                || pool
+                    .round
+                    .iter()
+                    .all(|match_hash| self.final_result(*match_hash).is_some())
+            {
+                return Err(Error::RefundNotOpen);
+            }
+            self.survivor_pools.remove(survivor_id);
+            let entrants: Vec<AccountId> = pool.entries.iter().map(|entry| entry.entrant).collect();
+            self.refund_entries(&entrants, pool.entry_fee)?;
+            self.env().emit_event(SurvivorPoolRefunded {
+                survivor_id,
+                entries: entrants.len() as u32,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x7379B40B)]
+        pub fn get_survivor_pool(&self, survivor_id: SurvivorId) -> Option<SurvivorPool> {
+            self.survivor_pools.get(survivor_id)
+        }
+
+        /// Eliminates the entrants of a survivor pool with a wrong pick, or no pick, in
+        /// the current round, which must have all its results. A pick on a voided match
+        /// survives. If nobody would be left, nobody is eliminated.
+        fn eliminate_survivors(
+            &mut self,
+            survivor_id: SurvivorId,
+            pool: &mut SurvivorPool,
+        ) -> Result<(), Error> {
+            if pool.round.is_empty() {
+                return Ok(());
+            }
+            let mut results = Vec::new();
+            for match_hash in &pool.round {
+                match self.final_result(*match_hash) {
+                    Some(result_odds) => results.push((*match_hash, result_odds.result)),
+                    None => return Err(Error::MatchNotResult),
+                }
+            }
+            let survives = |entry: &SurvivorEntry| {
+                entry.pick.is_some_and(|(pick_hash, winner)| {
+                    results.iter().any(|(match_hash, result)| {
+                        *match_hash == pick_hash
+                            && (*result == winner || *result == MatchResult::Voided)
+                    })
+                })
+            };
+            let alive = pool.entries.iter().filter(|entry| !entry.eliminated);
+            let all_eliminated = alive.clone().all(|entry| !survives(entry));
+            for entry in pool.entries.iter_mut().filter(|entry| !entry.eliminated) {
+                if !all_eliminated && !survives(entry) {
+                    entry.eliminated = true;
+                    self.env().emit_event(SurvivorEliminated {
+                        survivor_id,
+                        who: entry.entrant,
+                        round: pool.rounds,
+                    });
+                }
+                entry.pick = None;
+            }
+            pool.round = Vec::new();
+            Ok(())
+        }
+
+        /// Create a bracket challenge on a knockout tournament with a fixed entry fee.
+        /// The teams are paired in order in the first round. The owner then binds the
+        /// matches of each round with `set_bracket_round`.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x3C0659A4)]
+        pub fn create_bracket(
+            &mut self,
+            entry_fee: Balance,
+            teams: Vec<TeamName>,
+        ) -> Result<BracketId, Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("create_bracket"), &(entry_fee, &teams));
+            if teams.len() < 2 || teams.len() > MAX_BRACKET_TEAMS || !teams.len().is_power_of_two()
+            {
+                return Err(Error::InvalidBracket);
+            }
+            for (i, team) in teams.iter().enumerate() {
+                if teams[..i].contains(team) {
+                    return Err(Error::InvalidBracket);
+                }
+            }
+            let bracket_id = self.next_bracket_id;
+            self.next_bracket_id = bracket_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            self.env().emit_event(BracketCreated {
+                bracket_id,
+                entry_fee,
+                teams: teams.len() as u32,
+            });
+            self.brackets.insert(
+                bracket_id,
+                &Bracket {
+                    entry_fee,
+                    teams,
+                    winners: Vec::new(),
+                    round: Vec::new(),
+                    deadline: BlockNumber::MAX,
+                    round_end: 0,
+                    entries: Vec::new(),
+                },
+            );
+            Ok(bracket_id)
+        }
+
+        /// Bind the matches of the next round of a bracket challenge, one per game in
+        /// order, each between the teams of its game as team1 and team2. A round not
+        /// scored yet can be bound again, if one of its matches ended without a winner.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x81E1E9E4)]
+        pub fn set_bracket_round(
+            &mut self,
+            bracket_id: BracketId,
+            match_ids: Vec<MatchId>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("set_bracket_round"),
+                &(bracket_id, &match_ids),
+            );
+            let mut bracket = match self.brackets.get(bracket_id) {
+                Some(bracket) => bracket,
+                None => return Err(Error::BracketDoesNotExist),
+            };
+            let first_game = bracket.winners.len();
+            // The number of teams left is a power of two
+            let games = bracket.teams.len().saturating_sub(first_game) / 2;
+            if games == 0 || match_ids.len() != games {
+                return Err(Error::InvalidRound);
+            }
+            let current_block_number = self.env().block_number();
+            let mut round = Vec::new();
+            let mut round_start = BlockNumber::MAX;
+            let mut round_end = 0;
+            for (game, match_id) in (first_game..).zip(&match_ids) {
+                let betting_match = match self.matches.get(match_id) {
+                    Some(match_from_storage) => match_from_storage,
+                    None => return Err(Error::MatchDoesNotExist),
+                };
+                let (team1, team2) = Self::bracket_game(&bracket.teams, &bracket.winners, game);
+                if betting_match.team1 != *team1
+                    || betting_match.team2 != *team2
+                    || current_block_number > betting_match.start
+                {
+                    return Err(Error::InvalidRound);
+                }
+                round.push(Self::get_match_hash(&betting_match));
+                round_start = round_start.min(betting_match.start);
+                round_end = round_end.max(betting_match.start.saturating_add(betting_match.length));
+            }
+            if bracket.winners.is_empty() {
+                bracket.deadline = bracket.deadline.min(round_start);
+            }
+            bracket.round = round;
+            bracket.round_end = round_end;
+            self.brackets.insert(bracket_id, &bracket);
+            Ok(())
+        }
+
+        /// Enter a bracket challenge with the predicted winner of every game, paying its
+        /// entry fee, before the first round starts.
+        #[ink(message, payable, selector = 0x9ABA4334)]
+        pub fn enter_bracket(
+            &mut self,
+            bracket_id: BracketId,
+            picks: Vec<TeamName>,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let mut bracket = match self.brackets.get(bracket_id) {
+                Some(bracket) => bracket,
+                None => return Err(Error::BracketDoesNotExist),
+            };
+            if self.env().block_number() > bracket.deadline {
+                return Err(Error::EntriesClosed);
+            }
+            if bracket.entries.len() >= MAX_BRACKET_ENTRIES {
+                return Err(Error::ContestFull);
+            }
+            if bracket.entries.iter().any(|entry| entry.entrant == caller) {
+                return Err(Error::AlreadyEntered);
+            }
+            if picks.len() != bracket.teams.len().saturating_sub(1) {
+                return Err(Error::InvalidPredictions);
+            }
+            for (game, pick) in picks.iter().enumerate() {
+                let (team1, team2) = Self::bracket_game(&bracket.teams, &picks, game);
+                if pick != team1 && pick != team2 {
+                    return Err(Error::InvalidPredictions);
+                }
+            }
+            if Self::env().transferred_value() != bracket.entry_fee {
+                return Err(Error::WrongEntryFee);
+            }
+            self.record_stake(caller, bracket.entry_fee)?;
+            self.total_prize_pools = self
+                .total_prize_pools
+                .checked_add(bracket.entry_fee)
+                .ok_or(Error::ArithmeticOverflow)?;
+            bracket.entries.push(BracketEntry {
+                entrant: caller,
+                picks,
+                points: 0,
+            });
+            self.brackets.insert(bracket_id, &bracket);
+            Ok(())
+        }
+
+        /// Score the current round of a bracket challenge once all its matches have a
+        /// result. A correct pick is worth a point in the first round, and twice as much
+        /// in each following round. After the final, the brackets with the most points
+        /// share the prize pool, what can't be split evenly goes to the treasury.
+        /// Anyone can call it.
+        #[ink(message, selector = 0x82B2FE9E)]
+        pub fn score_bracket_round(&mut self, bracket_id: BracketId) -> Result<(), Error> {
+            let mut bracket = match self.brackets.get(bracket_id) {
+                Some(bracket) => bracket,
+                None => return Err(Error::BracketDoesNotExist),
+            };
+            if bracket.round.is_empty() {
+                return Err(Error::InvalidRound);
+            }
+            let first_game = bracket.winners.len();
+            let mut round_winners = Vec::new();
+            for (game, match_hash) in (first_game..).zip(&bracket.round) {
+                let (team1, team2) = Self::bracket_game(&bracket.teams, &bracket.winners, game);
+                match self
+                    .final_result(*match_hash)
+                    .map(|result_odds| result_odds.result)
+                {
+                    Some(MatchResult::Team1Victory) => round_winners.push(team1.clone()),
+                    Some(MatchResult::Team2Victory) => round_winners.push(team2.clone()),
+                    Some(_) => return Err(Error::NoWinner),
+                    None => return Err(Error::MatchNotResult),
+                }
+            }
+            // Both counts are powers of two, the teams left divide the teams
+            let points = bracket
+                .teams
+                .len()
+                .checked_div(bracket.teams.len().saturating_sub(first_game))
+                .ok_or(Error::ArithmeticOverflow)? as u32;
+            for entry in bracket.entries.iter_mut() {
+                let correct = entry.picks[first_game..]
+                    .iter()
+                    .zip(&round_winners)
+                    .filter(|(pick, winner)| pick == winner)
+                    .count() as u32;
+                entry.points = entry
+                    .points
+                    .checked_add(
+                        correct
+                            .checked_mul(points)
+                            .ok_or(Error::ArithmeticOverflow)?,
+                    )
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            bracket.winners.extend(round_winners.iter().cloned());
+            bracket.round = Vec::new();
+            self.env().emit_event(BracketRoundScored {
+                bracket_id,
+                winners: round_winners,
+            });
+            if bracket.winners.len() < bracket.teams.len().saturating_sub(1) {
+                self.brackets.insert(bracket_id, &bracket);
+                return Ok(());
+            }
+            let top_score = bracket
+                .entries
+                .iter()
+                .map(|entry| entry.points)
+                .max()
+                .unwrap_or(0);
+            let winners: Vec<AccountId> = bracket
+                .entries
+                .iter()
+                .filter(|entry| entry.points == top_score)
+                .map(|entry| entry.entrant)
+                .collect();
+            // There are at most MAX_BRACKET_ENTRIES entries
+            let prize_pool = bracket
+                .entry_fee
+                .checked_mul(bracket.entries.len() as Balance)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.brackets.remove(bracket_id);
+            let prize = self.share_prize_pool(&winners, prize_pool)?;
+            self.env().emit_event(BracketSettled {
+                bracket_id,
+                top_score,
+                winners_count: winners.len() as u32,
+                prize,
+            });
+            Ok(())
+        }
+
+        /// Refund the entries of a bracket challenge if a match of the current round still
+        /// has no winner once the oracle timeout after the round is over, and the owner
+        /// hasn't bound the round again. Anyone can call it.
+        #[ink(message, selector = 0x8109E9B0)]
+        pub fn refund_bracket(&mut self, bracket_id: BracketId) -> Result<(), Error> {
+            let bracket = match self.brackets.get(bracket_id) {
+                Some(bracket) => bracket,
+                None => return Err(Error::BracketDoesNotExist),
+            };
+            if !self.refund_open(bracket.round_end)
+                || bracket.round.iter().all(|match_hash| {
+                    matches!(
+                        self.final_result(*match_hash)
+                            .map(|result_odds| result_odds.result),
+                        Some(MatchResult::Team1Victory | MatchResult::Team2Victory)
+                    )
+                })
+            {
+                return Err(Error::RefundNotOpen);
+            }
+            self.brackets.remove(bracket_id);
+            let entrants: Vec<AccountId> =
+                bracket.entries.iter().map(|entry| entry.entrant).collect();
+            self.refund_entries(&entrants, bracket.entry_fee)?;
+            self.env().emit_event(BracketRefunded {
+                bracket_id,
+                entries: entrants.len() as u32,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xFB9E7F76)]
+        pub fn get_bracket(&self, bracket_id: BracketId) -> Option<Bracket> {
+            self.brackets.get(bracket_id)
+        }
+
+        /// Teams playing game `game` of a bracket, given the winners of the previous
+        /// games, which must all be known.
+        fn bracket_game<'a>(
+            teams: &'a [TeamName],
+            winners: &'a [TeamName],
+            game: usize,
+        ) -> (&'a TeamName, &'a TeamName) {
+            let slot = |i: usize| match teams.get(i) {
+                Some(team) => team,
+                None => &winners[i.saturating_sub(teams.len())],
+            };
+            // Games are fewer than the teams
+            (
+                slot(game.saturating_mul(2)),
+                slot(game.saturating_mul(2).saturating_add(1)),
+            )
+        }
+
+        /// Create an outright market on a competition between the runners of
+        /// `outcome_labels`, whose place pool pays the runners finishing in the first
+        /// `places`. Betting closes after `deadline`.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x2D578466)]
+        pub fn create_outright(
+            &mut self,
+            outcome_labels: Vec<TeamName>,
+            places: u32,
+            deadline: BlockNumber,
+        ) -> Result<OutrightId, Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("create_outright"),
+                &(&outcome_labels, places, deadline),
+            );
+            if outcome_labels.len() < 2
+                || outcome_labels.len() > MAX_OUTRIGHT_RUNNERS
+                || places == 0
+                || places as usize >= outcome_labels.len()
+                || deadline < self.env().block_number()
+            {
+                return Err(Error::InvalidOutright);
+            }
+            for (i, label) in outcome_labels.iter().enumerate() {
+                if outcome_labels[..i].contains(label) {
+                    return Err(Error::InvalidOutright);
+                }
+            }
+            let outright_id = self.next_outright_id;
+            self.next_outright_id = outright_id
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.env().emit_event(OutrightCreated {
+                outright_id,
+                runners: outcome_labels.len() as u32,
+                places,
+            });
+            self.outrights.insert(
+                outright_id,
+                &Outright {
+                    outcome_labels,
+                    places,
+                    deadline,
+                    bets: Vec::new(),
+                    next_bet_id: 0,
+                    win_pool: 0,
+                    place_pool: 0,
+                    prices: Vec::new(),
+                    priced_block: 0,
+                },
+            );
+            Ok(outright_id)
+        }
+
+        /// Index of the label of a runner of an outright market.
+        fn runner_index(outright: &Outright, runner: OutcomeId) -> Option<usize> {
+            runner
+                .index()
+                .filter(|index| *index < outright.outcome_labels.len())
+        }
+
+        /// Bet the transferred value on a runner of an outright market winning. An
+        /// each-way bet splits the stake evenly between the runner winning and the runner
+        /// finishing in the paid places, the odd unit going to the win.
+        #[ink(message, payable, selector = 0x1D994D41)]
+        pub fn bet_outright(
+            &mut self,
+            outright_id: OutrightId,
+            runner: OutcomeId,
+            each_way: bool,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let mut outright = match self.outrights.get(outright_id) {
+                Some(outright) => outright,
+                None => return Err(Error::OutrightDoesNotExist),
+            };
+            if self.env().block_number() > outright.deadline {
+                return Err(Error::MatchHasStarted);
+            }
+            if Self::runner_index(&outright, runner).is_none() {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            if outright.bets.len() >= MAX_OUTRIGHT_BETS {
+                return Err(Error::MatchFull);
+            }
+            let amount = Self::env().transferred_value();
+            Self::check_bet_amount(&self.get_config(), amount)?;
+            self.record_stake(caller, amount)?;
+            let place_stake = if each_way { amount / 2 } else { 0 };
+            // Half the stake at most
+            let win_stake = amount.saturating_sub(place_stake);
+            self.total_prize_pools = self
+                .total_prize_pools
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.stats.bets = self.stats.bets.saturating_add(1);
+            self.stats.volume = self.stats.volume.saturating_add(amount);
+            outright.win_pool = outright
+                .win_pool
+                .checked_add(win_stake)
+                .ok_or(Error::ArithmeticOverflow)?;
+            outright.place_pool = outright
+                .place_pool
+                .checked_add(place_stake)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let id = outright.next_bet_id;
+            outright.next_bet_id = id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            outright.bets.push(OutrightBet {
+                id,
+                bettor: caller,
+                runner,
+                win_stake,
+                place_stake,
+            });
+            self.outrights.insert(outright_id, &outright);
+            self.env().emit_event(OutrightBetPlaced {
+                outright_id,
+                who: caller,
+                runner,
+                win_stake,
+                place_stake,
+            });
+            Ok(())
+        }
+
+        /// Settle an outright market with the runners finishing in the paid places, in
+        /// order, or refund its stakes with `[Voided]`. The win pool is shared among the
+        /// bets on the winner, and the place pool among the bets on the placed runners, in
+        /// proportion to their stakes, less the protocol fee. A pool nobody won rolls over
+        /// to the jackpot, and the rounding goes to the treasury.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xEF25C3CC)]
+        pub fn settle_outright(
+            &mut self,
+            outright_id: OutrightId,
+            placings: Vec<OutcomeId>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("settle_outright"),
+                &(outright_id, &placings),
+            );
+            let outright = self.outright_to_settle(outright_id, &placings)?;
+            self.close_outright(outright_id, outright, &placings)
+        }
+
+        /// Report the runners finishing in the paid places of an outright market, in
+        /// order, as a registered score oracle. The market is settled once the quorum of
+        /// oracles has reported the same placings.
+        #[ink(message, selector = 0xAB0E3B88)]
+        pub fn report_outright_result(
+            &mut self,
+            outright_id: OutrightId,
+            placings: Vec<OutcomeId>,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if !self.score_oracles.contains(caller) {
+                return Err(Error::NotScoreOracle);
+            }
+            let outright = self.outright_to_settle(outright_id, &placings)?;
+            let mut reports = self.outright_reports.get(outright_id).unwrap_or_default();
+            if reports.iter().any(|(oracle, _)| *oracle == caller) {
+                return Err(Error::AlreadyReported);
+            }
+            let agreeing = reports
+                .iter()
+                .filter(|(_, reported)| *reported == placings)
+                .count()
+                .saturating_add(1);
+            reports.push((caller, placings.clone()));
+            self.env().emit_event(OutrightResultReported {
+                outright_id,
+                oracle: caller,
+            });
+            // Fewer oracles agree than the quorum
+            if (agreeing as u32) < self.get_config().score_quorum {
+                self.outright_reports.insert(outright_id, &reports);
+                return Ok(());
+            }
+            self.close_outright(outright_id, outright, &placings)
+        }
+
+        /// Refund the stakes of an outright market still without a result once the oracle
+        /// timeout after its deadline is over. Anyone can call it.
+        #[ink(message, selector = 0x7D4114AC)]
+        pub fn refund_outright(&mut self, outright_id: OutrightId) -> Result<(), Error> {
+            let outright = match self.outrights.get(outright_id) {
+                Some(outright) => outright,
+                None => return Err(Error::OutrightDoesNotExist),
+            };
+            if self.env().block_number()
+                <= outright
+                    .deadline
+                    .saturating_add(self.get_config().oracle_timeout)
+            {
+                return Err(Error::RefundNotOpen);
+            }
+            self.close_outright(outright_id, outright, &[OutcomeId::Voided])
+        }
+
+        /// Outright market over whose result can be set to `placings`: the runners of the
+        /// paid places, all different, or `[Voided]`.
+        fn outright_to_settle(
+            &self,
+            outright_id: OutrightId,
+            placings: &[OutcomeId],
+        ) -> Result<Outright, Error> {
+            let outright = match self.outrights.get(outright_id) {
+                Some(outright) => outright,
+                None => return Err(Error::OutrightDoesNotExist),
+            };
+            if self.env().block_number() <= outright.deadline {
+                return Err(Error::TimeMatchNotOver);
+            }
+            if placings == [OutcomeId::Voided] {
+                return Ok(outright);
+            }
+            if placings.len() != outright.places as usize {
+                return Err(Error::InvalidOutright);
+            }
+            for (i, runner) in placings.iter().enumerate() {
+                if Self::runner_index(&outright, *runner).is_none()
+                    || placings[..i].contains(runner)
+                {
+                    return Err(Error::InvalidOutright);
+                }
+            }
+            Ok(outright)
+        }
+
+        /// Pay out an outright market with its placings, or refund it if they are
+        /// `[Voided]`. The refunds are cut in proportion if the cashouts took more than
+        /// their stakes out of a pool.
+        fn close_outright(
+            &mut self,
+            outright_id: OutrightId,
+            outright: Outright,
+            placings: &[OutcomeId],
+        ) -> Result<(), Error> {
+            let winner = placings.first().copied().unwrap_or(OutcomeId::Voided);
+            let voided = winner == OutcomeId::Voided;
+            let wins = |bet: &&OutrightBet| voided || bet.runner == winner;
+            let places = |bet: &&OutrightBet| voided || placings.contains(&bet.runner);
+            let bets = &outright.bets;
+            let (win_pool, place_pool) = (outright.win_pool, outright.place_pool);
+            let win_backed = Self::checked_sum(bets.iter().filter(wins).map(|bet| bet.win_stake))?;
+            let place_backed =
+                Self::checked_sum(bets.iter().filter(places).map(|bet| bet.place_stake))?;
+            // Refunds are at most the stakes
+            let (win_paid, place_paid) = if voided {
+                (win_pool.min(win_backed), place_pool.min(place_backed))
+            } else {
+                (win_pool, place_pool)
+            };
+            self.outrights.remove(outright_id);
+            self.outright_reports.remove(outright_id);
+            let pools = win_pool
+                .checked_add(place_pool)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_prize_pools = self.total_prize_pools.saturating_sub(pools);
+            let fee_bps = if voided {
+                0
+            } else {
+                Balance::from(self.get_config().fee_bps)
+            };
+            let mut paid: Balance = 0;
+            let mut payments = Vec::new();
+            for bet in bets.iter() {
+                let mut payout = 0;
+                if wins(&bet) {
+                    payout = Self::mul_div(bet.win_stake, win_paid, win_backed)
+                        .ok_or(Error::ArithmeticOverflow)?;
+                }
+                if bet.place_stake > 0 && places(&bet) {
+                    payout = Self::mul_div(bet.place_stake, place_paid, place_backed)
+                        .and_then(|won| won.checked_add(payout))
+                        .ok_or(Error::ArithmeticOverflow)?;
+                }
+                if payout > 0 {
+                    paid = paid.checked_add(payout).ok_or(Error::ArithmeticOverflow)?;
+                    // Take the protocol fee on the winnings, less the discount of stakers
+                    let fee = Self::bps_of(payout, fee_bps)?;
+                    let discount =
+                        Self::bps_of(fee, Balance::from(self.fee_discount_bps(bet.bettor)))?;
+                    // The discount is at most 100%
+                    let fee = fee.saturating_sub(discount);
+                    self.collect_fee(bet.bettor, fee, None)?;
+                    // The fee is at most 100% of the winnings
+                    payments.push((bet.bettor, payout.saturating_sub(fee)));
+                }
+            }
+            self.stats.paid_out = self.stats.paid_out.saturating_add(Self::checked_sum(
+                payments.iter().map(|(_, payout)| *payout),
+            )?);
+            let mut rolled_over = 0;
+            if !voided && win_backed == 0 {
+                rolled_over = win_pool;
+            }
+            if !voided && place_backed == 0 {
+                rolled_over = rolled_over.saturating_add(place_pool);
+            }
+            self.jackpot = self
+                .jackpot
+                .checked_add(rolled_over)
+                .ok_or(Error::ArithmeticOverflow)?;
+            // The shares of each pool add up to at most the pool
+            self.treasury = self
+                .treasury
+                .checked_add(pools.saturating_sub(paid).saturating_sub(rolled_over))
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.env().emit_event(OutrightSettled {
+                outright_id,
+                winner,
+                win_pool,
+                place_pool,
+            });
+            self.pay_all(payments)
+        }
+
+        /// Update the probabilities, in basis points, of each runner of an outright market
+        /// winning and finishing in the paid places, which value its cashouts until the
+        /// result. The win probabilities add up to at most 100%, and the place ones to at
+        /// most 100% per paid place.
+        #[ink(message, selector = 0xCA80D03E)]
+        pub fn update_outright_prices(
+            &mut self,
+            outright_id: OutrightId,
+            prices: Vec<(u32, u32)>,
+        ) -> Result<(), Error> {
+            if !self.score_oracles.contains(Self::env().caller()) {
+                return Err(Error::NotScoreOracle);
+            }
+            let mut outright = match self.outrights.get(outright_id) {
+                Some(outright) => outright,
+                None => return Err(Error::OutrightDoesNotExist),
+            };
+            let win_total = prices
+                .iter()
+                .map(|(win_bps, _)| u64::from(*win_bps))
+                .fold(0u64, u64::saturating_add);
+            let place_total = prices
+                .iter()
+                .map(|(_, place_bps)| u64::from(*place_bps))
+                .fold(0u64, u64::saturating_add);
+            if prices.len() != outright.outcome_labels.len()
+                || win_total > 10_000
+                || place_total > u64::from(outright.places).saturating_mul(10_000)
+                || prices
+                    .iter()
+                    .any(|(win_bps, place_bps)| win_bps > place_bps || *place_bps > 10_000)
+            {
+                return Err(Error::InvalidOdds);
+            }
+            outright.prices = prices;
+            outright.priced_block = self.env().block_number();
+            self.outrights.insert(outright_id, &outright);
+            self.env().emit_event(OutrightPricesUpdated { outright_id });
+            Ok(())
+        }
+
+        /// Exit a bet on an outright market before its result, selling it back to the pools
+        /// for its value at the latest prices minus the cashout fee. The prices must have
+        /// been updated within the last day.
+        #[ink(message, selector = 0x1384C4F5)]
+        pub fn cashout_outright(
+            &mut self,
+            outright_id: OutrightId,
+            bet_id: BetId,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let mut outright = match self.outrights.get(outright_id) {
+                Some(outright) => outright,
+                None => return Err(Error::OutrightDoesNotExist),
+            };
+            let index = match outright.bets.iter().position(|bet| bet.id == bet_id) {
+                Some(index) => index,
+                None => return Err(Error::BetDoesNotExist),
+            };
+            if outright.bets[index].bettor != caller {
+                return Err(Error::NotBettor);
+            }
+            if outright.prices.is_empty()
+                || self.env().block_number()
+                    > outright.priced_block.saturating_add(MAX_OUTRIGHT_PRICE_AGE)
+            {
+                return Err(Error::StalePrices);
+            }
+            let (win_value, place_value) =
+                Self::outright_cashout_value(&outright, &outright.bets[index])?;
+            outright.bets.remove(index);
+            // The value of each part is at most its pool, the rest of the stake stays in it
+            outright.win_pool = outright.win_pool.saturating_sub(win_value);
+            outright.place_pool = outright.place_pool.saturating_sub(place_value);
+            let value = win_value.saturating_add(place_value);
+            self.total_prize_pools = self.total_prize_pools.saturating_sub(value);
+            let fee = Self::bps_of(value, CASHOUT_FEE_BPS)?;
+            let amount = value.saturating_sub(fee);
+            self.collect_fee(caller, fee, None)?;
+            self.outrights.insert(outright_id, &outright);
+            self.stats.paid_out = self.stats.paid_out.saturating_add(amount);
+            self.env()
+                .transfer(self.get_payout_address(caller), amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(OutrightCashout {
+                outright_id,
+                bet_id,
+                who: caller,
+                amount,
+                fee,
+            });
+            Ok(())
+        }
+
+        /// Value of the win and place parts of an outright bet before fees: what each would
+        /// win from its pool at the current stakes, times the probability of the oracle
+        /// prices. The runner of a place part shares the place pool with the other placed
+        /// runners, so the part wins from its slice of the pool, one per place.
+        fn outright_cashout_value(
+            outright: &Outright,
+            bet: &OutrightBet,
+        ) -> Result<(Balance, Balance), Error> {
+            let (win_bps, place_bps) = Self::runner_index(outright, bet.runner)
+                .and_then(|index| outright.prices.get(index))
+                .copied()
+                .ok_or(Error::StalePrices)?;
+            let backers = outright
+                .bets
+                .iter()
+                .filter(|other_bet| other_bet.runner == bet.runner);
+            let win_backed =
+                Self::checked_sum(backers.clone().map(|other_bet| other_bet.win_stake))?;
+            let place_backed = Self::checked_sum(backers.map(|other_bet| other_bet.place_stake))?;
+            // The bet is part of both the backers and the pool, a stake of 0 is worth 0
+            let value = |stake: Balance, pool: Balance, backed: Balance, bps: u32| {
+                Self::mul_div(stake, pool, backed)
+                    .map_or(Ok(0), |if_won| Self::bps_of(if_won, Balance::from(bps)))
+            };
+            Ok((
+                value(bet.win_stake, outright.win_pool, win_backed, win_bps)?,
+                value(
+                    bet.place_stake,
+                    outright
+                        .place_pool
+                        .checked_div(Balance::from(outright.places))
+                        .unwrap_or(0),
+                    place_backed,
+                    place_bps,
+                )?,
+            ))
+        }
+
+        #[ink(message, selector = 0xAB03DF99)]
+        pub fn get_outright(&self, outright_id: OutrightId) -> Option<Outright> {
+            self.outrights.get(outright_id)
+        }
+
+        /// Whether the oracle timeout after the matches ending at `end` is over, so the
+        /// games waiting for their results can be refunded.
+        fn refund_open(&self, end: BlockNumber) -> bool {
+            self.env().block_number() > end.saturating_add(self.get_config().oracle_timeout)
+        }
+
+        /// Returns the entry fee to each of `entrants`, taking it out of the prize pools.
+        fn refund_entries(
+            &mut self,
+            entrants: &[AccountId],
+            entry_fee: Balance,
+        ) -> Result<(), Error> {
+            // The games have at most MAX_CONTEST_ENTRIES entries
+            let refund = entry_fee
+                .checked_mul(entrants.len() as Balance)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_prize_pools = self.total_prize_pools.saturating_sub(refund);
+            for entrant in entrants {
+                self.pay_or_defer(*entrant, entry_fee)?;
+            }
+            Ok(())
+        }
+
+        /// Shares a prize pool between its winners and moves what can't be split evenly to
+        /// the treasury. Returns the prize of each winner.
+        fn share_prize_pool(
+            &mut self,
+            winners: &[AccountId],
+            prize_pool: Balance,
+        ) -> Result<Balance, Error> {
+            let winners_count = winners.len() as Balance;
+            let prize = prize_pool.checked_div(winners_count).unwrap_or(0);
+            self.total_prize_pools = self.total_prize_pools.saturating_sub(prize_pool);
+            // The prizes add up to at most the prize pool
+            self.treasury = self
+                .treasury
+                .checked_add(prize_pool.saturating_sub(prize.saturating_mul(winners_count)))
+                .ok_or(Error::ArithmeticOverflow)?;
+            for winner in winners {
+                self.stats.paid_out = self.stats.paid_out.saturating_add(prize);
+                self.pay_or_defer(*winner, prize)?;
+            }
+            Ok(prize)
+        }
+
+        /// Buy `amount` shares of an outcome in an LMSR market. The transferred value
+        /// must cover the cost of the shares, the excess is returned.
+        #[ink(message, payable, selector = 0xF60E25EE)]
+        pub fn buy_shares(
+            &mut self,
+            match_id: MatchId,
+            outcome: MatchResult,
+            amount: Balance,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let mut match_to_bet = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let current_block_number = self.env().block_number();
+            if current_block_number > Self::betting_closes(&match_to_bet) {
+                return Err(Error::MatchHasStarted);
+            }
+            if match_to_bet.uniform_price {
+                return self.order_shares(match_id, match_to_bet, caller, outcome, amount);
+            }
+            let cost = Self::shares_cost(&match_to_bet, outcome, amount)?;
+            let paid = Self::env().transferred_value();
+            if paid < cost {
+                return Err(Error::InsufficientPayment);
+            }
+            // Add the shares to the position of the caller
+            if !match_to_bet
+                .bets
+                .iter()
+                .any(|bet| bet.bettor == caller && bet.result == outcome)
+                && self.is_full(&match_to_bet)
+            {
+                return Err(Error::MatchFull);
+            }
+            Self::add_shares(&mut match_to_bet, caller, outcome, amount)?;
+            match_to_bet.collected = match_to_bet
+                .collected
+                .checked_add(cost)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.record_stake(caller, cost)?;
+            self.stats.bets = self.stats.bets.saturating_add(1);
+            self.stats.volume = self.stats.volume.saturating_add(cost);
+            self.escrow_in(match_id, cost)?;
+            self.matches.insert(match_id, &match_to_bet);
+            // Return the excess
+            if paid > cost {
+                self.env()
+                    .transfer(caller, paid.saturating_sub(cost))
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            self.env().emit_event(SharesBought {
+                match_id,
+                who: caller,
+                outcome,
+                amount,
+                cost,
+            });
+
+            Ok(())
+        }
+
+        /// Adds shares to the position of a buyer, opening it if needed.
+        fn add_shares(
+            betting_match: &mut Match,
+            buyer: AccountId,
+            outcome: MatchResult,
+            amount: Balance,
+        ) -> Result<(), Error> {
+            match betting_match
+                .bets
+                .iter_mut()
+                .find(|bet| bet.bettor == buyer && bet.result == outcome)
+            {
+                Some(bet) => {
+                    bet.amount = bet
+                        .amount
+                        .checked_add(amount)
+                        .ok_or(Error::ArithmeticOverflow)?
+                }
+                None => {
+                    betting_match.bets.push(Bet {
+                        id: betting_match.next_bet_id,
+                        bettor: buyer,
+                        amount,
+                        result: outcome,
+                        credit: 0,
+                        placed_by: None,
+                        weight_bps: FULL_BET_WEIGHT_BPS,
+                    });
+                    betting_match.next_bet_id = betting_match
+                        .next_bet_id
+                        .checked_add(1)
+                        .ok_or(Error::ArithmeticOverflow)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Queues an order for shares of a uniform-price market, priced with the other
+        /// orders of its block once the block is over. The transferred value is the most
+        /// the buyer pays.
+        fn order_shares(
+            &mut self,
+            match_id: MatchId,
+            mut betting_match: Match,
+            buyer: AccountId,
+            outcome: MatchResult,
+            amount: Balance,
+        ) -> Result<(), Error> {
+            let max_cost = Self::env().transferred_value();
+            Self::outcome_index(outcome)?;
+            let current_block_number = self.env().block_number();
+            let mut refunds = Vec::new();
+            let mut batch = match self.share_batches.get(match_id) {
+                Some(batch) if batch.block == current_block_number => batch,
+                // The orders of a previous block are priced first
+                Some(batch) => {
+                    refunds = self.clear_share_batch(match_id, &mut betting_match, batch)?;
+                    ShareBatch {
+                        block: current_block_number,
+                        orders: Vec::new(),
+                    }
+                }
+                None => ShareBatch {
+                    block: current_block_number,
+                    orders: Vec::new(),
+                },
+            };
+            // Each order may open a position
+            if betting_match.bets.len().saturating_add(batch.orders.len())
+                >= self.max_bets_of(&betting_match)
+            {
+                return Err(Error::MatchFull);
+            }
+            self.record_stake(buyer, max_cost)?;
+            self.escrow_in(match_id, max_cost)?;
+            batch.orders.push(ShareOrder {
+                buyer,
+                outcome,
+                amount,
+                max_cost,
+            });
+            self.share_batches.insert(match_id, &batch);
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(SharesOrdered {
+                match_id,
+                who: buyer,
+                outcome,
+                amount,
+                max_cost,
+            });
+            self.pay_all(refunds)
+        }
+
+        /// Prices the share orders of a block at one price per share for each outcome,
+        /// whatever their order in the block. Orders whose most they pay is below the
+        /// price are refunded, the others get their shares and what they paid above it.
+        /// Returns the refunds, to pay once the match is stored.
+        fn clear_share_batch(
+            &mut self,
+            match_id: MatchId,
+            betting_match: &mut Match,
+            batch: ShareBatch,
+        ) -> Result<Vec<(AccountId, Balance)>, Error> {
+            let liquidity = match betting_match.market {
+                Market::Lmsr(liquidity) => liquidity,
+                _ => return Err(Error::WrongMarket),
+            };
+            self.share_batches.remove(match_id);
+            let quantities = Self::outstanding_shares(&betting_match.bets)?;
+            let mut orders = batch.orders;
+            let mut refunds = Vec::new();
+            // Refunding an order moves the prices, so the others are priced again
+            let prices = loop {
+                let prices = Self::batch_prices(quantities, liquidity, &orders)?;
+                let (filled, refused): (Vec<ShareOrder>, Vec<ShareOrder>) =
+                    orders.into_iter().partition(|order| {
+                        Self::order_cost(order, &prices).is_ok_and(|cost| cost <= order.max_cost)
+                    });
+                for order in refused.iter() {
+                    self.escrow_out(match_id, order.max_cost)?;
+                    refunds.push((order.buyer, order.max_cost));
+                }
+                orders = filled;
+                if refused.is_empty() {
+                    break prices;
+                }
+            };
+            for order in orders {
+                let cost = Self::order_cost(&order, &prices)?;
+                Self::add_shares(betting_match, order.buyer, order.outcome, order.amount)?;
+                betting_match.collected = betting_match
+                    .collected
+                    .checked_add(cost)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                self.stats.bets = self.stats.bets.saturating_add(1);
+                self.stats.volume = self.stats.volume.saturating_add(cost);
+                // The cost is at most what the buyer pays
+                let excess = order.max_cost.saturating_sub(cost);
+                if excess > 0 {
+                    self.escrow_out(match_id, excess)?;
+                    refunds.push((order.buyer, excess));
+                }
+                self.env().emit_event(SharesBought {
+                    match_id,
+                    who: order.buyer,
+                    outcome: order.outcome,
+                    amount: order.amount,
+                    cost,
+                });
+            }
+            Ok(refunds)
+        }
+
+        /// Shares ordered on each outcome and their cost, bought outcome after outcome.
+        fn batch_prices(
+            mut quantities: [Balance; 3],
+            liquidity: Balance,
+            orders: &[ShareOrder],
+        ) -> Result<[(Balance, Balance); 3], Error> {
+            let mut prices: [(Balance, Balance); 3] = [(0, 0); 3];
+            for order in orders {
+                let index = Self::outcome_index(order.outcome)?;
+                prices[index].0 = prices[index]
+                    .0
+                    .checked_add(order.amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            for (index, (shares, cost)) in prices.iter_mut().enumerate() {
+                if *shares == 0 {
+                    continue;
+                }
+                let before = lmsr::cost(&quantities, liquidity);
+                quantities[index] = quantities[index]
+                    .checked_add(*shares)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                let after = lmsr::cost(&quantities, liquidity);
+                *cost = before
+                    .zip(after)
+                    .map(|(before, after)| after.saturating_sub(before))
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            Ok(prices)
+        }
+
+        /// Cost of an order at the price per share of its outcome in the batch.
+        fn order_cost(
+            order: &ShareOrder,
+            prices: &[(Balance, Balance); 3],
+        ) -> Result<Balance, Error> {
+            let (shares, cost) = prices[Self::outcome_index(order.outcome)?];
+            // Round up in favour of the market maker
+            Self::mul_div(order.amount, cost, shares)
+                .and_then(|cost| cost.checked_add(1))
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Price the share orders of a uniform-price market once their block is over.
+        /// They are also priced by the next order or the settlement. Anyone can call it.
+        #[ink(message, selector = 0xF9A426F3)]
+        pub fn clear_share_orders(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let batch = match self.share_batches.get(match_id) {
+                Some(batch) => batch,
+                None => return Err(Error::NoShareOrders),
+            };
+            if batch.block >= self.env().block_number() {
+                return Err(Error::ShareOrdersOpen);
+            }
+            let refunds = self.clear_share_batch(match_id, &mut betting_match, batch)?;
+            self.matches.insert(match_id, &betting_match);
+            self.pay_all(refunds)
+        }
+
+        #[ink(message, selector = 0x1FFFBF10)]
+        pub fn get_share_orders(&self, match_id: MatchId) -> Option<ShareBatch> {
+            self.share_batches.get(match_id)
+        }
+
+        /// Make the shares bought in the same block of an LMSR market all get the same
+        /// price, whatever their order in the block, so reordering transactions around a
+        /// large order gains nothing. Orders are then priced once their block is over,
+        /// and the transferred value is the most the buyer pays. Only the creator can
+        /// change it, before the first order.
+        #[ink(message, selector = 0xE37ECF39)]
+        pub fn set_uniform_price(&mut self, match_id: MatchId, enabled: bool) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if !matches!(betting_match.market, Market::Lmsr(_)) {
+                return Err(Error::WrongMarket);
+            }
+            if !betting_match.bets.is_empty() || self.share_batches.contains(match_id) {
+                return Err(Error::MatchHasBets);
+            }
+            betting_match.uniform_price = enabled;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Cost of buying `amount` shares of an outcome in an LMSR market.
+        #[ink(message, selector = 0x03F95A1D)]
+        pub fn quote_shares(
+            &self,
+            match_id: MatchId,
+            outcome: MatchResult,
+            amount: Balance,
+        ) -> Result<Balance, Error> {
+            match self.matches.get(match_id) {
+                Some(betting_match) => Self::shares_cost(&betting_match, outcome, amount),
+                None => Err(Error::MatchDoesNotExist),
+            }
+        }
+
+        fn shares_cost(
+            betting_match: &Match,
+            outcome: MatchResult,
+            amount: Balance,
+        ) -> Result<Balance, Error> {
+            let liquidity = match betting_match.market {
+                Market::Lmsr(liquidity) => liquidity,
+                _ => return Err(Error::WrongMarket),
+            };
+            let mut quantities = Self::outstanding_shares(&betting_match.bets)?;
+            let before = lmsr::cost(&quantities, liquidity);
+            let index = Self::outcome_index(outcome)?;
+            quantities[index] = quantities[index]
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let after = lmsr::cost(&quantities, liquidity);
+            // Round up in favour of the market maker
+            before
+                .zip(after)
+                .and_then(|(before, after)| after.saturating_sub(before).checked_add(1))
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Amount bet, or shares held, on each outcome.
+        fn outstanding_shares(bets: &[Bet]) -> Result<[Balance; 3], Error> {
+            let mut quantities: [Balance; 3] = [0; 3];
+            for bet in bets {
+                let index = Self::outcome_index(bet.result)?;
+                quantities[index] = quantities[index]
+                    .checked_add(bet.amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            Ok(quantities)
+        }
+
+        fn outcome_index(outcome: MatchResult) -> Result<usize, Error> {
+            match outcome {
+                MatchResult::Team1Victory => Ok(0),
+                MatchResult::Team2Victory => Ok(1),
+                MatchResult::Draw => Ok(2),
+                _ => Err(Error::OutcomeNotAllowed),
+            }
+        }
+
+        /// Whether the outcomes of a match are the 1X2 ones of its teams.
+        fn is_one_x_two(betting_match: &Match) -> bool {
+            betting_match.outcome_labels
+                == OutcomeId::one_x_two(
+                    &betting_match.team1,
+                    &betting_match.team2,
+                    betting_match.allow_draw,
+                )
+        }
+
+        /// Whether an outcome is labelled by the match, `Voided` never is.
+        fn is_outcome(betting_match: &Match, outcome: OutcomeId) -> bool {
+            (0..betting_match.outcome_labels.len())
+                .any(|index| OutcomeId::nth(index) == Some(outcome))
+        }
+
+        /// Post a back or lay order on an outcome at the given odds (in hundredths).
+        /// Back orders transfer the stake, lay orders transfer the liability.
+        #[ink(message, payable, selector = 0xC6960086)]
+        pub fn post_order(
+            &mut self,
+            match_id: MatchId,
+            outcome: MatchResult,
+            side: OrderSide,
+            odds: u32,
+        ) -> Result<OrderId, Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let current_block_number = self.env().block_number();
+            if current_block_number > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            // The exchange settles on the result, not on handicaps
+            if matches!(betting_match.market, Market::Handicap(_)) {
+                return Err(Error::WrongMarket);
+            }
+            if !Self::is_allowed_bettor(&betting_match, &caller) {
+                return Err(Error::NotAllowedBettor);
+            }
+            if !Self::is_outcome(&betting_match, outcome)
+                || outcome == MatchResult::Draw && betting_match.market == Market::DrawNoBet
+            {
+                return Err(Error::OutcomeNotAllowed);
+            }
+            if odds <= ODDS_UNIT {
+                return Err(Error::InvalidOdds);
+            }
+            let transferred = Self::env().transferred_value();
+            let stake = match side {
+                OrderSide::Back => transferred,
+                OrderSide::Lay => transferred
+                    .checked_mul(Balance::from(ODDS_UNIT))
+                    .and_then(|amount| {
+                        amount.checked_div(Balance::from(odds.saturating_sub(ODDS_UNIT)))
+                    })
+                    .ok_or(Error::ArithmeticOverflow)?,
+            };
+            self.record_stake(caller, transferred)?;
+            // What a lay liability can't cover after rounding is returned
+            let mut rest = 0;
+            if side == OrderSide::Lay {
+                // The liability is never more than what was transferred
+                rest = transferred.saturating_sub(Self::liability(stake, odds)?);
+            }
+            // The funds of the order are held in escrow until cancelled or settled
+            self.total_escrow = self
+                .total_escrow
+                .checked_add(transferred.saturating_sub(rest))
+                .ok_or(Error::ArithmeticOverflow)?;
+            let order_id = self.next_order_id;
+            self.next_order_id = order_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            self.open_positions = self.open_positions.saturating_add(1);
+            self.orders.insert(
+                order_id,
+                &Order {
+                    owner: caller,
+                    match_id,
+                    match_hash: Self::get_match_hash(&betting_match),
+                    outcome,
+                    side,
+                    odds,
+                    stake,
+                },
+            );
+            if rest > 0 {
+                self.env()
+                    .transfer(caller, rest)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            self.env().emit_event(OrderPosted {
+                order_id,
+                match_id,
+                who: caller,
+                side,
+                outcome,
+                odds,
+                stake,
+            });
+
+            Ok(order_id)
+        }
+
+        /// Pair a back order with a lay order on the same outcome whose odds are at
+        /// least the back odds. They are matched at the odds of the older order, for
+        /// as much stake as both have left.
+        #[ink(message, selector = 0x7F7DD9EB)]
+        pub fn match_orders(
+            &mut self,
+            back_order_id: OrderId,
+            lay_order_id: OrderId,
+        ) -> Result<MatchedBetId, Error> {
+            let mut back = match self.orders.get(back_order_id) {
+                Some(order) => order,
+                None => return Err(Error::OrderDoesNotExist),
+            };
+            let mut lay = match self.orders.get(lay_order_id) {
+                Some(order) => order,
+                None => return Err(Error::OrderDoesNotExist),
+            };
+            if back.side != OrderSide::Back
+                || lay.side != OrderSide::Lay
+                || back.match_hash != lay.match_hash
+                || back.outcome != lay.outcome
+                || back.odds > lay.odds
+            {
+                return Err(Error::OrdersDoNotMatch);
+            }
+            // Orders can't be matched once the match has started
+            let current_block_number = self.env().block_number();
+            let draw_no_bet = match self.matches.get(back.match_id) {
+                Some(betting_match)
+                    if current_block_number <= Self::betting_closes(&betting_match) =>
+                {
+                    betting_match.market == Market::DrawNoBet
+                }
+                Some(_) => return Err(Error::MatchHasStarted),
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let odds = if back_order_id < lay_order_id {
+                back.odds
+            } else {
+                lay.odds
+            };
+            let stake = back.stake.min(lay.stake);
+            // The layer gets back the liability locked above the agreed odds
+            // The agreed odds are never above the lay odds
+            let excess =
+                Self::liability(stake, lay.odds)?.saturating_sub(Self::liability(stake, odds)?);
+            let volume = stake
+                .checked_add(Self::liability(stake, odds)?)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let matched_bet_id = self.next_matched_bet_id;
+            self.next_matched_bet_id = matched_bet_id
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            // The stake is the smaller of the two
+            back.stake = back.stake.saturating_sub(stake);
+            lay.stake = lay.stake.saturating_sub(stake);
+            for (order_id, order) in [(back_order_id, &back), (lay_order_id, &lay)] {
+                if order.stake == 0 {
+                    self.orders.remove(order_id);
+                    // Positions are counted when opened
+                    self.open_positions = self.open_positions.saturating_sub(1);
+                } else {
+                    self.orders.insert(order_id, order);
+                }
+            }
+            self.stats.bets = self.stats.bets.saturating_add(1);
+            self.stats.volume = self.stats.volume.saturating_add(volume);
+            self.open_positions = self.open_positions.saturating_add(1);
+            self.matched_bets.insert(
+                matched_bet_id,
+                &MatchedBet {
+                    backer: back.owner,
+                    layer: lay.owner,
+                    match_hash: back.match_hash,
+                    outcome: back.outcome,
+                    stake,
+                    odds,
+                    draw_no_bet,
+                },
+            );
+            self.total_escrow = self.total_escrow.saturating_sub(excess);
+            if excess > 0 {
+                self.env()
+                    .transfer(self.get_payout_address(lay.owner), excess)
+                    .map_err(|_| Error::TransferFailed)?;
+            }
+            self.env().emit_event(OrdersMatched {
+                matched_bet_id,
+                back_order_id,
+                lay_order_id,
+                stake,
+                odds,
+            });
+
+            Ok(matched_bet_id)
+        }
+
+        /// Cancel the unmatched part of an order, returning its funds.
+        #[ink(message, selector = 0xE90B7FF6)]
+        pub fn cancel_order(&mut self, order_id: OrderId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let order = match self.orders.get(order_id) {
+                Some(order) => order,
+                None => return Err(Error::OrderDoesNotExist),
+            };
+            if order.owner != caller {
+                return Err(Error::NotBettor);
+            }
+            self.orders.remove(order_id);
+            // Positions are counted when opened
+            self.open_positions = self.open_positions.saturating_sub(1);
+            let refund = match order.side {
+                OrderSide::Back => order.stake,
+                OrderSide::Lay => Self::liability(order.stake, order.odds)?,
+            };
+            self.total_escrow = self.total_escrow.saturating_sub(refund);
+            self.env()
+                .transfer(self.get_payout_address(caller), refund)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(OrderCancelled { order_id, refund });
+
+            Ok(())
+        }
+
+        /// Settle a matched bet once its match has a result, paying the stake and
+        /// the liability to the winning side, or back to each side if the match was
+        /// voided or a draw-no-bet match was drawn. Anyone can call it.
+        #[ink(message, selector = 0x222663D9)]
+        pub fn settle_matched_bet(&mut self, matched_bet_id: MatchedBetId) -> Result<(), Error> {
+            let matched_bet = match self.matched_bets.get(matched_bet_id) {
+                Some(matched_bet) => matched_bet,
+                None => return Err(Error::MatchedBetDoesNotExist),
+            };
+            let result = match self.final_result(matched_bet.match_hash) {
+                Some(result_odds) => result_odds.result,
+                None => return Err(Error::MatchNotResult),
+            };
+            if result == MatchResult::Voided
+                || result == MatchResult::Draw && matched_bet.draw_no_bet
+            {
+                let liability = Self::liability(matched_bet.stake, matched_bet.odds)?;
+                self.matched_bets.remove(matched_bet_id);
+                // Positions are counted when opened, and their funds held in escrow
+                self.open_positions = self.open_positions.saturating_sub(1);
+                self.total_escrow = self
+                    .total_escrow
+                    .saturating_sub(matched_bet.stake.saturating_add(liability));
+                for (who, amount) in [
+                    (matched_bet.backer, matched_bet.stake),
+                    (matched_bet.layer, liability),
+                ] {
+                    self.pay_or_defer(who, amount)?;
+                    self.env().emit_event(WinningsClaimed { who, amount });
+                }
+                return Ok(());
+            }
+            let winner = if result == matched_bet.outcome {
+                matched_bet.backer
+            } else {
+                matched_bet.layer
+            };
+            let amount = matched_bet
+                .stake
+                .checked_add(Self::liability(matched_bet.stake, matched_bet.odds)?)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.matched_bets.remove(matched_bet_id);
+            // Positions are counted when opened, and their funds held in escrow
+            self.open_positions = self.open_positions.saturating_sub(1);
+            self.total_escrow = self.total_escrow.saturating_sub(amount);
+            self.stats.paid_out = self.stats.paid_out.saturating_add(amount);
+            self.pay_or_defer(winner, amount)?;
+            self.env().emit_event(WinningsClaimed {
+                who: winner,
+                amount,
+            });
+            self.env().emit_event(MatchedBetSettled {
+                matched_bet_id,
+                winner,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x97AA69B0)]
+        pub fn get_order(&self, order_id: OrderId) -> Option<Order> {
+            self.orders.get(order_id)
+        }
+
+        #[ink(message, selector = 0x30ADC39E)]
+        pub fn get_matched_bet(&self, matched_bet_id: MatchedBetId) -> Option<MatchedBet> {
+            self.matched_bets.get(matched_bet_id)
+        }
+
+        /// Amount a layer risks on a backer stake at the given odds.
+        fn liability(stake: Balance, odds: u32) -> Result<Balance, Error> {
+            stake
+                .checked_mul(Balance::from(odds.saturating_sub(ODDS_UNIT)))
+                .and_then(|amount| amount.checked_div(Balance::from(ODDS_UNIT)))
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// When a match ends the owner of the match can distribute funds to the winners and delete the match.
+        #[ink(message, selector = 0xC2C13B48)]
+        pub fn distribute_winnings(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.settle(caller, caller, u32::MAX).map(|_| ())
+        }
+
+        /// Distribute the winnings of a match over several calls, making at most `max_bets`
+        /// payouts per call so large matches fit in the block weight limit. Returns whether
+        /// the match has been closed. Callers other than the creator must wait for the
+        /// settlement grace period, as in `settle_match`.
+        #[ink(message, selector = 0xE62E02CA)]
+        pub fn distribute_winnings_chunk(
+            &mut self,
+            match_id: MatchId,
+            max_bets: u32,
+        ) -> Result<bool, Error> {
+            let caller = Self::env().caller();
+            if caller != match_id {
+                self.ensure_grace_period_over(match_id)?;
+            }
+            self.settle(match_id, caller, max_bets)
+                .map(|report| report.is_some())
+        }
+
+        /// If the creator does not distribute the winnings, anyone can do it once the
+        /// settlement grace period after the result is over, earning a share of the deposit.
+        #[ink(message, selector = 0xB4344FED)]
+        pub fn settle_match(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_grace_period_over(match_id)?;
+            self.settle(match_id, caller, u32::MAX).map(|_| ())
+        }
+
+        /// Settle several matches with a result in one call, once their settlement grace
+        /// period is over. Winnings that can't be transferred are queued as claims. The
+        /// deposits go back to the creators less the slash for settling late, without an
+        /// incentive. The matches can have 400 bets in total. If any match can't be
+        /// settled, none is.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x3B5C0349)]
+        pub fn settle_matches(&mut self, match_ids: Vec<MatchId>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("settle_matches"), &match_ids);
+            if match_ids.is_empty() || match_ids.len() > MAX_BATCH_SETTLEMENTS {
+                return Err(Error::InvalidBatch);
+            }
+            let mut bets: usize = 0;
+            for match_id in match_ids.iter() {
+                self.ensure_grace_period_over(*match_id)?;
+                // Each match was checked to exist
+                let bet_count = self
+                    .matches
+                    .get(match_id)
+                    .map_or(0, |betting_match| betting_match.bets.len());
+                bets = bets.saturating_add(bet_count);
+            }
+            if bets > MAX_BATCH_SETTLEMENT_BETS {
+                return Err(Error::InvalidBatch);
+            }
+            for match_id in match_ids {
+                self.settle(match_id, match_id, u32::MAX)?;
+            }
+            Ok(())
+        }
+
+        /// Settle a match with a result by posting the Merkle root of its `(account, amount)`
+        /// payouts computed off-chain, so settlement costs the same whatever the number of
+        /// bets. Winners then claim with `claim_with_proof` within the claim period. The
+        /// `total` of the root and the `fees` taken off-chain are trusted up to the escrow of
+        /// the match, and the root can only be posted once the creator's grace period is
+        /// over. As in `settle_match`, the deposit is slashed for the late settlement and
+        /// pays the incentive of the caller, the rounding goes where the dust policy says
+        /// and a pool nobody won rolls over to the jackpot.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xC5776C38)]
+        pub fn post_payout_root(
+            &mut self,
+            match_id: MatchId,
+            root: [u8; 32],
+            total: Balance,
+            fees: Balance,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("post_payout_root"),
+                &(match_id, root, total, fees),
+            );
+            // The creator settles on chain first
+            self.ensure_grace_period_over(match_id)?;
+            let mut match_to_close = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let result = match match_to_close.result {
+                Some(result) => result,
+                None => return Err(Error::MatchNotResult),
+            };
+            if self.settlements.contains(match_id) {
+                return Err(Error::SettlementInProgress);
+            }
+            // The deposit of LMSR markets and the bankroll fund the payouts
+            if matches!(
+                match_to_close.market,
+                Market::Lmsr(_) | Market::FixedOdds(_)
+            ) {
+                return Err(Error::WrongMarket);
+            }
+            // Claims of a previous match of the creator are keyed by the same id
+            if self
+                .payout_roots
+                .get(match_id)
+                .is_some_and(|payout_root| payout_root.remaining > 0)
+            {
+                return Err(Error::ClaimsOutstanding);
+            }
+            let escrow = self.escrows.get(match_id).unwrap_or(0);
+            let mut deposit = match_to_close.deposit;
+            // Boosts are only paid by the settlement on chain, so the budget goes back to
+            // the creator with the deposit
+            let boost_budget = match_to_close.boost_budget;
+            // The escrow holds the deposit, the boost budget, the bets and the sealed stakes
+            let sealed = self.sealed_stakes.get(match_id).unwrap_or(0);
+            let pool = escrow
+                .saturating_sub(deposit)
+                .saturating_sub(boost_budget)
+                .saturating_sub(sealed);
+            let mut incentive = 0;
+            if caller != match_id {
+                incentive = (deposit
+                    .checked_mul(SETTLEMENT_INCENTIVE_PERCENT)
+                    .ok_or(Error::ArithmeticOverflow)?
+                    / 100)
+                    .min(deposit);
+                deposit = deposit.saturating_sub(incentive);
+            }
+            // The root can share the deposit slashed for the late settlement among the
+            // winners, as the settlement on chain does
+            let slashed = self.late_settlement_slash(&match_to_close)?.min(deposit);
+            deposit = deposit.saturating_sub(slashed);
+            let available = pool.checked_add(slashed).ok_or(Error::ArithmeticOverflow)?;
+            let paid = total.checked_add(fees).ok_or(Error::ArithmeticOverflow)?;
+            if paid > available {
+                return Err(Error::EscrowExceeded);
+            }
+            // The payouts stay in the total escrow until claimed, and the sealed stakes of a
+            // void until refunded, those of a result go to the treasury
+            self.escrows.remove(match_id);
+            self.sealed_stakes.remove(match_id);
+            let mut kept = total;
+            if result == MatchResult::Voided {
+                kept = kept.saturating_add(sealed);
+            } else {
+                self.treasury = self
+                    .treasury
+                    .checked_add(sealed)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            self.total_escrow = self
+                .total_escrow
+                .saturating_sub(escrow.saturating_sub(kept));
+            let total_pool = Self::total_pool(&match_to_close)?;
+            // The fees of a root are paid by all the winners, no referrer shares them
+            self.collect_fee(self.env().account_id(), fees, Some((match_id, total_pool)))?;
+            let left = available.saturating_sub(paid);
+            if total == 0 {
+                // A pool nobody won rolls over to the jackpot, the slashed deposit stays in
+                // the treasury
+                let rolled_over = left.min(pool);
+                self.jackpot = self
+                    .jackpot
+                    .checked_add(rolled_over)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                self.treasury = self
+                    .treasury
+                    .checked_add(left.saturating_sub(rolled_over))
+                    .ok_or(Error::ArithmeticOverflow)?;
+            } else if self.get_config().dust_policy == DustPolicy::Creator {
+                deposit = deposit.checked_add(left).ok_or(Error::ArithmeticOverflow)?;
+            } else {
+                // The root pays the last winner, so its rounding goes to the treasury
+                self.treasury = self
+                    .treasury
+                    .checked_add(left)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            self.payout_roots.insert(
+                match_id,
+                &PayoutRoot {
+                    root,
+                    remaining: total,
+                    deadline: self
+                        .env()
+                        .block_number()
+                        .saturating_add(self.get_config().claim_period),
+                },
+            );
+            self.matches.remove(match_id);
+            // The match was counted when created
+            self.open_matches = self.open_matches.saturating_sub(1);
+            self.matches_hashes
+                .take(Self::get_match_hash(&match_to_close));
+            self.record_settled(match_id, &mut match_to_close, result);
+            self.share_jackpot(match_id, &match_to_close)?;
+            self.env().emit_event(PayoutRootPosted {
+                match_id,
+                root,
+                total,
+            });
+            if slashed > 0 {
+                self.env().emit_event(DepositSlashed {
+                    match_id,
+                    amount: slashed,
+                });
+            }
+            if incentive > 0 {
+                self.pay_or_defer(caller, incentive)?;
+            }
+            // The deposit goes back to whoever paid it
+            let deposit = deposit
+                .checked_add(boost_budget)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let payer = self.deposit_payers.take(match_id).unwrap_or(match_id);
+            self.pay_or_defer(payer, deposit)?;
+            self.env().emit_event(DepositReturned {
+                match_id,
+                who: payer,
+                amount: deposit,
+            });
+            self.env().emit_event(MatchClosed { match_id });
+
+            Ok(())
+        }
+
+        /// Claim the payout of the caller in a match settled with `post_payout_root`.
+        /// `proof` holds the sibling hashes from the leaf up to the root, each pair of
+        /// nodes being hashed in ascending order.
+        #[ink(message, selector = 0x3243B2A2)]
+        pub fn claim_with_proof(
+            &mut self,
+            match_id: MatchId,
+            amount: Balance,
+            proof: Vec<[u8; 32]>,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let mut payout_root = match self.payout_roots.get(match_id) {
+                Some(payout_root) => payout_root,
+                None => return Err(Error::NoPayoutRoot),
+            };
+            if self.env().block_number() > payout_root.deadline {
+                return Err(Error::ClaimPeriodOver);
+            }
+            if self.root_claims.contains((payout_root.root, caller)) {
+                return Err(Error::AlreadyClaimed);
+            }
+            if Self::merkle_root((caller, amount), &proof) != payout_root.root {
+                return Err(Error::InvalidProof);
+            }
+            if amount > payout_root.remaining {
+                return Err(Error::EscrowExceeded);
+            }
+            payout_root.remaining = payout_root.remaining.saturating_sub(amount);
+            // The total escrow includes the funds left to claim
+            self.total_escrow = self.total_escrow.saturating_sub(amount);
+            self.payout_roots.insert(match_id, &payout_root);
+            self.root_claims.insert((payout_root.root, caller), &());
+            self.stats.paid_out = self.stats.paid_out.saturating_add(amount);
+            self.env()
+                .transfer(self.get_payout_address(caller), amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(WinningsClaimed {
+                who: caller,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x3DE727C0)]
+        pub fn get_payout_root(&self, match_id: MatchId) -> Option<PayoutRoot> {
+            self.payout_roots.get(match_id)
+        }
+
+        /// Sweep the payouts of a match left unclaimed after the claim deadline to the
+        /// jackpot, or to the treasury. Pending claims are swept with `sweep_pending_claim`.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x760A0245)]
+        pub fn sweep_unclaimed(
+            &mut self,
+            match_id: MatchId,
+            to_jackpot: bool,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("sweep_unclaimed"),
+                &(match_id, to_jackpot),
+            );
+            let mut payout_root = match self.payout_roots.get(match_id) {
+                Some(payout_root) => payout_root,
+                None => return Err(Error::NoPayoutRoot),
+            };
+            if self.env().block_number() <= payout_root.deadline {
+                return Err(Error::ClaimPeriodNotOver);
+            }
+            let amount = payout_root.remaining;
+            if amount == 0 {
+                return Err(Error::NothingToClaim);
+            }
+            if to_jackpot {
+                self.jackpot = self
+                    .jackpot
+                    .checked_add(amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            } else {
+                self.treasury = self
+                    .treasury
+                    .checked_add(amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            // The total escrow includes the funds left to claim
+            self.total_escrow = self.total_escrow.saturating_sub(amount);
+            payout_root.remaining = 0;
+            self.payout_roots.insert(match_id, &payout_root);
+            self.env().emit_event(UnclaimedSwept {
+                match_id,
+                amount,
+                to_jackpot,
+            });
+
+            Ok(())
+        }
+
+        /// Sweep the pending claim of `account` to the jackpot, or to the treasury, once
+        /// the claim period has passed since the last payment deferred to it.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x93E937A8)]
+        pub fn sweep_pending_claim(
+            &mut self,
+            account: AccountId,
+            to_jackpot: bool,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("sweep_pending_claim"),
+                &(account, to_jackpot),
+            );
+            let amount = match self.pending_claims.get(account) {
+                Some(amount) => amount,
+                None => return Err(Error::NothingToClaim),
+            };
+            let deadline = self
+                .pending_claim_blocks
+                .get(account)
+                .unwrap_or(0)
+                .saturating_add(self.get_config().claim_period);
+            if self.env().block_number() <= deadline {
+                return Err(Error::ClaimPeriodNotOver);
+            }
+            if to_jackpot {
+                self.jackpot = self
+                    .jackpot
+                    .checked_add(amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            } else {
+                self.treasury = self
+                    .treasury
+                    .checked_add(amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            self.pending_claims.remove(account);
+            self.pending_claim_blocks.remove(account);
+            // The total includes the claim
+            self.total_pending_claims = self.total_pending_claims.saturating_sub(amount);
+            self.env().emit_event(PendingClaimSwept {
+                who: account,
+                amount,
+                to_jackpot,
+            });
+
+            Ok(())
+        }
+
+        /// Root of the Merkle tree with the `payout` leaf and the sibling hashes of `proof`.
+        /// A leaf is the hash of the encoded payout, a node the hash of its two children
+        /// in ascending order.
+        fn merkle_root(payout: (AccountId, Balance), proof: &[[u8; 32]]) -> [u8; 32] {
+            let mut node = [0u8; 32];
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(&payout, &mut node);
+            for sibling in proof {
+                let pair = if node <= *sibling {
+                    (node, *sibling)
+                } else {
+                    (*sibling, node)
+                };
+                ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(&pair, &mut node);
+            }
+            node
+        }
+
+        /// Checks the settlement grace period after the result of a match is over.
+        fn ensure_grace_period_over(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let mut match_to_settle = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.finalize_proposed_result(match_id, &mut match_to_settle)? {
+                self.matches.insert(match_id, &match_to_settle);
+            }
+            if match_to_settle.result.is_none() {
+                return Err(Error::MatchNotResult);
+            }
+            let current_block_number = self.env().block_number();
+            if current_block_number
+                <= match_to_settle
+                    .result_block
+                    .saturating_add(self.get_config().settlement_grace_period)
+            {
+                return Err(Error::SettlementGracePeriodNotOver);
+            }
+            Ok(())
+        }
+
+        /// Set the blocks after the result before anyone can settle a match.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0xA44C54BE)]
+        pub fn set_settlement_grace_period(&mut self, blocks: BlockNumber) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_settlement_grace_period"), &blocks);
+            self.update_config(|config| config.settlement_grace_period = blocks)
+        }
+
+        /// Set the blocks after the result during which the owner or the oracle can
+        /// correct it.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0xF9CD319B)]
+        pub fn set_correction_window(&mut self, blocks: BlockNumber) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_correction_window"), &blocks);
+            self.update_config(|config| config.correction_window = blocks)
+        }
+
+        /// Set who gets the remainder of the integer divisions of the distribution: the
+        /// last winner paid, the creator of the match or the treasury.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x0ECF231B)]
+        pub fn set_dust_policy(&mut self, policy: DustPolicy) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_dust_policy"), &policy);
+            self.update_config(|config| config.dust_policy = policy)
+        }
+
+        /// Set the blocks after a payout root is posted during which the payouts can be
+        /// claimed.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x2A4A4394)]
+        pub fn set_claim_period(&mut self, blocks: BlockNumber) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_claim_period"), &blocks);
+            self.update_config(|config| config.claim_period = blocks)
+        }
+
+        #[ink(message, selector = 0x10CD9E72)]
+        pub fn get_settlement_grace_period(&self) -> BlockNumber {
+            self.get_config().settlement_grace_period
+        }
+
+        /// Set the configuration of the contract.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0xCFDF5C7B)]
+        pub fn set_config(&mut self, config: Config) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_config"), &config);
+            self.update_config(|current| *current = config)
+        }
+
+        #[ink(message, selector = 0xD0707806)]
+        pub fn get_config(&self) -> Config {
+            self.config.get_or_default()
+        }
+
+        /// Version of the message API, for frontends and calling contracts to check they
+        /// speak the same one as this code.
+        #[ink(message, selector = 0xC7E0CA63)]
+        pub fn api_version(&self) -> u32 {
+            API_VERSION
+        }
+
+        /// Applies `update` to the configuration after checking the caller is the
+        /// governance contract, or the owner if there is none, and the result is valid.
+        /// Fee changes have to be scheduled while the admin delay is set.
+        fn update_config(&mut self, update: impl FnOnce(&mut Config)) -> Result<(), Error> {
+            self.ensure_config_admin()?;
+            let current = self.get_config();
+            let mut config = current;
+            update(&mut config);
+            if self.admin_delay > 0
+                && (config.fee_bps != current.fee_bps
+                    || config.min_match_fee_bps != current.min_match_fee_bps
+                    || config.max_match_fee_bps != current.max_match_fee_bps)
+            {
+                return Err(Error::ActionTimelocked);
+            }
+            self.apply_config(config)
+        }
+
+        /// Stores the configuration if it is valid.
+        fn apply_config(&mut self, config: Config) -> Result<(), Error> {
+            if config.min_bet > config.max_bet
+                || config.fee_bps > 10_000
+                || config.min_match_fee_bps > config.max_match_fee_bps
+                || config.max_match_fee_bps > 10_000
+                || config.score_quorum == 0
+                || config.epoch_length == 0
+                || config.rate_limit_window == 0
+                || config.min_match_length == 0
+                || config.min_match_length > config.max_match_length
+                || config
+                    .fee_tiers
+                    .iter()
+                    .any(|tier| tier.discount_bps > 10_000)
+            {
+                return Err(Error::InvalidConfig);
+            }
+            self.config.set(&config);
+            self.env().emit_event(ConfigUpdated { config });
+            Ok(())
+        }
+
+        /// Distributes the funds of a match to the winners and deletes it, making at most
+        /// `max_payouts` payouts per call. A settlement spanning several calls keeps its
+        /// progress in `settlements`. Returns whether the match has been closed.
+        /// The deposit goes back to the creator, minus the incentive of a `settler` other
+        /// than them.
+        fn settle(
+            &mut self,
+            match_id: MatchId,
+            settler: AccountId,
+            max_payouts: u32,
+        ) -> Result<Option<SettlementReport>, Error> {
+            // Get the match to close
+            let mut match_to_delete = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let finalized = self.finalize_proposed_result(match_id, &mut match_to_delete)?;
+            // Make sure the match has a result set already
+            let result = match match_to_delete.result {
+                Some(result) => result,
+                None => return Err(Error::MatchNotResult),
+            };
+            // The payments are made once the settlement is recorded
+            let mut payments = Vec::new();
+            // The last share orders are priced before the payouts
+            if let Some(batch) = self.share_batches.get(match_id) {
+                payments = self.clear_share_batch(match_id, &mut match_to_delete, batch)?;
+            }
+            let started = self.settlements.get(match_id);
+            // The payouts are computed once, the later calls only make their share of them
+            let (payouts, paid) = match started {
+                Some(settlement) => (
+                    self.settlement_payouts.get(match_id).unwrap_or_default(),
+                    settlement.paid,
+                ),
+                None => {
+                    let payouts = Self::bettor_payouts(Self::payouts(&match_to_delete, result)?)?;
+                    let paid = Self::checked_sum(
+                        payouts
+                            .iter()
+                            .map(|payout| payout.won.saturating_add(payout.refunded)),
+                    )?;
+                    (payouts, paid)
+                }
+            };
+            // Starting the distribution closes the correction window
+            if started.is_none() && match_to_delete.final_block > self.env().block_number() {
+                match_to_delete.final_block = self.env().block_number();
+                let match_hash = Self::get_match_hash(&match_to_delete);
+                if let Some(mut result_odds) = self.results.get(match_hash) {
+                    result_odds.final_block = match_to_delete.final_block;
+                    self.results.insert(match_hash, &result_odds);
+                }
+            }
+            // The settler starting the settlement earns the incentive
+            let settler = started.map_or(settler, |settlement| settlement.settler);
+            // Return deposit, with what is left of the shares sold in LMSR markets
+            let mut deposit = match_to_delete.deposit;
+            if let Market::Lmsr(_) = match_to_delete.market {
+                deposit = deposit
+                    .checked_add(match_to_delete.collected)
+                    .ok_or(Error::ArithmeticOverflow)?
+                    .saturating_sub(paid);
+            }
+            // The deposit of fixed-odds markets pays their losses before the bankroll
+            let mut creator_loss = 0;
+            if let Market::FixedOdds(_) = match_to_delete.market {
+                creator_loss = paid
+                    .saturating_sub(Self::total_pool(&match_to_delete)?)
+                    .min(deposit);
+                deposit = deposit.saturating_sub(creator_loss);
+            }
+            let mut incentive = 0;
+            if settler != match_id {
+                incentive = (match_to_delete
+                    .deposit
+                    .checked_mul(SETTLEMENT_INCENTIVE_PERCENT)
+                    .ok_or(Error::ArithmeticOverflow)?
+                    / 100)
+                    .min(deposit);
+                deposit = deposit.saturating_sub(incentive);
+            }
+            // Slash the deposit for each block the settlement is late, sharing it
+            // among the winners or keeping it in the treasury if nobody won.
+            let mut settlement = started.unwrap_or(Settlement {
+                settler,
+                cursor: 0,
+                paid,
+                made: 0,
+                slashed: self.late_settlement_slash(&match_to_delete)?.min(deposit),
+                shared: 0,
+                winners_count: 0,
+                fees: 0,
+                boosted: 0,
+            });
+            let slashed = settlement.slashed;
+            // The escrow must cover the payouts left and the deposit, minus the shares of
+            // the slashed deposit already paid
+            // The payouts made are part of the total
+            let remaining = paid.saturating_sub(settlement.made);
+            let needed = remaining
+                .checked_add(deposit)
+                .and_then(|needed| needed.checked_add(incentive))
+                .ok_or(Error::ArithmeticOverflow)?
+                .saturating_sub(settlement.shared);
+            self.cover_shortfall(match_id, needed)?;
+            // The fee share of the creator steps up with the milestones the pool reached
+            let total_pool = Self::total_pool(&match_to_delete)?;
+            for BettorPayout {
+                bettor,
+                mut won,
+                refunded,
+                refunded_credit,
+                withheld,
+                stake,
+            } in payouts
+                .iter()
+                .skip(settlement.cursor as usize)
+                .take(max_payouts as usize)
+            {
+                let total = won
+                    .checked_add(*refunded)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                settlement.made = settlement.made.saturating_add(total);
+                let share = slashed
+                    .checked_mul(total)
+                    .ok_or(Error::ArithmeticOverflow)?
+                    .checked_div(paid);
+                if let Some(share) = share {
+                    // The shares add up to at most the slashed deposit
+                    settlement.shared = settlement.shared.saturating_add(share);
+                    won = won.checked_add(share).ok_or(Error::ArithmeticOverflow)?;
+                }
+                let total = won
+                    .checked_add(*refunded)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                self.escrow_out(match_id, total)?;
+                self.credit_free_bet(*bettor, *refunded_credit)?;
+                self.treasury = self
+                    .treasury
+                    .checked_add(*withheld)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                // The withheld credits are part of the winnings
+                won = won.saturating_sub(*withheld);
+                // The credits are part of the total
+                let total = total
+                    .saturating_sub(*refunded_credit)
+                    .saturating_sub(*withheld);
+                // Take the protocol fee on the winnings, less the discount of stakers
+                let fee = Self::bps_of(won, Balance::from(self.match_fee_bps(&match_to_delete)))?;
+                let discount = Self::bps_of(fee, Balance::from(self.fee_discount_bps(*bettor)))?;
+                // The discount is at most 100%
+                let fee = fee.saturating_sub(discount);
+                self.collect_fee(*bettor, fee, Some((match_id, total_pool)))?;
+                settlement.fees = settlement.fees.saturating_add(fee);
+                // The fee is at most 100% of the winnings
+                won = won.saturating_sub(fee);
+                let mut total = total.saturating_sub(fee);
+                // The boost is paid from the budget of the creator until it runs out
+                let boost = Self::bps_of(
+                    won.saturating_sub(*stake),
+                    Balance::from(match_to_delete.boost_bps),
+                )?
+                .min(
+                    match_to_delete
+                        .boost_budget
+                        .saturating_sub(settlement.boosted),
+                );
+                if boost > 0 {
+                    self.escrow_out(match_id, boost)?;
+                    settlement.boosted = settlement.boosted.saturating_add(boost);
+                    won = won.checked_add(boost).ok_or(Error::ArithmeticOverflow)?;
+                    total = total.checked_add(boost).ok_or(Error::ArithmeticOverflow)?;
+                    self.env().emit_event(WinningsBoosted {
+                        match_id,
+                        who: *bettor,
+                        amount: boost,
+                    });
+                }
+                self.stats.paid_out = self.stats.paid_out.saturating_add(total);
+                payments.push((*bettor, total));
+                if won > 0 {
+                    settlement.winners_count = settlement.winners_count.saturating_add(1);
+                }
+                self.add_season_points(*bettor, *stake)?;
+                if *refunded > 0 {
+                    self.env().emit_event(BetRefunded {
+                        match_id,
+                        who: *bettor,
+                        amount: *refunded,
+                    });
+                }
+                settlement.cursor = settlement.cursor.saturating_add(1);
+            }
+            if (settlement.cursor as usize) < payouts.len() {
+                self.settlements.insert(match_id, &settlement);
+                if started.is_none() {
+                    self.settlement_payouts.insert(match_id, &payouts);
+                }
+                if finalized || started.is_none() {
+                    self.matches.insert(match_id, &match_to_delete);
+                }
+                self.pay_all(payments)?;
+                return Ok(None);
+            }
+            // The creator settled within the grace period
+            if settler == match_id
+                && self.env().block_number()
+                    <= match_to_delete
+                        .result_block
+                        .saturating_add(self.get_config().settlement_grace_period)
+            {
+                self.update_reputation(match_id, |reputation| {
+                    reputation.settled_on_time = reputation.settled_on_time.saturating_add(1)
+                });
+            }
+            // Every bet of a match played out accrues reward points, won or lost
+            if result != MatchResult::Voided {
+                for bet in match_to_delete.bets.iter() {
+                    self.accrue_reward_points(bet.bettor, bet.amount)?;
+                }
+            }
+            // Delete the match
+            self.settlements.remove(match_id);
+            self.settlement_payouts.remove(match_id);
+            self.matches.remove(match_id);
+            // The match was counted when created
+            self.open_matches = self.open_matches.saturating_sub(1);
+            // Hash the match object and delete it from the hash mapping.
+            let match_hash = Self::get_match_hash(&match_to_delete);
+            self.matches_hashes.take(match_hash);
+            self.env().emit_event(WinningsDistributed {
+                match_id,
+                total_pool: Self::total_pool(&match_to_delete)?,
+                winners_count: settlement.winners_count,
+            });
+            self.record_settled(match_id, &mut match_to_delete, result);
+            // The slashed part is at most the deposit
+            deposit = deposit.saturating_sub(slashed);
+            // The deposit bears what the insurance fund couldn't cover
+            let available = self.escrows.get(match_id).unwrap_or(0);
+            incentive = incentive.min(available);
+            deposit = deposit.min(available.saturating_sub(incentive));
+            self.escrow_out(
+                match_id,
+                incentive
+                    .checked_add(deposit)
+                    .ok_or(Error::ArithmeticOverflow)?,
+            )?;
+            // The boost budget left goes back to the creator with the deposit
+            let unused_boost = match_to_delete
+                .boost_budget
+                .saturating_sub(settlement.boosted)
+                .min(self.escrows.get(match_id).unwrap_or(0));
+            self.escrow_out(match_id, unused_boost)?;
+            deposit = deposit
+                .checked_add(unused_boost)
+                .ok_or(Error::ArithmeticOverflow)?;
+            // What is left, the slashed deposit nobody shared and rounding, goes to the treasury
+            let mut left = self.escrows.take(match_id).unwrap_or(0);
+            // but the sealed stakes of a void, which stay in the total escrow to be refunded
+            let sealed = self.sealed_stakes.take(match_id).unwrap_or(0);
+            if result == MatchResult::Voided {
+                left = left.saturating_sub(sealed);
+            }
+            self.total_escrow = self.total_escrow.saturating_sub(left);
+            // except the backing of fixed-odds markets and the stakes lost to the bankroll
+            let mut returned = 0;
+            if let Market::FixedOdds(_) = match_to_delete.market {
+                returned = Self::total_pool(&match_to_delete)?
+                    .checked_add(match_to_delete.backed)
+                    .ok_or(Error::ArithmeticOverflow)?
+                    .saturating_sub(paid.saturating_sub(creator_loss))
+                    .min(left);
+                self.bankroll_locked = self.bankroll_locked.saturating_sub(match_to_delete.backed);
+                self.unsettled_fixed_odds = self.unsettled_fixed_odds.saturating_sub(1);
+                self.bankroll = self
+                    .bankroll
+                    .checked_add(returned)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            let left = left.saturating_sub(returned);
+            // and a pool nobody won rolls over to the jackpot
+            let mut rolled_over = 0;
+            let pari_mutuel = !matches!(
+                match_to_delete.market,
+                Market::Lmsr(_) | Market::FixedOdds(_)
+            );
+            if paid == 0 && pari_mutuel {
+                rolled_over = Self::total_pool(&match_to_delete)?.min(left);
+            }
+            let left = left.saturating_sub(rolled_over);
+            // The rounding of the shares of the pool and of the slashed deposit goes
+            // where the dust policy says
+            let mut dust = 0;
+            if paid > 0 && pari_mutuel {
+                dust = Self::total_pool(&match_to_delete)?
+                    .saturating_sub(paid)
+                    .saturating_add(slashed.saturating_sub(settlement.shared))
+                    .min(left);
+            }
+            let rounding = dust;
+            let last_winner = payouts
+                .iter()
+                .rev()
+                .find(|payout| payout.won > 0)
+                .map(|payout| payout.bettor);
+            match (self.get_config().dust_policy, last_winner) {
+                (DustPolicy::LastWinner, Some(winner)) => {
+                    self.stats.paid_out = self.stats.paid_out.saturating_add(dust);
+                    payments.push((winner, dust));
+                }
+                (DustPolicy::Creator, _) => {
+                    deposit = deposit.checked_add(dust).ok_or(Error::ArithmeticOverflow)?;
+                }
+                _ => dust = 0,
+            }
+            self.jackpot = self
+                .jackpot
+                .checked_add(rolled_over)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.treasury = self
+                .treasury
+                .checked_add(left.saturating_sub(dust))
+                .ok_or(Error::ArithmeticOverflow)?;
+            if slashed > 0 {
+                self.env().emit_event(DepositSlashed {
+                    match_id,
+                    amount: slashed,
+                });
+            }
+            if incentive > 0 {
+                payments.push((settler, incentive));
+            }
+            // The deposit goes back to whoever paid it
+            let payer = self.deposit_payers.take(match_id).unwrap_or(match_id);
+            payments.push((payer, deposit));
+            self.env().emit_event(DepositReturned {
+                match_id,
+                who: payer,
+                amount: deposit,
+            });
+            self.env().emit_event(MatchClosed { match_id });
+            // Bettors without a payout lost every bet
+            let mut losers: Vec<AccountId> = Vec::new();
+            for bet in match_to_delete.bets.iter() {
+                if !losers.contains(&bet.bettor)
+                    && !payouts.iter().any(|payout| payout.bettor == bet.bettor)
+                {
+                    losers.push(bet.bettor);
+                }
+            }
+            let report = SettlementReport {
+                total_pool: Self::total_pool(&match_to_delete)?,
+                winners: settlement.winners_count,
+                losers: losers.len() as u32,
+                fees: settlement.fees,
+                dust: rounding,
+                boosted: settlement.boosted,
+            };
+            self.settlement_reports.insert(match_id, &report);
+            self.share_jackpot(match_id, &match_to_delete)?;
+            self.pay_all(payments)?;
+
+            Ok(Some(report))
+        }
+
+        /// Report of the last settlement of a match, `None` if it was never settled on
+        /// chain.
+        #[ink(message, selector = 0xF69A4B9D)]
+        pub fn get_settlement(&self, match_id: MatchId) -> Option<SettlementReport> {
+            self.settlement_reports.get(match_id)
+        }
+
+        /// Records the blocks at which the result of a match became final and its
+        /// distribution completed, and emits them for audits.
+        fn record_settled(
+            &mut self,
+            match_id: MatchId,
+            betting_match: &mut Match,
+            result: MatchResult,
+        ) {
+            if let Some((team1, team2)) = betting_match.team_ids {
+                self.record_team_result(team1, team2, result);
+            }
+            self.closed_statuses.insert(match_id, &betting_match.status);
+            let current_block_number = self.env().block_number();
+            betting_match.final_block = betting_match.final_block.min(current_block_number);
+            betting_match.settled_block = current_block_number;
+            self.env().emit_event(MatchSettled {
+                match_id,
+                result,
+                final_block: betting_match.final_block,
+                settled_block: betting_match.settled_block,
+            });
+        }
+
+        /// Adds the result of a settled match to the records of its teams.
+        fn record_team_result(&mut self, team1: TeamId, team2: TeamId, result: MatchResult) {
+            let mut stats1 = self.team_stats.get(team1).unwrap_or_default();
+            let mut stats2 = self.team_stats.get(team2).unwrap_or_default();
+            match result {
+                MatchResult::Team1Victory => {
+                    stats1.wins = stats1.wins.saturating_add(1);
+                    stats2.losses = stats2.losses.saturating_add(1);
+                }
+                MatchResult::Team2Victory => {
+                    stats1.losses = stats1.losses.saturating_add(1);
+                    stats2.wins = stats2.wins.saturating_add(1);
+                }
+                MatchResult::Draw => {
+                    stats1.draws = stats1.draws.saturating_add(1);
+                    stats2.draws = stats2.draws.saturating_add(1);
+                }
+                // Voided matches don't count
+                _ => return,
+            }
+            self.team_stats.insert(team1, &stats1);
+            self.team_stats.insert(team2, &stats2);
+        }
+
+        /// Payouts of a match with a result, in the order of its bets.
+        fn payouts(betting_match: &Match, result: MatchResult) -> Result<Vec<Payout>, Error> {
+            Ok(match (betting_match.market, betting_match.score) {
+                // Sponsors get their funds back
+                _ if result == MatchResult::Voided => {
+                    let mut refunds = Self::refunds(&betting_match.bets);
+                    refunds.extend(
+                        betting_match
+                            .sponsors
+                            .iter()
+                            .map(|(sponsor, amount)| Payout {
+                                bettor: *sponsor,
+                                won: 0,
+                                refunded: *amount,
+                                credit: 0,
+                                stake: 0,
+                            }),
+                    );
+                    refunds
+                }
+                (Market::Winner, _) => {
+                    Self::winner_payouts(&betting_match.bets, result, betting_match.pot_bonus)?
+                }
+                // A draw refunds every stake
+                (Market::DrawNoBet, _) if result == MatchResult::Draw => {
+                    Self::refunds(&betting_match.bets)
+                }
+                (Market::DrawNoBet, _) => {
+                    Self::winner_payouts(&betting_match.bets, result, betting_match.pot_bonus)?
+                }
+                (Market::Handicap(line), Some(score)) => {
+                    Self::handicap_payouts(&betting_match.bets, line, score)?
+                }
+                (Market::Handicap(_), None) => return Err(Error::ScoreRequired),
+                // Winning shares are redeemed 1:1
+                (Market::FixedOdds(odds), _) => {
+                    let index = Self::outcome_index(result)?;
+                    let mut payouts = Vec::new();
+                    for bet in betting_match.bets.iter().filter(|bet| bet.result == result) {
+                        payouts.push(Payout {
+                            bettor: bet.bettor,
+                            won: Self::mul_div(
+                                bet.amount,
+                                Balance::from(odds[index]),
+                                Balance::from(ODDS_UNIT),
+                            )
+                            .ok_or(Error::ArithmeticOverflow)?,
+                            refunded: 0,
+                            credit: bet.credit,
+                            stake: bet.amount,
+                        });
+                    }
+                    payouts
+                }
+                (Market::Lmsr(_), _) => betting_match
+                    .bets
+                    .iter()
+                    .filter(|bet| bet.result == result)
+                    .map(|bet| Payout {
+                        bettor: bet.bettor,
+                        won: bet.amount,
+                        refunded: 0,
+                        credit: bet.credit,
+                        stake: bet.amount,
+                    })
+                    .collect(),
+            })
+        }
+
+        /// Merges the payouts of the bets of each bettor, in the order of their first bet,
+        /// so that settlement pays each bettor once for all their winning, refunded and
+        /// losing bets.
+        fn bettor_payouts(payouts: Vec<Payout>) -> Result<Vec<BettorPayout>, Error> {
+            let mut merged: Vec<BettorPayout> = Vec::new();
+            for payout in payouts {
+                // Free-bet credits are refunded as credits, and withheld from the winnings,
+                // bet by bet
+                let refunded_credit = payout.credit.min(payout.refunded);
+                let withheld = payout
+                    .credit
+                    .saturating_sub(refunded_credit)
+                    .min(payout.won);
+                match merged
+                    .iter_mut()
+                    .find(|bettor_payout| bettor_payout.bettor == payout.bettor)
+                {
+                    Some(entry) => {
+                        entry.won = entry
+                            .won
+                            .checked_add(payout.won)
+                            .ok_or(Error::ArithmeticOverflow)?;
+                        entry.refunded = entry
+                            .refunded
+                            .checked_add(payout.refunded)
+                            .ok_or(Error::ArithmeticOverflow)?;
+                        entry.refunded_credit =
+                            entry.refunded_credit.saturating_add(refunded_credit);
+                        entry.withheld = entry.withheld.saturating_add(withheld);
+                        entry.stake = entry.stake.saturating_add(payout.stake);
+                    }
+                    None => merged.push(BettorPayout {
+                        bettor: payout.bettor,
+                        won: payout.won,
+                        refunded: payout.refunded,
+                        refunded_credit,
+                        withheld,
+                        stake: payout.stake,
+                    }),
+                }
+            }
+            Ok(merged)
+        }
+
+        /// Payouts refunding every stake.
+        fn refunds(bets: &[Bet]) -> Vec<Payout> {
+            bets.iter()
+                .map(|bet| Payout {
+                    bettor: bet.bettor,
+                    won: 0,
+                    refunded: bet.amount,
+                    credit: bet.credit,
+                    stake: 0,
+                })
+                .collect()
+        }
+
+        /// Adds funds paid into a match to its escrow.
+        fn escrow_in(&mut self, match_id: MatchId, amount: Balance) -> Result<(), Error> {
+            let escrow = self.escrows.get(match_id).unwrap_or(0);
+            let escrow = escrow
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_escrow = self
+                .total_escrow
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.escrows.insert(match_id, &escrow);
+            Ok(())
+        }
+
+        /// Takes funds paid out of a match from its escrow, so a match can never pay
+        /// out the funds of another one.
+        fn escrow_out(&mut self, match_id: MatchId, amount: Balance) -> Result<(), Error> {
+            let escrow = self.escrows.get(match_id).unwrap_or(0);
+            if amount > escrow {
+                return Err(Error::EscrowExceeded);
+            }
+            self.escrows
+                .insert(match_id, &(escrow.saturating_sub(amount)));
+            // The total is at least any escrow
+            self.total_escrow = self.total_escrow.saturating_sub(amount);
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x5D715835)]
+        pub fn get_escrow(&self, match_id: MatchId) -> Balance {
+            self.escrows.get(match_id).unwrap_or(0)
+        }
+
+        /// Whether the balance of the contract covers the funds held in escrow for
+        /// the open matches, the internal balances of the users, their free-bet credits,
+        /// the prize pools of the games, deferred payouts and accrued fees.
+        #[ink(message, selector = 0x53A1EAE9)]
+        pub fn solvency_check(&self) -> bool {
+            self.env().balance() >= self.liabilities()
+        }
+
+        /// Returns the balance of the contract, its total liabilities as counted by
+        /// `solvency_check`, and the part of them held in escrow.
+        #[ink(message, selector = 0x4795D50A)]
+        pub fn solvency(&self) -> (Balance, Balance, Balance) {
+            (self.env().balance(), self.liabilities(), self.total_escrow)
+        }
+
+        /// Funds of the contract owed to users, liquidity providers and the funds it backs.
+        fn liabilities(&self) -> Balance {
+            self.total_escrow
+                .saturating_add(self.total_balances)
+                .saturating_add(self.total_free_bets)
+                .saturating_add(self.total_prize_pools)
+                .saturating_add(self.bankroll)
+                .saturating_add(self.insurance_fund)
+                .saturating_add(self.parlay_insurance)
+                .saturating_add(self.total_pending_claims)
+                .saturating_add(self.total_accrued_fees)
+        }
+
+        /// Checks the balance of the contract still covers its liabilities once `staked`,
+        /// already received, is held for the bets.
+        fn ensure_solvent(&self, staked: Balance) -> Result<(), Error> {
+            if self.env().balance() < self.liabilities().saturating_add(staked) {
+                return Err(Error::Insolvent);
+            }
+            Ok(())
+        }
+
+        /// Returns what still keeps the contract from being terminated.
+        #[ink(message, selector = 0x05AB85A5)]
+        pub fn get_termination_blockers(&self) -> TerminationBlockers {
+            TerminationBlockers {
+                open_matches: self.open_matches,
+                open_positions: self.open_positions,
+                escrow: self.total_escrow,
+                balances: self.total_balances,
+                free_bets: self.total_free_bets,
+                prize_pools: self.total_prize_pools,
+                bankroll: self.bankroll.saturating_add(self.bankroll_locked),
+                insurance_fund: self.insurance_fund,
+                parlay_insurance: self.parlay_insurance,
+                treasury: self.treasury,
+                jackpot: self.jackpot,
+                pending_claims: self.total_pending_claims,
+                accrued_fees: self.total_accrued_fees,
+            }
+        }
+
+        /// Terminate the contract once it is wound down, sending what is left of its
+        /// balance to the owner. Every match and position must be settled, and every
+        /// claim, internal balance and fund of the contract paid out.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x476D839F)]
+        pub fn terminate(&mut self) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("terminate"), &());
+            if self.get_termination_blockers() != TerminationBlockers::default() {
+                return Err(Error::TerminationBlocked);
+            }
+            self.env().terminate_contract(self.owner)
+        }
+
+        /// Transfer `amount` to the payout address of `to`, or record it as a pending claim
+        /// of `to` if the transfer fails so a single account can't block a whole
+        /// distribution.
+        fn pay_or_defer(&mut self, to: AccountId, amount: Balance) -> Result<(), Error> {
+            // The payment is held as a pending claim until the transfer went through
+            let pending = self.pending_claims.get(to);
+            let deferred_block = self.pending_claim_blocks.get(to);
+            self.defer_payment(to, amount)?;
+            if self
+                .env()
+                .transfer(self.get_payout_address(to), amount)
+                .is_err()
+            {
+                self.env().emit_event(PaymentDeferred { who: to, amount });
+                return Ok(());
+            }
+            match pending {
+                Some(pending) => {
+                    self.pending_claims.insert(to, &pending);
+                }
+                None => self.pending_claims.remove(to),
+            }
+            match deferred_block {
+                Some(deferred_block) => {
+                    self.pending_claim_blocks.insert(to, &deferred_block);
+                }
+                None => self.pending_claim_blocks.remove(to),
+            }
+            // The total includes the payment
+            self.total_pending_claims = self.total_pending_claims.saturating_sub(amount);
+            Ok(())
+        }
+
+        /// Records `amount` as a pending claim of `to`, withdrawn with `withdraw_pending`.
+        fn defer_payment(&mut self, to: AccountId, amount: Balance) -> Result<(), Error> {
+            let pending = self.pending_claims.get(to).unwrap_or(0);
+            let pending = pending
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.pending_claims.insert(to, &pending);
+            self.pending_claim_blocks
+                .insert(to, &self.env().block_number());
+            self.total_pending_claims = self
+                .total_pending_claims
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            Ok(())
+        }
+
+        /// Makes the payments of a message with `pay_or_defer`, once its state is updated.
+        fn pay_all(&mut self, payments: Vec<(AccountId, Balance)>) -> Result<(), Error> {
+            for (to, amount) in payments {
+                self.pay_or_defer(to, amount)?;
+            }
+            Ok(())
+        }
+
+        /// Route the winnings, claims and refunds of the caller to `payout_address`, e.g. a
+        /// cold wallet. Setting the caller itself removes the payout address.
+        #[ink(message, selector = 0xC8DB9B86)]
+        pub fn set_payout_address(&mut self, payout_address: AccountId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if payout_address == caller {
+                self.payout_addresses.remove(caller);
+            } else {
+                self.payout_addresses.insert(caller, &payout_address);
+            }
+            self.env().emit_event(PayoutAddressSet {
+                who: caller,
+                payout_address,
+            });
+            Ok(())
+        }
+
+        /// Returns the address the payouts of an account go to.
+        #[ink(message, selector = 0x9D125B8F)]
+        pub fn get_payout_address(&self, account: AccountId) -> AccountId {
+            self.payout_addresses.get(account).unwrap_or(account)
+        }
+
+        /// Withdraw the payments to the caller that failed at settlement.
+        #[ink(message, selector = 0x895B29FA)]
+        pub fn withdraw_pending(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let amount = match self.pending_claims.get(caller) {
+                Some(amount) => amount,
+                None => return Err(Error::NothingToClaim),
+            };
+            // The claim is removed before the transfer, a failed transfer reverts it
+            self.pending_claims.remove(caller);
+            self.pending_claim_blocks.remove(caller);
+            // The total includes the claim
+            self.total_pending_claims = self.total_pending_claims.saturating_sub(amount);
+            self.env()
+                .transfer(self.get_payout_address(caller), amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(WinningsClaimed {
+                who: caller,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xB2C096C3)]
+        pub fn get_pending_claim(&self, account: AccountId) -> Balance {
+            self.pending_claims.get(account).unwrap_or(0)
+        }
+
+        /// Withdraw the pending claim of the caller swapped to the token `asset` by the DEX
+        /// router, sent to their payout address. Fails unless the swap returns at least
+        /// `min_out` of `asset`, which protects the caller from slippage.
+        #[ink(message, selector = 0xBB92C9D7)]
+        pub fn claim_winnings_as(
+            &mut self,
+            asset: AccountId,
+            min_out: Balance,
+        ) -> Result<Balance, Error> {
+            let caller = Self::env().caller();
+            let amount = match self.pending_claims.get(caller) {
+                Some(amount) => amount,
+                None => return Err(Error::NothingToClaim),
+            };
+            let router_address = match self.dex_router {
+                Some(router_address) => router_address,
+                None => return Err(Error::NoDexRouter),
+            };
+            // The claim is removed before the call, a failed swap reverts it
+            self.pending_claims.remove(caller);
+            self.pending_claim_blocks.remove(caller);
+            // The total includes the claim
+            self.total_pending_claims = self.total_pending_claims.saturating_sub(amount);
+            let mut router: ink::contract_ref!(SwapRouter) = router_address.into();
+            let amount_out = match router
+                .call_mut()
+                .swap_native_for(asset, min_out, self.get_payout_address(caller))
+                .transferred_value(amount)
+                .call_flags(NO_REENTRY)
+                .try_invoke()
+            {
+                Ok(Ok(Some(amount_out))) => amount_out,
+                _ => return Err(Error::SwapFailed),
+            };
+            // Don't trust the router to enforce the minimum
+            if amount_out < min_out {
+                return Err(Error::SlippageExceeded);
+            }
+            self.env().emit_event(WinningsSwapped {
+                who: caller,
+                asset,
+                amount,
+                amount_out,
+            });
+            Ok(amount_out)
+        }
+
+        /// Register the DEX router the winnings can be swapped through, or none.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xCA5732E5)]
+        pub fn set_dex_router(&mut self, router: Option<AccountId>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_dex_router"), &router);
+            self.dex_router = router;
+            Ok(())
+        }
+
+        /// Adds a fee paid by `payer` to the treasury, minus the shares of their referrer,
+        /// of the `creator` of the match it was paid on, given with the total pool of the
+        /// match, and of the jackpot. The shares of the referrer and the creator accrue
+        /// until they claim them with `claim_fees`, the protocol share is pushed to the fee
+        /// router if there is one.
+        fn collect_fee(
+            &mut self,
+            payer: AccountId,
+            fee: Balance,
+            creator: Option<(AccountId, Balance)>,
+        ) -> Result<(), Error> {
+            let mut referral_reward = 0;
+            if let Some(referrer) = self.referrers.get(payer) {
+                referral_reward = Self::bps_of(fee, Balance::from(self.referral_share_bps))?;
+                self.accrue_fee(referrer, referral_reward)?;
+            }
+            // The referral share is at most 100%
+            let mut protocol_fee = fee.saturating_sub(referral_reward);
+            if let Some((creator, total_pool)) = creator {
+                let creator_fee = Self::bps_of(
+                    protocol_fee,
+                    Balance::from(self.creator_fee_share_bps_at(total_pool)),
+                )?;
+                self.accrue_fee(creator, creator_fee)?;
+                // The creator share is at most 100%
+                protocol_fee = protocol_fee.saturating_sub(creator_fee);
+            }
+            let insurance_share =
+                Self::bps_of(protocol_fee, Balance::from(self.insurance_share_bps))?;
+            self.insurance_fund = self
+                .insurance_fund
+                .checked_add(insurance_share)
+                .ok_or(Error::ArithmeticOverflow)?;
+            // The insurance share is at most 100%
+            let protocol_fee = protocol_fee.saturating_sub(insurance_share);
+            let jackpot_share = Self::bps_of(protocol_fee, Balance::from(self.jackpot_share_bps))?;
+            self.jackpot = self
+                .jackpot
+                .checked_add(jackpot_share)
+                .ok_or(Error::ArithmeticOverflow)?;
+            // The jackpot share is at most 100%
+            self.route_protocol_fee(protocol_fee.saturating_sub(jackpot_share))?;
+            self.stats.fees = self.stats.fees.saturating_add(fee);
+            Ok(())
+        }
+
+        /// Accrues a protocol fee to the fee router, forwarded with `forward_fees`, or adds
+        /// it to the treasury if there is none.
+        fn route_protocol_fee(&mut self, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+            if let Some(router) = self.fee_router {
+                return self.accrue_fee(router, amount);
+            }
+            self.treasury = self
+                .treasury
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            Ok(())
+        }
+
+        /// Push the protocol fees accrued by the fee router to it. Anyone can call it.
+        #[ink(message, selector = 0x71C50C10)]
+        pub fn forward_fees(&mut self) -> Result<(), Error> {
+            let router_address = match self.fee_router {
+                Some(router_address) => router_address,
+                None => return Err(Error::NoFeeRouter),
+            };
+            let amount = self.accrued_fees.take(router_address).unwrap_or(0);
+            if amount == 0 {
+                return Err(Error::NothingToClaim);
+            }
+            // The total includes the fees of the router
+            self.total_accrued_fees = self.total_accrued_fees.saturating_sub(amount);
+            let mut router: ink::contract_ref!(FeeRouter) = router_address.into();
+            match router
+                .call_mut()
+                .receive_fees()
+                .transferred_value(amount)
+                .call_flags(NO_REENTRY)
+                .try_invoke()
+            {
+                Ok(Ok(())) => {}
+                _ => return Err(Error::TransferFailed),
+            }
+            self.env().emit_event(FeesClaimed {
+                who: router_address,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Register the contract the protocol fees are pushed to instead of accumulating in
+        /// the treasury, or none. The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x4891B399)]
+        pub fn set_fee_router(&mut self, router: Option<AccountId>) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_fee_router"), &router);
+            self.apply_action_now(AdminAction::SetFeeRouter(router))
+        }
+
+        #[ink(message, selector = 0x2A40FF8E)]
+        pub fn get_fee_router(&self) -> Option<AccountId> {
+            self.fee_router
+        }
+
+        /// Adds a share of a fee to the fees accrued by `beneficiary`.
+        fn accrue_fee(&mut self, beneficiary: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+            let accrued = self.accrued_fees.get(beneficiary).unwrap_or(0);
+            let accrued = accrued
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.accrued_fees.insert(beneficiary, &accrued);
+            self.total_accrued_fees = self
+                .total_accrued_fees
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            Ok(())
+        }
+
+        /// Cumulative statistics of the contract.
+        #[ink(message, selector = 0x0B58AA38)]
+        pub fn get_stats(&self) -> Stats {
+            self.stats
+        }
+
+        /// Place a bet recording who referred the caller. The first referrer of an
+        /// account is kept and earns a share of the fees it generates.
+        #[ink(message, payable, selector = 0x888D75D2)]
+        pub fn bet_with_referrer(
+            &mut self,
+            match_id: MatchId,
+            result: MatchResult,
+            referrer: AccountId,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if referrer != caller && !self.referrers.contains(caller) {
+                self.referrers.insert(caller, &referrer);
+            }
+            self.bet(match_id, result)
+        }
+
+        /// Claim the creator and referrer fees accrued by the caller.
+        #[ink(message, selector = 0x8F37B7C0)]
+        pub fn claim_fees(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let amount = self.take_accrued_fees(caller)?;
+            self.env().emit_event(FeesClaimed {
+                who: caller,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Claim the fees accrued by the caller, like `claim_fees`, emitting
+        /// `ReferralRewardsClaimed`. Kept for the integrations of the referral rewards.
+        #[ink(message, selector = 0xB09951C3)]
+        pub fn claim_referral_rewards(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let amount = self.take_accrued_fees(caller)?;
+            self.env().emit_event(ReferralRewardsClaimed {
+                who: caller,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Transfers the fees accrued by `account` to its payout address, returning them.
+        fn take_accrued_fees(&mut self, account: AccountId) -> Result<Balance, Error> {
+            let amount = self.accrued_fees.take(account).unwrap_or(0);
+            if amount == 0 {
+                return Err(Error::NothingToClaim);
+            }
+            // The total includes the fees of the account
+            self.total_accrued_fees = self.total_accrued_fees.saturating_sub(amount);
+            self.env()
+                .transfer(self.get_payout_address(account), amount)
+                .map_err(|_| Error::TransferFailed)?;
+            Ok(amount)
+        }
+
+        #[ink(message, selector = 0x84E074B5)]
+        pub fn get_referrer(&self, account: AccountId) -> Option<AccountId> {
+            self.referrers.get(account)
+        }
+
+        /// Creator and referrer fees accrued by an account and not claimed yet.
+        #[ink(message, selector = 0xE4740330)]
+        pub fn get_accrued_fees(&self, account: AccountId) -> Balance {
+            self.accrued_fees.get(account).unwrap_or(0)
+        }
+
+        /// Fees accrued by an account, like `get_accrued_fees`. Kept for the integrations
+        /// of the referral rewards.
+        #[ink(message, selector = 0x78368D4F)]
+        pub fn get_referral_rewards(&self, account: AccountId) -> Balance {
+            self.get_accrued_fees(account)
+        }
+
+        /// Set the lowest and highest fees, in basis points, creators can choose for their
+        /// match with `set_match_fee`.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x77F9F9F1)]
+        pub fn set_match_fee_bounds(&mut self, min_bps: u32, max_bps: u32) -> Result<(), Error> {
+            self.log_admin(
+                ink::selector_bytes!("set_match_fee_bounds"),
+                &(min_bps, max_bps),
+            );
+            self.update_config(|config| {
+                config.min_match_fee_bps = min_bps;
+                config.max_match_fee_bps = max_bps;
+            })
+        }
+
+        /// Choose the fee, in basis points, taken on the winnings of a match instead of the
+        /// protocol fee, within the bounds set by the owner, or go back to the protocol
+        /// fee. Only the creator can change it, before the first bet.
+        #[ink(message, selector = 0xFCBC734A)]
+        pub fn set_match_fee(&mut self, match_id: MatchId, bps: Option<u32>) -> Result<(), Error> {
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            let config = self.get_config();
+            if !betting_match.bets.is_empty()
+                || bps.is_some_and(|bps| {
+                    bps < config.min_match_fee_bps || bps > config.max_match_fee_bps
+                })
+            {
+                return Err(Error::InvalidMatchFee);
+            }
+            betting_match.fee_bps = bps;
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Fee, in basis points, taken on the winnings of a match.
+        fn match_fee_bps(&self, betting_match: &Match) -> u32 {
+            betting_match
+                .fee_bps
+                .unwrap_or_else(|| self.get_config().fee_bps)
+        }
+
+        /// Set the share of the fees, in basis points, earned by referrers.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x0C3F6328)]
+        pub fn set_referral_share(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_referral_share"), &bps);
+            if bps > 10_000 {
+                return Err(Error::InvalidConfig);
+            }
+            self.referral_share_bps = bps;
+            Ok(())
+        }
+
+        /// Set the share of the fees of a match, in basis points, earned by its creator
+        /// after the share of the referrer.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xC98AB4B7)]
+        pub fn set_creator_fee_share(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_creator_fee_share"), &bps);
+            if bps > 10_000 {
+                return Err(Error::InvalidConfig);
+            }
+            self.creator_fee_share_bps = bps;
+            Ok(())
+        }
+
+        /// Set the milestones of the total pool of a match stepping up the fee share of
+        /// its creator, at most `MAX_CREATOR_MILESTONES` by increasing pool and share.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x19E5C5C5)]
+        pub fn set_creator_milestones(
+            &mut self,
+            milestones: Vec<CreatorMilestone>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_creator_milestones"), &milestones);
+            if milestones.len() > MAX_CREATOR_MILESTONES
+                || milestones
+                    .iter()
+                    .any(|milestone| milestone.share_bps > 10_000)
+                || milestones.windows(2).any(|pair| {
+                    pair[0].min_pool >= pair[1].min_pool || pair[0].share_bps > pair[1].share_bps
+                })
+            {
+                return Err(Error::InvalidConfig);
+            }
+            self.creator_milestones = milestones;
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x1831DECC)]
+        pub fn get_creator_milestones(&self) -> Vec<CreatorMilestone> {
+            self.creator_milestones.clone()
+        }
+
+        /// Share of the fees of a match, in basis points, its creator earns at its current
+        /// total pool.
+        #[ink(message, selector = 0xCFBECE89)]
+        pub fn get_creator_fee_share(&self, match_id: MatchId) -> Option<u32> {
+            let betting_match = self.matches.get(match_id)?;
+            let total_pool = Self::total_pool(&betting_match).ok()?;
+            Some(self.creator_fee_share_bps_at(total_pool))
+        }
+
+        /// Fee share of a creator, in basis points, for the total pool of their match: the
+        /// share of the highest milestone the pool reaches, and at least the base share.
+        fn creator_fee_share_bps_at(&self, total_pool: Balance) -> u32 {
+            self.creator_milestones
+                .iter()
+                .filter(|milestone| total_pool >= milestone.min_pool)
+                .map(|milestone| milestone.share_bps)
+                .fold(self.creator_fee_share_bps, u32::max)
+        }
+
+        /// Set the minimum bet, below which stakes are rejected as dust.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0xA03D0192)]
+        pub fn set_min_bet(&mut self, min_bet: Balance) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_min_bet"), &min_bet);
+            self.update_config(|config| config.min_bet = min_bet)
+        }
+
+        /// Set the protocol fee, in basis points, taken on the winnings paid at settlement.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x774AC12E)]
+        pub fn set_protocol_fee(&mut self, bps: u32) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_protocol_fee"), &bps);
+            self.update_config(|config| config.fee_bps = bps)
+        }
+
+        /// Part of the deposit slashed for each block the settlement is late
+        /// after the grace period.
+        fn late_settlement_slash(&self, betting_match: &Match) -> Result<Balance, Error> {
+            let deadline = betting_match
+                .result_block
+                .saturating_add(self.get_config().settlement_grace_period);
+            let delay = self.env().block_number().saturating_sub(deadline);
+            let slashed = Balance::from(delay)
+                .checked_mul(betting_match.deposit)
+                .ok_or(Error::ArithmeticOverflow)?;
+            Self::bps_of(slashed, Balance::from(self.slash_per_block_bps))
+        }
+
+        #[ink(message, selector = 0x8CCD69E7)]
+        pub fn get_creator_reputation(&self, account: AccountId) -> CreatorReputation {
+            self.creator_reputations.get(account).unwrap_or_default()
+        }
+
+        /// Minimum deposit of `creator` to create a match, before the subsidy of LMSR
+        /// markets. Each match they settled on time beyond the disputes they lost cuts it by
+        /// 5%, down to half of the configured one.
+        #[ink(message, selector = 0xC25FC9F1)]
+        pub fn get_min_deposit_for(&self, creator: AccountId) -> Result<Balance, Error> {
+            let reputation = self.get_creator_reputation(creator);
+            let reliable = reputation
+                .settled_on_time
+                .saturating_sub(reputation.disputes_lost);
+            let discount = reliable
+                .saturating_mul(REPUTATION_DISCOUNT_BPS)
+                .min(MAX_REPUTATION_DISCOUNT_BPS);
+            let min_deposit = self.get_config().min_deposit;
+            // The discount is at most 50%
+            Ok(min_deposit.saturating_sub(Self::bps_of(min_deposit, Balance::from(discount))?))
+        }
+
+        fn update_reputation(
+            &mut self,
+            creator: AccountId,
+            update: impl FnOnce(&mut CreatorReputation),
+        ) {
+            let mut reputation = self.get_creator_reputation(creator);
+            update(&mut reputation);
+            self.creator_reputations.insert(creator, &reputation);
+        }
+
+        /// Set the part of the deposit, in basis points, slashed per block of late settlement.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x4CBD25F7)]
+        pub fn set_slash_per_block(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_slash_per_block"), &bps);
+            self.slash_per_block_bps = bps;
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x6899C3C5)]
+        pub fn get_treasury(&self) -> Balance {
+            self.treasury
+        }
+
+        /// Grant free-bet credits funded by the treasury to an account. Credits can't be
+        /// withdrawn, `bet` uses them before the transferred value and only the profit of
+        /// the bets they fund is paid out.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xB4AF489F)]
+        pub fn grant_free_bet(&mut self, account: AccountId, amount: Balance) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("grant_free_bet"), &(account, amount));
+            if amount > self.treasury {
+                return Err(Error::NotEnoughTreasury);
+            }
+            // The credits become liabilities
+            self.ensure_solvent(amount)?;
+            self.treasury = self.treasury.saturating_sub(amount);
+            self.credit_free_bet(account, amount)?;
+            self.env().emit_event(FreeBetGranted {
+                who: account,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Provide the transferred value as liquidity to the bankroll, minting liquidity
+        /// pool shares in proportion to the bankroll, its locked funds included.
+        #[ink(message, payable, selector = 0xFF93E568)]
+        pub fn provide_liquidity(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            self.ensure_bankroll_settled()?;
+            let amount = Self::env().transferred_value();
+            let value = self.bankroll_value()?;
+            let shares = if self.total_lp_shares == 0 || value == 0 {
+                amount
+            } else {
+                Self::mul_div(amount, self.total_lp_shares, value)
+                    .ok_or(Error::ArithmeticOverflow)?
+            };
+            if shares == 0 {
+                return Err(Error::InvalidLiquidity);
+            }
+            self.bankroll = self
+                .bankroll
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_lp_shares = self
+                .total_lp_shares
+                .checked_add(shares)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let owned = self.lp_shares.get(caller).unwrap_or(0);
+            // The shares of a provider are part of the total
+            self.lp_shares.insert(caller, &owned.saturating_add(shares));
+            self.env().emit_event(LiquidityProvided {
+                who: caller,
+                amount,
+                shares,
+            });
+            Ok(())
+        }
+
+        /// Redeem liquidity pool shares for their slice of the bankroll, with the profit or
+        /// loss of the markets it backed. Funds locked in open matches can't be withdrawn.
+        #[ink(message, selector = 0xE7039DA6)]
+        pub fn withdraw_liquidity(&mut self, shares: Balance) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let owned = self.lp_shares.get(caller).unwrap_or(0);
+            if shares == 0 || shares > owned {
+                return Err(Error::NotEnoughShares);
+            }
+            self.ensure_bankroll_settled()?;
+            let amount = Self::mul_div(shares, self.bankroll_value()?, self.total_lp_shares)
+                .ok_or(Error::ArithmeticOverflow)?;
+            if amount > self.bankroll {
+                return Err(Error::NotEnoughBankroll);
+            }
+            self.bankroll = self.bankroll.saturating_sub(amount);
+            self.total_lp_shares = self.total_lp_shares.saturating_sub(shares);
+            if owned == shares {
+                self.lp_shares.remove(caller);
+            } else {
+                self.lp_shares.insert(caller, &owned.saturating_sub(shares));
+            }
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(LiquidityWithdrawn {
+                who: caller,
+                amount,
+                shares,
+            });
+            Ok(())
+        }
+
+        /// Fails while a fixed-odds match has a result not settled yet: the shares would
+        /// be priced at the worst-case loss locked for it, not at its known outcome.
+        fn ensure_bankroll_settled(&self) -> Result<(), Error> {
+            if self.unsettled_fixed_odds > 0 {
+                return Err(Error::BankrollSettlementPending);
+            }
+            Ok(())
+        }
+
+        /// Free and locked funds of the bankroll.
+        #[ink(message, selector = 0xF3A124EA)]
+        pub fn get_bankroll(&self) -> (Balance, Balance) {
+            (self.bankroll, self.bankroll_locked)
+        }
+
+        #[ink(message, selector = 0x0751A4FE)]
+        pub fn get_lp_shares(&self, account: AccountId) -> Balance {
+            self.lp_shares.get(account).unwrap_or(0)
+        }
+
+        /// Funds of the bankroll, free or locked in matches.
+        fn bankroll_value(&self) -> Result<Balance, Error> {
+            self.bankroll
+                .checked_add(self.bankroll_locked)
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Worst-case loss of a fixed-odds match once a bet is placed: what the most
+        /// expensive outcome pays beyond the pool. Nothing in other markets.
+        fn fixed_odds_liability(
+            betting_match: &Match,
+            result: MatchResult,
+            amount: Balance,
+        ) -> Result<Balance, Error> {
+            let odds = match betting_match.market {
+                Market::FixedOdds(odds) => odds,
+                _ => return Ok(0),
+            };
+            let mut payouts: [Balance; 3] = [0; 3];
+            let bets = betting_match
+                .bets
+                .iter()
+                .map(|bet| (bet.result, bet.amount))
+                .chain([(result, amount)]);
+            for (outcome, stake) in bets {
+                let index = Self::outcome_index(outcome)?;
+                let payout =
+                    Self::mul_div(stake, Balance::from(odds[index]), Balance::from(ODDS_UNIT))
+                        .ok_or(Error::ArithmeticOverflow)?;
+                payouts[index] = payouts[index]
+                    .checked_add(payout)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            let pool = Self::total_pool(betting_match)?
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            Ok(payouts
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(0)
+                .saturating_sub(pool))
+        }
+
+        /// Largest part of `amount` staked on `result` that keeps the liability of a
+        /// fixed-odds match within `limit`, zero if none does.
+        fn fixed_odds_fillable(
+            betting_match: &Match,
+            result: MatchResult,
+            amount: Balance,
+            limit: Balance,
+        ) -> Result<Balance, Error> {
+            if Self::fixed_odds_liability(betting_match, result, amount)? <= limit {
+                return Ok(amount);
+            }
+            let odds = match betting_match.market {
+                Market::FixedOdds(odds) => odds,
+                _ => return Ok(amount),
+            };
+            let index = Self::outcome_index(result)?;
+            // Each unit staked on the outcome adds its odds to the payout and one to the pool
+            let margin = Balance::from(odds[index]).saturating_sub(Balance::from(ODDS_UNIT));
+            if margin == 0 {
+                return Ok(0);
+            }
+            let mut payout: Balance = 0;
+            for bet in betting_match.bets.iter().filter(|bet| bet.result == result) {
+                payout = payout
+                    .checked_add(
+                        Self::mul_div(
+                            bet.amount,
+                            Balance::from(odds[index]),
+                            Balance::from(ODDS_UNIT),
+                        )
+                        .ok_or(Error::ArithmeticOverflow)?,
+                    )
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            let headroom = limit
+                .saturating_add(Self::total_pool(betting_match)?)
+                .saturating_sub(payout);
+            // Beyond the balance type is more than the amount anyway
+            let fillable = Self::mul_div(headroom, Balance::from(ODDS_UNIT), margin)
+                .map_or(amount, |fillable| fillable.min(amount));
+            // The liability of the other outcomes can still exceed the limit
+            if Self::fixed_odds_liability(betting_match, result, fillable)? > limit {
+                return Ok(0);
+            }
+            Ok(fillable)
+        }
+
+        /// Draws from the insurance fund what the escrow of a match lacks to pay `needed`.
+        fn cover_shortfall(&mut self, match_id: MatchId, needed: Balance) -> Result<(), Error> {
+            let escrow = self.escrows.get(match_id).unwrap_or(0);
+            let drawn = needed.saturating_sub(escrow).min(self.insurance_fund);
+            if drawn == 0 {
+                return Ok(());
+            }
+            self.insurance_fund = self.insurance_fund.saturating_sub(drawn);
+            self.insurance_drawn = self.insurance_drawn.saturating_add(drawn);
+            self.escrow_in(match_id, drawn)?;
+            self.env().emit_event(InsuranceDrawn {
+                match_id,
+                amount: drawn,
+            });
+            Ok(())
+        }
+
+        /// Top up the insurance fund with the transferred value.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, payable, selector = 0x12D4B2CA)]
+        pub fn top_up_insurance(&mut self) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("top_up_insurance"), &());
+            let amount = Self::env().transferred_value();
+            self.insurance_fund = self
+                .insurance_fund
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.env().emit_event(InsuranceToppedUp { amount });
+            Ok(())
+        }
+
+        /// Set the share of the protocol fees, in basis points, added to the insurance fund.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xA7B6E022)]
+        pub fn set_insurance_share(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_insurance_share"), &bps);
+            if bps > 10_000 {
+                return Err(Error::InvalidConfig);
+            }
+            self.insurance_share_bps = bps;
+            Ok(())
+        }
+
+        /// Set the premium of insured parlays, in basis points of their stake.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x5FC2CBA0)]
+        pub fn set_parlay_insurance_premium(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_parlay_insurance_premium"), &bps);
+            if bps == 0 || bps > 10_000 {
+                return Err(Error::InvalidConfig);
+            }
+            self.parlay_insurance_premium_bps = bps;
+            Ok(())
+        }
+
+        /// Premium of insured parlays in basis points, funds of the parlay insurance, and
+        /// the total it refunded.
+        #[ink(message, selector = 0x10DA3791)]
+        pub fn get_parlay_insurance(&self) -> (u32, Balance, Balance) {
+            (
+                self.parlay_insurance_premium_bps,
+                self.parlay_insurance,
+                self.parlay_insurance_paid,
+            )
+        }
+
+        /// Funds of the insurance fund, and the total drawn from it to cover shortfalls.
+        #[ink(message, selector = 0x6DC6FD19)]
+        pub fn get_insurance(&self) -> (Balance, Balance) {
+            (self.insurance_fund, self.insurance_drawn)
+        }
+
+        /// Set the maximum worst-case loss of a fixed-odds match beyond the creator deposit,
+        /// in basis points of the bankroll.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x11D605C6)]
+        pub fn set_max_exposure(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_max_exposure"), &bps);
+            if bps > 10_000 {
+                return Err(Error::InvalidConfig);
+            }
+            self.max_exposure_bps = bps;
+            Ok(())
+        }
+
+        /// Worst-case loss of a match the bankroll is exposed to, beyond the creator deposit.
+        #[ink(message, selector = 0xC6E90968)]
+        pub fn get_exposure(&self, match_id: MatchId) -> Balance {
+            self.matches
+                .get(match_id)
+                .map_or(0, |betting_match| betting_match.backed)
+        }
+
+        /// End the season of the leaderboard and start a new one. The top accounts of the
+        /// season are paid the prizes, in order, from the treasury.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x9D71BD4D)]
+        pub fn reset_season(&mut self, prizes: Vec<Balance>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("reset_season"), &prizes);
+            if prizes.len() > MAX_SEASON_PRIZES {
+                return Err(Error::InvalidConfig);
+            }
+            let leaderboard = self.get_leaderboard(0, prizes.len() as u32);
+            let mut paid: Balance = 0;
+            for prize in prizes.iter().take(leaderboard.len()) {
+                paid = paid.checked_add(*prize).ok_or(Error::ArithmeticOverflow)?;
+            }
+            if paid > self.treasury {
+                return Err(Error::NotEnoughTreasury);
+            }
+            self.treasury = self.treasury.saturating_sub(paid);
+            self.stats.paid_out = self.stats.paid_out.saturating_add(paid);
+            let season = self.season;
+            self.season = self
+                .season
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.pay_all(
+                leaderboard
+                    .iter()
+                    .zip(&prizes)
+                    .map(|((account, _), prize)| (*account, *prize))
+                    .collect(),
+            )?;
+            self.env().emit_event(SeasonEnded {
+                season,
+                prizes: paid,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xBF23B5D9)]
+        pub fn get_season(&self) -> u32 {
+            self.season
+        }
+
+        /// Points scored by an account in the current season: the stakes of its winning
+        /// bets in the settled matches.
+        #[ink(message, selector = 0x7BEF54A3)]
+        pub fn get_season_points(&self, account: AccountId) -> Balance {
+            self.season_points.get((self.season, account)).unwrap_or(0)
+        }
+
+        /// Accounts of the current season with their points, from the most points, at
+        /// most 50 from `offset`.
+        #[ink(message, selector = 0x091A420A)]
+        pub fn get_leaderboard(&self, offset: u32, limit: u32) -> Vec<(AccountId, Balance)> {
+            let count = self.season_accounts_count.get(self.season).unwrap_or(0);
+            let mut leaderboard: Vec<(AccountId, Balance)> = (0..count)
+                .filter_map(|index| self.season_accounts.get((self.season, index)))
+                .map(|account| (account, self.get_season_points(account)))
+                .collect();
+            // Ties keep the order of the first points
+            leaderboard.sort_by_key(|(_, points)| core::cmp::Reverse(*points));
+            leaderboard
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit.min(MAX_LEADERBOARD_PAGE) as usize)
+                .collect()
+        }
+
+        /// Adds points to an account in the current season.
+        fn add_season_points(&mut self, account: AccountId, points: Balance) -> Result<(), Error> {
+            if points == 0 {
+                return Ok(());
+            }
+            let season_points = match self.season_points.get((self.season, account)) {
+                Some(season_points) => season_points,
+                None => {
+                    let count = self.season_accounts_count.get(self.season).unwrap_or(0);
+                    self.season_accounts.insert((self.season, count), &account);
+                    self.season_accounts_count.insert(
+                        self.season,
+                        &count.checked_add(1).ok_or(Error::ArithmeticOverflow)?,
+                    );
+                    0
+                }
+            };
+            self.season_points.insert(
+                (self.season, account),
+                &season_points
+                    .checked_add(points)
+                    .ok_or(Error::ArithmeticOverflow)?,
+            );
+            Ok(())
+        }
+
+        /// Fee discount of a bettor, in basis points, from the platform tokens they stake
+        /// in the staking contract. Zero if there is no staking contract or it fails.
+        fn fee_discount_bps(&self, bettor: AccountId) -> u32 {
+            let tiers = self.get_config().fee_tiers;
+            if tiers.iter().all(|tier| tier.discount_bps == 0) {
+                return 0;
+            }
+            let staking_address = match self.staking_contract {
+                Some(staking_address) => staking_address,
+                None => return 0,
+            };
+            let staking: ink::contract_ref!(Staking) = staking_address.into();
+            match staking
+                .call()
+                .staked_of(bettor)
+                .call_flags(NO_REENTRY)
+                .try_invoke()
+            {
+                Ok(Ok(staked)) => Self::tier_discount_bps(&tiers, staked),
+                _ => 0,
+            }
+        }
+
+        /// Highest discount of the tiers `staked` tokens reach.
+        fn tier_discount_bps(tiers: &[FeeTier], staked: Balance) -> u32 {
+            tiers
+                .iter()
+                .filter(|tier| staked >= tier.min_staked)
+                .map(|tier| tier.discount_bps)
+                .max()
+                .unwrap_or(0)
+        }
+
+        /// Set the fee discount tiers of the stakers of the platform token, at most
+        /// `MAX_FEE_TIERS`.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x9324A4BD)]
+        pub fn set_fee_tiers(&mut self, tiers: Vec<FeeTier>) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_fee_tiers"), &tiers);
+            if tiers.len() > MAX_FEE_TIERS {
+                return Err(Error::InvalidConfig);
+            }
+            self.update_config(|config| {
+                config.fee_tiers = [FeeTier::default(); MAX_FEE_TIERS];
+                config.fee_tiers[..tiers.len()].copy_from_slice(&tiers);
+            })
+        }
+
+        /// Register the staking contract of the platform token, or none.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x00E16AB8)]
+        pub fn set_staking_contract(&mut self, staking: Option<AccountId>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_staking_contract"), &staking);
+            self.staking_contract = staking;
+            Ok(())
+        }
+
+        /// Adds the reward points of a settled bet of `stake` to an account.
+        fn accrue_reward_points(
+            &mut self,
+            account: AccountId,
+            stake: Balance,
+        ) -> Result<(), Error> {
+            let points = Self::bps_of(stake, Balance::from(self.get_config().reward_points_bps))?;
+            if points == 0 {
+                return Ok(());
+            }
+            let reward_points = self
+                .reward_points
+                .get(account)
+                .unwrap_or(0)
+                .checked_add(points)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.reward_points.insert(account, &reward_points);
+            Ok(())
+        }
+
+        /// Convert the reward points of the caller into as many platform tokens, minted to
+        /// their payout address, up to what is left of the emission cap of the epoch. The
+        /// points left over can be converted in a later epoch. Returns the amount minted.
+        #[ink(message, selector = 0x8027650C)]
+        pub fn claim_rewards(&mut self) -> Result<Balance, Error> {
+            let caller = Self::env().caller();
+            let points = match self.reward_points.get(caller) {
+                Some(points) => points,
+                None => return Err(Error::NothingToClaim),
+            };
+            let token_address = match self.reward_token {
+                Some(token_address) => token_address,
+                None => return Err(Error::NoRewardToken),
+            };
+            let config = self.get_config();
+            // The epoch length is never zero
+            let epoch = self
+                .env()
+                .block_number()
+                .checked_div(config.epoch_length)
+                .unwrap_or(0);
+            let emitted = self.epoch_emissions.get(epoch).unwrap_or(0);
+            let amount = points.min(config.epoch_emission_cap.saturating_sub(emitted));
+            if amount == 0 {
+                return Err(Error::EmissionCapReached);
+            }
+            // The amount is at most the points and what is left of the cap
+            let points = points.saturating_sub(amount);
+            if points == 0 {
+                self.reward_points.remove(caller);
+            } else {
+                self.reward_points.insert(caller, &points);
+            }
+            self.epoch_emissions
+                .insert(epoch, &emitted.saturating_add(amount));
+            // A failed mint reverts the points
+            let mut token: ink::contract_ref!(PSP22Mintable) = token_address.into();
+            match token
+                .call_mut()
+                .mint(self.get_payout_address(caller), amount)
+                .call_flags(NO_REENTRY)
+                .try_invoke()
+            {
+                Ok(Ok(Ok(()))) => {}
+                _ => return Err(Error::MintFailed),
+            }
+            self.env().emit_event(RewardsClaimed {
+                who: caller,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        #[ink(message, selector = 0x14FA34B7)]
+        pub fn get_reward_points(&self, account: AccountId) -> Balance {
+            self.reward_points.get(account).unwrap_or(0)
+        }
+
+        /// Register the platform token the reward points are converted into, or none.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x1F717C47)]
+        pub fn set_reward_token(&mut self, token: Option<AccountId>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_reward_token"), &token);
+            self.reward_token = token;
+            Ok(())
+        }
+
+        /// Set the reward points accrued per settled bet, in basis points of its stake, the
+        /// length of an emission epoch and the most platform tokens minted per epoch.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x41551F84)]
+        pub fn set_reward_emissions(
+            &mut self,
+            reward_points_bps: u32,
+            epoch_length: BlockNumber,
+            epoch_emission_cap: Balance,
+        ) -> Result<(), Error> {
+            self.log_admin(
+                ink::selector_bytes!("set_reward_emissions"),
+                &(reward_points_bps, epoch_length, epoch_emission_cap),
+            );
+            self.update_config(|config| {
+                config.reward_points_bps = reward_points_bps;
+                config.epoch_length = epoch_length;
+                config.epoch_emission_cap = epoch_emission_cap;
+            })
+        }
+
+        #[ink(message, selector = 0xEE1D2382)]
+        pub fn get_free_bet(&self, account: AccountId) -> Balance {
+            self.free_bets.get(account).unwrap_or(0)
+        }
+
+        /// Adds free-bet credits to an account.
+        fn credit_free_bet(&mut self, account: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+            let free_bet = self
+                .get_free_bet(account)
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_free_bets = self
+                .total_free_bets
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.free_bets.insert(account, &free_bet);
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x2F3A120B)]
+        pub fn get_jackpot(&self) -> Balance {
+            self.jackpot
+        }
+
+        /// Set the share of the protocol fees, in basis points, added to the jackpot.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xAD1EAA43)]
+        pub fn set_jackpot_share(&mut self, bps: u32) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_jackpot_share"), &bps);
+            if bps > 10_000 {
+                return Err(Error::InvalidConfig);
+            }
+            self.jackpot_share_bps = bps;
+            Ok(())
+        }
+
+        /// Attach `amount` of the jackpot to a match before its result, making it featured.
+        /// The bettors predicting the exact score with `predict_score` split it, otherwise
+        /// it rolls over at settlement.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x5DC3D725)]
+        pub fn feature_match(&mut self, match_id: MatchId, amount: Balance) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("feature_match"), &(match_id, amount));
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if betting_match.result.is_some() || betting_match.proposed_result.is_some() {
+                return Err(Error::ResultAlreadySet);
+            }
+            if amount > self.jackpot {
+                return Err(Error::NotEnoughJackpot);
+            }
+            self.jackpot = self.jackpot.saturating_sub(amount);
+            betting_match.jackpot = betting_match
+                .jackpot
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(JackpotAttached { match_id, amount });
+            Ok(())
+        }
+
+        /// Predict the exact score of a featured match before it starts, to share its
+        /// jackpot. Only bettors of the match can predict, a new prediction replaces
+        /// the previous one.
+        #[ink(message, selector = 0xA1E90155)]
+        pub fn predict_score(
+            &mut self,
+            match_id: MatchId,
+            team1_goals: u32,
+            team2_goals: u32,
+        ) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if betting_match.jackpot == 0 {
+                return Err(Error::MatchNotFeatured);
+            }
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            if !betting_match.bets.iter().any(|bet| bet.bettor == caller) {
+                return Err(Error::NotBettor);
+            }
+            betting_match
+                .score_predictions
+                .retain(|(bettor, _)| *bettor != caller);
+            betting_match
+                .score_predictions
+                .push((caller, (team1_goals, team2_goals)));
+            self.matches.insert(match_id, &betting_match);
+            Ok(())
+        }
+
+        /// Add the transferred value to the pot of a match, shared among the winners on top
+        /// of the losing stakes. It is refunded to the sponsor if the match is voided.
+        #[ink(message, payable, selector = 0x57476C97)]
+        pub fn sponsor_match(&mut self, match_id: MatchId) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let amount = Self::env().transferred_value();
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            // Only pools share a pot
+            if !matches!(betting_match.market, Market::Winner | Market::DrawNoBet) {
+                return Err(Error::WrongMarket);
+            }
+            if amount == 0 {
+                return Err(Error::InvalidSponsorship);
+            }
+            let sponsors_count = betting_match.sponsors.len();
+            match betting_match
+                .sponsors
+                .iter_mut()
+                .find(|(sponsor, _)| *sponsor == caller)
+            {
+                Some((_, sponsored)) => {
+                    *sponsored = sponsored
+                        .checked_add(amount)
+                        .ok_or(Error::ArithmeticOverflow)?
+                }
+                None if sponsors_count >= MAX_MATCH_SPONSORS => {
+                    return Err(Error::InvalidSponsorship)
+                }
+                None => betting_match.sponsors.push((caller, amount)),
+            }
+            betting_match.pot_bonus = betting_match
+                .pot_bonus
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.escrow_in(match_id, amount)?;
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(MatchSponsored {
+                match_id,
+                sponsor: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Escrow the transferred value in the boost budget of a match and set the boost,
+        /// in basis points, of the net winnings. The winners are boosted in the order of
+        /// the payouts until the budget runs out, and what is left of it goes back to the
+        /// creator at settlement. Only the creator can fund it, while betting is open.
+        #[ink(message, payable, selector = 0x8F89A783)]
+        pub fn fund_boost(&mut self, match_id: MatchId, boost_bps: u32) -> Result<(), Error> {
+            let amount = Self::env().transferred_value();
+            if Self::env().caller() != match_id {
+                return Err(Error::NotCreator);
+            }
+            let mut betting_match = match self.matches.get(match_id) {
+                Some(match_from_storage) => match_from_storage,
+                None => return Err(Error::MatchDoesNotExist),
+            };
+            if self.env().block_number() > Self::betting_closes(&betting_match) {
+                return Err(Error::MatchHasStarted);
+            }
+            // The shares of LMSR markets pay no more than their price
+            if let Market::Lmsr(_) = betting_match.market {
+                return Err(Error::WrongMarket);
+            }
+            if boost_bps == 0 || boost_bps > 10_000 {
+                return Err(Error::InvalidBoost);
+            }
+            betting_match.boost_bps = boost_bps;
+            betting_match.boost_budget = betting_match
+                .boost_budget
+                .checked_add(amount)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.escrow_in(match_id, amount)?;
+            self.matches.insert(match_id, &betting_match);
+            self.env().emit_event(BoostFunded {
+                match_id,
+                amount,
+                boost_bps,
+            });
+            Ok(())
+        }
+
+        /// Boost, in basis points, of the winnings of a match and what is left of its
+        /// budget, net of the boosts paid by a settlement in progress.
+        #[ink(message, selector = 0xF5AA3BFA)]
+        pub fn get_boost(&self, match_id: MatchId) -> Option<(u32, Balance)> {
+            let betting_match = self.matches.get(match_id)?;
+            let boosted = self
+                .settlements
+                .get(match_id)
+                .map_or(0, |settlement| settlement.boosted);
+            Some((
+                betting_match.boost_bps,
+                betting_match.boost_budget.saturating_sub(boosted),
+            ))
+        }
+
+        /// Flip a coin against the bankroll with the transferred value as stake. The flip is
+        /// decided by the randomness of the next block, and a won flip pays 1.96 times the
+        /// stake, the bankroll keeping a 2% edge, less the protocol fee on the profit. The
+        /// bankroll locks the profit until then.
+        #[ink(message, payable, selector = 0x8BE08033)]
+        pub fn place_coin_flip(&mut self, side: CoinSide) -> Result<CoinFlipId, Error> {
+            let caller = Self::env().caller();
+            self.ensure_not_paused()?;
+            self.ensure_not_blacklisted(caller)?;
+            let amount = Self::env().transferred_value();
+            Self::check_bet_amount(&self.get_config(), amount)?;
+            if self.randomness_oracle.is_none() {
+                return Err(Error::NoRandomnessOracle);
+            }
+            let payout = Self::bps_of(amount, Balance::from(COIN_FLIP_PAYOUT_BPS))?;
+            let backed = payout.saturating_sub(amount);
+            let max_exposure =
+                Self::bps_of(self.bankroll_value()?, Balance::from(self.max_exposure_bps))?;
+            if backed > max_exposure {
+                return Err(Error::ExposureExceeded);
+            }
+            if backed > self.bankroll {
+                return Err(Error::NotEnoughBankroll);
+            }
+            self.ensure_solvent(amount)?;
+            self.record_stake(caller, amount)?;
+            let coin_flip_id = self.next_coin_flip_id;
+            self.next_coin_flip_id = coin_flip_id
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let draw_block = self
+                .env()
+                .block_number()
+                .checked_add(1)
+                .ok_or(Error::ArithmeticOverflow)?;
+            // The stake and the backing are held in escrow until the flip is decided
+            self.bankroll = self.bankroll.saturating_sub(backed);
+            self.bankroll_locked = self
+                .bankroll_locked
+                .checked_add(backed)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.total_escrow = self
+                .total_escrow
+                .checked_add(payout)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.stats.bets = self.stats.bets.saturating_add(1);
+            self.stats.volume = self.stats.volume.saturating_add(amount);
+            self.open_positions = self.open_positions.saturating_add(1);
+            self.coin_flips.insert(
+                coin_flip_id,
+                &CoinFlip {
+                    player: caller,
+                    amount,
+                    side,
+                    backed,
+                    draw_block,
+                },
+            );
+            self.env().emit_event(CoinFlipPlaced {
+                coin_flip_id,
+                who: caller,
+                amount,
+                side,
+            });
+            Ok(coin_flip_id)
+        }
+
+        /// Decide a coin flip with the randomness the oracle published for its block,
+        /// paying the player if the flip is won. Anyone can call it.
+        #[ink(message, selector = 0x83504E51)]
+        pub fn resolve_coin_flip(&mut self, coin_flip_id: CoinFlipId) -> Result<(), Error> {
+            let coin_flip = match self.coin_flips.get(coin_flip_id) {
+                Some(coin_flip) => coin_flip,
+                None => return Err(Error::CoinFlipDoesNotExist),
+            };
+            if self.env().block_number() < coin_flip.draw_block {
+                return Err(Error::RandomnessNotReady);
+            }
+            let oracle_address = match self.randomness_oracle {
+                Some(oracle_address) => oracle_address,
+                None => return Err(Error::NoRandomnessOracle),
+            };
+            let mut oracle: ink::contract_ref!(RandomnessOracle) = oracle_address.into();
+            let seed = match oracle
+                .call_mut()
+                .get_randomness(coin_flip.draw_block)
+                .call_flags(NO_REENTRY)
+                .try_invoke()
+            {
+                Ok(Ok(Some(seed))) => seed,
+                Ok(Ok(None)) => return Err(Error::RandomnessNotReady),
+                _ => return Err(Error::OracleCallFailed),
+            };
+            self.apply_coin_flip(coin_flip_id, coin_flip, seed)
+        }
+
+        /// Pays a coin flip decided by `seed`, or returns its stake and backing to the
+        /// bankroll.
+        fn apply_coin_flip(
+            &mut self,
+            coin_flip_id: CoinFlipId,
+            coin_flip: CoinFlip,
+            seed: [u8; 32],
+        ) -> Result<(), Error> {
+            let side = Self::coin_side(seed, coin_flip_id);
+            let held = coin_flip
+                .amount
+                .checked_add(coin_flip.backed)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.coin_flips.remove(coin_flip_id);
+            // Positions are counted when opened, and their funds held in escrow
+            self.open_positions = self.open_positions.saturating_sub(1);
+            self.total_escrow = self.total_escrow.saturating_sub(held);
+            self.bankroll_locked = self.bankroll_locked.saturating_sub(coin_flip.backed);
+            let mut payout = 0;
+            if side == coin_flip.side {
+                // Take the protocol fee on the winnings
+                let fee = Self::bps_of(coin_flip.backed, Balance::from(self.get_config().fee_bps))?;
+                self.collect_fee(coin_flip.player, fee, None)?;
+                // The fee is at most 100% of the winnings
+                payout = held.saturating_sub(fee);
+                self.stats.paid_out = self.stats.paid_out.saturating_add(payout);
+                self.add_season_points(coin_flip.player, coin_flip.amount)?;
+                self.pay_or_defer(coin_flip.player, payout)?;
+            } else {
+                self.bankroll = self
+                    .bankroll
+                    .checked_add(held)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            self.env().emit_event(CoinFlipResolved {
+                coin_flip_id,
+                who: coin_flip.player,
+                side,
+                payout,
+            });
+            Ok(())
+        }
+
+        /// Refund the stake of a coin flip the randomness oracle never published the seed
+        /// of, a day after its draw block, and unlock its backing. A flip whose seed is
+        /// published is resolved instead. Anyone can call it.
+        #[ink(message, selector = 0x4C45922E)]
+        pub fn refund_coin_flip(&mut self, coin_flip_id: CoinFlipId) -> Result<(), Error> {
+            let coin_flip = match self.coin_flips.get(coin_flip_id) {
+                Some(coin_flip) => coin_flip,
+                None => return Err(Error::CoinFlipDoesNotExist),
+            };
+            if self.env().block_number() <= coin_flip.draw_block.saturating_add(COIN_FLIP_TIMEOUT) {
+                return Err(Error::RefundNotOpen);
+            }
+            // The player can't take a refund on a flip the seed says they lost
+            if let Some(oracle_address) = self.randomness_oracle {
+                let mut oracle: ink::contract_ref!(RandomnessOracle) = oracle_address.into();
+                if let Ok(Ok(Some(seed))) = oracle
+                    .call_mut()
+                    .get_randomness(coin_flip.draw_block)
+                    .call_flags(NO_REENTRY)
+                    .try_invoke()
+                {
+                    return self.apply_coin_flip(coin_flip_id, coin_flip, seed);
+                }
+            }
+            self.coin_flips.remove(coin_flip_id);
+            // Positions are counted when opened, and their funds held in escrow
+            self.open_positions = self.open_positions.saturating_sub(1);
+            self.total_escrow = self
+                .total_escrow
+                .saturating_sub(coin_flip.amount.saturating_add(coin_flip.backed));
+            self.bankroll_locked = self.bankroll_locked.saturating_sub(coin_flip.backed);
+            self.bankroll = self
+                .bankroll
+                .checked_add(coin_flip.backed)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.pay_or_defer(coin_flip.player, coin_flip.amount)?;
+            self.env().emit_event(CoinFlipRefunded {
+                coin_flip_id,
+                who: coin_flip.player,
+                amount: coin_flip.amount,
+            });
+            Ok(())
+        }
+
+        /// Side of a coin flip drawn from the seed of its block, mixed with its id so the
+        /// flips of a block are independent.
+        fn coin_side(seed: [u8; 32], coin_flip_id: CoinFlipId) -> CoinSide {
+            let mut draw =
+                <ink::env::hash::Blake2x256 as ink::env::hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(
+                &(seed, coin_flip_id),
+                &mut draw,
+            );
+            if draw[0] & 1 == 0 {
+                CoinSide::Heads
+            } else {
+                CoinSide::Tails
+            }
+        }
+
+        #[ink(message, selector = 0xB10D7408)]
+        pub fn get_coin_flip(&self, coin_flip_id: CoinFlipId) -> Option<CoinFlip> {
+            self.coin_flips.get(coin_flip_id)
+        }
+
+        /// Register the randomness beacon contract coin flips are decided by, or none.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x4C929B6B)]
+        pub fn set_randomness_oracle(&mut self, oracle: Option<AccountId>) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_randomness_oracle"), &oracle);
+            self.apply_action_now(AdminAction::SetRandomnessOracle(oracle))
+        }
+
+        /// Splits the jackpot of a featured match among the bettors who predicted its
+        /// score, rolling it over if nobody did.
+        fn share_jackpot(&mut self, match_id: MatchId, betting_match: &Match) -> Result<(), Error> {
+            if betting_match.jackpot == 0 {
+                return Ok(());
+            }
+            let winners: Vec<AccountId> = betting_match
+                .score_predictions
+                .iter()
+                .filter(|(_, score)| Some(*score) == betting_match.score)
+                .map(|(bettor, _)| *bettor)
+                .collect();
+            // There are fewer winners than bets
+            let winners_count = winners.len() as Balance;
+            let share = betting_match
+                .jackpot
+                .checked_div(winners_count)
+                .unwrap_or(0);
+            // The shares add up to at most the jackpot, the rest rolls over
+            let left = betting_match
+                .jackpot
+                .saturating_sub(share.saturating_mul(winners_count));
+            self.jackpot = self
+                .jackpot
+                .checked_add(left)
+                .ok_or(Error::ArithmeticOverflow)?;
+            for winner in winners {
+                self.stats.paid_out = self.stats.paid_out.saturating_add(share);
+                self.pay_or_defer(winner, share)?;
+                self.env().emit_event(JackpotWon {
+                    match_id,
+                    who: winner,
+                    amount: share,
+                });
+            }
+            Ok(())
+        }
+
+        /// Withdraw funds of the treasury to the owner.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xD875F67D)]
+        pub fn withdraw_treasury(&mut self, amount: Balance) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("withdraw_treasury"), &amount);
+            if amount > self.treasury {
+                return Err(Error::NotEnoughTreasury);
+            }
+            // The balance left has to cover the liabilities
+            self.ensure_solvent(amount)?;
+            self.treasury = self.treasury.saturating_sub(amount);
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        /// Schedule the withdrawal of any funds of the contract to the owner, as a recovery
+        /// path from catastrophic bugs. It can only be executed after a long timelock,
+        /// announced by an event so users have time to exit. Replaces any scheduled one.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x4499E752)]
+        pub fn schedule_emergency_withdraw(&mut self, amount: Balance) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("schedule_emergency_withdraw"), &amount);
+            let executable_at = self
+                .env()
+                .block_number()
+                .checked_add(EMERGENCY_WITHDRAW_TIMELOCK)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.emergency_withdrawal = Some((amount, executable_at));
+            self.env().emit_event(EmergencyWithdrawalScheduled {
+                amount,
+                executable_at,
+            });
+            Ok(())
+        }
+
+        /// Cancel the scheduled emergency withdrawal.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xE173C739)]
+        pub fn cancel_emergency_withdraw(&mut self) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("cancel_emergency_withdraw"), &());
+            let (amount, _) = match self.emergency_withdrawal.take() {
+                Some(emergency_withdrawal) => emergency_withdrawal,
+                None => return Err(Error::NoEmergencyWithdrawal),
+            };
+            self.env()
+                .emit_event(EmergencyWithdrawalCancelled { amount });
+            Ok(())
+        }
+
+        /// Execute the scheduled emergency withdrawal once its timelock is over.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x47EDB03A)]
+        pub fn execute_emergency_withdraw(&mut self) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            if caller != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("execute_emergency_withdraw"), &());
+            let (amount, executable_at) = match self.emergency_withdrawal {
+                Some(emergency_withdrawal) => emergency_withdrawal,
+                None => return Err(Error::NoEmergencyWithdrawal),
+            };
+            if self.env().block_number() < executable_at {
+                return Err(Error::TimelockNotOver);
+            }
+            self.emergency_withdrawal = None;
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env()
+                .emit_event(EmergencyWithdrawalExecuted { amount });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x60920AD8)]
+        pub fn get_emergency_withdrawal(&self) -> Option<(Balance, BlockNumber)> {
+            self.emergency_withdrawal
+        }
+
+        /// Prevent an account from creating matches and betting, e.g. for a compliance
+        /// takedown, without pausing the whole contract. Its open bets and match stay.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x3B2C784A)]
+        pub fn blacklist(&mut self, account: AccountId) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("blacklist"), &account);
+            self.blacklist.insert(account, &());
+            self.env().emit_event(Blacklisted { account });
+            Ok(())
+        }
+
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0xC7ED7207)]
+        pub fn unblacklist(&mut self, account: AccountId) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("unblacklist"), &account);
+            self.blacklist.remove(account);
+            self.env().emit_event(Unblacklisted { account });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x5663FE67)]
+        pub fn is_blacklisted(&self, account: AccountId) -> bool {
+            self.blacklist.contains(account)
+        }
+
+        fn ensure_not_paused(&self) -> Result<(), Error> {
+            if self.paused {
+                return Err(Error::ContractPaused);
+            }
+            Ok(())
+        }
+
+        /// Checks the caller is the governance contract, or the owner if there is none.
+        fn ensure_config_admin(&self) -> Result<(), Error> {
+            if Self::env().caller() != self.governance.unwrap_or(self.owner) {
+                return Err(Error::BadOrigin);
+            }
+            Ok(())
+        }
+
+        /// Hand the configuration over to a governance contract, so parameter changes go
+        /// through its on-chain votes rather than the owner key, or back to the owner.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0x394A5B32)]
+        pub fn set_governance(&mut self, governance: Option<AccountId>) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_governance"), &governance);
+            self.ensure_config_admin()?;
+            self.governance = governance;
+            self.env().emit_event(GovernanceSet { governance });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xC2809940)]
+        pub fn get_governance(&self) -> Option<AccountId> {
+            self.governance
+        }
+
+        /// Set the guardian allowed to pause the contract in an emergency, or none.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x5133ED53)]
+        pub fn set_guardian(&mut self, guardian: Option<AccountId>) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("set_guardian"), &guardian);
+            self.guardian = guardian;
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xCC049358)]
+        pub fn get_guardian(&self) -> Option<AccountId> {
+            self.guardian
+        }
+
+        /// Refuse new matches and positions until the contract is unpaused. Open matches
+        /// can still get their results and be settled, and funds withdrawn.
+        /// The dispatch origin for this call must be the guardian or the owner.
+        #[ink(message, selector = 0x81E0C604)]
+        pub fn pause(&mut self) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("pause"), &());
+            let caller = self.ensure_guardian()?;
+            self.paused = true;
+            self.env().emit_event(Paused { by: caller });
+            Ok(())
+        }
+
+        /// The dispatch origin for this call must be the guardian or the owner.
+        #[ink(message, selector = 0x67616649)]
+        pub fn unpause(&mut self) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("unpause"), &());
+            let caller = self.ensure_guardian()?;
+            self.paused = false;
+            self.env().emit_event(Unpaused { by: caller });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xFA7D505B)]
+        pub fn is_paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Returns the caller if it is the guardian or the owner.
+        fn ensure_guardian(&self) -> Result<AccountId, Error> {
+            let caller = Self::env().caller();
+            if caller != self.owner && Some(caller) != self.guardian {
+                return Err(Error::BadOrigin);
+            }
+            Ok(caller)
+        }
+
+        /// Replace the code of the contract with the uploaded code `code_hash`, keeping its
+        /// storage. The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x9852F7B0)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("upgrade"), &code_hash);
+            self.apply_action_now(AdminAction::Upgrade(code_hash))
+        }
+
+        /// Set the blocks between scheduling a sensitive admin action and executing it.
+        /// While it is not zero, fee changes, oracle changes and upgrades, the delay
+        /// included, can only be made with `schedule_action`.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x4B378764)]
+        pub fn set_admin_delay(&mut self, delay: BlockNumber) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("set_admin_delay"), &delay);
+            self.apply_action_now(AdminAction::SetAdminDelay(delay))
+        }
+
+        #[ink(message, selector = 0x8480EA4E)]
+        pub fn get_admin_delay(&self) -> BlockNumber {
+            self.admin_delay
+        }
+
+        /// Schedule an admin action to be executed once the admin delay is over, giving
+        /// users time to react. Returns the id of the scheduled action.
+        /// The dispatch origin for this call must be the account allowed to make the action.
+        #[ink(message, selector = 0x6CFFD00D)]
+        pub fn schedule_action(&mut self, action: AdminAction) -> Result<ActionId, Error> {
+            self.log_admin(ink::selector_bytes!("schedule_action"), &action);
+            if Self::env().caller() != self.action_admin(&action) {
+                return Err(Error::BadOrigin);
+            }
+            let action_id = self.next_action_id;
+            self.next_action_id = action_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            let executable_at = self
+                .env()
+                .block_number()
+                .checked_add(self.admin_delay)
+                .ok_or(Error::ArithmeticOverflow)?;
+            self.scheduled_actions.insert(
+                action_id,
+                &ScheduledAction {
+                    action: action.clone(),
+                    executable_at,
+                },
+            );
+            self.env().emit_event(ActionScheduled {
+                action_id,
+                action,
+                executable_at,
+            });
+            Ok(action_id)
+        }
+
+        /// Execute a scheduled admin action once its delay is over. Anyone can call it.
+        #[ink(message, selector = 0xDE775C14)]
+        pub fn execute_action(&mut self, action_id: ActionId) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("execute_action"), &action_id);
+            let scheduled = match self.scheduled_actions.get(action_id) {
+                Some(scheduled) => scheduled,
+                None => return Err(Error::ActionDoesNotExist),
+            };
+            if self.env().block_number() < scheduled.executable_at {
+                return Err(Error::ActionNotReady);
+            }
+            self.scheduled_actions.remove(action_id);
+            self.apply_action(scheduled.action)?;
+            self.env().emit_event(ActionExecuted { action_id });
+            Ok(())
+        }
+
+        /// Cancel a scheduled admin action.
+        /// The dispatch origin for this call must be the account allowed to make the action.
+        #[ink(message, selector = 0x35C210A8)]
+        pub fn cancel_action(&mut self, action_id: ActionId) -> Result<(), Error> {
+            self.log_admin(ink::selector_bytes!("cancel_action"), &action_id);
+            let scheduled = match self.scheduled_actions.get(action_id) {
+                Some(scheduled) => scheduled,
+                None => return Err(Error::ActionDoesNotExist),
+            };
+            if Self::env().caller() != self.action_admin(&scheduled.action) {
+                return Err(Error::BadOrigin);
+            }
+            self.scheduled_actions.remove(action_id);
+            self.env().emit_event(ActionCancelled { action_id });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x5D3DE425)]
+        pub fn get_scheduled_action(&self, action_id: ActionId) -> Option<ScheduledAction> {
+            self.scheduled_actions.get(action_id)
+        }
+
+        /// Account allowed to make an admin action: the governance contract, or the owner
+        /// if there is none, for fee changes, the owner for the others.
+        fn action_admin(&self, action: &AdminAction) -> AccountId {
+            match action {
+                AdminAction::SetProtocolFee(_) | AdminAction::SetMatchFeeBounds(_, _) => {
+                    self.governance.unwrap_or(self.owner)
+                }
+                _ => self.owner,
+            }
+        }
+
+        /// Makes an admin action right away, if the caller is allowed to and there is no
+        /// admin delay.
+        fn apply_action_now(&mut self, action: AdminAction) -> Result<(), Error> {
+            if Self::env().caller() != self.action_admin(&action) {
+                return Err(Error::BadOrigin);
+            }
+            if self.admin_delay > 0 {
+                return Err(Error::ActionTimelocked);
+            }
+            self.apply_action(action)
+        }
+
+        fn apply_action(&mut self, action: AdminAction) -> Result<(), Error> {
+            match action {
+                AdminAction::SetProtocolFee(bps) => {
+                    let mut config = self.get_config();
+                    config.fee_bps = bps;
+                    self.apply_config(config)?;
+                }
+                AdminAction::SetMatchFeeBounds(min_bps, max_bps) => {
+                    let mut config = self.get_config();
+                    config.min_match_fee_bps = min_bps;
+                    config.max_match_fee_bps = max_bps;
+                    self.apply_config(config)?;
+                }
+                AdminAction::SetFeeRouter(router) => {
+                    // The fees the former router didn't get go to the treasury
+                    if let Some(former) = self.fee_router {
+                        let accrued = self.accrued_fees.take(former).unwrap_or(0);
+                        self.total_accrued_fees = self.total_accrued_fees.saturating_sub(accrued);
+                        self.treasury = self
+                            .treasury
+                            .checked_add(accrued)
+                            .ok_or(Error::ArithmeticOverflow)?;
+                    }
+                    self.fee_router = router
+                }
+                AdminAction::SetResultOracle(oracle) => self.result_oracle = oracle,
+                AdminAction::SetRandomnessOracle(oracle) => self.randomness_oracle = oracle,
+                AdminAction::AuthorizeOracle(oracle, sport) => {
+                    self.sport_oracles.insert((oracle, sport.clone()), &());
+                    self.env().emit_event(OracleAuthorized {
+                        oracle,
+                        sport,
+                        authorized: true,
+                    });
+                }
+                AdminAction::RevokeOracle(oracle, sport) => {
+                    self.sport_oracles.remove((oracle, sport.clone()));
+                    self.env().emit_event(OracleAuthorized {
+                        oracle,
+                        sport,
+                        authorized: false,
+                    });
+                }
+                AdminAction::AddScoreOracle(oracle) => {
+                    self.score_oracles.insert(oracle, &());
+                }
+                AdminAction::RemoveScoreOracle(oracle) => self.score_oracles.remove(oracle),
+                AdminAction::Upgrade(code_hash) => self
+                    .env()
+                    .set_code_hash(&code_hash)
+                    .map_err(|_| Error::UpgradeFailed)?,
+                AdminAction::SetAdminDelay(delay) => self.admin_delay = delay,
+            }
+            Ok(())
+        }
+
+        /// Records a privileged call of the message `selector` with `params` in the admin
+        /// log. A call that fails reverts, its record included.
+        fn log_admin(&mut self, selector: [u8; 4], params: &impl scale::Encode) {
+            let mut params_hash =
+                <ink::env::hash::Blake2x256 as ink::env::hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(params, &mut params_hash);
+            // The capacity is not zero
+            let position = self
+                .admin_log_count
+                .checked_rem(ADMIN_LOG_CAPACITY)
+                .unwrap_or(0);
+            self.admin_log.insert(
+                position,
+                &AdminLogEntry {
+                    who: Self::env().caller(),
+                    selector,
+                    block: self.env().block_number(),
+                    params_hash,
+                },
+            );
+            // Billions of privileged calls are out of reach
+            self.admin_log_count = self.admin_log_count.saturating_add(1);
+        }
+
+        /// Privileged calls kept in the admin log, from the oldest, at most 50 from
+        /// `offset`. Only the last 1000 are kept.
+        #[ink(message, selector = 0x50BEA2A0)]
+        pub fn get_admin_log(&self, offset: u32, limit: u32) -> Vec<AdminLogEntry> {
+            let oldest = self
+                .admin_log_count
+                .saturating_sub(ADMIN_LOG_CAPACITY)
+                .saturating_add(offset);
+            (oldest..self.admin_log_count)
+                .take(limit.min(MAX_ADMIN_LOG_PAGE) as usize)
+                .filter_map(|index| {
+                    // The capacity is not zero
+                    self.admin_log
+                        .get(index.checked_rem(ADMIN_LOG_CAPACITY).unwrap_or(0))
+                })
+                .collect()
+        }
+
+        fn ensure_not_blacklisted(&self, account: AccountId) -> Result<(), Error> {
+            if self.blacklist.contains(account) {
+                return Err(Error::AccountBlacklisted);
+            }
+            Ok(())
+        }
+
+        /// Exclude the caller from betting until `until_block`. An exclusion can only
+        /// be extended, not shortened.
+        #[ink(message, selector = 0xC11B2AFE)]
+        pub fn self_exclude(&mut self, until_block: BlockNumber) -> Result<(), Error> {
+            let caller = Self::env().caller();
+            let until = self
+                .self_exclusions
+                .get(caller)
+                .map_or(until_block, |until| until.max(until_block));
+            self.self_exclusions.insert(caller, &until);
+            self.env()
+                .emit_event(SelfExclusionSet { who: caller, until });
+            Ok(())
+        }
+
+        fn ensure_not_self_excluded(&self, account: AccountId) -> Result<(), Error> {
+            if self
+                .self_exclusions
+                .get(account)
+                .is_some_and(|until| self.env().block_number() < until)
+            {
+                return Err(Error::SelfExcluded);
+            }
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x5004B513)]
+        pub fn get_self_exclusion(&self, account: AccountId) -> Option<BlockNumber> {
+            self.self_exclusions.get(account)
+        }
+
+        /// Limit the amount the caller can stake in any window of `PERSONAL_LIMIT_PERIOD`
+        /// blocks. A lower limit applies at once, a higher one only after
+        /// `PERSONAL_LIMIT_PERIOD` blocks. What was staked in the window still counts.
+        #[ink(message, selector = 0x539EFADB)]
+        pub fn set_personal_limit(&mut self, amount_per_period: Balance) -> Result<(), Error> {
+            let caller = Self::env().caller();
             let current_block_number = self.env().block_number();
-            if current_block_number > match_to_bet.start {
-                return Err(Error::MatchHasStarted);
+            let mut limit = match self.personal_limits.get(caller) {
+                Some(limit) => limit,
+                None => PersonalLimit {
+                    amount_per_period,
+                    pending_increase: None,
+                    stakes: Vec::new(),
+                },
+            };
+            Self::apply_limit_increase(&mut limit, current_block_number);
+            let applies_from = if amount_per_period > limit.amount_per_period {
+                let applies_from = current_block_number.saturating_add(PERSONAL_LIMIT_PERIOD);
+                limit.pending_increase = Some((amount_per_period, applies_from));
+                applies_from
+            } else {
+                limit.amount_per_period = amount_per_period;
+                limit.pending_increase = None;
+                current_block_number
+            };
+            self.personal_limits.insert(caller, &limit);
+            self.env().emit_event(PersonalLimitSet {
+                who: caller,
+                amount_per_period,
+                applies_from,
+            });
+            Ok(())
+        }
+
+        /// Applies the pending increase of a personal limit once it is due.
+        fn apply_limit_increase(limit: &mut PersonalLimit, current_block_number: BlockNumber) {
+            if let Some((amount_per_period, applies_from)) = limit.pending_increase {
+                if current_block_number >= applies_from {
+                    limit.amount_per_period = amount_per_period;
+                    limit.pending_increase = None;
+                }
+            }
+        }
+
+        #[ink(message, selector = 0xE4EEB700)]
+        pub fn get_personal_limit(&self, account: AccountId) -> Option<PersonalLimit> {
+            self.personal_limits.get(account)
+        }
+
+        /// Checks `account` is not self-excluded and can stake `amount` within its
+        /// personal limit, counting it in the rolling window.
+        fn record_stake(&mut self, account: AccountId, amount: Balance) -> Result<(), Error> {
+            let current_block_number = self.env().block_number();
+            self.ensure_not_self_excluded(account)?;
+            if let Some(mut limit) = self.personal_limits.get(account) {
+                Self::apply_limit_increase(&mut limit, current_block_number);
+                // Buckets that left the window no longer count
+                limit.stakes.retain(|(bucket, _)| {
+                    bucket.saturating_add(PERSONAL_LIMIT_PERIOD) > current_block_number
+                });
+                let bucket = current_block_number.saturating_sub(
+                    current_block_number
+                        .checked_rem(PERSONAL_LIMIT_BUCKET)
+                        .unwrap_or(0),
+                );
+                match limit.stakes.last_mut() {
+                    Some((last, staked)) if *last == bucket => {
+                        *staked = staked
+                            .checked_add(amount)
+                            .ok_or(Error::ArithmeticOverflow)?
+                    }
+                    _ => limit.stakes.push((bucket, amount)),
+                }
+                let staked = Self::checked_sum(limit.stakes.iter().map(|(_, staked)| *staked))?;
+                if staked > limit.amount_per_period {
+                    return Err(Error::PersonalLimitExceeded);
+                }
+                self.personal_limits.insert(account, &limit);
+            }
+            Ok(())
+        }
+
+        /// Counts a bet placed by an account in its rate limit window, failing if it has
+        /// placed the most bets allowed in it.
+        fn record_bet_rate(&mut self, account: AccountId) -> Result<(), Error> {
+            let config = self.get_config();
+            if config.max_bets_per_window == 0 {
+                return Ok(());
+            }
+            let current_block_number = self.env().block_number();
+            let (mut window_start, mut count) = self.bet_rates.get(account).unwrap_or_default();
+            if current_block_number >= window_start.saturating_add(config.rate_limit_window) {
+                window_start = current_block_number;
+                count = 0;
+            }
+            if count >= config.max_bets_per_window {
+                return Err(Error::RateLimited);
+            }
+            // The count is below the limit
+            count = count.saturating_add(1);
+            self.bet_rates.insert(account, &(window_start, count));
+            Ok(())
+        }
+
+        /// Set the most bets an account can place per window of `window` blocks, as a
+        /// defense against spam, `0` for no limit.
+        /// The dispatch origin for this call must be the governance contract, or the owner
+        /// if there is none.
+        #[ink(message, selector = 0xC4239A72)]
+        pub fn set_bet_rate_limit(
+            &mut self,
+            max_bets: u32,
+            window: BlockNumber,
+        ) -> Result<(), Error> {
+            self.log_admin(
+                ink::selector_bytes!("set_bet_rate_limit"),
+                &(max_bets, window),
+            );
+            self.update_config(|config| {
+                config.max_bets_per_window = max_bets;
+                config.rate_limit_window = window;
+            })
+        }
+
+        /// Simply checks if a match exists.
+        #[ink(message, selector = 0x7A91265C)]
+        pub fn exists_match(&self, owner: AccountId) -> bool {
+            self.matches.contains(owner)
+        }
+        #[ink(message, selector = 0x38785C75)]
+        pub fn get_match(&self, owner: AccountId) -> Option<Match> {
+            self.matches.get(owner)
+        }
+
+        /// Bet of a match by its id, while the match is open.
+        #[ink(message, selector = 0x23629F3D)]
+        pub fn get_bet(&self, match_id: MatchId, bet_id: BetId) -> Option<Bet> {
+            self.matches
+                .get(match_id)?
+                .bets
+                .into_iter()
+                .find(|bet| bet.id == bet_id)
+        }
+
+        /// Bets held by an account on an open match.
+        #[ink(message, selector = 0x443BF460)]
+        pub fn get_bets_of(&self, match_id: MatchId, account: AccountId) -> Vec<Bet> {
+            self.matches
+                .get(match_id)
+                .map(|betting_match| {
+                    betting_match
+                        .bets
+                        .into_iter()
+                        .filter(|bet| bet.bettor == account)
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+
+        /// Outcomes of a match with their labels.
+        #[ink(message, selector = 0xF4439959)]
+        pub fn get_outcomes(&self, match_id: MatchId) -> Vec<(OutcomeId, TeamName)> {
+            self.matches
+                .get(match_id)
+                .map(|betting_match| {
+                    betting_match
+                        .outcome_labels
+                        .into_iter()
+                        .enumerate()
+                        .filter_map(|(index, label)| Some((OutcomeId::nth(index)?, label)))
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+
+        /// Add a team to the registry, returning its id.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x3C8FD8FB)]
+        pub fn register_team(
+            &mut self,
+            name: TeamName,
+            metadata: Vec<u8>,
+        ) -> Result<TeamId, Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(ink::selector_bytes!("register_team"), &(&name, &metadata));
+            if self.team_names.contains(&name) {
+                return Err(Error::TeamAlreadyExists);
+            }
+            let team_id = self.next_team_id;
+            self.next_team_id = team_id.checked_add(1).ok_or(Error::ArithmeticOverflow)?;
+            self.team_names.insert(&name, &team_id);
+            self.teams.insert(
+                team_id,
+                &Team {
+                    name: name.clone(),
+                    metadata,
+                },
+            );
+            self.env().emit_event(TeamRegistered { team_id, name });
+            Ok(team_id)
+        }
+
+        /// Rename a registered team or change its metadata. Open matches keep the name
+        /// they were created with.
+        /// The dispatch origin for this call must be the owner.
+        #[ink(message, selector = 0x9880D440)]
+        pub fn update_team(
+            &mut self,
+            team_id: TeamId,
+            name: TeamName,
+            metadata: Vec<u8>,
+        ) -> Result<(), Error> {
+            if Self::env().caller() != self.owner {
+                return Err(Error::BadOrigin);
+            }
+            self.log_admin(
+                ink::selector_bytes!("update_team"),
+                &(team_id, &name, &metadata),
+            );
+            let team = match self.teams.get(team_id) {
+                Some(team) => team,
+                None => return Err(Error::TeamDoesNotExist),
+            };
+            if team.name != name {
+                if self.team_names.contains(&name) {
+                    return Err(Error::TeamAlreadyExists);
+                }
+                self.team_names.remove(&team.name);
+                self.team_names.insert(&name, &team_id);
+            }
+            self.teams.insert(
+                team_id,
+                &Team {
+                    name: name.clone(),
+                    metadata,
+                },
+            );
+            self.env().emit_event(TeamRegistered { team_id, name });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x97FC4D12)]
+        pub fn get_team(&self, team_id: TeamId) -> Option<Team> {
+            self.teams.get(team_id)
+        }
+
+        #[ink(message, selector = 0xF98F5AA3)]
+        pub fn get_team_stats(&self, team_id: TeamId) -> TeamStats {
+            self.team_stats.get(team_id).unwrap_or_default()
+        }
+
+        /// Returns the id of a registered team name, if any.
+        #[ink(message, selector = 0xAAEF24FA)]
+        pub fn find_team(&self, name: TeamName) -> Option<TeamId> {
+            self.team_names.get(name)
+        }
+
+        /// Returns the id of the open match of a fixture with the kind of `market`, if any.
+        #[ink(message, selector = 0x6CAF1779)]
+        pub fn find_match_by_spec(
+            &self,
+            team1: TeamName,
+            team2: TeamName,
+            start: BlockNumber,
+            length: BlockNumber,
+            market: Market,
+        ) -> Option<MatchId> {
+            self.matches_hashes.get(Self::fixture_hash(
+                &team1,
+                &team2,
+                start,
+                length,
+                market.kind(),
+            ))
+        }
+
+        /// Summary of a match, lighter than `get_match` as it leaves out the bets.
+        #[ink(message, selector = 0x4B324902)]
+        pub fn get_match_info(&self, match_id: MatchId) -> Option<MatchInfo> {
+            let status = self.get_status(match_id)?;
+            let betting_match = self.matches.get(match_id)?;
+            Some(MatchInfo {
+                total_pool: Self::total_pool(&betting_match).ok()?,
+                outcome_pools: Self::outcome_pools(&betting_match).ok()?,
+                bet_count: betting_match.bets.len() as u32,
+                fee_bps: self.match_fee_bps(&betting_match),
+                team1: betting_match.team1,
+                team2: betting_match.team2,
+                start: betting_match.start,
+                length: betting_match.length,
+                status,
+                market: betting_match.market,
+                result: betting_match.result,
+                live_score: betting_match.live_score.map(|(score, _)| score),
+            })
+        }
+
+        /// Amount bet, or shares held, on each outcome of a match, in the order of their
+        /// labels.
+        fn outcome_pools(betting_match: &Match) -> Result<Vec<Balance>, Error> {
+            let mut pools = Vec::new();
+            for index in 0..betting_match.outcome_labels.len() {
+                let outcome = OutcomeId::nth(index);
+                pools.push(Self::checked_sum(
+                    betting_match
+                        .bets
+                        .iter()
+                        .filter(|bet| Some(bet.result) == outcome)
+                        .map(|bet| bet.amount),
+                )?);
+            }
+            Ok(pools)
+        }
+
+        /// Implied decimal odds of each outcome of a match, in hundredths.
+        fn implied_odds(betting_match: &Match) -> Result<[u32; 3], Error> {
+            let total_pool = Self::total_pool(betting_match)?;
+            let mut odds = [0; 3];
+            for (outcome_odds, outcome_pool) in odds
+                .iter_mut()
+                .zip(Self::outstanding_shares(&betting_match.bets)?)
+            {
+                if let Some(ratio) = total_pool
+                    .checked_mul(Balance::from(ODDS_UNIT))
+                    .ok_or(Error::ArithmeticOverflow)?
+                    .checked_div(outcome_pool)
+                {
+                    *outcome_odds = u32::try_from(ratio).unwrap_or(u32::MAX);
+                }
+            }
+            Ok(odds)
+        }
+
+        /// Amount bet on a match with the funds added to the pot, or paid for shares
+        /// in LMSR markets.
+        fn total_pool(betting_match: &Match) -> Result<Balance, Error> {
+            match betting_match.market {
+                Market::Lmsr(_) => Ok(betting_match.collected),
+                _ => Self::checked_sum(
+                    betting_match
+                        .bets
+                        .iter()
+                        .map(|bet| bet.amount)
+                        .chain([betting_match.pot_bonus]),
+                ),
+            }
+        }
+
+        /// Hash of the fixture and market kind of a match, so the same market can't be
+        /// opened twice on a fixture.
+        pub fn get_match_hash(betting_match: &Match) -> [u8; 32] {
+            Self::fixture_hash(
+                &betting_match.team1,
+                &betting_match.team2,
+                betting_match.start,
+                betting_match.length,
+                betting_match.market.kind(),
+            )
+        }
+
+        fn fixture_hash(
+            team1: &TeamName,
+            team2: &TeamName,
+            start: BlockNumber,
+            length: BlockNumber,
+            market_kind: u8,
+        ) -> [u8; 32] {
+            let entropy = (team1, team2, start, length, market_kind);
+            let mut message =
+                <ink::env::hash::Sha2x256 as ink::env::hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<ink::env::hash::Sha2x256, _>(&entropy, &mut message);
+            message
+        }
+
+        /// Weighted distribution of the whole pot among the bets on the result.
+        fn winner_payouts(
+            bets: &[Bet],
+            result: MatchResult,
+            pot_bonus: Balance,
+        ) -> Result<Vec<Payout>, Error> {
+            // Iterate over all bets to get the winners accounts
+            let mut total_winners: Balance = 0u32.into();
+            let mut total_weighted: Balance = 0;
+            let mut total_bet: Balance = pot_bonus;
+            let mut winners = Vec::new();
+            for bet in bets.iter() {
+                total_bet = total_bet
+                    .checked_add(bet.amount)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                if bet.result == result {
+                    // The winning stakes are part of the total
+                    total_winners = total_winners.saturating_add(bet.amount);
+                    total_weighted = total_weighted.saturating_add(Self::weighted_stake(bet));
+                    winners.push(bet)
+                }
+            }
+            // Winners get their stake back and share the rest by weighted stake, by stake
+            // if the weights round down to nothing
+            let total_lost = total_bet.saturating_sub(total_winners);
+            let weighted = |bet: &Bet| {
+                if total_weighted == 0 {
+                    (bet.amount, total_winners)
+                } else {
+                    (Self::weighted_stake(bet), total_weighted)
+                }
+            };
+            winners
+                .iter()
+                .map(|winner_bet| {
+                    let (stake, total) = weighted(winner_bet);
+                    Ok(Payout {
+                        bettor: winner_bet.bettor,
+                        won: winner_bet
+                            .amount
+                            .checked_add(
+                                Self::mul_div(stake, total_lost, total)
+                                    .ok_or(Error::ArithmeticOverflow)?,
+                            )
+                            .ok_or(Error::ArithmeticOverflow)?,
+                        refunded: 0,
+                        credit: winner_bet.credit,
+                        stake: winner_bet.amount,
+                    })
+                })
+                .collect()
+        }
+
+        /// Stake of a bet weighted by how early it was placed.
+        fn weighted_stake(bet: &Bet) -> Balance {
+            Self::mul_div(
+                bet.amount,
+                Balance::from(bet.weight_bps),
+                Balance::from(FULL_BET_WEIGHT_BPS),
+            )
+            .unwrap_or(bet.amount)
+        }
+
+        /// Handicap distribution: pushed stakes are refunded and the lost stakes
+        /// are shared among the won stakes, weighted by amount.
+        fn handicap_payouts(
+            bets: &[Bet],
+            line: i32,
+            score: (u32, u32),
+        ) -> Result<Vec<Payout>, Error> {
+            let splits: Vec<(Balance, Balance, Balance)> = bets
+                .iter()
+                .map(|bet| Self::handicap_split(line, bet.result, score, bet.amount))
+                .collect();
+            let mut total_won: Balance = 0;
+            let mut total_lost: Balance = 0;
+            for (won, _, lost) in &splits {
+                total_won = total_won
+                    .checked_add(*won)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                total_lost = total_lost
+                    .checked_add(*lost)
+                    .ok_or(Error::ArithmeticOverflow)?;
+            }
+            let mut payouts = Vec::new();
+            for (bet, (won, pushed, _)) in bets.iter().zip(splits) {
+                // The prize is at most the lost stakes, none if nothing was won
+                let prize = Self::mul_div(won, total_lost, total_won).unwrap_or(0);
+                // The portions of a stake add up to the stake
+                if won.saturating_add(pushed) > 0 {
+                    payouts.push(Payout {
+                        bettor: bet.bettor,
+                        won: won.checked_add(prize).ok_or(Error::ArithmeticOverflow)?,
+                        refunded: pushed,
+                        credit: bet.credit,
+                        stake: won,
+                    });
+                }
+            }
+            Ok(payouts)
+        }
+
+        /// Splits a stake backing `side` on a handicap line into its (won, pushed, lost)
+        /// portions. Quarter lines put half of the stake on each adjacent line.
+        fn handicap_split(
+            line: i32,
+            side: MatchResult,
+            score: (u32, u32),
+            amount: Balance,
+        ) -> (Balance, Balance, Balance) {
+            // Scores and lines are far from the bounds of an i64
+            let goal_difference = i64::from(score.0)
+                .saturating_sub(i64::from(score.1))
+                .saturating_mul(4);
+            let mut split: (Balance, Balance, Balance) = (0, 0, 0);
+            let halves = if line % 2 == 0 {
+                [(i64::from(line), amount), (i64::from(line), 0)]
+            } else {
+                [
+                    (i64::from(line).saturating_sub(1), amount / 2),
+                    (
+                        i64::from(line).saturating_add(1),
+                        amount.saturating_sub(amount / 2),
+                    ),
+                ]
+            };
+            // The portions add up to at most the stake
+            for (half_line, stake) in halves {
+                let mut margin = goal_difference.saturating_add(half_line);
+                if side == MatchResult::Team2Victory {
+                    margin = margin.saturating_neg();
+                }
+                match margin.cmp(&0) {
+                    core::cmp::Ordering::Greater => split.0 = split.0.saturating_add(stake),
+                    core::cmp::Ordering::Equal => split.1 = split.1.saturating_add(stake),
+                    core::cmp::Ordering::Less => split.2 = split.2.saturating_add(stake),
+                }
+            }
+            split
+        }
+    }
+
+    impl BettingMarket for Betting {
+        #[ink(message, payable)]
+        fn bet(&mut self, match_id: MatchId, result: MatchResult) -> Result<(), Error> {
+            Betting::bet(self, match_id, result)
+        }
+
+        #[ink(message)]
+        fn get_odds(&self, match_id: MatchId) -> Option<[u32; 3]> {
+            let betting_match = self.matches.get(match_id)?;
+            match betting_match.market {
+                Market::Lmsr(_) => None,
+                Market::FixedOdds(odds) => Some(odds),
+                _ => Self::implied_odds(&betting_match).ok(),
+            }
+        }
+
+        #[ink(message)]
+        fn get_status(&self, match_id: MatchId) -> Option<MatchStatus> {
+            Betting::get_status(self, match_id)
+        }
+
+        #[ink(message)]
+        fn claim_winnings(&mut self) -> Result<(), Error> {
+            self.withdraw_pending()
+        }
+    }
+
+    /// Fixed point arithmetic of the LMSR market maker, scaled by `ONE`.
+    mod lmsr {
+        use super::Balance;
+
+        const ONE: u128 = 1_000_000_000_000;
+        const LN_2: u128 = 693_147_180_560;
+        /// Exponents above this are rounded to zero.
+        const MAX_EXPONENT: u128 = 64;
+
+        /// Cost function `C(q) = b * ln(sum(e^(q_i / b)))`, computed as
+        /// `max(q) + b * ln(sum(e^((q_i - max(q)) / b)))` to keep the exponents small.
+        /// `None` on overflow.
+        pub fn cost(quantities: &[Balance], liquidity: Balance) -> Option<Balance> {
+            let max = quantities.iter().copied().max().unwrap_or(0);
+            let mut sum: u128 = 0;
+            for quantity in quantities {
+                sum = sum.checked_add(exp_neg(ratio(max.checked_sub(*quantity)?, liquidity)?))?;
+            }
+            liquidity
+                .checked_mul(ln(sum))?
+                .checked_div(ONE)?
+                .checked_add(max)
+        }
+
+        /// `x / b` in fixed point, capped to `MAX_EXPONENT`.
+        fn ratio(x: Balance, b: Balance) -> Option<u128> {
+            let whole = x.checked_div(b)?;
+            let fraction = x.checked_rem(b)?.checked_mul(ONE)?.checked_div(b)?;
+            whole
+                .min(MAX_EXPONENT)
+                .checked_mul(ONE)?
+                .checked_add(if whole >= MAX_EXPONENT { 0 } else { fraction })
+        }
+
+        /// `e^(-x)`, reduced to `2^(-k) * e^(-r)` with `r < ln(2)`.
+        // The terms of the series are below `ONE`
+        #[allow(clippy::arithmetic_side_effects)]
+        fn exp_neg(x: u128) -> u128 {
+            let k = x / LN_2;
+            let r = x % LN_2;
+            // Taylor series with alternating terms
+            let mut sum = ONE;
+            let mut term = ONE;
+            let mut n = 1;
+            while term > 0 {
+                term = term * r / ONE / n;
+                if n % 2 == 1 {
+                    sum -= term;
+                } else {
+                    sum += term;
+                }
+                n += 1;
+            }
+            sum >> k
+        }
+
+        /// `ln(x)` for `x >= 1`, reduced to `k * ln(2) + ln(m)` with `1 <= m < 2`.
+        // `m` and the terms of the series are below `2 * ONE`
+        #[allow(clippy::arithmetic_side_effects)]
+        fn ln(x: u128) -> u128 {
+            let mut k = 0;
+            let mut m = x;
+            while m >= 2 * ONE {
+                m /= 2;
+                k += 1;
+            }
+            // ln(m) = 2 * atanh(z) with z = (m - 1) / (m + 1)
+            let z = (m - ONE) * ONE / (m + ONE);
+            let z2 = z * z / ONE;
+            let mut sum = 0;
+            let mut term = z;
+            let mut n = 1;
+            while term > 0 {
+                sum += term / n;
+                term = term * z2 / ONE;
+                n += 2;
+            }
+            k * LN_2 + 2 * sum
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::bool_assert_comparison,
+        clippy::let_and_return
+    )]
+    mod tests {
+        use crate::betting::{
+            AdminAction, AdminLogEntry, Bet, Betting, CoinSide, Config, CreatorMilestone,
+            CreatorReputation, DustPolicy, Error, FeeTier, Market, MatchResult, MatchSpec,
+            MatchStatus, OrderSide, OutcomeId, SettlementReport, TeamStats, TerminationBlockers,
+            FULL_BET_WEIGHT_BPS,
+        };
+        use crate::BettingMarket;
+        use ink::primitives::AccountId;
+
+        fn set_accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.alice,
+                100000000000000,
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.bob,
+                100000000000000,
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                100000000000000,
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.django,
+                100000000000000,
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.eve,
+                100000000000000,
+            );
+            accounts
+        }
+
+        fn create_contract(who: AccountId) -> Betting {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(who);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            let betting = Betting::new();
+            betting
+        }
+
+        fn create_match(
+            betting: &mut Betting,
+            who: AccountId,
+            t1: &str,
+            t2: &str,
+            start: u32,
+            length: u32,
+            deposit: u128,
+        ) -> AccountId {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(who);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(deposit);
+            // ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(deposit);
+            // Dispatch a signed extrinsic.
+            assert_eq!(
+                betting.create_match_to_bet(
+                    t1.as_bytes().to_vec(),
+                    t2.as_bytes().to_vec(),
+                    start,
+                    length,
+                    true,
+                    false,
+                    None
+                ),
+                Ok(())
+            );
+            who
+        }
+
+        /// Decodes the recorded events of type `E`, in the order they were emitted.
+        fn emitted<E: ink::env::Event + scale::Decode>() -> Vec<E> {
+            ink::env::test::recorded_events()
+                .filter(|event| {
+                    event.topics.first().map(|topic| &topic[..])
+                        == E::SIGNATURE_TOPIC.as_ref().map(|topic| &topic[..])
+                })
+                .map(|event| E::decode(&mut &event.data[..]).unwrap())
+                .collect()
+        }
+
+        /// We test if the default constructor does its job.
+        #[ink::test]
+        fn constructor_works() {
+            let accounts = set_accounts();
+            let betting = create_contract(accounts.alice);
+            assert_eq!(betting.exists_match(accounts.alice), false);
+        }
+
+        #[ink::test]
+        fn selectors_are_stable() {
+            use ink::reflect::{DispatchableConstructorInfo, DispatchableMessageInfo};
+            let accounts = set_accounts();
+            let betting = create_contract(accounts.alice);
+            assert_eq!(betting.api_version(), 2);
+            // Frontends and calling contracts rely on these
+            assert_eq!(
+                <Betting as DispatchableConstructorInfo<{ ink::selector_id!("new") }>>::SELECTOR,
+                [0x9B, 0xAE, 0x9D, 0x5E]
+            );
+            assert_eq!(
+                <Betting as DispatchableMessageInfo<
+                    { ink::selector_id!("create_match_to_bet") },
+                >>::SELECTOR,
+                [0x04, 0x1D, 0x24, 0x37]
+            );
+            assert_eq!(
+                <Betting as DispatchableMessageInfo<{ ink::selector_id!("bet") }>>::SELECTOR,
+                [0x61, 0x0E, 0x57, 0xB8]
+            );
+            assert_eq!(
+                <Betting as DispatchableMessageInfo<
+                    { ink::selector_id!("distribute_winnings") },
+                >>::SELECTOR,
+                [0xC2, 0xC1, 0x3B, 0x48]
+            );
+            assert_eq!(
+                <Betting as DispatchableMessageInfo<
+                    { ink::selector_id!("BettingMarket::claim_winnings") },
+                >>::SELECTOR,
+                [0xDD, 0x2D, 0xC9, 0x50]
+            );
+        }
+
+        #[ink::test]
+        fn create_match_to_bet_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            assert_eq!(betting.exists_match(accounts.alice), false);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+
+            assert_eq!(betting.exists_match(match_id), true);
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(1, emitted_events.len());
+        }
+
+        #[ink::test]
+        fn not_enough_deposit_when_create_match_to_bet() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            assert_eq!(betting.exists_match(accounts.alice), false);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1);
+
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    10,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::NotEnoughDeposit)
+            );
+            assert_eq!(betting.exists_match(accounts.alice), false);
+        }
+
+        #[ink::test]
+        fn create_matches_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let spec = |creator: AccountId, team1: &str, team2: &str| MatchSpec {
+                creator,
+                team1: team1.as_bytes().to_vec(),
+                team2: team2.as_bytes().to_vec(),
+                start: 10,
+                length: 10,
+                allow_draw: true,
+                draw_no_bet: false,
+            };
+            let specs = vec![
+                spec(accounts.bob, "team1", "team2"),
+                spec(accounts.charlie, "team3", "team4"),
+            ];
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.create_matches(specs.clone()), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.create_matches(Vec::new()), Err(Error::InvalidBatch));
+            // The deposit is split among the matches
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1500000000000);
+            assert_eq!(
+                betting.create_matches(specs.clone()),
+                Err(Error::NotEnoughDeposit)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(2000000000001);
+            assert_eq!(betting.create_matches(specs), Ok(()));
+            assert_eq!(betting.get_match(accounts.bob).unwrap().team1, b"team1");
+            assert_eq!(betting.get_match(accounts.charlie).unwrap().team1, b"team3");
+            assert_eq!(betting.get_escrow(accounts.bob), 1000000000001);
+            assert_eq!(betting.get_escrow(accounts.charlie), 1000000000000);
+
+            // The owner paid the deposit, so gets it back
+            for _ in 0..21 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.set_result(accounts.bob, MatchResult::Draw, None),
+                Ok(())
+            );
+            let alice =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.alice)
+                    .unwrap();
+            let bob =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.alice),
+                Ok(alice + 1000000000001)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob)
+            );
+        }
+
+        #[ink::test]
+        fn match_exist_when_create_match_to_bet() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            assert_eq!(betting.exists_match(accounts.alice), false);
+
+            create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+
+            assert_eq!(betting.exists_match(accounts.alice), true);
+
+            //Try to added it again
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    10,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::OriginHasAlreadyOpenMatch)
+            );
+        }
+
+        #[ink::test]
+        fn error_creating_a_match_with_an_open_match() {
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            assert_eq!(betting.exists_match(accounts.alice), false);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::TimeMatchOver)
+            );
+            assert_eq!(betting.exists_match(accounts.alice), false);
+        }
+
+        #[ink::test]
+        fn matches_start_after_the_lead_time_and_last_within_bounds() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(
+                betting.set_config(Config {
+                    min_match_length: 20,
+                    max_match_length: 10,
+                    ..betting.get_config()
+                }),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(
+                betting.set_config(Config {
+                    min_lead_time: 5,
+                    min_match_length: 10,
+                    max_match_length: 100,
+                    ..betting.get_config()
+                }),
+                Ok(())
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let create = |betting: &mut Betting, start: u32, length: u32| {
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    start,
+                    length,
+                    true,
+                    false,
+                    None,
+                )
+            };
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(create(&mut betting, 1, 10), Err(Error::MatchStartTooSoon));
+            assert_eq!(create(&mut betting, 6, 10), Err(Error::MatchStartTooSoon));
+            assert_eq!(create(&mut betting, 7, 9), Err(Error::InvalidMatchLength));
+            assert_eq!(create(&mut betting, 7, 101), Err(Error::InvalidMatchLength));
+            assert!(!betting.exists_match(accounts.django));
+            assert_eq!(create(&mut betting, 7, 100), Ok(()));
+            assert!(betting.exists_match(accounts.django));
+        }
+
+        #[ink::test]
+        fn error_creating_two_equal_matches() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            assert_eq!(betting.exists_match(accounts.alice), false);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    false,
+                    None
+                ),
+                Ok(())
+            );
+
+            //Bob to create same match as alice just created
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::MatchAlreadyExists)
+            );
+            assert_eq!(
+                betting.find_match_by_spec(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    Market::Winner
+                ),
+                Some(accounts.alice)
+            );
+            assert_eq!(
+                betting.find_match_by_spec(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    2,
+                    1,
+                    Market::Winner
+                ),
+                None
+            );
+        }
+
+        #[ink::test]
+        fn fixtures_can_have_one_match_per_market_kind_and_length() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let find = |betting: &Betting, length, market| {
+                betting.find_match_by_spec(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    7,
+                    length,
+                    market,
+                )
+            };
+            let create = |betting: &mut Betting, length, draw_no_bet| {
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    7,
+                    length,
+                    true,
+                    draw_no_bet,
+                    None,
+                )
+            };
+            assert_eq!(create(&mut betting, 10, false), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                create(&mut betting, 10, false),
+                Err(Error::MatchAlreadyExists)
+            );
+            // Another market kind on the same fixture
+            assert_eq!(create(&mut betting, 10, true), Ok(()));
+            // Another length of the same fixture
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(create(&mut betting, 20, false), Ok(()));
+            // The handicap line doesn't make another market
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_handicap_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    7,
+                    10,
+                    -2,
+                ),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_handicap_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    7,
+                    10,
+                    1,
+                ),
+                Err(Error::MatchAlreadyExists)
+            );
+            assert_eq!(find(&betting, 10, Market::Winner), Some(accounts.alice));
+            assert_eq!(find(&betting, 10, Market::DrawNoBet), Some(accounts.bob));
+            assert_eq!(find(&betting, 20, Market::Winner), Some(accounts.charlie));
+            assert_eq!(
+                find(&betting, 10, Market::Handicap(5)),
+                Some(accounts.django)
+            );
+            assert_eq!(find(&betting, 20, Market::DrawNoBet), None);
+        }
+
+        #[ink::test]
+        fn matches_can_be_created_from_the_team_registry() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.register_team("team1".as_bytes().to_vec(), Vec::new()),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.register_team("team1".as_bytes().to_vec(), Vec::new()),
+                Ok(0)
+            );
+            assert_eq!(
+                betting.register_team("team2".as_bytes().to_vec(), "league".as_bytes().to_vec()),
+                Ok(1)
+            );
+            assert_eq!(
+                betting.register_team("team2".as_bytes().to_vec(), Vec::new()),
+                Err(Error::TeamAlreadyExists)
+            );
+            assert_eq!(
+                betting.update_team(0, "team2".as_bytes().to_vec(), Vec::new()),
+                Err(Error::TeamAlreadyExists)
+            );
+            assert_eq!(
+                betting.update_team(0, "team3".as_bytes().to_vec(), Vec::new()),
+                Ok(())
+            );
+            assert_eq!(betting.find_team("team1".as_bytes().to_vec()), None);
+            assert_eq!(betting.find_team("team3".as_bytes().to_vec()), Some(0));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_registered_match_to_bet(0, 2, 1, 1, true, false, None),
+                Err(Error::TeamDoesNotExist)
+            );
+            assert_eq!(
+                betting.create_registered_match_to_bet(0, 1, 1, 1, true, false, None),
+                Ok(())
+            );
+            let betting_match = betting.get_match(accounts.bob).unwrap();
+            assert_eq!(betting_match.team1, "team3".as_bytes().to_vec());
+            assert_eq!(betting_match.team_ids, Some((0, 1)));
+            assert_eq!(betting.get_team_stats(0).matches, 1);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(accounts.bob, MatchResult::Team2Victory), Ok(()));
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.bob, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            // Only settled results count
+            assert_eq!(betting.get_team_stats(1).wins, 0);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                betting.get_team_stats(0),
+                TeamStats {
+                    matches: 1,
+                    wins: 0,
+                    losses: 1,
+                    draws: 0,
+                }
+            );
+            assert_eq!(betting.get_team_stats(1).wins, 1);
+        }
+
+        #[ink::test]
+        fn bets_get_sequential_ids_indexed_in_their_events() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                1,
+                1000000000000,
+            );
+            for (bettor, amount) in [
+                (accounts.bob, 10000000000),
+                (accounts.charlie, 10000000000),
+                (accounts.bob, 20000000000),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            }
+            let bets = betting.get_match(match_id).unwrap().bets;
+            assert_eq!(
+                bets.iter()
+                    .map(|bet| (bet.id, bet.bettor))
+                    .collect::<Vec<_>>(),
+                vec![(0, accounts.bob), (1, accounts.charlie), (2, accounts.bob)]
+            );
+            assert_eq!(betting.get_match(match_id).unwrap().next_bet_id, 3);
+            let placed = emitted::<crate::betting::BetPlaced>();
+            assert_eq!(
+                placed.iter().map(|bet| bet.bet_id).collect::<Vec<_>>(),
+                vec![0, 1, 2]
+            );
+            // The match, the bet and the bettor are topics of the event
+            let topics = ink::env::test::recorded_events()
+                .filter(|event| {
+                    event.topics.first().map(|topic| &topic[..])
+                        == <crate::betting::BetPlaced as ink::env::Event>::SIGNATURE_TOPIC
+                            .as_ref()
+                            .map(|topic| &topic[..])
+                })
+                .map(|event| event.topics.len())
+                .collect::<Vec<_>>();
+            assert_eq!(topics, vec![4, 4, 4]);
+        }
+
+        #[ink::test]
+        fn match_info_summarizes_the_match_without_its_bets() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert!(betting.get_match_info(accounts.django).is_none());
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                2,
+                1,
+                1000000000000,
+            );
+            for (bettor, result) in [
+                (accounts.bob, MatchResult::Team1Victory),
+                (accounts.charlie, MatchResult::Draw),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            let info = betting.get_match_info(match_id).unwrap();
+            assert_eq!(info.team1, "team1".as_bytes().to_vec());
+            assert_eq!(info.team2, "team2".as_bytes().to_vec());
+            assert_eq!((info.start, info.length), (2, 1));
+            assert_eq!(info.status, MatchStatus::Upcoming);
+            assert_eq!(info.result, None);
+            assert_eq!(info.total_pool, 20000000000);
+            assert_eq!(info.outcome_pools, [10000000000, 0, 10000000000]);
+            assert_eq!(info.bet_count, 2);
+
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Ok(())
+            );
+            let info = betting.get_match_info(match_id).unwrap();
+            assert_eq!(info.status, MatchStatus::Settled);
+            assert_eq!(info.result, Some(MatchResult::Draw));
+
+            // Closed matches have no info
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert!(betting.get_match_info(match_id).is_none());
+        }
+
+        #[ink::test]
+        fn team_stats_count_draws_but_not_voided_matches() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(
+                betting.register_team("team1".as_bytes().to_vec(), Vec::new()),
+                Ok(0)
+            );
+            assert_eq!(
+                betting.register_team("team2".as_bytes().to_vec(), Vec::new()),
+                Ok(1)
+            );
+            for (creator, start) in [(accounts.bob, 1), (accounts.charlie, 2)] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(creator);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+                assert_eq!(
+                    betting.create_registered_match_to_bet(0, 1, start, 1, true, false, None),
+                    Ok(())
+                );
+            }
+            assert_eq!(betting.get_team_stats(0).matches, 2);
+            assert_eq!(betting.get_team_stats(1).matches, 2);
+
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.bob, MatchResult::Draw, None),
+                Ok(())
+            );
+            assert_eq!(betting.void_match(accounts.charlie, 0), Ok(()));
+            for creator in [accounts.bob, accounts.charlie] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(creator);
+                assert_eq!(betting.distribute_winnings(), Ok(()));
+            }
+            for team_id in [0, 1] {
+                assert_eq!(
+                    betting.get_team_stats(team_id),
+                    TeamStats {
+                        matches: 2,
+                        wins: 0,
+                        losses: 0,
+                        draws: 1,
+                    }
+                );
+            }
+            // Unknown teams have no record
+            assert_eq!(betting.get_team_stats(2), TeamStats::default());
+        }
+
+        #[ink::test]
+        fn bet_for_pays_the_beneficiary() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet_for(match_id, MatchResult::Team1Victory, accounts.bob),
+                Ok(())
+            );
+            let bet = &betting.get_match(match_id).unwrap().bets[0];
+            assert_eq!(bet.bettor, accounts.bob);
+            assert_eq!(bet.placed_by, Some(accounts.charlie));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100020000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(99990000000000)
+            );
+        }
+
+        #[ink::test]
+        fn delegate_bets_within_the_allowance() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(betting.deposit(), Ok(()));
+            assert_eq!(
+                betting.approve_delegate(accounts.charlie, 15000000000),
+                Ok(())
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.bet_as_delegate(
+                    accounts.bob,
+                    match_id,
+                    MatchResult::Team1Victory,
+                    10000000000
+                ),
+                Ok(())
+            );
+            let bet = &betting.get_match(match_id).unwrap().bets[0];
+            assert_eq!(bet.bettor, accounts.bob);
+            assert_eq!(bet.placed_by, Some(accounts.charlie));
+            assert_eq!(
+                betting.get_allowance(accounts.bob, accounts.charlie),
+                5000000000
+            );
+            assert_eq!(
+                betting.bet_as_delegate(accounts.bob, match_id, MatchResult::Draw, 10000000000),
+                Err(Error::AllowanceExceeded)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                betting.bet_as_delegate(accounts.bob, match_id, MatchResult::Draw, 1000000000),
+                Err(Error::AllowanceExceeded)
+            );
+
+            // A cap of zero revokes the delegate
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.approve_delegate(accounts.charlie, 0), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.bet_as_delegate(accounts.bob, match_id, MatchResult::Draw, 1000000000),
+                Err(Error::AllowanceExceeded)
+            );
+            assert_eq!(betting.get_balance(accounts.bob), 20000000000);
+        }
+
+        #[ink::test]
+        fn winnings_go_to_the_payout_address() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.set_payout_address(accounts.frank), Ok(()));
+            assert_eq!(betting.get_payout_address(accounts.bob), accounts.frank);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(99990000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.frank),
+                Ok(20000000000)
+            );
+
+            // Setting the account itself removes the payout address
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.set_payout_address(accounts.bob), Ok(()));
+            assert_eq!(betting.get_payout_address(accounts.bob), accounts.bob);
+        }
+
+        #[ink::test]
+        fn bets_are_rejected_when_insolvent() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            let (balance, liabilities, escrow) = betting.solvency();
+            assert_eq!(escrow, 1010000000000);
+            assert_eq!(liabilities, escrow);
+            assert!(balance >= liabilities);
+
+            // Liabilities beyond the balance, e.g. after an accounting bug
+            betting.total_escrow = balance + 1;
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team2Victory),
+                Err(Error::Insolvent)
+            );
+            assert_eq!(betting.get_match(match_id).unwrap().bets.len(), 1);
+        }
+
+        #[ink::test]
+        fn settlement_emits_an_event_per_payment() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                1,
+                1000000000000,
+            );
+            for (bettor, amount) in [
+                (accounts.bob, 10000000000000),
+                (accounts.charlie, 20000000000000),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.void_match(match_id, 0), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+
+            let refunds = emitted::<crate::betting::BetRefunded>()
+                .into_iter()
+                .map(|refund| (refund.match_id, refund.who, refund.amount))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                refunds,
+                vec![
+                    (match_id, accounts.bob, 10000000000000),
+                    (match_id, accounts.charlie, 20000000000000)
+                ]
+            );
+            let returned = emitted::<crate::betting::DepositReturned>();
+            assert_eq!(returned.len(), 1);
+            assert_eq!(returned[0].who, accounts.django);
+            assert_eq!(returned[0].amount, 1000000000000);
+            let distributed = emitted::<crate::betting::WinningsDistributed>();
+            assert_eq!(distributed.len(), 1);
+            assert_eq!(distributed[0].total_pool, 30000000000000);
+            assert_eq!(distributed[0].winners_count, 0);
+            let closed = emitted::<crate::betting::MatchClosed>();
+            assert_eq!(closed.len(), 1);
+            assert_eq!(closed[0].match_id, match_id);
+        }
+
+        #[ink::test]
+        fn rounding_dust_follows_the_dust_policy() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            for (creator, policy) in [
+                (accounts.django, DustPolicy::LastWinner),
+                (accounts.frank, DustPolicy::Creator),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+                assert_eq!(betting.set_dust_policy(policy), Ok(()));
+                ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.frank,
+                    100000000000000,
+                );
+                let start = ink::env::block_number::<ink::env::DefaultEnvironment>() + 1;
+                let match_id = create_match(
+                    &mut betting,
+                    creator,
+                    "team1",
+                    "team2",
+                    start,
+                    1,
+                    1000000000000,
+                );
+                // Three winners share a pool of 40000000001
+                for bettor in [accounts.bob, accounts.charlie, accounts.eve] {
+                    ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                    ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                    assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+                }
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000001);
+                assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+                for _ in 0..3 {
+                    ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+                }
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+                assert_eq!(
+                    betting.set_result(match_id, MatchResult::Team1Victory, None),
+                    Ok(())
+                );
+                let treasury = betting.get_treasury();
+                let eve = balance_of(accounts.eve);
+                let creator_balance = balance_of(creator);
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(creator);
+                assert_eq!(betting.distribute_winnings(), Ok(()));
+                // Nothing attributable to the match is left
+                assert_eq!(betting.get_escrow(match_id), 0);
+                assert_eq!(betting.get_treasury(), treasury);
+                let dust = 2;
+                if policy == DustPolicy::LastWinner {
+                    assert_eq!(balance_of(accounts.eve), eve + 13333333333 + dust);
+                } else {
+                    assert_eq!(balance_of(accounts.eve), eve + 13333333333);
+                    assert_eq!(balance_of(creator), creator_balance + 1000000000000 + dust);
+                }
+                assert!(betting.solvency_check());
+            }
+        }
+
+        #[ink::test]
+        fn terminate_only_once_wound_down() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.terminate(), Err(Error::TerminationBlocked));
+            let blockers = betting.get_termination_blockers();
+            assert_eq!(blockers.open_matches, 1);
+            assert_eq!(blockers.escrow, 1000000000000);
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.terminate(), Err(Error::BadOrigin));
+            assert_eq!(
+                betting.get_termination_blockers(),
+                TerminationBlockers::default()
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            let balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(contract)
+                    .unwrap();
+            let should_terminate = move || betting.terminate().unwrap();
+            ink::env::test::assert_contract_termination::<ink::env::DefaultEnvironment, _>(
+                should_terminate,
+                accounts.alice,
+                balance,
+            );
+        }
+
+        #[ink::test]
+        fn match_timing_moves_the_betting_cutoff_and_the_result() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                5,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_match_timing(match_id, 3, 4),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.set_match_timing(match_id, 3, 4), Ok(()));
+
+            // Betting closes after block 7
+            for _ in 0..8 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.get_status(match_id),
+                Some(MatchStatus::BettingClosed)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::MatchHasStarted)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_match_timing(match_id, 0, 0),
+                Err(Error::MatchHasStarted)
+            );
+
+            // The result can be set after block 19
+            for _ in 0..11 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Err(Error::TimeMatchNotOver)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Ok(())
+            );
+        }
+
+        #[ink::test]
+        fn live_bets_earn_less_the_later_they_are_placed() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                5,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_live_betting(match_id, Some(4)),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_live_betting(match_id, Some(5)),
+                Err(Error::InvalidLiveCutoff)
+            );
+            assert_eq!(betting.set_live_betting(match_id, Some(4)), Ok(()));
+            assert_eq!(betting.get_live_cutoff(match_id), Some(4));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            // Two blocks into the five of the live window, from block 10 to 14
+            for _ in 0..12 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Upcoming));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            assert_eq!(
+                betting.get_bet(match_id, 0).unwrap().weight_bps,
+                FULL_BET_WEIGHT_BPS
+            );
+            assert_eq!(betting.get_bet(match_id, 1).unwrap().weight_bps, 6_000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_live_betting(match_id, None),
+                Err(Error::MatchHasStarted)
+            );
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.get_status(match_id),
+                Some(MatchStatus::BettingClosed)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::MatchHasStarted)
+            );
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            let bob = balance_of(accounts.bob);
+            let charlie = balance_of(accounts.charlie);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // The stake of eve is shared 10:6 between bob and charlie
+            assert_eq!(balance_of(accounts.bob), bob + 16250000000);
+            assert_eq!(balance_of(accounts.charlie), charlie + 13750000000);
+        }
+
+        #[ink::test]
+        fn creator_reputation_cuts_the_deposit() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                betting.get_min_deposit_for(accounts.django),
+                Ok(950000000000)
+            );
+            // The owner pays the full deposit of the matches created for Django
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(950000000000);
+            assert_eq!(
+                betting.create_matches(vec![MatchSpec {
+                    creator: accounts.django,
+                    team1: b"team1".to_vec(),
+                    team2: b"team2".to_vec(),
+                    start: 10,
+                    length: 10,
+                    allow_draw: true,
+                    draw_no_bet: false,
+                }]),
+                Err(Error::NotEnoughDeposit)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                4,
+                1,
+                950000000000,
+            );
+            assert!(betting.exists_match(match_id));
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team1Victory),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.reject_result(match_id), Ok(()));
+            assert_eq!(betting.void_match(match_id, 0), Ok(()));
+            assert_eq!(
+                betting.get_creator_reputation(accounts.django),
+                CreatorReputation {
+                    matches_created: 2,
+                    settled_on_time: 1,
+                    cancelled: 1,
+                    disputes_lost: 1,
+                }
+            );
+            assert_eq!(
+                betting.get_min_deposit_for(accounts.django),
+                Ok(1000000000000)
+            );
+        }
+
+        #[ink::test]
+        fn sponsorship_boosts_the_pot_and_is_refunded_on_void() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            for voided in [false, true] {
+                let start = ink::env::block_number::<ink::env::DefaultEnvironment>() + 1;
+                let match_id = create_match(
+                    &mut betting,
+                    accounts.django,
+                    "team1",
+                    "team2",
+                    start,
+                    1,
+                    1000000000000,
+                );
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+                assert_eq!(
+                    betting.sponsor_match(match_id),
+                    Err(Error::InvalidSponsorship)
+                );
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+                assert_eq!(betting.sponsor_match(match_id), Ok(()));
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+                for _ in 0..3 {
+                    ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+                }
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+                if voided {
+                    assert_eq!(betting.void_match(match_id, 0), Ok(()));
+                } else {
+                    assert_eq!(
+                        betting.set_result(match_id, MatchResult::Team1Victory, None),
+                        Ok(())
+                    );
+                }
+                let bob = balance_of(accounts.bob);
+                let eve = balance_of(accounts.eve);
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+                assert_eq!(betting.distribute_winnings(), Ok(()));
+                if voided {
+                    assert_eq!(balance_of(accounts.bob), bob + 10000000000);
+                    assert_eq!(balance_of(accounts.eve), eve + 30000000000);
+                } else {
+                    // Bob wins the losing stake and the sponsorship
+                    assert_eq!(balance_of(accounts.bob), bob + 50000000000);
+                    assert_eq!(balance_of(accounts.eve), eve);
+                }
+                assert_eq!(betting.get_escrow(match_id), 0);
+            }
+        }
+
+        #[ink::test]
+        fn the_boost_budget_is_paid_first_come_first_served() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            // A budget running out on the second winner, then one outlasting the winners
+            for (budget, boosts) in [
+                (6000000000, [5000000000, 1000000000]),
+                (20000000000, [5000000000, 5000000000]),
+            ] {
+                let start = ink::env::block_number::<ink::env::DefaultEnvironment>() + 1;
+                let match_id = create_match(
+                    &mut betting,
+                    accounts.django,
+                    "team1",
+                    "team2",
+                    start,
+                    1,
+                    1000000000000,
+                );
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(budget);
+                assert_eq!(betting.fund_boost(match_id, 5000), Err(Error::NotCreator));
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+                assert_eq!(betting.fund_boost(match_id, 0), Err(Error::InvalidBoost));
+                assert_eq!(
+                    betting.fund_boost(match_id, 10001),
+                    Err(Error::InvalidBoost)
+                );
+                assert_eq!(betting.fund_boost(match_id, 5000), Ok(()));
+                assert_eq!(betting.get_boost(match_id), Some((5000, budget)));
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+                assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+                for _ in 0..3 {
+                    ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+                }
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+                assert_eq!(
+                    betting.set_result(match_id, MatchResult::Team1Victory, None),
+                    Ok(())
+                );
+                let bob = balance_of(accounts.bob);
+                let charlie = balance_of(accounts.charlie);
+                let django = balance_of(accounts.django);
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+                assert_eq!(betting.distribute_winnings(), Ok(()));
+                // Each winner doubles the stake, plus half the profit while the budget lasts
+                assert_eq!(balance_of(accounts.bob), bob + 20000000000 + boosts[0]);
+                assert_eq!(
+                    balance_of(accounts.charlie),
+                    charlie + 20000000000 + boosts[1]
+                );
+                // The creator gets the unused budget back with the deposit
+                let boosted = boosts[0] + boosts[1];
+                assert_eq!(
+                    balance_of(accounts.django),
+                    django + 1000000000000 + budget - boosted
+                );
+                assert_eq!(
+                    betting
+                        .get_settlement(match_id)
+                        .map(|report| report.boosted),
+                    Some(boosted)
+                );
+                assert_eq!(betting.get_escrow(match_id), 0);
+            }
+        }
+
+        #[ink::test]
+        fn coin_flips_are_backed_by_the_bankroll() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.place_coin_flip(CoinSide::Heads),
+                Err(Error::NoRandomnessOracle)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_randomness_oracle(Some(accounts.frank)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(200000000000);
+            assert_eq!(
+                betting.place_coin_flip(CoinSide::Heads),
+                Err(Error::ExposureExceeded)
+            );
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.place_coin_flip(CoinSide::Heads), Ok(0));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.place_coin_flip(CoinSide::Tails), Ok(1));
+            // The bankroll locks the profit of both flips, 0.96 times their stakes
+            assert_eq!(betting.get_bankroll(), (80800000000, 19200000000));
+            assert_eq!(betting.get_termination_blockers().open_positions, 2);
+            assert!(betting.solvency_check());
+            assert_eq!(betting.resolve_coin_flip(0), Err(Error::RandomnessNotReady));
+            assert_eq!(
+                betting.resolve_coin_flip(2),
+                Err(Error::CoinFlipDoesNotExist)
+            );
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            // Seeds drawing heads for both flips
+            let seed = (0..=u8::MAX)
+                .map(|byte| [byte; 32])
+                .find(|seed| {
+                    Betting::coin_side(*seed, 0) == CoinSide::Heads
+                        && Betting::coin_side(*seed, 1) == CoinSide::Heads
+                })
+                .unwrap();
+            let charlie = balance_of(accounts.charlie);
+            let coin_flip = betting.get_coin_flip(0).unwrap();
+            assert_eq!(betting.apply_coin_flip(0, coin_flip, seed), Ok(()));
+            assert_eq!(balance_of(accounts.charlie), charlie + 19600000000);
+            let eve = balance_of(accounts.eve);
+            let coin_flip = betting.get_coin_flip(1).unwrap();
+            assert_eq!(betting.apply_coin_flip(1, coin_flip, seed), Ok(()));
+            assert_eq!(balance_of(accounts.eve), eve);
+            // The bankroll paid 9.6 to charlie and won the 10 of eve
+            assert_eq!(betting.get_bankroll(), (100400000000, 0));
+            assert_eq!(betting.get_coin_flip(0), None);
+            assert_eq!(betting.get_termination_blockers().open_positions, 0);
+            assert_eq!(betting.get_termination_blockers().escrow, 0);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn coin_flips_without_randomness_are_refunded() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            assert_eq!(betting.set_randomness_oracle(Some(accounts.frank)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.place_coin_flip(CoinSide::Heads), Ok(0));
+
+            // The seed of the draw block is never published
+            for _ in 0..14401 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.refund_coin_flip(0), Err(Error::RefundNotOpen));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            // Without an oracle no seed can decide the flip
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_randomness_oracle(None), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            let charlie = balance_of(accounts.charlie);
+            assert_eq!(betting.refund_coin_flip(0), Ok(()));
+            assert_eq!(balance_of(accounts.charlie), charlie + 10000000000);
+            assert_eq!(betting.get_bankroll(), (100000000000, 0));
+            assert_eq!(betting.get_coin_flip(0), None);
+            assert_eq!(
+                betting.refund_coin_flip(0),
+                Err(Error::CoinFlipDoesNotExist)
+            );
+            assert_eq!(betting.get_termination_blockers().open_positions, 0);
+            assert_eq!(betting.get_termination_blockers().escrow, 0);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn outcomes_are_labelled_per_match() {
+            use scale::Encode;
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            // The outcomes are encoded like the variants of the former enum
+            assert_eq!(MatchResult::Team1Victory.encode(), [0]);
+            assert_eq!(MatchResult::Draw.encode(), [2]);
+            assert_eq!(MatchResult::Voided.encode(), [3]);
+            assert_eq!(OutcomeId::nth(2), Some(MatchResult::Draw));
+            assert_eq!(OutcomeId::nth(3), Some(OutcomeId(4)));
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                1,
+                1000000000000,
+            );
+            assert_eq!(
+                betting.get_outcomes(match_id),
+                [
+                    (MatchResult::Team1Victory, b"team1".to_vec()),
+                    (MatchResult::Team2Victory, b"team2".to_vec()),
+                    (MatchResult::Draw, b"Draw".to_vec()),
+                ]
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, OutcomeId(4)),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(betting.bet(match_id, MatchResult::Draw), Ok(()));
+        }
+
+        #[ink::test]
+        fn matches_can_have_more_outcomes() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            let labels: Vec<Vec<u8>> = ["a", "b", "c", "d"]
+                .iter()
+                .map(|label| label.as_bytes().to_vec())
+                .collect();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_multi_outcome_match_to_bet(
+                    b"race".to_vec(),
+                    b"final".to_vec(),
+                    10,
+                    1,
+                    vec![labels[0].clone(), labels[0].clone()]
+                ),
+                Err(Error::InvalidOutcomeLabels)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_multi_outcome_match_to_bet(
+                    b"race".to_vec(),
+                    b"final".to_vec(),
+                    10,
+                    1,
+                    labels.clone()
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+            // The fourth outcome follows the one of voided matches
+            assert_eq!(
+                betting.get_outcomes(match_id),
+                [
+                    (OutcomeId(0), labels[0].clone()),
+                    (OutcomeId(1), labels[1].clone()),
+                    (OutcomeId(2), labels[2].clone()),
+                    (OutcomeId(4), labels[3].clone()),
+                ]
+            );
+            assert_eq!(
+                betting.set_outcome_cap(match_id, OutcomeId(4), Some(15000000000)),
+                Ok(())
+            );
+            assert_eq!(
+                betting.get_outcome_cap(match_id, OutcomeId(4)),
+                Some(15000000000)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, OutcomeId(5)),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(betting.bet(match_id, OutcomeId(4)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(betting.bet(match_id, OutcomeId(0)), Ok(()));
+            assert_eq!(
+                betting.get_match_info(match_id).unwrap().outcome_pools,
+                [20000000000, 0, 0, 10000000000]
+            );
+
+            for _ in 0..12 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            // The outcomes are not derived from a score
+            assert_eq!(betting.set_score(match_id, 1, 0), Err(Error::WrongMarket));
+            assert_eq!(betting.set_result(match_id, OutcomeId(4), None), Ok(()));
+            let bob = balance_of(accounts.bob);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob + 30000000000);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn creators_choose_the_fee_within_bounds() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(100), Ok(()));
+            assert_eq!(
+                betting.set_match_fee_bounds(600, 500),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(betting.set_match_fee_bounds(50, 500), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            assert_eq!(betting.get_match_info(match_id).unwrap().fee_bps, 100);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_match_fee(match_id, Some(300)),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_match_fee(match_id, Some(600)),
+                Err(Error::InvalidMatchFee)
+            );
+            assert_eq!(betting.set_match_fee(match_id, Some(300)), Ok(()));
+            assert_eq!(betting.get_match_info(match_id).unwrap().fee_bps, 300);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // The fee can't change once bettors rely on it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_match_fee(match_id, None),
+                Err(Error::InvalidMatchFee)
+            );
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // 3% of the 20 won
+            assert_eq!(betting.get_treasury(), 600000000);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 19400000000)
+            );
+        }
+
+        #[ink::test]
+        fn bets_can_be_queried_by_id_and_bettor() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Draw), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            assert_eq!(
+                betting.get_bet(match_id, 1),
+                Some(Bet {
+                    id: 1,
+                    bettor: accounts.charlie,
+                    amount: 20000000000,
+                    result: MatchResult::Draw,
+                    credit: 0,
+                    placed_by: None,
+                    weight_bps: FULL_BET_WEIGHT_BPS,
+                })
+            );
+            assert_eq!(betting.get_bet(match_id, 3), None);
+            assert_eq!(betting.get_bet(accounts.eve, 0), None);
+            let bets = betting.get_bets_of(match_id, accounts.bob);
+            assert_eq!(
+                bets.iter()
+                    .map(|bet| (bet.id, bet.amount, bet.result))
+                    .collect::<Vec<_>>(),
+                [
+                    (0, 10000000000, MatchResult::Team1Victory),
+                    (2, 30000000000, MatchResult::Team2Victory),
+                ]
+            );
+            assert!(betting.get_bets_of(match_id, accounts.eve).is_empty());
+        }
+
+        #[ink::test]
+        fn score_oracles_update_the_running_score_in_play() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.add_score_oracle(accounts.bob), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                2,
+                3,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.update_score(match_id, (0, 0)),
+                Err(Error::MatchNotInPlay)
+            );
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.update_score(match_id, (1, 0)),
+                Err(Error::NotScoreOracle)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.get_live_score(match_id), None);
+            assert_eq!(betting.update_score(match_id, (1, 0)), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(betting.update_score(match_id, (1, 1)), Ok(()));
+            assert_eq!(betting.get_live_score(match_id), Some(((1, 1), 4)));
+            assert_eq!(
+                betting.get_match_info(match_id).unwrap().live_score,
+                Some((1, 1))
+            );
+            // Every update is emitted
+            let updates = ink::env::test::recorded_events()
+                .filter(|event| {
+                    event.topics.first().map(|topic| &topic[..])
+                        == <crate::betting::ScoreUpdated as ink::env::Event>::SIGNATURE_TOPIC
+                            .as_ref()
+                            .map(|topic| &topic[..])
+                })
+                .count();
+            assert_eq!(updates, 2);
+
+            for _ in 0..2 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.update_score(match_id, (2, 1)),
+                Err(Error::MatchNotInPlay)
+            );
+        }
+
+        #[ink::test]
+        fn score_changes_suspend_the_live_bets() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.add_score_oracle(accounts.bob), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.set_score_suspension(match_id, 2),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.set_live_betting(match_id, Some(8)), Ok(()));
+            assert_eq!(betting.set_score_suspension(match_id, 2), Ok(()));
+            assert_eq!(betting.get_score_suspension(match_id), Some((2, None)));
+            for _ in 0..11 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            // The kick-off score changes nothing
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.update_score(match_id, (0, 0)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            // A goal at block 12 suspends the bets until block 14
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.update_score(match_id, (1, 0)), Ok(()));
+            assert_eq!(betting.get_score_suspension(match_id), Some((2, Some(14))));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::MarketSuspended)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::MarketSuspended)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            // The same score again doesn't suspend the bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.update_score(match_id, (1, 0)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+        }
+
+        #[ink::test]
+        fn median_of_the_oracle_reports_sets_the_score() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            for oracle in [accounts.bob, accounts.charlie, accounts.eve] {
+                assert_eq!(betting.add_score_oracle(oracle), Ok(()));
+            }
+            assert_eq!(betting.set_score_quorum(0), Err(Error::InvalidConfig));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.report_score(match_id, 2, 1),
+                Err(Error::NotScoreOracle)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.report_score(match_id, 2, 1), Ok(()));
+            assert_eq!(
+                betting.report_score(match_id, 2, 1),
+                Err(Error::AlreadyReported)
+            );
+            // A bad feed is outvoted
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(betting.report_score(match_id, 0, 9), Ok(()));
+            assert_eq!(betting.get_score_reports(match_id).len(), 2);
+            assert_eq!(betting.get_match(match_id).unwrap().result, None);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.report_score(match_id, 3, 1), Ok(()));
+
+            let betting_match = betting.get_match(match_id).unwrap();
+            assert_eq!(betting_match.score, Some((2, 1)));
+            assert_eq!(betting_match.result, Some(MatchResult::Team1Victory));
+            assert!(betting.get_score_reports(match_id).is_empty());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.add_score_oracle(accounts.frank), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.report_score(match_id, 2, 1),
+                Err(Error::ResultAlreadySet)
+            );
+        }
+
+        #[ink::test]
+        fn challenges_double_the_bond_and_the_last_answer_stands() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_result_confirmation_timeout(5), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team2Victory),
+                Err(Error::NoResultProposed)
+            );
+            // The creator bonds the proposal
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(50000000000);
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team1Victory),
+                Err(Error::BondTooLow)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team1Victory),
+                Ok(())
+            );
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team2Victory),
+                Err(Error::ResultAlreadyProposed)
+            );
+            let django_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django,
+                )
+                .unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(150000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team2Victory),
+                Err(Error::BondTooLow)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(200000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team1Victory),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team2Victory),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(350000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team1Victory),
+                Err(Error::BondTooLow)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(400000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team1Victory),
+                Ok(())
+            );
+            assert_eq!(betting.get_challenges(match_id).len(), 3);
+            assert_eq!(betting.get_escrow(match_id), 1700000000000);
+
+            // Unchallenged for the timeout, the last answer stands
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(800000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team2Victory),
+                Err(Error::ResultFinal)
+            );
+            let charlie_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie,
+                )
+                .unwrap();
+            assert_eq!(betting.finalize_result(match_id), Ok(()));
+            assert_eq!(
+                betting.get_match(match_id).unwrap().result,
+                Some(MatchResult::Team1Victory)
+            );
+            // Django and charlie get their bonds back and share the bond of bob
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(django_balance + 140000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(charlie_balance + 560000000000)
+            );
+            assert!(betting.get_challenges(match_id).is_empty());
+            assert_eq!(betting.get_escrow(match_id), 1000000000000);
+        }
+
+        #[ink::test]
+        fn bettors_vote_on_a_result_the_oracle_never_set() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_vote_timing(2, 3), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for (bettor, result, amount) in [
+                (accounts.bob, MatchResult::Team1Victory, 10000000000),
+                (accounts.charlie, MatchResult::Team2Victory, 20000000000),
+                (accounts.eve, MatchResult::Team1Victory, 30000000000),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            // The match ends at block 2, the vote runs from block 5 to 7
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.vote_result(match_id, MatchResult::Team1Victory),
+                Err(Error::VotingNotOpen)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.vote_result(match_id, MatchResult::Team1Victory),
+                Err(Error::NotBettor)
+            );
+            for (voter, result) in [
+                (accounts.bob, MatchResult::Team1Victory),
+                (accounts.charlie, MatchResult::Team2Victory),
+                (accounts.eve, MatchResult::Team1Victory),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(voter);
+                assert_eq!(betting.vote_result(match_id, result), Ok(()));
+            }
+            assert_eq!(
+                betting.vote_result(match_id, MatchResult::Draw),
+                Err(Error::AlreadyVoted)
+            );
+            // A bet that voted can't vote again from another account
+            let bet_id = betting
+                .matches
+                .get(match_id)
+                .unwrap()
+                .bets
+                .iter()
+                .find(|bet| bet.bettor == accounts.eve)
+                .unwrap()
+                .id;
+            assert_eq!(
+                betting.transfer_bet(match_id, bet_id, accounts.frank),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.vote_result(match_id, MatchResult::Draw),
+                Err(Error::AlreadyVoted)
+            );
+            assert_eq!(betting.close_vote(match_id), Err(Error::VotingNotOver));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(betting.close_vote(match_id), Ok(()));
+            let betting_match = betting.get_match(match_id).unwrap();
+            assert_eq!(betting_match.result, Some(MatchResult::Team1Victory));
+            // Bob gets a quarter of 10% of the deposit
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 25000000000)
+            );
+            assert_eq!(betting_match.deposit, 900000000000);
+            assert!(betting.get_result_votes(match_id).is_empty());
+            assert_eq!(betting.close_vote(match_id), Err(Error::ResultAlreadySet));
+        }
+
+        #[ink::test]
+        fn oracles_set_the_results_of_their_sports() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(
+                betting.authorize_oracle(accounts.frank, b"football".to_vec()),
+                Ok(())
+            );
+            assert_eq!(
+                betting.authorize_oracle(accounts.eve, b"tennis".to_vec()),
+                Ok(())
+            );
+            assert!(betting.is_oracle_for(accounts.frank, b"football/premier-league".to_vec()));
+            assert!(!betting.is_oracle_for(accounts.frank, b"footballx".to_vec()));
+            assert!(!betting.is_oracle_for(accounts.eve, b"football".to_vec()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            // Without a sport only the owner can set the result
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Err(Error::BadOrigin)
+            );
+            assert_eq!(
+                betting.set_match_sport(match_id, b"football/premier-league".to_vec()),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_match_sport(match_id, b"football/premier-league".to_vec()),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.revoke_oracle(accounts.frank, b"football".to_vec()),
+                Ok(())
+            );
+            assert!(!betting.is_oracle_for(accounts.frank, b"football/premier-league".to_vec()));
+        }
+
+        #[ink::test]
+        fn set_fee_router_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.get_fee_router(), None);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_fee_router(Some(accounts.frank)),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_fee_router(Some(accounts.frank)), Ok(()));
+            assert_eq!(betting.get_fee_router(), Some(accounts.frank));
+            assert_eq!(betting.set_fee_router(None), Ok(()));
+            assert_eq!(betting.get_fee_router(), None);
+        }
+
+        #[ink::test]
+        fn claim_winnings_as_needs_a_claim_and_a_router() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.claim_winnings_as(accounts.frank, 1),
+                Err(Error::NothingToClaim)
+            );
+            assert_eq!(
+                betting.set_dex_router(Some(accounts.eve)),
+                Err(Error::BadOrigin)
+            );
+            betting.pending_claims.insert(accounts.bob, &1000);
+            betting.total_pending_claims = 1000;
+            assert_eq!(
+                betting.claim_winnings_as(accounts.frank, 1),
+                Err(Error::NoDexRouter)
+            );
+            assert_eq!(betting.get_pending_claim(accounts.bob), 1000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_dex_router(Some(accounts.eve)), Ok(()));
+            assert_eq!(betting.dex_router, Some(accounts.eve));
+        }
+
+        #[ink::test]
+        fn pending_claims_are_swept_after_the_claim_period() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_claim_period(2), Ok(()));
+            assert_eq!(betting.defer_payment(accounts.bob, 1000), Ok(()));
+            assert_eq!(betting.defer_payment(accounts.charlie, 500), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.sweep_pending_claim(accounts.charlie, true),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.sweep_pending_claim(accounts.bob, false),
+                Err(Error::ClaimPeriodNotOver)
+            );
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.sweep_pending_claim(accounts.bob, false), Ok(()));
+            assert_eq!(betting.sweep_pending_claim(accounts.charlie, true), Ok(()));
+            assert_eq!(
+                betting.sweep_pending_claim(accounts.bob, false),
+                Err(Error::NothingToClaim)
+            );
+            assert_eq!(betting.get_pending_claim(accounts.bob), 0);
+            assert_eq!(betting.total_pending_claims, 0);
+            assert_eq!(betting.get_treasury(), 1000);
+            assert_eq!(betting.get_jackpot(), 500);
+        }
+
+        #[ink::test]
+        fn failed_transfers_leave_the_claims_pending() {
+            let accounts = set_accounts();
+            // An account without a balance can't make any transfer
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(AccountId::from([0x42; 32]));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let mut betting = Betting::new();
+            assert_eq!(betting.pay_or_defer(accounts.bob, 1500), Ok(()));
+            assert_eq!(betting.pay_or_defer(accounts.bob, 500), Ok(()));
+            assert_eq!(betting.get_pending_claim(accounts.bob), 2000);
+            assert_eq!(betting.total_pending_claims, 2000);
+            // The claim is removed before the transfer, a failed transfer reverts it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.withdraw_pending(), Err(Error::TransferFailed));
+            assert_eq!(betting.get_pending_claim(accounts.bob), 0);
+            assert_eq!(betting.total_pending_claims, 0);
+        }
+
+        #[ink::test]
+        fn settled_bets_accrue_reward_points() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(
+                betting.set_reward_emissions(5_000, 0, 1000),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(betting.set_reward_emissions(5_000, 10, 0), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            assert_eq!(betting.get_reward_points(accounts.bob), 0);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Winners and losers alike accrue half their stake, once the match is settled
+            assert_eq!(betting.get_reward_points(accounts.bob), 5000000000);
+            assert_eq!(betting.get_reward_points(accounts.charlie), 10000000000);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.claim_rewards(), Err(Error::NothingToClaim));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.claim_rewards(), Err(Error::NoRewardToken));
+            assert_eq!(
+                betting.set_reward_token(Some(accounts.frank)),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_reward_token(Some(accounts.frank)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            // Nothing can be minted beyond the cap of the epoch
+            assert_eq!(betting.claim_rewards(), Err(Error::EmissionCapReached));
+            assert_eq!(betting.get_reward_points(accounts.bob), 5000000000);
+        }
+
+        #[ink::test]
+        fn fee_tiers_discount_the_fee_of_stakers() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let tier = |min_staked, discount_bps| FeeTier {
+                min_staked,
+                discount_bps,
+            };
+            assert_eq!(
+                betting.set_fee_tiers(vec![tier(1, 100); 5]),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(
+                betting.set_fee_tiers(vec![tier(1, 10_001)]),
+                Err(Error::InvalidConfig)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_fee_tiers(vec![tier(1000, 1_000)]),
+                Err(Error::BadOrigin)
+            );
+            assert_eq!(
+                betting.set_staking_contract(Some(accounts.frank)),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_fee_tiers(vec![tier(1000, 1_000), tier(10000, 5_000)]),
+                Ok(())
+            );
+            let tiers = betting.get_config().fee_tiers;
+            assert_eq!(tiers[1], tier(10000, 5_000));
+            assert_eq!(tiers[2], FeeTier::default());
+            assert_eq!(Betting::tier_discount_bps(&tiers, 999), 0);
+            assert_eq!(Betting::tier_discount_bps(&tiers, 1000), 1_000);
+            assert_eq!(Betting::tier_discount_bps(&tiers, 50000), 5_000);
+            // Without a staking contract nobody gets a discount
+            assert_eq!(betting.fee_discount_bps(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn governance_changes_the_config_and_guardian_pauses() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_governance(Some(accounts.frank)),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_governance(Some(accounts.frank)), Ok(()));
+            assert_eq!(betting.get_governance(), Some(accounts.frank));
+            // The owner key can't change the configuration anymore
+            assert_eq!(betting.set_protocol_fee(100), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(betting.set_protocol_fee(100), Ok(()));
+            assert_eq!(betting.get_config().fee_bps, 100);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_guardian(Some(accounts.eve)), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.pause(), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.pause(), Ok(()));
+            assert!(betting.is_paused());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::ContractPaused)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.unpause(), Ok(()));
+            create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+        }
+
+        #[ink::test]
+        fn admin_actions_wait_for_the_admin_delay() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_admin_delay(10), Ok(()));
+            assert_eq!(betting.get_admin_delay(), 10);
+            // Sensitive changes can't be made right away anymore
+            assert_eq!(betting.set_protocol_fee(100), Err(Error::ActionTimelocked));
+            assert_eq!(
+                betting.add_score_oracle(accounts.bob),
+                Err(Error::ActionTimelocked)
+            );
+            assert_eq!(betting.set_admin_delay(0), Err(Error::ActionTimelocked));
+            // Other parameters can
+            assert_eq!(betting.set_claim_period(100), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.schedule_action(AdminAction::SetProtocolFee(100)),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.schedule_action(AdminAction::SetProtocolFee(100)),
+                Ok(0)
+            );
+            assert_eq!(
+                betting.schedule_action(AdminAction::AddScoreOracle(accounts.bob)),
+                Ok(1)
+            );
+            assert_eq!(
+                betting.get_scheduled_action(0).unwrap().action,
+                AdminAction::SetProtocolFee(100)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.execute_action(0), Err(Error::ActionNotReady));
+            assert_eq!(betting.cancel_action(1), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.cancel_action(1), Ok(()));
+            assert_eq!(betting.get_scheduled_action(1), None);
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.execute_action(0), Ok(()));
+            assert_eq!(betting.get_config().fee_bps, 100);
+            assert_eq!(betting.execute_action(0), Err(Error::ActionDoesNotExist));
+            assert_eq!(betting.execute_action(1), Err(Error::ActionDoesNotExist));
+        }
+
+        #[ink::test]
+        fn privileged_calls_are_logged() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.get_admin_log(0, 10), vec![]);
+            assert_eq!(betting.set_protocol_fee(100), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(betting.blacklist(accounts.bob), Ok(()));
+            // Calls anyone can make are not logged
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.set_payout_address(accounts.eve), Ok(()));
+            let hash = |params: &dyn Fn(&mut Vec<u8>)| {
+                let mut encoded = Vec::new();
+                params(&mut encoded);
+                let mut hash = [0u8; 32];
+                ink::env::hash_bytes::<ink::env::hash::Blake2x256>(&encoded, &mut hash);
+                hash
+            };
+            let log = betting.get_admin_log(0, 10);
+            assert_eq!(
+                log,
+                vec![
+                    AdminLogEntry {
+                        who: accounts.alice,
+                        selector: ink::selector_bytes!("set_protocol_fee"),
+                        block: 0,
+                        params_hash: hash(&|out| scale::Encode::encode_to(&100u32, out)),
+                    },
+                    AdminLogEntry {
+                        who: accounts.alice,
+                        selector: ink::selector_bytes!("blacklist"),
+                        block: 1,
+                        params_hash: hash(&|out| scale::Encode::encode_to(&accounts.bob, out)),
+                    },
+                ]
+            );
+            assert_eq!(betting.get_admin_log(1, 10), log[1..].to_vec());
+
+            // The oldest calls are overwritten
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            for _ in 0..1000 {
+                assert_eq!(betting.unblacklist(accounts.bob), Ok(()));
+            }
+            let log = betting.get_admin_log(0, 100);
+            assert_eq!(log.len(), 50);
+            assert!(log
+                .iter()
+                .all(|entry| entry.selector == ink::selector_bytes!("unblacklist")));
+        }
+
+        #[ink::test]
+        fn bets_are_rate_limited() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_bet_rate_limit(2, 0), Err(Error::InvalidConfig));
+            assert_eq!(betting.set_bet_rate_limit(2, 2), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                5,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            for amount in [1000000000, 2000000000] {
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            }
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::RateLimited)
+            );
+            // Others are not limited
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // The window of Bob lasts two blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::RateLimited)
+            );
+            // Then Bob can bet again
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+        }
+
+        #[ink::test]
+        fn outcome_caps_limit_the_stakes() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                5,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_outcome_cap(match_id, MatchResult::Team1Victory, Some(3000000000)),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_outcome_cap(match_id, MatchResult::Team1Victory, Some(3000000000)),
+                Ok(())
+            );
+            assert_eq!(
+                betting.get_outcome_cap(match_id, MatchResult::Team1Victory),
+                Some(3000000000)
+            );
+            assert_eq!(betting.get_outcome_cap(match_id, MatchResult::Draw), None);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(2000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Filled up to the cap, the rest is refunded
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            let charlie_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie,
+                )
+                .unwrap();
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(2000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(charlie_balance - 1000000000)
+            );
+            let bet = &betting.get_match(match_id).unwrap().bets[1];
+            assert_eq!((bet.bettor, bet.amount), (accounts.charlie, 1000000000));
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let event = <crate::betting::BetPartiallyFilled as scale::Decode>::decode(
+                &mut &emitted_events.last().unwrap().data[..],
+            )
+            .unwrap();
+            assert_eq!((event.filled, event.refunded), (1000000000, 1000000000));
+            // Nothing is left to fill, the other outcomes are open
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::OutcomeCapExceeded)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(2000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // Removing the cap opens the outcome again
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_outcome_cap(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(2000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+        }
+
+        #[ink::test]
+        fn bet_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            let bet = Bet {
+                id: 0,
+                bettor: accounts.bob,
+                amount: 10000000000,
+                result: MatchResult::Team1Victory,
+                credit: 0,
+                placed_by: None,
+                weight_bps: FULL_BET_WEIGHT_BPS,
+            };
+            assert_eq!(
+                betting.get_match(match_id).unwrap().bets.contains(&bet),
+                true
+            );
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(2, emitted_events.len());
+        }
+
+        #[ink::test]
+        fn bet_many_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match1 = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.bob,
+                "team3",
+                "team4",
+                10,
+                10,
+                1000000000000,
+            );
+            let bets = vec![
+                (match1, MatchResult::Team1Victory, 10000000000),
+                (match2, MatchResult::Draw, 20000000000),
+            ];
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            // The transferred value must be the sum of the stakes
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(betting.bet_many(bets.clone()), Err(Error::InvalidBatch));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(betting.bet_many(Vec::new()), Err(Error::InvalidBatch));
+
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(betting.bet_many(bets), Ok(()));
+            let bet = &betting.get_match(match1).unwrap().bets[0];
+            assert_eq!(
+                (bet.bettor, bet.amount, bet.result),
+                (accounts.charlie, 10000000000, MatchResult::Team1Victory)
+            );
+            let bet = &betting.get_match(match2).unwrap().bets[0];
+            assert_eq!(
+                (bet.bettor, bet.amount, bet.result),
+                (accounts.charlie, 20000000000, MatchResult::Draw)
+            );
+            assert_eq!(betting.get_escrow(match1), 1010000000000);
+            assert_eq!(betting.get_escrow(match2), 1020000000000);
+        }
+
+        #[ink::test]
+        fn sealed_bet_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                2,
+                10,
+                1000000000000,
+            );
+            let salt = [3u8; 32];
+            let commitment = betting.sealed_bet_hash(accounts.bob, MatchResult::Draw, salt);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.commit_bet(match_id, commitment), Ok(()));
+            assert_eq!(
+                betting.commit_bet(match_id, commitment),
+                Err(Error::AlreadyBet)
+            );
+            // The pick is hidden until revealed
+            assert!(betting.get_match(match_id).unwrap().bets.is_empty());
+            assert_eq!(betting.get_escrow(match_id), 1010000000000);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.reveal_bet(match_id, MatchResult::Team1Victory, salt),
+                Err(Error::InvalidReveal)
+            );
+            assert_eq!(
+                betting.reveal_bet(match_id, MatchResult::Draw, salt),
+                Ok(())
+            );
+            let bet = &betting.get_match(match_id).unwrap().bets[0];
+            assert_eq!(
+                (bet.bettor, bet.amount, bet.result),
+                (accounts.bob, 10000000000, MatchResult::Draw)
+            );
+            assert_eq!(betting.get_escrow(match_id), 1010000000000);
+            assert_eq!(betting.get_sealed_bet(match_id, accounts.bob), None);
+
+            // Charlie copies the commitment but can't reveal it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.commit_bet(match_id, commitment), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.reveal_bet(match_id, MatchResult::Draw, salt),
+                Err(Error::InvalidReveal)
+            );
+            // Reveals close when the match starts
+            let commitment = betting.sealed_bet_hash(accounts.eve, MatchResult::Draw, salt);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.commit_bet(match_id, commitment), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.reveal_bet(match_id, MatchResult::Draw, salt),
+                Err(Error::MatchHasStarted)
+            );
+        }
+
+        #[ink::test]
+        fn sealed_bets_of_voided_matches_are_refunded() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_bet_rate_limit(1, 100), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            let salt = [3u8; 32];
+            for bettor in [accounts.bob, accounts.charlie] {
+                let commitment = betting.sealed_bet_hash(bettor, MatchResult::Draw, salt);
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.commit_bet(match_id, commitment), Ok(()));
+            }
+            // The commit counts against the rate limit
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::RateLimited)
+            );
+            assert_eq!(
+                betting.refund_sealed_bet(match_id),
+                Err(Error::RefundNotOpen)
+            );
+
+            // Alice voids the match, Bob is refunded before the settlement
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.void_match(match_id, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(betting.refund_sealed_bet(match_id), Ok(()));
+            assert_eq!(betting.refund_sealed_bet(match_id), Err(Error::NoSealedBet));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100000000000000)
+            );
+            assert_eq!(betting.get_escrow(match_id), 1010000000000);
+
+            // The settlement keeps the stake of Charlie to refund it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.total_escrow, 10000000000);
+            assert!(betting.solvency_check());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(betting.refund_sealed_bet(match_id), Ok(()));
+            assert_eq!(betting.total_escrow, 0);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(100000000000000)
+            );
+        }
+
+        #[ink::test]
+        fn bet_from_balance_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+
+            // Bob funds his balance
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(betting.deposit(), Ok(()));
+            assert_eq!(betting.get_balance(accounts.bob), 30000000000);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.bet_from_balance(match_id, MatchResult::Team1Victory, 40000000000),
+                Err(Error::NotEnoughBalance)
+            );
+            assert_eq!(
+                betting.bet_from_balance(match_id, MatchResult::Team1Victory, 10000000000),
+                Ok(())
+            );
+            assert_eq!(betting.get_balance(accounts.bob), 20000000000);
+            assert_eq!(
+                betting.get_match(match_id).unwrap().bets[0].amount,
+                10000000000
+            );
+            assert!(betting.solvency_check());
+            // Bob withdraws the rest
+            assert_eq!(betting.withdraw(20000000000), Ok(()));
+            assert_eq!(betting.get_balance(accounts.bob), 0);
+            assert_eq!(betting.withdraw(1), Err(Error::NotEnoughBalance));
+        }
+
+        #[ink::test]
+        fn bet_error_match_full() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            assert_eq!(betting.set_max_bets_per_match(2), Ok(()));
+            assert_eq!(betting.set_match_max_bets(match_id, Some(1)), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team2Victory),
+                Err(Error::MatchFull)
+            );
+            assert_eq!(
+                betting.set_match_max_bets(match_id, None),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_match_max_bets(match_id, None), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // The global cap still applies
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Draw),
+                Err(Error::MatchFull)
+            );
+        }
+
+        #[ink::test]
+        fn set_config_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            let config = Config {
+                min_bet: 1000000000,
+                max_bet: 20000000000,
+                ..betting.get_config()
+            };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.set_config(config), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_config(Config {
+                    min_bet: 30000000000,
+                    ..config
+                }),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(betting.set_config(config), Ok(()));
+            assert_eq!(betting.get_config(), config);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::InvalidBetAmount)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+        }
+
+        #[ink::test]
+        fn zero_and_dust_bets_are_rejected() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                10,
+                10,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.bet(match1, MatchResult::Team1Victory),
+                Err(Error::ZeroBetAmount)
+            );
+            assert_eq!(
+                betting.place_parlay(vec![
+                    (match1, MatchResult::Team1Victory),
+                    (match2, MatchResult::Draw),
+                ]),
+                Err(Error::ZeroBetAmount)
+            );
+            assert_eq!(betting.set_min_bet(1000000000), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_config(Config {
+                    max_bet: 20000000000,
+                    ..betting.get_config()
+                }),
+                Ok(())
+            );
+            assert_eq!(betting.set_min_bet(30000000000), Err(Error::InvalidConfig));
+            assert_eq!(betting.set_min_bet(1000000000), Ok(()));
+            assert_eq!(betting.get_config().min_bet, 1000000000);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(999999999);
+            assert_eq!(
+                betting.bet(match1, MatchResult::Team1Victory),
+                Err(Error::BetTooSmall)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(betting.bet(match1, MatchResult::Team1Victory), Ok(()));
+        }
+
+        #[ink::test]
+        fn emergency_withdraw_after_timelock() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.django);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.schedule_emergency_withdraw(10000000000000),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.execute_emergency_withdraw(),
+                Err(Error::NoEmergencyWithdrawal)
+            );
+            assert_eq!(betting.schedule_emergency_withdraw(10000000000000), Ok(()));
+            // Cancelling is announced too
+            let events = ink::env::test::recorded_events().count();
+            assert_eq!(betting.cancel_emergency_withdraw(), Ok(()));
+            assert_eq!(ink::env::test::recorded_events().count(), events + 1);
+            assert_eq!(betting.get_emergency_withdrawal(), None);
+            assert_eq!(
+                betting.cancel_emergency_withdraw(),
+                Err(Error::NoEmergencyWithdrawal)
+            );
+            assert_eq!(betting.schedule_emergency_withdraw(10000000000000), Ok(()));
+            assert_eq!(
+                betting.execute_emergency_withdraw(),
+                Err(Error::TimelockNotOver)
+            );
+            for _ in 0..100000 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.execute_emergency_withdraw(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(109000000000000)
+            );
+            assert_eq!(betting.get_emergency_withdrawal(), None);
+        }
+
+        #[ink::test]
+        fn overflow_is_an_error() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    u32::MAX,
+                    1,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::ArithmeticOverflow)
+            );
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(u128::MAX);
+            assert_eq!(betting.deposit(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+            assert_eq!(betting.deposit(), Err(Error::ArithmeticOverflow));
+            assert_eq!(betting.get_balance(accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn stakes_near_the_balance_limit_are_checked() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            // The contract holds every stake below, which are not debited from the bettors
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                ink::env::test::callee::<ink::env::DefaultEnvironment>(),
+                u128::MAX,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(u128::MAX / 2);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(u128::MAX / 4);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // The escrow of the match can't hold another half of the limit
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(u128::MAX / 2);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team2Victory),
+                Err(Error::ArithmeticOverflow)
+            );
+            assert_eq!(betting.get_match(match_id).unwrap().bets.len(), 2);
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Bob wins the whole pool without the payout math overflowing
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100000000000000 + u128::MAX / 2 + u128::MAX / 4)
+            );
+            assert_eq!(betting.get_escrow(match_id), 0);
+        }
+
+        #[ink::test]
+        fn blacklisted_account_cannot_bet() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.blacklist(accounts.bob), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.blacklist(accounts.bob), Ok(()));
+            assert!(betting.is_blacklisted(accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::AccountBlacklisted)
+            );
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team3".as_bytes().to_vec(),
+                    "team4".as_bytes().to_vec(),
+                    10,
+                    10,
+                    true,
+                    false,
+                    None
+                ),
+                Err(Error::AccountBlacklisted)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.unblacklist(accounts.bob), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+        }
+
+        #[ink::test]
+        fn self_exclusion_and_personal_limit() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                20,
+                10,
+                1000000000000,
+            );
+            // Bob self-excludes for 5 blocks
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.self_exclude(5), Ok(()));
+            assert_eq!(betting.self_exclude(2), Ok(()));
+            assert_eq!(betting.get_self_exclusion(accounts.bob), Some(5));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::SelfExcluded)
+            );
+            for _ in 0..5 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            // Then Bob sets a stake limit
+            assert_eq!(betting.set_personal_limit(15000000000), Ok(()));
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team2Victory),
+                Err(Error::PersonalLimitExceeded)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            let staked = |betting: &Betting| -> u128 {
+                let limit = betting.get_personal_limit(accounts.bob).unwrap();
+                limit.stakes.iter().map(|(_, staked)| staked).sum()
+            };
+            assert_eq!(staked(&betting), 15000000000);
+            // A higher limit waits for a period
+            assert_eq!(betting.set_personal_limit(20000000000), Ok(()));
+            assert_eq!(
+                betting
+                    .get_personal_limit(accounts.bob)
+                    .unwrap()
+                    .amount_per_period,
+                15000000000
+            );
+            assert_eq!(
+                betting.record_stake(accounts.bob, 1000000000),
+                Err(Error::PersonalLimitExceeded)
+            );
+            // The stakes count for a rolling window, not until a period ends
+            for _ in 0..14_394 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.record_stake(accounts.bob, 1000000000),
+                Err(Error::PersonalLimitExceeded)
+            );
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.record_stake(accounts.bob, 20000000000), Ok(()));
+            assert_eq!(staked(&betting), 20000000000);
+            assert_eq!(
+                betting.record_stake(accounts.bob, 1),
+                Err(Error::PersonalLimitExceeded)
+            );
+            // A lower limit applies at once
+            assert_eq!(betting.set_personal_limit(20000000001), Ok(()));
+            assert_eq!(betting.set_personal_limit(10000000000), Ok(()));
+            let limit = betting.get_personal_limit(accounts.bob).unwrap();
+            assert_eq!(limit.amount_per_period, 10000000000);
+            assert_eq!(limit.pending_increase, None);
+        }
+
+        #[ink::test]
+        fn betting_market_trait_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                BettingMarket::bet(&mut betting, match_id, MatchResult::Team1Victory),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(
+                BettingMarket::bet(&mut betting, match_id, MatchResult::Team2Victory),
+                Ok(())
+            );
+            assert_eq!(
+                BettingMarket::get_odds(&betting, match_id),
+                Some([400, 133, 0])
+            );
+            assert_eq!(
+                BettingMarket::get_status(&betting, match_id),
+                Some(MatchStatus::Upcoming)
+            );
+            assert_eq!(
+                BettingMarket::claim_winnings(&mut betting),
+                Err(Error::NothingToClaim)
+            );
+        }
+
+        #[ink::test]
+        fn bet_error_match_not_exist() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(accounts.alice, MatchResult::Team1Victory),
+                Err(Error::MatchDoesNotExist)
+            );
+        }
+
+        #[ink::test]
+        fn bet_error_match_has_start() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                10,
+                1000000000000,
+            );
+            // Advance 2 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(accounts.alice, MatchResult::Team1Victory),
+                Err(Error::MatchHasStarted)
+            );
+        }
+
+        #[ink::test]
+        fn bet_error_duplicate_bet() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::AlreadyBet)
+            );
+        }
+
+        #[ink::test]
+        fn set_result_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+
+            assert_eq!(betting.exists_match(match_id), true);
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(2, emitted_events.len());
+        }
+
+        #[ink::test]
+        fn result_evidence_is_stored_and_emitted() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, Some(vec![0; 257])),
+                Err(Error::InvalidEvidence)
+            );
+            let evidence =
+                b"ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_vec();
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, Some(evidence.clone())),
+                Ok(())
+            );
+            assert_eq!(
+                betting.get_match(match_id).unwrap().evidence,
+                Some(evidence.clone())
+            );
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let result_set = emitted_events
+                .iter()
+                .find(|event| {
+                    event.topics.first().map(|topic| &topic[..])
+                        == <crate::betting::MatchResultSet as ink::env::Event>::SIGNATURE_TOPIC
+                            .as_ref()
+                            .map(|topic| &topic[..])
+                })
+                .map(|event| {
+                    <crate::betting::MatchResultSet as scale::Decode>::decode(&mut &event.data[..])
+                        .unwrap()
+                })
+                .unwrap();
+            assert_eq!(result_set.evidence, Some(evidence));
+        }
+
+        #[ink::test]
+        fn two_way_match_rejects_draw() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    false,
+                    false,
+                    None
+                ),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(accounts.bob, MatchResult::Draw),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(betting.bet(accounts.bob, MatchResult::Team2Victory), Ok(()));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.bob, MatchResult::Draw, None),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(
+                betting.set_score(accounts.bob, 1, 1),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(
+                betting.set_result(accounts.bob, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+        }
+
+        #[ink::test]
+        fn set_result_bad_origin() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //set Bob as the caller
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Err(Error::BadOrigin)
+            );
+        }
+        #[ink::test]
+        fn set_result_match_not_exist() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.set_result(accounts.alice, MatchResult::Team1Victory, None),
+                Err(Error::MatchDoesNotExist)
+            );
+        }
+        #[ink::test]
+        fn set_result_match_not_finished() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Err(Error::TimeMatchNotOver)
+            );
+        }
+
+        #[ink::test]
+        fn the_bets_of_a_bettor_are_paid_together() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            // Bob hedges with a bet on team2
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            for (amount, result) in [
+                (10000000000, MatchResult::Team1Victory),
+                (30000000000, MatchResult::Team1Victory),
+                (5000000000, MatchResult::Team2Victory),
+            ] {
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(15000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // The whole pool in one payout, bob counts as a single winner
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 60000000000)
+            );
+            let distributed = ink::env::test::recorded_events()
+                .find(|event| {
+                    event.topics.first().map(|topic| &topic[..])
+                        == <crate::betting::WinningsDistributed as ink::env::Event>::SIGNATURE_TOPIC
+                            .as_ref()
+                            .map(|topic| &topic[..])
+                })
+                .map(|event| {
+                    <crate::betting::WinningsDistributed as scale::Decode>::decode(
+                        &mut &event.data[..],
+                    )
+                    .unwrap()
+                })
+                .unwrap();
+            assert_eq!(distributed.winners_count, 1);
+            assert_eq!(betting.get_escrow(match_id), 0);
+        }
+
+        #[ink::test]
+        fn settlement_reports_the_distribution() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(100), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for (bettor, amount, result) in [
+                (accounts.bob, 10000000000, MatchResult::Team1Victory),
+                (accounts.eve, 30000000000, MatchResult::Team1Victory),
+                (accounts.charlie, 6000000000, MatchResult::Team2Victory),
+                (accounts.charlie, 4000000000, MatchResult::Draw),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(betting.get_settlement(match_id), None);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // 1% of the 12.5 won by bob and the 37.5 won by eve
+            assert_eq!(
+                betting.get_settlement(match_id),
+                Some(SettlementReport {
+                    total_pool: 50000000000,
+                    winners: 2,
+                    losers: 1,
+                    fees: 500000000,
+                    dust: 0,
+                    boosted: 0,
+                })
+            );
+        }
+
+        #[ink::test]
+        fn distribute_winnings_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+
+            assert_eq!(betting.exists_match(match_id), true);
+            // Bob bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Charlie bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // Eve bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // The deposit and the bets are in the escrow of the match
+            assert_eq!(betting.get_escrow(match_id), 51000000000000);
+            let info = betting.get_match_info(match_id).unwrap();
+            assert_eq!(info.total_pool, 50000000000000);
+            assert_eq!(info.outcome_pools, [40000000000000, 10000000000000, 0]);
+            assert_eq!(info.bet_count, 3);
+            assert_eq!(info.status, MatchStatus::Upcoming);
+            assert!(betting.solvency_check());
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //Alice set the result
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            //Django distributes the winnings
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.get_escrow(match_id), 0);
+            //bob has 90 + 12.5 (winner)
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(102500000000000)
+            );
+            //charlie has 90 (loser)
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(90000000000000)
+            );
+            //eve has 90 + 37.5 (winner)
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(107500000000000)
+            );
+            // Created, 3 bets, result, distributed, settled, deposit returned, closed
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(9, emitted_events.len());
+            // All transfers went through
+            assert_eq!(betting.get_pending_claim(accounts.bob), 0);
+            assert_eq!(betting.withdraw_pending(), Err(Error::NothingToClaim));
+            let stats = betting.get_stats();
+            assert_eq!(stats.matches_created, 1);
+            assert_eq!(stats.bets, 3);
+            assert_eq!(stats.volume, 50000000000000);
+            assert_eq!(stats.paid_out, 50000000000000);
+            assert_eq!(stats.fees, 0);
+        }
+
+        #[ink::test]
+        fn draw_no_bet_refunds_on_draw() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates the match
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    true,
+                    None
+                ),
+                Ok(())
+            );
+            // Bob and Charlie bet, the draw can't be bet on
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Draw),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Team1Victory),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Team2Victory),
+                Ok(())
+            );
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.django, MatchResult::Draw, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Every stake is refunded
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100000000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(100000000000000)
+            );
+            assert_eq!(betting.get_escrow(accounts.django), 0);
+        }
+
+        #[ink::test]
+        fn void_match_refunds_every_stake() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            let django_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django,
+                )
+                .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Voided),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            assert_eq!(betting.void_match(match_id, 1), Err(Error::BadOrigin));
+
+            // Alice voids the match before it ends
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.void_match(match_id, 1), Ok(()));
+            assert_eq!(
+                betting.void_match(match_id, 1),
+                Err(Error::ResultAlreadySet)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100000000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(100000000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(django_balance + 1000000000000)
+            );
+        }
+
+        #[ink::test]
+        fn private_match_allowlist() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates a private match for Bob
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    10,
+                    true,
+                    false,
+                    Some(vec![accounts.bob])
+                ),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Team1Victory),
+                Err(Error::NotAllowedBettor)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Team1Victory),
+                Ok(())
+            );
+            assert_eq!(
+                betting.add_allowed_bettor(accounts.django, accounts.charlie),
+                Err(Error::NotCreator)
+            );
+            // Django invites Charlie, then removes them
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.add_allowed_bettor(accounts.django, accounts.charlie),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Team2Victory),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.remove_allowed_bettor(accounts.django, accounts.charlie),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.bet(accounts.django, MatchResult::Draw),
+                Err(Error::NotAllowedBettor)
+            );
+            assert_eq!(betting.get_match(accounts.django).unwrap().bets.len(), 2);
+
+            // Public matches have no allowlist
+            let match_id = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                10,
+                10,
+                1000000000000,
+            );
+            assert_eq!(
+                betting.add_allowed_bettor(match_id, accounts.bob),
+                Err(Error::MatchNotPrivate)
+            );
+        }
+
+        #[ink::test]
+        fn distribute_winnings_match_not_exist() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+
+            assert_eq!(betting.exists_match(match_id), true);
+            // Bob bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Charlie bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // Eve bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //Alice set the result
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+
+            //alice distribute winner doesn't exists
+            assert_eq!(betting.distribute_winnings(), Err(Error::MatchDoesNotExist));
+        }
+
+        #[ink::test]
+        fn distribute_winnings_match_not_result_yet() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+
+            assert_eq!(betting.exists_match(match_id), true);
+            // Bob bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Charlie bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // Eve bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            //Django distributes the winnings
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Err(Error::MatchNotResult));
+        }
+
+        #[ink::test]
+        fn handicap_bet_draw_not_allowed() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_handicap_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    10,
+                    -1
+                ),
+                Ok(())
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(accounts.alice, MatchResult::Draw),
+                Err(Error::OutcomeNotAllowed)
+            );
+        }
+
+        #[ink::test]
+        fn handicap_quarter_line_splits_stake() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates a team1 -0.25 handicap match
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_handicap_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    -1
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+            // Bob bets on team1
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Charlie bets on team2
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //Alice can only set the score
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Draw, None),
+                Err(Error::ScoreRequired)
+            );
+            assert_eq!(betting.set_score(match_id, 1, 1), Ok(()));
+            //Django distributes the winnings
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //bob has 90 + 5 (half pushed, half lost)
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(95000000000000)
+            );
+            //charlie has 90 + 15 (half pushed, half won)
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(105000000000000)
+            );
+            // Created, 2 bets, score, result, 2 refunds, distributed, settled, deposit returned,
+            // closed
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(11, emitted_events.len());
+        }
+
+        #[ink::test]
+        fn parlay_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match1 = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.django,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            // Charlie and Eve make the pools, 2.0 and 4.0 for team1
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match1, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match2, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match1, MatchResult::Team2Victory), Ok(()));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match2, MatchResult::Team2Victory), Ok(()));
+            // The bankroll has to back the winnings of Bob's parlay on both
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.place_parlay(vec![
+                    (match1, MatchResult::Team1Victory),
+                    (match2, MatchResult::Team1Victory)
+                ]),
+                Err(Error::ExposureExceeded)
+            );
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.frank,
+                100000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.place_parlay(vec![
+                    (match1, MatchResult::Team1Victory),
+                    (match2, MatchResult::Team1Victory)
+                ]),
+                Ok(0)
+            );
+            let legs = betting.get_parlay(0).unwrap().legs;
+            assert_eq!((legs[0].odds, legs[1].odds), (200, 400));
+            // The stake and the 7 of winnings backed are held in escrow
+            assert_eq!(betting.get_bankroll(), (3000000000000, 7000000000000));
+            assert_eq!(betting.get_termination_blockers().escrow, 70000000000000);
+            assert!(betting.solvency_check());
+            assert_eq!(betting.claim_parlay(0), Err(Error::MatchNotResult));
+            // Later bets don't move the odds of the parlay
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match1, MatchResult::Team1Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //Alice set the results
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            //Django distributes his match before the parlay is claimed
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //Bob claims 1 * 2.0 * 4.0
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.claim_parlay(0), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(107000000000000)
+            );
+            assert!(betting.get_parlay(0).is_none());
+            assert_eq!(betting.get_bankroll(), (3000000000000, 0));
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn contest_top_scorers_share_the_prize_pool() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                2,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.create_contest(1000000000, vec![match1, match1]),
+                Err(Error::InvalidContest)
+            );
+            let contest_id = betting
+                .create_contest(1000000000, vec![match1, match2])
+                .unwrap();
+
+            let entries = [
+                (
+                    accounts.bob,
+                    [MatchResult::Team1Victory, MatchResult::Team1Victory],
+                ),
+                (
+                    accounts.charlie,
+                    [MatchResult::Team1Victory, MatchResult::Team2Victory],
+                ),
+                (
+                    accounts.eve,
+                    [MatchResult::Team2Victory, MatchResult::Team2Victory],
+                ),
+            ];
+            for (entrant, predictions) in entries {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(entrant);
+                ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1);
+                assert_eq!(
+                    betting.enter_contest(contest_id, predictions.to_vec()),
+                    Err(Error::WrongEntryFee)
+                );
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+                assert_eq!(
+                    betting.enter_contest(contest_id, predictions[..1].to_vec()),
+                    Err(Error::InvalidPredictions)
+                );
+                assert_eq!(
+                    betting.enter_contest(contest_id, predictions.to_vec()),
+                    Ok(())
+                );
+            }
+            assert_eq!(
+                betting.enter_contest(contest_id, entries[2].1.to_vec()),
+                Err(Error::AlreadyEntered)
+            );
+            assert!(betting.solvency_check());
+            assert_eq!(
+                betting.settle_contest(contest_id),
+                Err(Error::MatchNotResult)
+            );
+
+            // Entries close when the first match starts
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.enter_contest(contest_id, entries[0].1.to_vec()),
+                Err(Error::EntriesClosed)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            assert_eq!(betting.settle_contest(contest_id), Ok(()));
+            // Charlie predicted both results
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(100002000000000)
+            );
+            assert_eq!(betting.get_contest(contest_id), None);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn survivor_pool_pays_the_last_entrant() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            let survivor_id = betting.create_survivor_pool(1000000000).unwrap();
+            assert_eq!(
+                betting.add_survivor_round(survivor_id, vec![match1, match2]),
+                Ok(())
+            );
+            for entrant in [accounts.bob, accounts.charlie, accounts.django] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(entrant);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+                assert_eq!(betting.enter_survivor_pool(survivor_id), Ok(()));
+            }
+            assert!(betting.solvency_check());
+
+            let picks = [
+                (accounts.bob, match1, MatchResult::Team1Victory),
+                (accounts.charlie, match2, MatchResult::Team1Victory),
+                (accounts.django, match2, MatchResult::Team2Victory),
+            ];
+            for (entrant, match_id, winner) in picks {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(entrant);
+                assert_eq!(
+                    betting.pick_survivor(survivor_id, match_id, MatchResult::Draw),
+                    Err(Error::OutcomeNotAllowed)
+                );
+                assert_eq!(betting.pick_survivor(survivor_id, match_id, winner), Ok(()));
+            }
+            assert_eq!(
+                betting.pick_survivor(survivor_id, match1, MatchResult::Team2Victory),
+                Err(Error::AlreadyPicked)
+            );
+
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.enter_survivor_pool(survivor_id),
+                Err(Error::EntriesClosed)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let match3 = create_match(
+                &mut betting,
+                accounts.bob,
+                "team1",
+                "team3",
+                10,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.add_survivor_round(survivor_id, vec![match3]),
+                Err(Error::MatchNotResult)
+            );
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            // Django picked the wrong team
+            assert_eq!(
+                betting.add_survivor_round(survivor_id, vec![match3]),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.pick_survivor(survivor_id, match3, MatchResult::Team2Victory),
+                Err(Error::NotSurvivor)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.pick_survivor(survivor_id, match3, MatchResult::Team1Victory),
+                Err(Error::TeamAlreadyUsed)
+            );
+            assert_eq!(
+                betting.pick_survivor(survivor_id, match3, MatchResult::Team2Victory),
+                Ok(())
+            );
+
+            // Charlie doesn't pick in the second round
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match3, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(betting.end_survivor_pool(survivor_id), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 3000000000)
+            );
+            assert_eq!(betting.get_survivor_pool(survivor_id), None);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn games_without_results_are_refunded_after_the_oracle_timeout() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let team = |name: &str| name.as_bytes().to_vec();
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(betting.set_vote_timing(3, 5), Ok(()));
+            let contest_id = betting
+                .create_contest(1000000000, vec![match1, match2])
+                .unwrap();
+            let settled_contest_id = betting.create_contest(1000000000, vec![match1]).unwrap();
+            let survivor_id = betting.create_survivor_pool(1000000000).unwrap();
+            assert_eq!(
+                betting.add_survivor_round(survivor_id, vec![match1, match2]),
+                Ok(())
+            );
+            let bracket_id = betting
+                .create_bracket(
+                    1000000000,
+                    vec![team("team1"), team("team2"), team("team3"), team("team4")],
+                )
+                .unwrap();
+            assert_eq!(
+                betting.set_bracket_round(bracket_id, vec![match1, match2]),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            let predictions = vec![MatchResult::Team1Victory, MatchResult::Team1Victory];
+            assert_eq!(betting.enter_contest(contest_id, predictions), Ok(()));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.enter_contest(settled_contest_id, vec![MatchResult::Team1Victory]),
+                Ok(())
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(betting.enter_survivor_pool(survivor_id), Ok(()));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.enter_bracket(
+                    bracket_id,
+                    vec![team("team1"), team("team3"), team("team1")]
+                ),
+                Ok(())
+            );
+
+            // Match1 ends in a draw, match2 never gets a result
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_result(match1, MatchResult::Draw, None), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.refund_contest(contest_id),
+                Err(Error::RefundNotOpen)
+            );
+            assert_eq!(
+                betting.refund_survivor_pool(survivor_id),
+                Err(Error::RefundNotOpen)
+            );
+            assert_eq!(
+                betting.refund_bracket(bracket_id),
+                Err(Error::RefundNotOpen)
+            );
+            for _ in 0..2 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            // A contest with all its results is settled instead
+            assert_eq!(
+                betting.refund_contest(settled_contest_id),
+                Err(Error::RefundNotOpen)
+            );
+            assert_eq!(betting.refund_contest(contest_id), Ok(()));
+            assert_eq!(betting.refund_survivor_pool(survivor_id), Ok(()));
+            assert_eq!(betting.refund_bracket(bracket_id), Ok(()));
+            assert_eq!(betting.get_contest(contest_id), None);
+            assert_eq!(betting.get_survivor_pool(survivor_id), None);
+            assert_eq!(betting.get_bracket(bracket_id), None);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance - 1000000000)
+            );
+            assert_eq!(betting.settle_contest(settled_contest_id), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance)
+            );
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn bracket_points_double_each_round() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let team = |name: &str| name.as_bytes().to_vec();
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.create_bracket(
+                    1000000000,
+                    vec![team("team1"), team("team2"), team("team3")]
+                ),
+                Err(Error::InvalidBracket)
+            );
+            let bracket_id = betting
+                .create_bracket(
+                    1000000000,
+                    vec![team("team1"), team("team2"), team("team3"), team("team4")],
+                )
+                .unwrap();
+            assert_eq!(
+                betting.set_bracket_round(bracket_id, vec![match2, match1]),
+                Err(Error::InvalidRound)
+            );
+            assert_eq!(
+                betting.set_bracket_round(bracket_id, vec![match1, match2]),
+                Ok(())
+            );
+
+            // Team2 can't reach the final if team1 won their game
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.enter_bracket(
+                    bracket_id,
+                    vec![team("team1"), team("team3"), team("team2")]
+                ),
+                Err(Error::InvalidPredictions)
+            );
+            assert_eq!(
+                betting.enter_bracket(
+                    bracket_id,
+                    vec![team("team1"), team("team3"), team("team1")]
+                ),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.enter_bracket(
+                    bracket_id,
+                    vec![team("team1"), team("team4"), team("team1")]
+                ),
+                Ok(())
+            );
+            assert!(betting.solvency_check());
+
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000);
+            assert_eq!(
+                betting.enter_bracket(
+                    bracket_id,
+                    vec![team("team1"), team("team3"), team("team1")]
+                ),
+                Err(Error::EntriesClosed)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.score_bracket_round(bracket_id),
+                Err(Error::MatchNotResult)
+            );
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            assert_eq!(betting.score_bracket_round(bracket_id), Ok(()));
+            let bracket = betting.get_bracket(bracket_id).unwrap();
+            assert_eq!(bracket.winners, vec![team("team1"), team("team4")]);
+            assert_eq!(bracket.entries[0].points, 1);
+            assert_eq!(bracket.entries[1].points, 2);
+
+            let match3 = create_match(
+                &mut betting,
+                accounts.bob,
+                "team1",
+                "team4",
+                10,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(betting.set_bracket_round(bracket_id, vec![match3]), Ok(()));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.set_result(match3, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            let charlie_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie,
+                )
+                .unwrap();
+            assert_eq!(betting.score_bracket_round(bracket_id), Ok(()));
+            // Both picked the champion, worth two points, so charlie leads with four
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(charlie_balance + 2000000000)
+            );
+            assert_eq!(betting.get_bracket(bracket_id), None);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn each_way_bets_are_split_between_the_win_and_place_pools() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            let runners: Vec<Vec<u8>> = ["a", "b", "c", "d"]
+                .iter()
+                .map(|runner| runner.as_bytes().to_vec())
+                .collect();
+            let deadline = ink::env::block_number::<ink::env::DefaultEnvironment>() + 1;
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.create_outright(runners.clone(), 2, deadline),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.create_outright(runners.clone(), 4, deadline),
+                Err(Error::InvalidOutright)
+            );
+            assert_eq!(
+                betting.create_outright(vec![runners[0].clone(), runners[0].clone()], 1, deadline),
+                Err(Error::InvalidOutright)
+            );
+            let outright_id = betting.create_outright(runners, 2, deadline).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            // Runner d is the fourth outcome, after the one of voided matches
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(5), true),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(
+                betting.bet_outright(outright_id, MatchResult::Voided, true),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(0), true),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(1), false),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(40000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(2), true),
+                Ok(())
+            );
+            let bets = betting.get_outright(outright_id).unwrap().bets;
+            assert_eq!(
+                (bets[0].win_stake, bets[0].place_stake),
+                (10000000000, 10000000000)
+            );
+            assert_eq!((bets[1].win_stake, bets[1].place_stake), (30000000000, 0));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.settle_outright(outright_id, vec![OutcomeId(1), OutcomeId(0)]),
+                Err(Error::TimeMatchNotOver)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.settle_outright(outright_id, vec![OutcomeId(1), OutcomeId(1)]),
+                Err(Error::InvalidOutright)
+            );
+            let bob = balance_of(accounts.bob);
+            let charlie = balance_of(accounts.charlie);
+            let eve = balance_of(accounts.eve);
+            // B wins ahead of A
+            assert_eq!(
+                betting.settle_outright(outright_id, vec![OutcomeId(1), OutcomeId(0)]),
+                Ok(())
+            );
+            // Charlie takes the win pool of 60, bob the place pool of 30
+            assert_eq!(balance_of(accounts.charlie), charlie + 60000000000);
+            assert_eq!(balance_of(accounts.bob), bob + 30000000000);
+            assert_eq!(balance_of(accounts.eve), eve);
+            assert_eq!(betting.get_outright(outright_id), None);
+            assert_eq!(betting.get_termination_blockers().prize_pools, 0);
+        }
+
+        #[ink::test]
+        fn outright_bets_are_cashed_out_at_the_oracle_prices() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            let runners: Vec<Vec<u8>> = ["a", "b", "c"]
+                .iter()
+                .map(|runner| runner.as_bytes().to_vec())
+                .collect();
+            let deadline = ink::env::block_number::<ink::env::DefaultEnvironment>() + 10;
+            let outright_id = betting.create_outright(runners, 2, deadline).unwrap();
+            assert_eq!(betting.add_score_oracle(accounts.django), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(0), true),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(1), false),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(0), false),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.cashout_outright(outright_id, 0),
+                Err(Error::StalePrices)
+            );
+
+            let prices = vec![(6000, 9000), (3000, 7000), (1000, 4000)];
+            assert_eq!(
+                betting.update_outright_prices(outright_id, prices.clone()),
+                Err(Error::NotScoreOracle)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.update_outright_prices(outright_id, prices[..2].to_vec()),
+                Err(Error::InvalidOdds)
+            );
+            assert_eq!(
+                betting.update_outright_prices(
+                    outright_id,
+                    vec![(7000, 9000), (3000, 7000), (1000, 4000)]
+                ),
+                Err(Error::InvalidOdds)
+            );
+            assert_eq!(betting.update_outright_prices(outright_id, prices), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.cashout_outright(outright_id, 0),
+                Err(Error::NotBettor)
+            );
+            // The win part would get 25 of the win pool at 60%, the place part the slice of
+            // the place pool of 10 for one of the two places at 90%, less the 5% fee
+            let bob = balance_of(accounts.bob);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.cashout_outright(outright_id, 0), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob + 18525000000);
+            let outright = betting.get_outright(outright_id).unwrap();
+            assert_eq!(
+                (outright.win_pool, outright.place_pool),
+                (35000000000, 5500000000)
+            );
+            assert_eq!(
+                betting.cashout_outright(outright_id, 0),
+                Err(Error::BetDoesNotExist)
+            );
+
+            // The prices go stale a day after their update
+            for _ in 0..14401 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                betting.cashout_outright(outright_id, 2),
+                Err(Error::StalePrices)
+            );
+            // Eve takes what is left of the win pool, nobody backs the places any more
+            let eve = balance_of(accounts.eve);
+            let jackpot = betting.get_jackpot();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.settle_outright(outright_id, vec![OutcomeId(0), OutcomeId(1)]),
+                Ok(())
+            );
+            assert_eq!(balance_of(accounts.eve), eve + 35000000000);
+            assert_eq!(betting.get_jackpot(), jackpot + 5500000000);
+            assert_eq!(betting.get_termination_blockers().prize_pools, 0);
+        }
+
+        #[ink::test]
+        fn cashed_out_place_bets_leave_the_other_places_their_share() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            let runners: Vec<Vec<u8>> = ["a", "b", "c"]
+                .iter()
+                .map(|runner| runner.as_bytes().to_vec())
+                .collect();
+            let deadline = ink::env::block_number::<ink::env::DefaultEnvironment>() + 10;
+            let outright_id = betting.create_outright(runners, 2, deadline).unwrap();
+            assert_eq!(betting.add_score_oracle(accounts.django), Ok(()));
+            // Each-way bets of 10 and 10 on every runner
+            for (bettor, runner) in [
+                (accounts.bob, OutcomeId(0)),
+                (accounts.charlie, OutcomeId(1)),
+                (accounts.eve, OutcomeId(2)),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+                assert_eq!(betting.bet_outright(outright_id, runner, true), Ok(()));
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.update_outright_prices(
+                    outright_id,
+                    vec![(5000, 8000), (3000, 7000), (2000, 5000)]
+                ),
+                Ok(())
+            );
+            // 30 of the win pool at 50% and 15 of the place pool at 80%, less the 5% fee
+            let bob = balance_of(accounts.bob);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.cashout_outright(outright_id, 0), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob + 25650000000);
+            let outright = betting.get_outright(outright_id).unwrap();
+            assert_eq!(
+                (outright.win_pool, outright.place_pool),
+                (15000000000, 18000000000)
+            );
+
+            for _ in 0..11 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            let charlie = balance_of(accounts.charlie);
+            let eve = balance_of(accounts.eve);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.settle_outright(outright_id, vec![OutcomeId(1), OutcomeId(2)]),
+                Ok(())
+            );
+            // Both placed runners still get 9 of their 10 on the places
+            assert_eq!(balance_of(accounts.charlie), charlie + 24000000000);
+            assert_eq!(balance_of(accounts.eve), eve + 9000000000);
+            assert_eq!(betting.get_termination_blockers().prize_pools, 0);
+        }
+
+        #[ink::test]
+        fn score_oracles_settle_outrights_less_the_fee() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            assert_eq!(betting.set_protocol_fee(300), Ok(()));
+            assert_eq!(betting.set_score_quorum(2), Ok(()));
+            for oracle in [accounts.django, accounts.eve, accounts.frank] {
+                assert_eq!(betting.add_score_oracle(oracle), Ok(()));
+            }
+            let runners: Vec<Vec<u8>> = ["a", "b", "c"]
+                .iter()
+                .map(|runner| runner.as_bytes().to_vec())
+                .collect();
+            let deadline = ink::env::block_number::<ink::env::DefaultEnvironment>() + 1;
+            let outright_id = betting.create_outright(runners, 1, deadline).unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(0), false),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(1), false),
+                Ok(())
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(1)]),
+                Err(Error::TimeMatchNotOver)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(4)]),
+                Err(Error::InvalidOutright)
+            );
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(1)]),
+                Ok(())
+            );
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(1)]),
+                Err(Error::AlreadyReported)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(0)]),
+                Err(Error::NotScoreOracle)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(0)]),
+                Ok(())
+            );
+            assert!(betting.get_outright(outright_id).is_some());
+            // Two oracles agree on B
+            let charlie = balance_of(accounts.charlie);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                betting.report_outright_result(outright_id, vec![OutcomeId(1)]),
+                Ok(())
+            );
+            assert_eq!(betting.get_outright(outright_id), None);
+            // Charlie takes the win pool of 40, less the 3% fee
+            assert_eq!(balance_of(accounts.charlie), charlie + 38800000000);
+            assert_eq!(betting.get_treasury(), 1200000000);
+            assert_eq!(betting.get_termination_blockers().prize_pools, 0);
+        }
+
+        #[ink::test]
+        fn outrights_without_a_result_are_refunded_after_the_timeout() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            assert_eq!(betting.set_protocol_fee(300), Ok(()));
+            assert_eq!(betting.set_vote_timing(5, 5), Ok(()));
+            let runners: Vec<Vec<u8>> = ["a", "b", "c"]
+                .iter()
+                .map(|runner| runner.as_bytes().to_vec())
+                .collect();
+            let deadline = ink::env::block_number::<ink::env::DefaultEnvironment>() + 1;
+            let outright_id = betting.create_outright(runners, 2, deadline).unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(0), true),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(
+                betting.bet_outright(outright_id, OutcomeId(1), false),
+                Ok(())
+            );
+
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.refund_outright(outright_id),
+                Err(Error::RefundNotOpen)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let bob = balance_of(accounts.bob);
+            let charlie = balance_of(accounts.charlie);
+            // Refunds are free of fees
+            assert_eq!(betting.refund_outright(outright_id), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob + 20000000000);
+            assert_eq!(balance_of(accounts.charlie), charlie + 30000000000);
+            assert_eq!(betting.get_treasury(), 0);
+            assert_eq!(betting.get_outright(outright_id), None);
+            assert_eq!(
+                betting.refund_outright(outright_id),
+                Err(Error::OutrightDoesNotExist)
+            );
+            assert_eq!(betting.get_termination_blockers().prize_pools, 0);
+        }
+
+        #[ink::test]
+        fn parlay_lost_pays_nothing() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match1 = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.django,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.place_parlay(vec![
+                    (match1, MatchResult::Team1Victory),
+                    (match2, MatchResult::Draw)
+                ]),
+                Ok(0)
+            );
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            //Only Bob can claim it
+            assert_eq!(betting.claim_parlay(0), Err(Error::NotBettor));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.claim_parlay(0), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(99000000000000)
+            );
+            assert_eq!(betting.claim_parlay(0), Err(Error::ParlayDoesNotExist));
+            // The bankroll won the stake
+            assert_eq!(betting.get_bankroll(), (1000000000000, 0));
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn insured_parlays_losing_a_single_leg_are_refunded() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            let balance_of = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_parlay_insurance_premium(2000),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_parlay_insurance_premium(0),
+                Err(Error::InvalidConfig)
+            );
+            let match1 = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.django,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            // Charlie pays a premium of 10% on a stake of 1000, bob on a stake of 100
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1100000000000);
+            assert_eq!(
+                betting.place_insured_parlay(vec![
+                    (match1, MatchResult::Team2Victory),
+                    (match2, MatchResult::Draw)
+                ]),
+                Ok(0)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(110000000000);
+            assert_eq!(
+                betting.place_insured_parlay(vec![
+                    (match1, MatchResult::Team1Victory),
+                    (match2, MatchResult::Draw)
+                ]),
+                Ok(1)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(
+                betting.place_parlay(vec![
+                    (match1, MatchResult::Team1Victory),
+                    (match2, MatchResult::Draw)
+                ]),
+                Ok(2)
+            );
+            assert_eq!(
+                betting.get_parlay(0).map(|parlay| parlay.amount),
+                Some(1000000000000)
+            );
+            assert_eq!(
+                betting.get_parlay(1).map(|parlay| parlay.premium),
+                Some(10000000000)
+            );
+            assert_eq!(betting.get_parlay(2).map(|parlay| parlay.premium), Some(0));
+            assert_eq!(betting.get_parlay_insurance(), (1000, 110000000000, 0));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            // Two legs lost, nothing is refunded
+            let charlie = balance_of(accounts.charlie);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(betting.claim_parlay(0), Ok(()));
+            assert_eq!(balance_of(accounts.charlie), charlie);
+            // A single leg lost refunds the stake of the insured parlay only
+            let bob = balance_of(accounts.bob);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.claim_parlay(1), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob + 100000000000);
+            assert_eq!(betting.claim_parlay(2), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob + 100000000000);
+            assert_eq!(
+                betting.get_parlay_insurance(),
+                (1000, 10000000000, 100000000000)
+            );
+            assert_eq!(
+                betting.get_termination_blockers().parlay_insurance,
+                10000000000
+            );
+        }
+
+        #[ink::test]
+        fn parlay_needs_two_different_matches() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.place_parlay(vec![(match_id, MatchResult::Team1Victory)]),
+                Err(Error::InvalidParlay)
+            );
+            assert_eq!(
+                betting.place_parlay(vec![
+                    (match_id, MatchResult::Team1Victory),
+                    (match_id, MatchResult::Draw)
+                ]),
+                Err(Error::InvalidParlay)
+            );
+        }
+
+        #[ink::test]
+        fn lmsr_buy_shares_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //Django creates the market, funding 10 * ln(3) of max loss
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(12000000000000);
+            assert_eq!(
+                betting.create_lmsr_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    10000000000000
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+            // 10 shares cost 10 * (ln(e + 2) - ln(3)) ~ 4.528
+            let cost = betting
+                .quote_shares(match_id, MatchResult::Team1Victory, 10000000000000)
+                .unwrap();
+            assert!(cost.abs_diff(4528324252639) < 1000);
+            // Bob overpays and gets the excess back
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000000);
+            assert_eq!(
+                betting.buy_shares(match_id, MatchResult::Team1Victory, 10000000000000),
+                Ok(())
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100000000000000 - cost)
+            );
+            // The price moved up with volume
+            assert!(
+                betting
+                    .quote_shares(match_id, MatchResult::Team1Victory, 10000000000000)
+                    .unwrap()
+                    > cost
+            );
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //bob redeems his 10 shares
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(110000000000000 - cost)
+            );
+            //django funded the loss
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(90000000000000 + cost)
+            );
+        }
+
+        #[ink::test]
+        fn uniform_price_shares_cost_the_same_in_a_block() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(12000000000000);
+            assert_eq!(
+                betting.create_lmsr_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    10000000000000
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_uniform_price(match_id, true),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.set_uniform_price(match_id, true), Ok(()));
+            // Both orders together cost as much as buying their shares at once
+            let cost = betting
+                .quote_shares(match_id, MatchResult::Team1Victory, 20000000000000)
+                .unwrap();
+
+            let balance = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                    .unwrap()
+            };
+            let eve_balance = balance(accounts.eve);
+            for buyer in [accounts.bob, accounts.charlie] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(buyer);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+                assert_eq!(
+                    betting.buy_shares(match_id, MatchResult::Team1Victory, 10000000000000),
+                    Ok(())
+                );
+            }
+            // Eve does not pay enough for the price of the block
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.buy_shares(match_id, MatchResult::Team1Victory, 10000000000000),
+                Ok(())
+            );
+            assert_eq!(betting.get_share_orders(match_id).unwrap().orders.len(), 3);
+            assert_eq!(
+                betting.clear_share_orders(match_id),
+                Err(Error::ShareOrdersOpen)
+            );
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(betting.clear_share_orders(match_id), Ok(()));
+            assert_eq!(
+                betting.clear_share_orders(match_id),
+                Err(Error::NoShareOrders)
+            );
+            assert_eq!(balance(accounts.eve), eve_balance);
+            assert_eq!(balance(accounts.bob), balance(accounts.charlie));
+            let paid = 100000000000000 - balance(accounts.bob);
+            assert!(paid.abs_diff(cost / 2) <= 1);
+            assert_eq!(
+                betting.get_bets_of(match_id, accounts.charlie)[0].amount,
+                10000000000000
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                betting.set_uniform_price(match_id, false),
+                Err(Error::MatchHasBets)
+            );
+        }
+
+        #[ink::test]
+        fn lmsr_market_errors() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            //The deposit must fund the max loss
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_lmsr_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    10,
+                    10000000000000
+                ),
+                Err(Error::NotEnoughDeposit)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(12000000000000);
+            assert_eq!(
+                betting.create_lmsr_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    10,
+                    10000000000000
+                ),
+                Ok(())
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.bet(accounts.alice, MatchResult::Team1Victory),
+                Err(Error::WrongMarket)
+            );
+            assert_eq!(
+                betting.buy_shares(accounts.alice, MatchResult::Team1Victory, 10000000000000),
+                Err(Error::InsufficientPayment)
+            );
+        }
+
+        #[ink::test]
+        fn exchange_orders_match_and_settle() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            // Bob backs team1 at 3.00
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.post_order(match_id, MatchResult::Team1Victory, OrderSide::Back, 300),
+                Ok(0)
+            );
+            // Charlie lays team1 at 3.00, risking 20
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000000);
+            assert_eq!(
+                betting.post_order(match_id, MatchResult::Team1Victory, OrderSide::Lay, 300),
+                Ok(1)
+            );
+            assert_eq!(betting.match_orders(0, 1), Ok(0));
+            assert!(betting.get_order(0).is_none());
+            assert!(betting.get_order(1).is_none());
+            // The deposit, the stake and the liability are held in escrow
+            assert_eq!(betting.get_termination_blockers().escrow, 31000000000000);
+            assert!(betting.solvency_check());
+            assert_eq!(betting.settle_matched_bet(0), Err(Error::MatchNotResult));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(betting.settle_matched_bet(0), Ok(()));
+            assert_eq!(betting.get_termination_blockers().escrow, 1000000000000);
+            //bob has 90 + 30
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(120000000000000)
+            );
+            //charlie has 80
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(80000000000000)
+            );
+        }
+
+        #[ink::test]
+        fn exchange_refunds_draw_no_bet_draws() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    true,
+                    None
+                ),
+                Ok(())
+            );
+            // Bob backs team1 at 3.00, charlie lays it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.post_order(
+                    accounts.django,
+                    MatchResult::Team1Victory,
+                    OrderSide::Back,
+                    300
+                ),
+                Ok(0)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000000);
+            assert_eq!(
+                betting.post_order(
+                    accounts.django,
+                    MatchResult::Team1Victory,
+                    OrderSide::Lay,
+                    300
+                ),
+                Ok(1)
+            );
+            assert_eq!(betting.match_orders(0, 1), Ok(0));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.django, MatchResult::Draw, None),
+                Ok(())
+            );
+            assert_eq!(betting.settle_matched_bet(0), Ok(()));
+            // Both sides get their funds back
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100000000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(100000000000000)
+            );
+            assert_eq!(betting.get_termination_blockers().escrow, 1000000000000);
+        }
+
+        #[ink::test]
+        fn exchange_payouts_that_fail_are_deferred() {
+            let accounts = set_accounts();
+            // An account without a balance can't make any transfer
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(AccountId::from([0x42; 32]));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let mut betting = Betting::new();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1000000000000);
+            assert_eq!(
+                betting.create_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    1,
+                    1,
+                    true,
+                    false,
+                    None
+                ),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.post_order(
+                    accounts.django,
+                    MatchResult::Team1Victory,
+                    OrderSide::Back,
+                    300
+                ),
+                Ok(0)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(20000000000000);
+            assert_eq!(
+                betting.post_order(
+                    accounts.django,
+                    MatchResult::Team1Victory,
+                    OrderSide::Lay,
+                    300
+                ),
+                Ok(1)
+            );
+            assert_eq!(betting.match_orders(0, 1), Ok(0));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.django, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            // The failed payout doesn't block the settlement, bob claims it later
+            assert_eq!(betting.settle_matched_bet(0), Ok(()));
+            assert!(betting.get_matched_bet(0).is_none());
+            assert_eq!(betting.get_pending_claim(accounts.bob), 30000000000000);
+        }
+
+        #[ink::test]
+        fn exchange_partial_match_and_cancel() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.alice,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            // Bob backs team1 at 2.00
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.post_order(match_id, MatchResult::Team1Victory, OrderSide::Back, 200),
+                Ok(0)
+            );
+            // Charlie lays team1 at 3.00 for a stake of 20, risking 40
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(40000000000000);
+            assert_eq!(
+                betting.post_order(match_id, MatchResult::Team1Victory, OrderSide::Lay, 300),
+                Ok(1)
+            );
+            // A lay below the back odds does not match
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.post_order(match_id, MatchResult::Team1Victory, OrderSide::Lay, 150),
+                Ok(2)
+            );
+            assert_eq!(betting.match_orders(0, 2), Err(Error::OrdersDoNotMatch));
+            assert_eq!(betting.cancel_order(2), Ok(()));
+            // Matched at bob's 2.00 for 10, charlie gets 10 of liability back
+            assert_eq!(betting.match_orders(0, 1), Ok(0));
+            assert_eq!(betting.get_order(1).unwrap().stake, 10000000000000);
+            assert_eq!(betting.get_termination_blockers().escrow, 41000000000000);
+            assert_eq!(betting.cancel_order(1), Ok(()));
+            assert_eq!(betting.get_termination_blockers().escrow, 21000000000000);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(90000000000000)
+            );
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            assert_eq!(betting.settle_matched_bet(0), Ok(()));
+            //charlie won the 10 of bob
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(110000000000000)
+            );
+        }
+
+        #[ink::test]
+        fn settle_match_after_grace_period() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            //Eve can't settle during the grace period
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                betting.settle_match(match_id),
+                Err(Error::SettlementGracePeriodNotOver)
+            );
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.settle_match(match_id), Ok(()));
+            assert!(!betting.exists_match(match_id));
+            //bob got his bet back with a block of slashing, eve earned 10% of the deposit
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100001000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(100100000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(99899000000000)
+            );
+        }
+
+        #[ink::test]
+        fn settle_matches_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match1, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match2, MatchResult::Team2Victory), Ok(()));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match1, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            // Every match needs a result
+            assert_eq!(
+                betting.settle_matches(vec![match2, match1]),
+                Err(Error::MatchNotResult)
+            );
+            assert_eq!(
+                betting.set_result(match2, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(
+                betting.settle_matches(vec![match1; 9]),
+                Err(Error::InvalidBatch)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.settle_matches(vec![match1, match2]),
+                Err(Error::BadOrigin)
+            );
+
+            // The creators settle during the grace period
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.settle_matches(vec![match1, match2]),
+                Err(Error::SettlementGracePeriodNotOver)
+            );
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.settle_matches(vec![match1, match2]), Ok(()));
+            assert!(!betting.exists_match(match1));
+            assert!(!betting.exists_match(match2));
+            // Bob got the stake back with the 0.1% of the deposit slashed for the block
+            // settled late, nobody won match2 and the creators got the rest of their deposits
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100001000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(99999000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(99999000000000)
+            );
+        }
+
+        fn payout_leaf(account: AccountId, amount: u128) -> [u8; 32] {
+            let mut leaf = [0u8; 32];
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(&(account, amount), &mut leaf);
+            leaf
+        }
+
+        fn merkle_node(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+            let mut node = [0u8; 32];
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(
+                &(a.min(b), a.max(b)),
+                &mut node,
+            );
+            node
+        }
+
+        #[ink::test]
+        fn claim_with_proof_works() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(300), Ok(()));
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            // Bob and Charlie share the pool of 40, less the 3% fee
+            let bob_leaf = payout_leaf(accounts.bob, 19400000000000);
+            let charlie_leaf = payout_leaf(accounts.charlie, 19400000000000);
+            let root = merkle_node(bob_leaf, charlie_leaf);
+            // The creator settles on chain during the grace period
+            assert_eq!(
+                betting.post_payout_root(match_id, root, 38800000000000, 1200000000000),
+                Err(Error::SettlementGracePeriodNotOver)
+            );
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            // The pool and the 0.001 slashed for a block late bound the payouts and fees
+            assert_eq!(
+                betting.post_payout_root(match_id, root, 40001000000001, 0),
+                Err(Error::EscrowExceeded)
+            );
+            assert_eq!(
+                betting.post_payout_root(match_id, root, 38800000000000, 1300000000000),
+                Err(Error::EscrowExceeded)
+            );
+            assert_eq!(betting.set_payout_address(accounts.frank), Ok(()));
+            assert_eq!(
+                betting.post_payout_root(match_id, root, 38800000000000, 1200000000000),
+                Ok(())
+            );
+            assert!(!betting.exists_match(match_id));
+            // The fee and the slashed deposit the root didn't share
+            assert_eq!(betting.get_treasury(), 1201000000000);
+            // Django gets the deposit back less the slash and the incentive of alice, paid
+            // to their payout address
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(99899000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.frank),
+                Ok(100000000000)
+            );
+            assert!(betting.solvency_check());
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.claim_with_proof(match_id, 30000000000000, vec![charlie_leaf]),
+                Err(Error::InvalidProof)
+            );
+            assert_eq!(
+                betting.claim_with_proof(match_id, 19400000000000, vec![charlie_leaf]),
+                Ok(())
+            );
+            assert_eq!(
+                betting.claim_with_proof(match_id, 19400000000000, vec![charlie_leaf]),
+                Err(Error::AlreadyClaimed)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(109400000000000)
+            );
+            assert_eq!(
+                betting.get_payout_root(match_id).unwrap().remaining,
+                19400000000000
+            );
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn unclaimed_payouts_are_swept_after_the_deadline() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_claim_period(2), Ok(()));
+            assert_eq!(betting.set_settlement_grace_period(2), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            let bob_leaf = payout_leaf(accounts.bob, 10000000000000);
+            let charlie_leaf = payout_leaf(accounts.charlie, 10000000000000);
+            let root = merkle_node(bob_leaf, charlie_leaf);
+            assert_eq!(
+                betting.post_payout_root(match_id, root, 20000000000000, 0),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.claim_with_proof(match_id, 10000000000000, vec![charlie_leaf]),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.sweep_unclaimed(match_id, true),
+                Err(Error::ClaimPeriodNotOver)
+            );
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                betting.claim_with_proof(match_id, 10000000000000, vec![bob_leaf]),
+                Err(Error::ClaimPeriodOver)
+            );
+            assert_eq!(
+                betting.sweep_unclaimed(match_id, true),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.sweep_unclaimed(match_id, true), Ok(()));
+            assert_eq!(betting.get_jackpot(), 10000000000000);
+            assert_eq!(betting.get_payout_root(match_id).unwrap().remaining, 0);
+            assert_eq!(
+                betting.sweep_unclaimed(match_id, true),
+                Err(Error::NothingToClaim)
+            );
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn jackpot_rolls_over_to_exact_score() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_jackpot_share(10_001), Err(Error::InvalidConfig));
+            // Nobody wins the first match, its pool rolls over to the jackpot
+            let match1 = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for bettor in [accounts.bob, accounts.charlie] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+                assert_eq!(betting.bet(match1, MatchResult::Team1Victory), Ok(()));
+            }
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_result(match1, MatchResult::Draw, None), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.get_jackpot(), 20000000000);
+            assert_eq!(betting.get_treasury(), 0);
+
+            // The jackpot is attached to the next match
+            let match2 = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                5,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                betting.predict_score(match2, 2, 1),
+                Err(Error::MatchNotFeatured)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.feature_match(match2, 20000000001),
+                Err(Error::NotEnoughJackpot)
+            );
+            assert_eq!(betting.feature_match(match2, 20000000000), Ok(()));
+            assert_eq!(betting.get_jackpot(), 0);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match2, MatchResult::Team1Victory), Ok(()));
+            assert_eq!(betting.predict_score(match2, 2, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match2, MatchResult::Team2Victory), Ok(()));
+            assert_eq!(betting.predict_score(match2, 1, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(betting.predict_score(match2, 2, 1), Err(Error::NotBettor));
+
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_score(match2, 2, 1), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Bob won the pool of match2 and the jackpot
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100020000000000)
+            );
+            assert_eq!(betting.get_jackpot(), 0);
+        }
+
+        #[ink::test]
+        fn season_leaderboard_ranks_winning_stakes() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+            // A late settlement funds the treasury
+            create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.django, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            for _ in 0..15 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                25,
+                1,
+                1000000000000,
+            );
+            let bets = [
+                (accounts.bob, MatchResult::Team1Victory, 5000000000),
+                (accounts.charlie, MatchResult::Team1Victory, 3000000000),
+                (accounts.django, MatchResult::Team2Victory, 2000000000),
+            ];
+            for (bettor, result, amount) in bets {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(amount);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                betting.get_leaderboard(0, 10),
+                vec![(accounts.bob, 5000000000), (accounts.charlie, 3000000000)]
+            );
+            assert_eq!(
+                betting.get_leaderboard(1, 10),
+                vec![(accounts.charlie, 3000000000)]
+            );
+            assert_eq!(betting.get_season_points(accounts.django), 0);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.reset_season(Vec::new()), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.reset_season(vec![betting.get_treasury(), 1]),
+                Err(Error::NotEnoughTreasury)
+            );
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            // Only two accounts scored, the third prize isn't paid
+            assert_eq!(
+                betting.reset_season(vec![6000000000, 4000000000, 1]),
+                Ok(())
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 6000000000)
+            );
+            assert_eq!(betting.get_season(), 1);
+            assert_eq!(betting.get_leaderboard(0, 10), Vec::new());
+            assert_eq!(betting.get_season_points(accounts.bob), 0);
+            assert!(betting.solvency_check());
+        }
+
+        /// Lowers the minimum deposit, so the deposit of fixed-odds matches doesn't cover
+        /// their whole liability.
+        fn set_min_deposit(betting: &mut Betting, min_deposit: u128) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(betting.owner);
+            assert_eq!(
+                betting.set_config(Config {
+                    min_deposit,
+                    ..betting.get_config()
+                }),
+                Ok(())
+            );
+        }
+
+        #[ink::test]
+        fn bankroll_backs_fixed_odds_bets() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            set_min_deposit(&mut betting, 5000000000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+            assert_eq!(betting.get_lp_shares(accounts.bob), 100000000000);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000);
+            assert_eq!(
+                betting.create_fixed_odds_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    1,
+                    [200, 100, 0],
+                ),
+                Err(Error::InvalidOdds)
+            );
+            // 1/2.00 + 1/3.00 leaves bettors an edge over the book
+            assert_eq!(
+                betting.create_fixed_odds_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    1,
+                    [200, 300, 0],
+                ),
+                Err(Error::InvalidOdds)
+            );
+            assert_eq!(
+                betting.create_fixed_odds_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    1,
+                    [200, 190, 0],
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+            assert_eq!(
+                BettingMarket::get_odds(&betting, match_id),
+                Some([200, 190, 0])
+            );
+
+            // The deposit takes the first 5 of the worst-case loss, the bankroll locks the rest
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Draw),
+                Err(Error::OutcomeNotAllowed)
+            );
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // Team1 winning costs 40 for a pool of 30
+            assert_eq!(betting.get_exposure(match_id), 5000000000);
+            assert_eq!(betting.get_bankroll(), (95000000000, 5000000000));
+
+            // The locked funds count in the price of the shares
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(50000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+            assert_eq!(betting.get_lp_shares(accounts.charlie), 50000000000);
+            assert!(betting.solvency_check());
+
+            for _ in 0..12 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            let amount = Self::env().transferred_value();
-            // Create the bet to be placed
-            let bet = Bet {
-                bettor: caller,
-                amount,
-                result: result.clone(),
-            };
-            // Check if the bet already exists
-            if match_to_bet.bets.contains(&bet) {
-                return Err(Error::AlreadyBet);
-            } else {
-                match_to_bet.bets.push(bet);
-                // Store the betting match in the list of open matches
-                self.matches.insert(match_id, &match_to_bet);
-                // Emit an event.
-                self.env().emit_event(BetPlaced {
-                    match_id,
-                    who: caller,
-                    amount,
-                    result,
-                });
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            // The shares can't move until the known loss is settled
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.withdraw_liquidity(100000000000),
+                Err(Error::BankrollSettlementPending)
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.provide_liquidity(),
+                Err(Error::BankrollSettlementPending)
+            );
+            let django_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django,
+                )
+                .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Of the 10 of loss, the deposit paid 5 and the bankroll the other 5
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(django_balance)
+            );
+            assert_eq!(betting.get_bankroll(), (145000000000, 0));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.withdraw_liquidity(100000000001),
+                Err(Error::NotEnoughShares)
+            );
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(betting.withdraw_liquidity(100000000000), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 96666666666)
+            );
+            assert_eq!(betting.get_lp_shares(accounts.bob), 0);
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn bets_above_the_exposure_are_partially_filled() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            set_min_deposit(&mut betting, 5000000000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(50000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000);
+            assert_eq!(
+                betting.create_fixed_odds_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    1,
+                    [200, 150, 0],
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(20000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Team2 winning 1.5x of 150 for a pool of 170 uses the deposit and the bankroll
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(200000000000);
+            assert_eq!(betting.deposit(), Ok(()));
+            assert_eq!(
+                betting.bet_from_balance(match_id, MatchResult::Team2Victory, 200000000000),
+                Ok(())
+            );
+            let bet = &betting.get_match(match_id).unwrap().bets[1];
+            assert_eq!((bet.bettor, bet.amount), (accounts.eve, 150000000000));
+            // Only the filled stake left the internal balance
+            assert_eq!(betting.get_balance(accounts.eve), 50000000000);
+            assert_eq!(betting.get_exposure(match_id), 50000000000);
+            assert_eq!(betting.get_bankroll(), (0, 50000000000));
+            assert_eq!(
+                betting.bet_from_balance(match_id, MatchResult::Team2Victory, 10000000000),
+                Err(Error::ExposureExceeded)
+            );
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn exposure_is_capped_by_the_bankroll() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_max_exposure(10_001), Err(Error::InvalidConfig));
+            assert_eq!(betting.set_max_exposure(2_000), Ok(()));
+            set_min_deposit(&mut betting, 5000000000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(betting.provide_liquidity(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000);
+            assert_eq!(
+                betting.create_fixed_odds_match_to_bet(
+                    "team1".as_bytes().to_vec(),
+                    "team2".as_bytes().to_vec(),
+                    10,
+                    1,
+                    [200, 150, 0],
+                ),
+                Ok(())
+            );
+            let match_id = accounts.django;
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(25000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            assert_eq!(betting.get_exposure(match_id), 20000000000);
+            // 20% of the bankroll is at risk already, beyond the deposit
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(
+                betting.bet(match_id, MatchResult::Team1Victory),
+                Err(Error::ExposureExceeded)
+            );
+            // A bet on the other side lowers the exposure and releases bankroll funds
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            assert_eq!(betting.get_exposure(match_id), 10000000000);
+            assert_eq!(betting.get_bankroll(), (90000000000, 10000000000));
+            assert!(betting.solvency_check());
+
+            for _ in 0..12 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            Ok(())
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Eve won 15 of a pool of 35, the bankroll gets its backing back with 20 of profit
+            assert_eq!(betting.get_bankroll(), (120000000000, 0));
+            assert!(betting.solvency_check());
         }
 
-        /// Set the result of an existing match.
-        /// The dispatch origin for this call must be the owner.
-        /// Get root of the node?? like ensure_root(origin)?;
-        #[ink(message)]
-        pub fn set_result(
-            &mut self,
-            match_id: AccountId,
-            result: MatchResult,
-        ) -> Result<(), Error> {
-            let caller = Self::env().caller();
-            // Only owner of the SC can call this message.
-            if caller != self.owner {
-                return Err(Error::BadOrigin);
+        #[ink::test]
+        fn insurance_covers_a_shortfall_before_the_deposit() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(
+                betting.set_config(Config {
+                    fee_bps: 100,
+                    ..betting.get_config()
+                }),
+                Ok(())
+            );
+            assert_eq!(betting.set_insurance_share(5_000), Ok(()));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000);
+            assert_eq!(betting.top_up_insurance(), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            // Bad debt of 8 in the escrow of the match
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            let balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(contract)
+                    .unwrap();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract,
+                balance - 8000000000,
+            );
+            let escrow = betting.get_escrow(match_id);
+            betting.escrows.insert(match_id, &(escrow - 8000000000));
+            betting.total_escrow -= 8000000000;
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            //Find the match where owner wants to set the result
-            let mut match_to_set_result = match self.matches.take(&match_id) {
-                Some(match_from_storage) => match_from_storage,
-                None => return Err(Error::MatchDoesNotExist),
-            };
-            // Check if start and length are valid
-            let current_block_number = self.env().block_number();
-            if current_block_number <= (match_to_set_result.start + match_to_set_result.length) {
-                return Err(Error::TimeMatchNotOver);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            let django_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django,
+                )
+                .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // The fund covers 5, the deposit the other 3, and half the fee refills the fund
+            assert_eq!(betting.get_insurance(), (100000000, 5000000000));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(django_balance + 997000000000)
+            );
+            assert!(betting.solvency_check());
+        }
+
+        #[ink::test]
+        fn result_can_be_corrected_within_the_window() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_correction_window(5), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            //set the result
-            match_to_set_result.result = Some(result.clone());
-            // Store the betting match in the list of open matches
-            self.matches.insert(match_id, &match_to_set_result);
-            // Emit an event.
-            self.env().emit_event(MatchResultSet { match_id, result });
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            for _ in 0..4 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team2Victory, None),
+                Ok(())
+            );
+            let events = ink::env::test::recorded_events().count();
+            assert_eq!(
+                betting.get_match(match_id).unwrap().result,
+                Some(MatchResult::Team2Victory)
+            );
+            // The window is counted from the first result
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Err(Error::ResultAlreadySet)
+            );
+            assert_eq!(ink::env::test::recorded_events().count(), events);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                ),
+                Ok(100010000000000)
+            );
+        }
+
+        #[ink::test]
+        fn settlement_records_the_final_and_settled_blocks() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_correction_window(10), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            assert_eq!(betting.get_match(match_id).unwrap().final_block, 13);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
 
-            Ok(())
+            // The first payout closes the correction window
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings_chunk(match_id, 1), Ok(false));
+            assert_eq!(betting.get_match(match_id).unwrap().final_block, 5);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team2Victory, None),
+                Err(Error::SettlementInProgress)
+            );
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings_chunk(match_id, 1), Ok(true));
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let settled = emitted_events
+                .iter()
+                .find(|event| {
+                    event.topics.first().map(|topic| &topic[..])
+                        == <crate::betting::MatchSettled as ink::env::Event>::SIGNATURE_TOPIC
+                            .as_ref()
+                            .map(|topic| &topic[..])
+                })
+                .map(|event| {
+                    <crate::betting::MatchSettled as scale::Decode>::decode(&mut &event.data[..])
+                        .unwrap()
+                })
+                .unwrap();
+            assert_eq!(settled.match_id, match_id);
+            assert_eq!(settled.result, MatchResult::Team1Victory);
+            assert_eq!(settled.final_block, 5);
+            assert_eq!(settled.settled_block, 6);
         }
 
-        /// When a match ends the owner of the match can distribute funds to the winners and delete the match.
-        #[ink(message)]
-        pub fn distribute_winnings(&mut self) -> Result<(), Error> {
-            let caller = Self::env().caller();
-            // Get the match that user wants to close, deleting it
-            let mut match_to_delete = match self.matches.take(&caller) {
-                Some(match_from_storage) => match_from_storage,
-                None => return Err(Error::MatchDoesNotExist),
-            };
-            // Make sure the match has a result set already
-            if !match_to_delete.result.is_some() {
-                return Err(Error::MatchNotResult);
-            }
-            // Hash the match object and delete it from the hash mapping.
-            let match_hash = Self::get_match_hash(&match_to_delete);
-            self.matches_hashes.take(&match_hash);
-            // Iterate over all bets to get the winners accounts
-            let mut total_winners: Balance = 0u32.into();
-            let mut total_bet: Balance = 0u32.into();
-            let mut winners = Vec::new();
-            for bet in match_to_delete.bets.iter_mut() {
-                total_bet += bet.amount;
-                if Some(bet.result) == match_to_delete.result {
-                    total_winners += bet.amount;
-                    winners.push(bet)
-                }
+        #[ink::test]
+        fn free_bet_pays_only_the_profit() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+            // A late settlement funds the treasury
+            create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for _ in 0..3 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            // Distribute funds
-            for winner_bet in &winners {
-                let weighted = winner_bet.amount / (total_winners / 100);
-                let amount_won = weighted * (total_bet / 100);
-                self.env()
-                    .transfer(winner_bet.bettor, amount_won)
-                    .map_err(|_| Error::TransferFailed)?;
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(accounts.django, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            for _ in 0..15 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            // Return deposit
-            self.env()
-                .transfer(caller, match_to_delete.deposit)
-                .map_err(|_| Error::TransferFailed)?;
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.get_treasury(), 10000000000);
 
-            Ok(())
-        }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.grant_free_bet(accounts.bob, 20000000000),
+                Err(Error::NotEnoughTreasury)
+            );
+            assert_eq!(betting.grant_free_bet(accounts.bob, 10000000000), Ok(()));
+            assert_eq!(betting.get_treasury(), 0);
+            assert_eq!(betting.get_free_bet(accounts.bob), 10000000000);
 
-        /// Simply checks if a match exists.
-        #[ink(message)]
-        pub fn exists_match(&self, owner: AccountId) -> bool {
-            self.matches.contains(owner)
-        }
-        #[ink(message)]
-        pub fn get_match(&self, owner: AccountId) -> Option<Match> {
-            self.matches.get(owner)
-        }
+            let match_id = create_match(
+                &mut betting,
+                accounts.eve,
+                "team3",
+                "team4",
+                25,
+                1,
+                1000000000000,
+            );
+            // The credits of Bob are bet with the transferred value
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(5000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            assert_eq!(betting.get_free_bet(accounts.bob), 0);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(99995000000000)
+            );
+            assert_eq!(
+                betting.get_match(match_id).unwrap().bets[0].credit,
+                10000000000
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(15000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            assert!(betting.solvency_check());
 
-        pub fn get_match_hash(betting_match: &Match) -> [u8; 32] {
-            let entropy = (
-                &betting_match.team1,
-                &betting_match.team2,
-                betting_match.start,
-                betting_match.length,
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
             );
-            let mut message =
-                <ink::env::hash::Sha2x256 as ink::env::hash::HashOutput>::Type::default();
-            ink::env::hash_encoded::<ink::env::hash::Sha2x256, _>(&entropy, &mut message);
-            message
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Bob won the pool of 30 but the 10 of credits
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100015000000000)
+            );
+            assert_eq!(betting.get_treasury(), 10000000000);
+            assert!(betting.solvency_check());
+
+            // Frank bets the credits without any funds
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.grant_free_bet(accounts.frank, 10000000000), Ok(()));
+            let match_id = create_match(
+                &mut betting,
+                accounts.eve,
+                "team5",
+                "team6",
+                60,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(betting.bet(match_id, MatchResult::Draw), Ok(()));
+            let bet = &betting.get_match(match_id).unwrap().bets[0];
+            assert_eq!((bet.amount, bet.credit), (10000000000, 10000000000));
+            assert_eq!(betting.get_free_bet(accounts.frank), 0);
+            assert!(betting.solvency_check());
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        use crate::betting::{Bet, Betting, Error, MatchResult};
-        use ink::primitives::AccountId;
+        #[ink::test]
+        fn distribute_winnings_in_chunks() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
 
-        fn set_accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
-            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.alice,
-                100000000000000,
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
             );
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.bob,
-                100000000000000,
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
             );
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.charlie,
-                100000000000000,
+            //Django pays bob first
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings_chunk(match_id, 1), Ok(false));
+            assert!(betting.exists_match(match_id));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(105000000000000)
             );
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.django,
-                100000000000000,
+            //The payouts are kept for the next chunks
+            let settlement = betting.settlements.get(match_id).unwrap();
+            assert_eq!(settlement.paid, 30000000000000);
+            assert_eq!(settlement.made, 15000000000000);
+            assert_eq!(
+                betting
+                    .settlement_payouts
+                    .get(match_id)
+                    .map(|payouts| payouts.len()),
+                Some(2)
             );
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.eve,
-                100000000000000,
+            //The result can't change until the match is closed
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team2Victory, None),
+                Err(Error::SettlementInProgress)
+            );
+            //Then eve, closing the match
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings_chunk(match_id, 1), Ok(true));
+            assert!(!betting.exists_match(match_id));
+            assert!(betting.settlement_payouts.get(match_id).is_none());
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(105000000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(100000000000000)
             );
-            accounts
         }
 
-        fn create_contract(who: AccountId) -> Betting {
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(who);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
-            let betting = Betting::new();
-            betting
+        #[ink::test]
+        fn late_settlement_slashes_deposit_to_winners() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            // 10 blocks late after the grace period
+            for _ in 0..15 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //bob gets the 1% slashed
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(100010000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(99990000000000)
+            );
         }
 
-        fn create_match(
-            betting: &mut Betting,
-            who: AccountId,
-            t1: &str,
-            t2: &str,
-            start: u32,
-            length: u32,
-            deposit: u128,
-        ) -> AccountId {
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(who);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(deposit);
-            // ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(deposit);
-            // Dispatch a signed extrinsic.
+        #[ink::test]
+        fn late_settlement_slashes_deposit_to_treasury_without_winners() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            for _ in 0..15 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.get_treasury(), 10000000000);
+
+            //Only alice can withdraw it
+            assert_eq!(
+                betting.withdraw_treasury(10000000000),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.create_match_to_bet(
-                    t1.as_bytes().to_vec(),
-                    t2.as_bytes().to_vec(),
-                    start,
-                    length
-                ),
-                Ok(())
+                betting.withdraw_treasury(20000000000),
+                Err(Error::NotEnoughTreasury)
             );
-            who
-        }
-
-        /// We test if the default constructor does its job.
-        #[ink::test]
-        fn constructor_works() {
-            let accounts = set_accounts();
-            let betting = create_contract(accounts.alice);
-            assert_eq!(betting.exists_match(accounts.alice), false);
+            assert_eq!(betting.withdraw_treasury(10000000000), Ok(()));
+            assert_eq!(betting.get_treasury(), 0);
         }
 
         #[ink::test]
-        fn create_match_to_bet_works() {
+        fn treasury_withdrawal_leaves_deferred_payouts_covered() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
-
-            assert_eq!(betting.exists_match(accounts.alice), false);
-
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
             let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
-                10,
-                10,
+                1,
+                1,
                 1000000000000,
             );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            for _ in 0..15 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            let treasury = betting.get_treasury();
+            assert_eq!(treasury, 10000000000);
 
-            assert_eq!(betting.exists_match(match_id), true);
+            // A payout to bob failed, leaving the balance beyond the treasury owed to him
+            let (balance, _, _) = betting.solvency();
+            let claim = balance - treasury + 1;
+            assert_eq!(betting.defer_payment(accounts.bob, claim), Ok(()));
+            assert_eq!(betting.solvency().1, claim);
+            assert!(betting.solvency_check());
 
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(1, emitted_events.len());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.withdraw_treasury(treasury), Err(Error::Insolvent));
+            assert_eq!(betting.get_treasury(), treasury);
+            assert_eq!(betting.withdraw_treasury(treasury - 1), Ok(()));
+            assert!(betting.solvency_check());
         }
 
         #[ink::test]
-        fn not_enough_deposit_when_create_match_to_bet() {
+        fn proposed_result_confirmed_by_owner() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
 
-            assert_eq!(betting.exists_match(accounts.alice), false);
+            //Django creates the match
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
 
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //Only django can propose the result
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team1Victory),
+                Err(Error::NotCreator)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team1Victory),
+                Ok(())
+            );
+            //Not distributable until confirmed
+            assert_eq!(betting.distribute_winnings(), Err(Error::MatchNotResult));
+            //The owner can't bypass the proposal
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1);
-
             assert_eq!(
-                betting.create_match_to_bet(
-                    "team1".as_bytes().to_vec(),
-                    "team2".as_bytes().to_vec(),
-                    10,
-                    10
-                ),
-                Err(Error::NotEnoughDeposit)
+                betting.set_result(match_id, MatchResult::Team2Victory, None),
+                Err(Error::ResultAlreadyProposed)
             );
-            assert_eq!(betting.exists_match(accounts.alice), false);
+            assert_eq!(
+                betting.set_score(match_id, 0, 1),
+                Err(Error::ResultAlreadyProposed)
+            );
+            assert_eq!(betting.set_settlement_grace_period(5), Ok(()));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(betting.confirm_result(match_id), Ok(()));
+            //The grace period starts with the confirmation
+            let betting_match = betting.matches.get(match_id).unwrap();
+            assert_eq!(
+                betting_match.result_block,
+                ink::env::block_number::<ink::env::DefaultEnvironment>()
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.settle_match(match_id),
+                Err(Error::SettlementGracePeriodNotOver)
+            );
+            //Django settles in time, without a slash
+            assert_eq!(betting.late_settlement_slash(&betting_match), Ok(0));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert!(!ink::env::test::recorded_events().any(|event| {
+                event.topics.first().map(|topic| &topic[..])
+                    == <crate::betting::DepositSlashed as ink::env::Event>::SIGNATURE_TOPIC
+                        .as_ref()
+                        .map(|topic| &topic[..])
+            }));
         }
 
         #[ink::test]
-        fn match_exist_when_create_match_to_bet() {
+        fn proposed_result_final_after_timeout() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_result_confirmation_timeout(5), Ok(()));
 
-            assert_eq!(betting.exists_match(accounts.alice), false);
-
-            create_match(
+            let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
-                10,
-                10,
+                1,
+                1,
                 1000000000000,
             );
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Upcoming));
 
-            assert_eq!(betting.exists_match(accounts.alice), true);
-
-            //Try to added it again
-            assert_eq!(
-                betting.create_match_to_bet(
-                    "team1".as_bytes().to_vec(),
-                    "team2".as_bytes().to_vec(),
-                    10,
-                    10
-                ),
-                Err(Error::OriginHasAlreadyOpenMatch)
-            );
-        }
-
-        #[ink::test]
-        fn error_creating_a_match_with_an_open_match() {
             // Advance 3 blocks
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                betting.get_status(match_id),
+                Some(MatchStatus::BettingClosed)
+            );
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-
-            let accounts = set_accounts();
-            let mut betting = create_contract(accounts.alice);
-
-            assert_eq!(betting.exists_match(accounts.alice), false);
-
+            assert_eq!(
+                betting.get_status(match_id),
+                Some(MatchStatus::AwaitingResult)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(betting.propose_result(match_id, MatchResult::Draw), Ok(()));
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Disputed));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(200000000000);
+            assert_eq!(
+                betting.challenge_result(match_id, MatchResult::Team2Victory),
+                Ok(())
+            );
+            let django_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django,
+                )
+                .unwrap();
+            //Alice rejects the answer of bob, whose bond goes to django, who proposes again
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
-
+            assert_eq!(betting.reject_result(match_id), Ok(()));
             assert_eq!(
-                betting.create_match_to_bet(
-                    "team1".as_bytes().to_vec(),
-                    "team2".as_bytes().to_vec(),
-                    1,
-                    1
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
                 ),
-                Err(Error::TimeMatchOver)
+                Ok(django_balance + 300000000000)
             );
-            assert_eq!(betting.exists_match(accounts.alice), false);
+            assert!(betting.get_challenges(match_id).is_empty());
+            assert_eq!(betting.get_escrow(match_id), 1000000000000);
+            assert_eq!(
+                betting.get_status(match_id),
+                Some(MatchStatus::AwaitingResult)
+            );
+            assert_eq!(
+                betting.confirm_result(match_id),
+                Err(Error::NoResultProposed)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(100000000000);
+            assert_eq!(
+                betting.propose_result(match_id, MatchResult::Team1Victory),
+                Ok(())
+            );
+            assert_eq!(
+                betting.finalize_result(match_id),
+                Err(Error::MatchNotResult)
+            );
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            //Unchallenged, it can't be rejected anymore
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Settled));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.reject_result(match_id), Err(Error::ResultFinal));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // The deleted match stays settled
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Settled));
         }
+
         #[ink::test]
-        fn error_creating_two_equal_matches() {
+        fn voided_matches_are_cancelled() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.get_status(accounts.django), None);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
+            );
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Upcoming));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.void_match(match_id, 1), Ok(()));
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Cancelled));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Cancelled));
 
-            assert_eq!(betting.exists_match(accounts.alice), false);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
-            assert_eq!(
-                betting.create_match_to_bet(
-                    "team1".as_bytes().to_vec(),
-                    "team2".as_bytes().to_vec(),
-                    1,
-                    1
-                ),
-                Ok(())
+            // A new match of the creator starts over
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                10,
+                1000000000000,
             );
+            assert_eq!(betting.get_status(match_id), Some(MatchStatus::Upcoming));
+        }
 
-            //Bob to create same match as alice just created
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(1000000000000);
+        fn sign_result(betting: &Betting, match_id: AccountId, result: MatchResult) -> [u8; 65] {
+            let secp = secp256k1::Secp256k1::new();
+            let secret_key = secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+            let message_hash = betting.result_payload_hash(match_id, result).unwrap();
+            let message = secp256k1::Message::from_digest_slice(&message_hash).unwrap();
+            let (recovery_id, compact) = secp
+                .sign_ecdsa_recoverable(&message, &secret_key)
+                .serialize_compact();
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
 
-            assert_eq!(
-                betting.create_match_to_bet(
-                    "team1".as_bytes().to_vec(),
-                    "team2".as_bytes().to_vec(),
-                    1,
-                    1
-                ),
-                Err(Error::MatchAlreadyExists)
-            );
+        /// Signs a bet with the key of `bettor_key_account`.
+        fn sign_bet(
+            betting: &Betting,
+            match_id: AccountId,
+            result: MatchResult,
+            amount: u128,
+            nonce: u64,
+        ) -> [u8; 65] {
+            let secp = secp256k1::Secp256k1::new();
+            let secret_key = secp256k1::SecretKey::from_slice(&[9u8; 32]).unwrap();
+            let message_hash = betting.bet_payload_hash(match_id, result, amount, nonce);
+            let message = secp256k1::Message::from_digest_slice(&message_hash).unwrap();
+            let (recovery_id, compact) = secp
+                .sign_ecdsa_recoverable(&message, &secret_key)
+                .serialize_compact();
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        fn bettor_key_account() -> AccountId {
+            let secp = secp256k1::Secp256k1::new();
+            let secret_key = secp256k1::SecretKey::from_slice(&[9u8; 32]).unwrap();
+            let key = secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize();
+            let mut account = [0u8; 32];
+            ink::env::hash_bytes::<ink::env::hash::Blake2x256>(&key, &mut account);
+            AccountId::from(account)
         }
 
         #[ink::test]
-        fn bet_works() {
+        fn bet_with_signature_works() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
-
             let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
                 10,
                 10,
                 1000000000000,
             );
+            // The bettor funds their internal balance
+            let bettor = bettor_key_account();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                bettor,
+                100000000000000,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000);
+            assert_eq!(betting.deposit(), Ok(()));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
 
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
-            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
-
-            let bet = Bet {
-                bettor: accounts.bob,
-                amount: 10000000000,
-                result: MatchResult::Team1Victory,
-            };
+            //Eve relays the signed bet
+            let signature = sign_bet(
+                &betting,
+                match_id,
+                MatchResult::Team1Victory,
+                10000000000,
+                0,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            //The signature does not cover another amount
             assert_eq!(
-                betting.get_match(match_id).unwrap().bets.contains(&bet),
-                true
+                betting.bet_with_signature(
+                    match_id,
+                    MatchResult::Team1Victory,
+                    20000000000,
+                    0,
+                    signature
+                ),
+                Err(Error::NotEnoughBalance)
+            );
+            assert_eq!(
+                betting.bet_with_signature(
+                    match_id,
+                    MatchResult::Team1Victory,
+                    10000000000,
+                    0,
+                    signature
+                ),
+                Ok(())
             );
+            let bet = &betting.get_match(match_id).unwrap().bets[0];
+            assert_eq!((bet.bettor, bet.amount), (bettor, 10000000000));
+            assert_eq!(betting.get_balance(bettor), 20000000000);
+            assert_eq!(betting.get_bet_nonce(bettor), 1);
+            //The signed bet can't be replayed
+            assert_eq!(
+                betting.bet_with_signature(
+                    match_id,
+                    MatchResult::Team1Victory,
+                    10000000000,
+                    0,
+                    signature
+                ),
+                Err(Error::InvalidNonce)
+            );
+        }
 
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(2, emitted_events.len());
+        fn oracle_key() -> [u8; 33] {
+            let secp = secp256k1::Secp256k1::new();
+            let secret_key = secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+            secp256k1::PublicKey::from_secret_key(&secp, &secret_key).serialize()
         }
 
         #[ink::test]
-        fn bet_error_match_not_exist() {
+        fn submit_signed_result_works() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
 
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            let signature = sign_result(&betting, match_id, MatchResult::Team2Victory);
+
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            //Eve relays the result, the key is not registered yet
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
             assert_eq!(
-                betting.bet(accounts.alice, MatchResult::Team1Victory),
-                Err(Error::MatchDoesNotExist)
+                betting.submit_signed_result(match_id, MatchResult::Team2Victory, signature),
+                Err(Error::InvalidSignature)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.add_oracle_key(oracle_key()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            //The signature does not cover another result
+            assert_eq!(
+                betting.submit_signed_result(match_id, MatchResult::Team1Victory, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                betting.submit_signed_result(match_id, MatchResult::Team2Victory, signature),
+                Ok(())
             );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
         }
 
         #[ink::test]
-        fn bet_error_match_has_start() {
+        fn pull_result_needs_oracle_and_fixture() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
 
-            create_match(
+            let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
                 1,
-                10,
+                1,
                 1000000000000,
             );
-            // Advance 2 blocks
+            // Advance 3 blocks
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(betting.pull_result(match_id), Err(Error::NoResultOracle));
             assert_eq!(
-                betting.bet(accounts.alice, MatchResult::Team1Victory),
-                Err(Error::MatchHasStarted)
+                betting.set_result_oracle(Some(accounts.frank)),
+                Err(Error::BadOrigin)
             );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_result_oracle(Some(accounts.frank)), Ok(()));
+            assert_eq!(betting.pull_result(match_id), Err(Error::NoFixture));
         }
 
         #[ink::test]
-        fn bet_error_duplicate_bet() {
+        fn transferred_bet_pays_new_owner() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
 
             let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
-                10,
-                10,
+                1,
+                1,
                 1000000000000,
             );
-
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
             assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            //Charlie can't transfer the bet of bob
+            assert_eq!(
+                betting.transfer_bet(match_id, 0, accounts.charlie),
+                Err(Error::NotBettor)
+            );
+            assert_eq!(
+                betting.transfer_bet(match_id, 2, accounts.charlie),
+                Err(Error::BetDoesNotExist)
+            );
+            // Not to a blacklisted or self-excluded account, nor while paused
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.blacklist(accounts.frank), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(betting.self_exclude(100), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.transfer_bet(match_id, 0, accounts.frank),
+                Err(Error::AccountBlacklisted)
+            );
+            assert_eq!(
+                betting.transfer_bet(match_id, 0, accounts.charlie),
+                Err(Error::SelfExcluded)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.pause(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.transfer_bet(match_id, 0, accounts.eve),
+                Err(Error::ContractPaused)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.unpause(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.transfer_bet(match_id, 0, accounts.eve), Ok(()));
 
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.bet(match_id, MatchResult::Team1Victory),
-                Err(Error::AlreadyBet)
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            //Not once the match has a result
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                betting.transfer_bet(match_id, 0, accounts.bob),
+                Err(Error::ResultAlreadySet)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //eve holds the winning bet
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(120000000000000)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(90000000000000)
             );
         }
 
         #[ink::test]
-        fn set_result_works() {
+        fn cashout_works() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
 
+            //Django creates the match
             let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
                 1,
                 1,
                 1000000000000,
             );
+            // Bob bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // Charlie bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
+            // Eve bets
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
 
-            assert_eq!(betting.exists_match(match_id), true);
+            //Bob cashes out 12.5 * 0.75 minus 5%
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.cashout(match_id, 0), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(98906250000000)
+            );
+            assert_eq!(betting.get_treasury(), 468750000000);
+            assert_eq!(betting.cashout(match_id, 1), Err(Error::NotBettor));
+            assert_eq!(betting.cashout(match_id, 0), Err(Error::BetDoesNotExist));
+            // The ids of the remaining bets are kept
+            let bets = betting.get_match(match_id).unwrap().bets;
+            assert_eq!(bets.iter().map(|bet| bet.id).collect::<Vec<_>>(), [1, 2]);
 
             // Advance 3 blocks
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.set_result(match_id, MatchResult::Team1Victory),
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
                 Ok(())
             );
-
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(2, emitted_events.len());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //eve has 70 + 40 + 0.625 left in the pot by bob
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(110625000000000)
+            );
         }
+
         #[ink::test]
-        fn set_result_bad_origin() {
+        fn cashout_needs_another_backer_of_the_result() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
-
             let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
                 1,
                 1,
                 1000000000000,
             );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
 
-            // Advance 3 blocks
-            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            //set Bob as the caller
+            // Bob alone backs his result, his bet would be sold for nothing
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let bob_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(betting.cashout(match_id, 0), Err(Error::NoCashoutLiquidity));
             assert_eq!(
-                betting.set_result(match_id, MatchResult::Team1Victory),
-                Err(Error::BadOrigin)
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance)
             );
+            assert_eq!(betting.get_match(match_id).unwrap().bets.len(), 2);
+
+            // Once eve backs it too, the rest of the pool prices it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.cashout(match_id, 0), Ok(()));
         }
+
         #[ink::test]
-        fn set_result_match_not_exist() {
+        fn referrer_and_creator_earn_shares_of_fees() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(1000), Ok(()));
+            assert_eq!(
+                betting.set_creator_fee_share(10_001),
+                Err(Error::InvalidConfig)
+            );
+            assert_eq!(betting.set_creator_fee_share(5_000), Ok(()));
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            // Bob bets, referred by eve
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(
+                betting.bet_with_referrer(match_id, MatchResult::Team1Victory, accounts.eve),
+                Ok(())
+            );
+            assert_eq!(betting.get_referrer(accounts.bob), Some(accounts.eve));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+            assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
 
             // Advance 3 blocks
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.set_result(accounts.alice, MatchResult::Team1Victory),
-                Err(Error::MatchDoesNotExist)
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //bob wins 20 minus 10% of fee, eve earns 20% of the fee and django half the rest
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(108000000000000)
+            );
+            assert_eq!(betting.get_accrued_fees(accounts.eve), 400000000000);
+            assert_eq!(betting.get_accrued_fees(accounts.django), 800000000000);
+            assert_eq!(betting.get_treasury(), 800000000000);
+            assert_eq!(
+                betting.get_termination_blockers().accrued_fees,
+                1200000000000
+            );
+            let django_balance =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django,
+                )
+                .unwrap();
+            assert_eq!(betting.claim_fees(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.django
+                ),
+                Ok(django_balance + 800000000000)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.claim_fees(), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(100400000000000)
             );
+            assert_eq!(betting.claim_fees(), Err(Error::NothingToClaim));
         }
+
         #[ink::test]
-        fn set_result_match_not_finished() {
+        fn stats_count_matches_bets_payouts_and_fees() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(1000), Ok(()));
+            assert_eq!(betting.get_stats().matches_created, 0);
             let match_id = create_match(
                 &mut betting,
-                accounts.alice,
+                accounts.django,
                 "team1",
                 "team2",
-                10,
-                10,
+                1,
+                1,
                 1000000000000,
             );
+            for (bettor, result) in [
+                (accounts.bob, MatchResult::Team1Victory),
+                (accounts.charlie, MatchResult::Team2Victory),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            let stats = betting.get_stats();
+            assert_eq!(stats.matches_created, 1);
+            assert_eq!(stats.bets, 2);
+            assert_eq!(stats.volume, 20000000000000);
+            assert_eq!(stats.paid_out, 0);
 
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.set_result(match_id, MatchResult::Team1Victory),
-                Err(Error::TimeMatchNotOver)
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // Bob wins the pool of 20 minus the fee of 2
+            let stats = betting.get_stats();
+            assert_eq!(stats.matches_created, 1);
+            assert_eq!(stats.bets, 2);
+            assert_eq!(stats.paid_out, 18000000000000);
+            assert_eq!(stats.fees, 2000000000000);
+
+            // The counters are cumulative
+            create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                10,
+                1,
+                1000000000000,
             );
+            assert_eq!(betting.get_stats().matches_created, 2);
         }
 
         #[ink::test]
-        fn distribute_winnings_works() {
+        fn referral_rewards_are_claimed_with_the_former_messages() {
+            use ink::reflect::DispatchableMessageInfo;
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(1000), Ok(()));
+            assert_eq!(
+                <Betting as DispatchableMessageInfo<
+                    { ink::selector_id!("claim_referral_rewards") },
+                >>::SELECTOR,
+                ink::selector_bytes!("claim_referral_rewards")
+            );
+            assert_eq!(
+                <Betting as DispatchableMessageInfo<
+                    { ink::selector_id!("get_referral_rewards") },
+                >>::SELECTOR,
+                ink::selector_bytes!("get_referral_rewards")
+            );
 
-            //Django creates the match
             let match_id = create_match(
                 &mut betting,
                 accounts.django,
@@ -740,59 +19117,108 @@ mod betting {
                 1,
                 1000000000000,
             );
-
-            assert_eq!(betting.exists_match(match_id), true);
-            // Bob bets
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
             ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
-            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
-            // Charlie bets
+            assert_eq!(
+                betting.bet_with_referrer(match_id, MatchResult::Team1Victory, accounts.eve),
+                Ok(())
+            );
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
             ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
             assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
-            // Eve bets
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
-            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
-
-            // Advance 3 blocks
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            //Alice set the result
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.set_result(match_id, MatchResult::Team1Victory),
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
                 Ok(())
             );
-            //Django distributes the winnings
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
             assert_eq!(betting.distribute_winnings(), Ok(()));
-            //bob has 90 + 12.5 (winner)
+
+            //eve earns 20% of the fee of 2
+            assert_eq!(betting.get_referral_rewards(accounts.eve), 400000000000);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(betting.claim_referral_rewards(), Ok(()));
             assert_eq!(
-                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
-                Ok(102500000000000)
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+                Ok(100400000000000)
             );
-            //charlie has 90 (loser)
+            let claimed = emitted::<crate::betting::ReferralRewardsClaimed>();
+            assert_eq!(claimed.len(), 1);
             assert_eq!(
-                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
-                    accounts.charlie
-                ),
-                Ok(90000000000000)
+                (claimed[0].who, claimed[0].amount),
+                (accounts.eve, 400000000000)
             );
-            //eve has 90 + 37.5 (winner)
+            assert_eq!(betting.get_referral_rewards(accounts.eve), 0);
+            assert_eq!(betting.claim_referral_rewards(), Err(Error::NothingToClaim));
+        }
+
+        #[ink::test]
+        fn protocol_fees_accrue_to_the_fee_router() {
+            let accounts = set_accounts();
+            let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(1000), Ok(()));
+            assert_eq!(betting.forward_fees(), Err(Error::NoFeeRouter));
+            assert_eq!(betting.set_fee_router(Some(accounts.frank)), Ok(()));
+            assert_eq!(betting.forward_fees(), Err(Error::NothingToClaim));
+
+            let match_id = create_match(
+                &mut betting,
+                accounts.django,
+                "team1",
+                "team2",
+                1,
+                1,
+                1000000000000,
+            );
+            for (bettor, result) in [
+                (accounts.bob, MatchResult::Team1Victory),
+                (accounts.charlie, MatchResult::Team2Victory),
+            ] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bettor);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
+                assert_eq!(betting.bet(match_id, result), Ok(()));
+            }
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
-                Ok(107500000000000)
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
             );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+
+            // The fee accrues to the router instead of being pushed at settlement
+            assert_eq!(betting.get_accrued_fees(accounts.frank), 2000000000000);
+            assert_eq!(betting.get_treasury(), 0);
+            assert!(betting.solvency_check());
+
+            // Fees the former router didn't get go to the treasury
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_fee_router(None), Ok(()));
+            assert_eq!(betting.get_accrued_fees(accounts.frank), 0);
+            assert_eq!(betting.get_treasury(), 2000000000000);
+            assert_eq!(betting.get_termination_blockers().accrued_fees, 0);
         }
 
         #[ink::test]
-        fn distribute_winnings_match_not_exist() {
+        fn referral_share_is_at_most_the_whole_fee() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(1000), Ok(()));
+            assert_eq!(
+                betting.set_referral_share(10_001),
+                Err(Error::InvalidConfig)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(betting.set_referral_share(5_000), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_referral_share(5_000), Ok(()));
 
-            //Django creates the match
             let match_id = create_match(
                 &mut betting,
                 accounts.django,
@@ -802,42 +19228,64 @@ mod betting {
                 1,
                 1000000000000,
             );
-
-            assert_eq!(betting.exists_match(match_id), true);
-            // Bob bets
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
             ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
-            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
-            // Charlie bets
+            assert_eq!(
+                betting.bet_with_referrer(match_id, MatchResult::Team1Victory, accounts.eve),
+                Ok(())
+            );
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
             ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
             assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
-            // Eve bets
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
-            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
 
-            // Advance 3 blocks
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            //Alice set the result
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                betting.set_result(match_id, MatchResult::Team1Victory),
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
                 Ok(())
             );
-
-            //alice distribute winner doesn't exists
-            assert_eq!(betting.distribute_winnings(), Err(Error::MatchDoesNotExist));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            //eve earns half of the fee of 2
+            assert_eq!(betting.get_accrued_fees(accounts.eve), 1000000000000);
         }
 
         #[ink::test]
-        fn distribute_winnings_match_not_result_yet() {
+        fn creator_fee_share_steps_up_with_the_pool_milestones() {
             let accounts = set_accounts();
             let mut betting = create_contract(accounts.alice);
+            assert_eq!(betting.set_protocol_fee(1000), Ok(()));
+            assert_eq!(betting.set_creator_fee_share(1_000), Ok(()));
+            let milestone = |min_pool, share_bps| CreatorMilestone {
+                min_pool,
+                share_bps,
+            };
+            for milestones in [
+                vec![milestone(1, 100); 5],
+                vec![milestone(1, 10_001)],
+                vec![milestone(2, 100), milestone(1, 200)],
+                vec![milestone(1, 200), milestone(2, 100)],
+            ] {
+                assert_eq!(
+                    betting.set_creator_milestones(milestones),
+                    Err(Error::InvalidConfig)
+                );
+            }
+            let milestones = vec![
+                milestone(15000000000000, 3_000),
+                milestone(30000000000000, 5_000),
+            ];
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                betting.set_creator_milestones(milestones.clone()),
+                Err(Error::BadOrigin)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(betting.set_creator_milestones(milestones.clone()), Ok(()));
+            assert_eq!(betting.get_creator_milestones(), milestones);
 
-            //Django creates the match
             let match_id = create_match(
                 &mut betting,
                 accounts.django,
@@ -847,47 +19295,182 @@ mod betting {
                 1,
                 1000000000000,
             );
-
-            assert_eq!(betting.exists_match(match_id), true);
-            // Bob bets
+            assert_eq!(betting.get_creator_fee_share(match_id), Some(1_000));
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
             ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
             assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
-            // Charlie bets
+            assert_eq!(betting.get_creator_fee_share(match_id), Some(1_000));
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
             ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(10000000000000);
             assert_eq!(betting.bet(match_id, MatchResult::Team2Victory), Ok(()));
-            // Eve bets
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(30000000000000);
-            assert_eq!(betting.bet(match_id, MatchResult::Team1Victory), Ok(()));
+            // The pool of 20 crossed the first milestone
+            assert_eq!(betting.get_creator_fee_share(match_id), Some(3_000));
 
-            //Django distributes the winnings
+            // Advance 3 blocks
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                betting.set_result(match_id, MatchResult::Team1Victory, None),
+                Ok(())
+            );
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
-            assert_eq!(betting.distribute_winnings(), Err(Error::MatchNotResult));
+            assert_eq!(betting.distribute_winnings(), Ok(()));
+            // The fee of 2 is shared 30% to django and 70% to the treasury
+            assert_eq!(betting.get_accrued_fees(accounts.django), 600000000000);
+            assert_eq!(betting.get_treasury(), 1400000000000);
+            assert_eq!(betting.get_creator_fee_share(match_id), None);
+        }
+    }
+    /// Property-based tests of the payouts, on random bets and results.
+    #[cfg(test)]
+    #[allow(clippy::arithmetic_side_effects)]
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Stakes large enough to overflow products of `Balance`, small enough for
+        /// their sum not to.
+        const MAX_STAKE: Balance = Balance::MAX >> 8;
+
+        fn outcome() -> impl Strategy<Value = MatchResult> {
+            prop_oneof![
+                Just(MatchResult::Team1Victory),
+                Just(MatchResult::Team2Victory),
+                Just(MatchResult::Draw),
+            ]
+        }
+
+        fn bets(outcome: impl Strategy<Value = MatchResult>) -> impl Strategy<Value = Vec<Bet>> {
+            prop::collection::vec(
+                (
+                    prop_oneof![1..1_000_000_000_000_000u128, 1..=MAX_STAKE],
+                    outcome,
+                    prop_oneof![Just(FULL_BET_WEIGHT_BPS), 1..=FULL_BET_WEIGHT_BPS],
+                ),
+                0..32,
+            )
+            .prop_map(|bets| {
+                bets.into_iter()
+                    .enumerate()
+                    .map(|(id, (amount, result, weight_bps))| Bet {
+                        id: id as BetId,
+                        bettor: AccountId::from([id as u8; 32]),
+                        amount,
+                        result,
+                        credit: 0,
+                        placed_by: None,
+                        weight_bps,
+                    })
+                    .collect()
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn winners_share_at_most_the_pool(
+                bets in bets(outcome()),
+                result in outcome(),
+                pot_bonus in 0..1_000_000_000_000_000u128,
+            ) {
+                let payouts = Betting::winner_payouts(&bets, result, pot_bonus).unwrap();
+                let pool: Balance = bets.iter().map(|bet| bet.amount).sum::<Balance>() + pot_bonus;
+                let paid: Balance = payouts.iter().map(|payout| payout.won + payout.refunded).sum();
+                prop_assert!(paid <= pool);
+                // Every winner gets at least the stake back
+                let winners: Vec<&Bet> = bets.iter().filter(|bet| bet.result == result).collect();
+                prop_assert_eq!(payouts.len(), winners.len());
+                for (payout, bet) in payouts.iter().zip(winners) {
+                    prop_assert_eq!(payout.bettor, bet.bettor);
+                    prop_assert!(payout.won >= bet.amount);
+                }
+            }
+
+            #[test]
+            fn handicap_payouts_stay_within_the_pool(
+                bets in bets(prop_oneof![
+                    Just(MatchResult::Team1Victory),
+                    Just(MatchResult::Team2Victory),
+                ]),
+                line in -20..20i32,
+                score in (0..15u32, 0..15u32),
+            ) {
+                let payouts = Betting::handicap_payouts(&bets, line, score).unwrap();
+                let pool: Balance = bets.iter().map(|bet| bet.amount).sum();
+                let paid: Balance = payouts.iter().map(|payout| payout.won + payout.refunded).sum();
+                prop_assert!(paid <= pool);
+                // The won and pushed portions of a stake are always given back
+                let kept: Vec<(Balance, Balance)> = bets
+                    .iter()
+                    .map(|bet| Betting::handicap_split(line, bet.result, score, bet.amount))
+                    .map(|(won, pushed, _)| (won, pushed))
+                    .filter(|(won, pushed)| won + pushed > 0)
+                    .collect();
+                prop_assert_eq!(payouts.len(), kept.len());
+                for (payout, (won, pushed)) in payouts.iter().zip(kept) {
+                    prop_assert!(payout.won >= won);
+                    prop_assert_eq!(payout.refunded, pushed);
+                }
+            }
+
+            #[test]
+            fn mul_div_is_exact(
+                amount in any::<Balance>(),
+                numerator in any::<Balance>(),
+                denominator in 1..=Balance::MAX,
+            ) {
+                let result = Betting::mul_div(amount, numerator, denominator);
+                if let Some(product) = amount.checked_mul(numerator) {
+                    prop_assert_eq!(result, Some(product / denominator));
+                }
+                // A share of at most the whole never overflows
+                if amount <= denominator {
+                    prop_assert!(result.is_some_and(|result| result <= numerator));
+                }
+            }
+        }
+
+        #[test]
+        fn mul_div_of_extreme_amounts() {
+            assert_eq!(
+                Betting::mul_div(Balance::MAX, Balance::MAX, Balance::MAX),
+                Some(Balance::MAX)
+            );
+            assert_eq!(Betting::mul_div(Balance::MAX, 3, 2), None);
+            assert_eq!(
+                Betting::mul_div(1 << 100, 3 << 100, 1 << 101),
+                Some(3 << 99)
+            );
+            assert_eq!(Betting::mul_div(1, 1, 0), None);
         }
     }
+
     /// This is how you'd write end-to-end (E2E) or integration tests for ink! contracts.
     ///
     /// When running these you need to make sure that you:
     /// - Compile the tests with the `e2e-tests` feature flag enabled (`--features e2e-tests`)
     /// - Are running a Substrate node which contains `pallet-contracts` in the background
     #[cfg(all(test, feature = "e2e-tests"))]
+    #[allow(clippy::arithmetic_side_effects)]
     mod e2e_tests {
         /// Imports all the definitions from the outer scope so we can use them here.
         use super::*;
 
         /// A helper function used for calling contract messages.
-        use ink_e2e::ContractsBackend;
+        use ink_e2e::{AccountKeyring, ChainBackend, ContractsBackend};
 
         /// The End-to-End test `Result` type.
         type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-        /// We test that we can upload and instantiate the contract using its default constructor.
+        /// Block the match starts at, above the blocks of the setup.
+        const START: BlockNumber = 20;
+
+        /// We test that we can upload and instantiate the contract using its constructor.
         #[ink_e2e::test]
-        async fn default_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        async fn new_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
             // Given
-            let mut constructor = BettingRef::default();
+            let mut constructor = BettingRef::new();
 
             // When
             let contract = client
@@ -898,43 +19481,705 @@ mod betting {
             let call_builder = contract.call_builder::<Betting>();
 
             // Then
-            let get = call_builder.get();
-            let get_result = client.call(&ink_e2e::alice(), &get).dry_run().await?;
-            assert!(matches!(get_result.return_value(), false));
+            let exists_match = call_builder.exists_match(ink_e2e::account_id(AccountKeyring::Dave));
+            let exists_match_result = client
+                .call(&ink_e2e::alice(), &exists_match)
+                .dry_run()
+                .await?;
+            assert!(!exists_match_result.return_value());
 
             Ok(())
         }
 
-        /// We test that we can read and write a value from the on-chain contract.
+        /// We test a whole match: it is created, several accounts bet, the owner sets the
+        /// result and the creator distributes the winnings.
         #[ink_e2e::test]
-        async fn it_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        async fn match_lifecycle_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
             // Given
-            let mut constructor = BettingRef::new(false);
+            let mut constructor = BettingRef::new();
             let contract = client
-                .instantiate("betting", &ink_e2e::bob(), &mut constructor)
+                .instantiate("betting", &ink_e2e::alice(), &mut constructor)
                 .submit()
                 .await
                 .expect("instantiate failed");
             let mut call_builder = contract.call_builder::<Betting>();
+            let match_id = ink_e2e::account_id(AccountKeyring::Dave);
 
-            let get = call_builder.get();
-            let get_result = client.call(&ink_e2e::bob(), &get).dry_run().await?;
-            assert!(matches!(get_result.return_value(), false));
+            // Dave creates the match
+            let create_match = call_builder.create_match_to_bet(
+                "team1".as_bytes().to_vec(),
+                "team2".as_bytes().to_vec(),
+                START,
+                1,
+                true,
+                false,
+                None,
+            );
+            let create_match_result = client
+                .call(&ink_e2e::dave(), &create_match)
+                .value(1000000000000)
+                .submit()
+                .await
+                .expect("create_match_to_bet failed");
+            assert_eq!(create_match_result.return_value(), Ok(()));
 
-            // When
-            let flip = call_builder.flip();
-            let _flip_result = client
-                .call(&ink_e2e::bob(), &flip)
+            // Bob, Charlie and Eve bet
+            for (bettor, result, amount) in [
+                (ink_e2e::bob(), MatchResult::Team1Victory, 10000000000000),
+                (
+                    ink_e2e::charlie(),
+                    MatchResult::Team2Victory,
+                    10000000000000,
+                ),
+                (ink_e2e::eve(), MatchResult::Team1Victory, 30000000000000),
+            ] {
+                let bet = call_builder.bet(match_id, result);
+                let bet_result = client
+                    .call(&bettor, &bet)
+                    .value(amount)
+                    .submit()
+                    .await
+                    .expect("bet failed");
+                assert_eq!(bet_result.return_value(), Ok(()));
+            }
+            let get_escrow = call_builder.get_escrow(match_id);
+            let escrow = client
+                .call(&ink_e2e::alice(), &get_escrow)
+                .dry_run()
+                .await?;
+            assert_eq!(escrow.return_value(), 51000000000000);
+            let bob_balance = client
+                .free_balance(ink_e2e::account_id(AccountKeyring::Bob))
+                .await?;
+            let charlie_balance = client
+                .free_balance(ink_e2e::account_id(AccountKeyring::Charlie))
+                .await?;
+            let eve_balance = client
+                .free_balance(ink_e2e::account_id(AccountKeyring::Eve))
+                .await?;
+
+            // Each call makes a block: Alice makes calls until the match is over
+            let set_result = call_builder.set_result(match_id, MatchResult::Team1Victory, None);
+            while client
+                .call(&ink_e2e::alice(), &set_result)
+                .dry_run()
+                .await?
+                .return_value()
+                == Err(Error::TimeMatchNotOver)
+            {
+                let set_max_bets = call_builder.set_max_bets_per_match(DEFAULT_MAX_BETS_PER_MATCH);
+                client
+                    .call(&ink_e2e::alice(), &set_max_bets)
+                    .submit()
+                    .await
+                    .expect("set_max_bets_per_match failed");
+            }
+            // Alice sets the result
+            let set_result_result = client
+                .call(&ink_e2e::alice(), &set_result)
                 .submit()
                 .await
-                .expect("flip failed");
+                .expect("set_result failed");
+            assert_eq!(set_result_result.return_value(), Ok(()));
 
-            // Then
-            let get = call_builder.get();
-            let get_result = client.call(&ink_e2e::bob(), &get).dry_run().await?;
-            assert!(matches!(get_result.return_value(), true));
+            // Dave distributes the winnings
+            let distribute_winnings = call_builder.distribute_winnings();
+            let distribute_winnings_result = client
+                .call(&ink_e2e::dave(), &distribute_winnings)
+                .submit()
+                .await
+                .expect("distribute_winnings failed");
+            assert_eq!(distribute_winnings_result.return_value(), Ok(()));
+
+            // Then the winners share the pot, weighted by their stakes
+            assert_eq!(
+                client
+                    .free_balance(ink_e2e::account_id(AccountKeyring::Bob))
+                    .await?,
+                bob_balance + 12500000000000
+            );
+            assert_eq!(
+                client
+                    .free_balance(ink_e2e::account_id(AccountKeyring::Charlie))
+                    .await?,
+                charlie_balance
+            );
+            assert_eq!(
+                client
+                    .free_balance(ink_e2e::account_id(AccountKeyring::Eve))
+                    .await?,
+                eve_balance + 37500000000000
+            );
+            let exists_match = call_builder.exists_match(match_id);
+            let exists_match_result = client
+                .call(&ink_e2e::alice(), &exists_match)
+                .dry_run()
+                .await?;
+            assert!(!exists_match_result.return_value());
+            let escrow = client
+                .call(&ink_e2e::alice(), &get_escrow)
+                .dry_run()
+                .await?;
+            assert_eq!(escrow.return_value(), 0);
+
+            Ok(())
+        }
+
+        /// We test that a voided match refunds every bettor and returns the deposit of its
+        /// creator.
+        #[ink_e2e::test]
+        async fn voided_match_refunds_every_bettor(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            // Given
+            let mut constructor = BettingRef::new();
+            let contract = client
+                .instantiate("betting", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<Betting>();
+            let match_id = ink_e2e::account_id(AccountKeyring::Dave);
+            let create_match = call_builder.create_match_to_bet(
+                "team1".as_bytes().to_vec(),
+                "team2".as_bytes().to_vec(),
+                START,
+                1,
+                true,
+                false,
+                None,
+            );
+            client
+                .call(&ink_e2e::dave(), &create_match)
+                .value(1000000000000)
+                .submit()
+                .await
+                .expect("create_match_to_bet failed");
+            for (bettor, result) in [
+                (ink_e2e::bob(), MatchResult::Team1Victory),
+                (ink_e2e::charlie(), MatchResult::Team2Victory),
+            ] {
+                let bet = call_builder.bet(match_id, result);
+                client
+                    .call(&bettor, &bet)
+                    .value(10000000000000)
+                    .submit()
+                    .await
+                    .expect("bet failed");
+            }
+            let bob_balance = client
+                .free_balance(ink_e2e::account_id(AccountKeyring::Bob))
+                .await?;
+            let charlie_balance = client
+                .free_balance(ink_e2e::account_id(AccountKeyring::Charlie))
+                .await?;
+
+            // When Alice voids the match and Dave distributes it
+            let void_match = call_builder.void_match(match_id, 0);
+            let void_match_result = client
+                .call(&ink_e2e::alice(), &void_match)
+                .submit()
+                .await
+                .expect("void_match failed");
+            assert_eq!(void_match_result.return_value(), Ok(()));
+            let distribute_winnings = call_builder.distribute_winnings();
+            let distribute_winnings_result = client
+                .call(&ink_e2e::dave(), &distribute_winnings)
+                .submit()
+                .await
+                .expect("distribute_winnings failed");
+            assert_eq!(distribute_winnings_result.return_value(), Ok(()));
+
+            // Then each bettor gets the stake back and the escrow is empty
+            assert_eq!(
+                client
+                    .free_balance(ink_e2e::account_id(AccountKeyring::Bob))
+                    .await?,
+                bob_balance + 10000000000000
+            );
+            assert_eq!(
+                client
+                    .free_balance(ink_e2e::account_id(AccountKeyring::Charlie))
+                    .await?,
+                charlie_balance + 10000000000000
+            );
+            let get_escrow = call_builder.get_escrow(match_id);
+            let escrow = client
+                .call(&ink_e2e::alice(), &get_escrow)
+                .dry_run()
+                .await?;
+            assert_eq!(escrow.return_value(), 0);
+
+            Ok(())
+        }
+
+        /// We test that a fee router calling back into the contract when it receives the
+        /// protocol fees can't reenter it: the settlement goes through and accounts for
+        /// every payment once.
+        #[ink_e2e::test]
+        async fn fee_router_cannot_reenter(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            // Given
+            let mut constructor = BettingRef::new();
+            let contract = client
+                .instantiate("betting", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<Betting>();
+            let mut receiver_constructor =
+                malicious_receiver::MaliciousReceiverRef::new(contract.account_id);
+            let receiver = client
+                .instantiate(
+                    "malicious_receiver",
+                    &ink_e2e::alice(),
+                    &mut receiver_constructor,
+                )
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let receiver_call_builder =
+                receiver.call_builder::<malicious_receiver::MaliciousReceiver>();
+            let set_fee_router = call_builder.set_fee_router(Some(receiver.account_id));
+            let set_fee_router_result = client
+                .call(&ink_e2e::alice(), &set_fee_router)
+                .submit()
+                .await
+                .expect("set_fee_router failed");
+            assert_eq!(set_fee_router_result.return_value(), Ok(()));
+            let set_protocol_fee = call_builder.set_protocol_fee(500);
+            let set_protocol_fee_result = client
+                .call(&ink_e2e::alice(), &set_protocol_fee)
+                .submit()
+                .await
+                .expect("set_protocol_fee failed");
+            assert_eq!(set_protocol_fee_result.return_value(), Ok(()));
+            let match_id = ink_e2e::account_id(AccountKeyring::Dave);
+            let create_match = call_builder.create_match_to_bet(
+                "team1".as_bytes().to_vec(),
+                "team2".as_bytes().to_vec(),
+                START,
+                1,
+                true,
+                false,
+                None,
+            );
+            client
+                .call(&ink_e2e::dave(), &create_match)
+                .value(1000000000000)
+                .submit()
+                .await
+                .expect("create_match_to_bet failed");
+            for (bettor, result) in [
+                (ink_e2e::bob(), MatchResult::Team1Victory),
+                (ink_e2e::charlie(), MatchResult::Team2Victory),
+            ] {
+                let bet = call_builder.bet(match_id, result);
+                client
+                    .call(&bettor, &bet)
+                    .value(10000000000000)
+                    .submit()
+                    .await
+                    .expect("bet failed");
+            }
+            let set_result = call_builder.set_result(match_id, MatchResult::Team1Victory, None);
+            while client
+                .call(&ink_e2e::alice(), &set_result)
+                .dry_run()
+                .await?
+                .return_value()
+                == Err(Error::TimeMatchNotOver)
+            {
+                let set_max_bets = call_builder.set_max_bets_per_match(DEFAULT_MAX_BETS_PER_MATCH);
+                client
+                    .call(&ink_e2e::alice(), &set_max_bets)
+                    .submit()
+                    .await
+                    .expect("set_max_bets_per_match failed");
+            }
+            client
+                .call(&ink_e2e::alice(), &set_result)
+                .submit()
+                .await
+                .expect("set_result failed");
+
+            // When the fee accrued at settlement is forwarded to the router
+            let distribute_winnings = call_builder.distribute_winnings();
+            let distribute_winnings_result = client
+                .call(&ink_e2e::dave(), &distribute_winnings)
+                .submit()
+                .await
+                .expect("distribute_winnings failed");
+            assert_eq!(distribute_winnings_result.return_value(), Ok(()));
+            let forward_fees = call_builder.forward_fees();
+            let forward_fees_result = client
+                .call(&ink_e2e::dave(), &forward_fees)
+                .submit()
+                .await
+                .expect("forward_fees failed");
+
+            // Then the router got the fee but its call back was denied
+            assert_eq!(forward_fees_result.return_value(), Ok(()));
+            let attempts = client
+                .call(&ink_e2e::alice(), &receiver_call_builder.attempts())
+                .dry_run()
+                .await?;
+            assert_eq!(attempts.return_value(), 1);
+            let reentered = client
+                .call(&ink_e2e::alice(), &receiver_call_builder.reentered())
+                .dry_run()
+                .await?;
+            assert!(!reentered.return_value());
+            assert!(client.free_balance(receiver.account_id).await? > 0);
+            let solvency_check = call_builder.solvency_check();
+            let solvent = client
+                .call(&ink_e2e::alice(), &solvency_check)
+                .dry_run()
+                .await?;
+            assert!(solvent.return_value());
+            let treasury = client
+                .call(&ink_e2e::alice(), &call_builder.get_treasury())
+                .dry_run()
+                .await?;
+            assert_eq!(treasury.return_value(), 0);
+
+            Ok(())
+        }
+
+        /// We test that the result of a match linked to a fixture is pulled from the
+        /// result oracle once the oracle knows it.
+        #[ink_e2e::test]
+        async fn pull_result_sets_the_oracle_result(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            // Given
+            let mut constructor = BettingRef::new();
+            let contract = client
+                .instantiate("betting", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<Betting>();
+            let mut oracle_constructor = mock_oracle::MockOracleRef::new();
+            let oracle = client
+                .instantiate("mock_oracle", &ink_e2e::alice(), &mut oracle_constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut oracle_call_builder = oracle.call_builder::<mock_oracle::MockOracle>();
+            let set_result_oracle = call_builder.set_result_oracle(Some(oracle.account_id));
+            let set_result_oracle_result = client
+                .call(&ink_e2e::alice(), &set_result_oracle)
+                .submit()
+                .await
+                .expect("set_result_oracle failed");
+            assert_eq!(set_result_oracle_result.return_value(), Ok(()));
+            let match_id = ink_e2e::account_id(AccountKeyring::Dave);
+            let create_match = call_builder.create_match_to_bet(
+                "team1".as_bytes().to_vec(),
+                "team2".as_bytes().to_vec(),
+                START,
+                1,
+                true,
+                false,
+                None,
+            );
+            client
+                .call(&ink_e2e::dave(), &create_match)
+                .value(1000000000000)
+                .submit()
+                .await
+                .expect("create_match_to_bet failed");
+            let set_fixture_id = call_builder.set_fixture_id(match_id, 7);
+            let set_fixture_id_result = client
+                .call(&ink_e2e::alice(), &set_fixture_id)
+                .submit()
+                .await
+                .expect("set_fixture_id failed");
+            assert_eq!(set_fixture_id_result.return_value(), Ok(()));
+            let pull_result = call_builder.pull_result(match_id);
+            while client
+                .call(&ink_e2e::bob(), &pull_result)
+                .dry_run()
+                .await?
+                .return_value()
+                == Err(Error::TimeMatchNotOver)
+            {
+                let set_max_bets = call_builder.set_max_bets_per_match(DEFAULT_MAX_BETS_PER_MATCH);
+                client
+                    .call(&ink_e2e::alice(), &set_max_bets)
+                    .submit()
+                    .await
+                    .expect("set_max_bets_per_match failed");
+            }
+            // The oracle doesn't know the result yet
+            let pull_result_result = client.call(&ink_e2e::bob(), &pull_result).dry_run().await?;
+            assert_eq!(
+                pull_result_result.return_value(),
+                Err(Error::MatchNotResult)
+            );
+
+            // When the oracle publishes the result
+            let oracle_set_result = oracle_call_builder.set_result(7, MatchResult::Team2Victory.0);
+            client
+                .call(&ink_e2e::alice(), &oracle_set_result)
+                .submit()
+                .await
+                .expect("set_result failed");
+            let pull_result_result = client
+                .call(&ink_e2e::bob(), &pull_result)
+                .submit()
+                .await
+                .expect("pull_result failed");
+
+            // Then anyone pulling it sets it on the match
+            assert_eq!(pull_result_result.return_value(), Ok(()));
+            let get_match = call_builder.get_match(match_id);
+            let betting_match = client
+                .call(&ink_e2e::alice(), &get_match)
+                .dry_run()
+                .await?
+                .return_value()
+                .expect("match not found");
+            assert_eq!(betting_match.result, Some(MatchResult::Team2Victory));
 
             Ok(())
         }
     }
+
+    /// Quasi end-to-end tests running the contract in an in-memory runtime, with blocks
+    /// and value transfers, much faster than against a node.
+    ///
+    /// When running these you need to make sure that you:
+    /// - Compile the tests with the `sandbox-tests` feature flag enabled (`--features sandbox-tests`)
+    /// - Have `cargo-contract` installed to build the contract
+    #[cfg(all(test, feature = "sandbox-tests"))]
+    #[allow(clippy::arithmetic_side_effects)]
+    mod sandbox_tests {
+        use super::*;
+        use ink_sandbox::api::prelude::*;
+        use ink_sandbox::{AccountId32, DefaultSandbox, Sandbox};
+
+        const ALICE: [u8; 32] = [1; 32];
+        const BOB: [u8; 32] = [2; 32];
+        const CHARLIE: [u8; 32] = [3; 32];
+        const DJANGO: [u8; 32] = [4; 32];
+        const EVE: [u8; 32] = [5; 32];
+
+        /// Funds of each account.
+        const INITIAL_BALANCE: u128 = 1_000_000_000_000_000_000_000_000_000_000;
+
+        /// The contract deployed in a sandbox by Alice, with funded accounts.
+        struct Harness {
+            sandbox: DefaultSandbox,
+            contract: AccountId32,
+        }
+
+        impl Harness {
+            fn new() -> Self {
+                let mut sandbox = DefaultSandbox::default();
+                for account in [ALICE, BOB, CHARLIE, DJANGO, EVE] {
+                    sandbox
+                        .mint_into(&AccountId32::new(account), INITIAL_BALANCE)
+                        .expect("mint failed");
+                }
+                // The contract comes before its contract dependencies
+                let wasm_path = ink_e2e::build_root_and_contract_dependencies()
+                    .into_iter()
+                    .next()
+                    .expect("contract not built");
+                let wasm = std::fs::read(wasm_path).expect("wasm not found");
+                let result = sandbox.deploy_contract(
+                    wasm,
+                    0,
+                    ink::selector_bytes!("new").to_vec(),
+                    Vec::new(),
+                    AccountId32::new(ALICE),
+                    DefaultSandbox::default_gas_limit(),
+                    None,
+                );
+                let contract = result.result.expect("instantiate failed").account_id;
+                Self { sandbox, contract }
+            }
+
+            /// Calls a message of the contract, decoding its return value.
+            fn call<Args: scale::Encode, R: scale::Decode>(
+                &mut self,
+                caller: [u8; 32],
+                selector: [u8; 4],
+                args: Args,
+                value: Balance,
+            ) -> R {
+                let mut data = selector.to_vec();
+                args.encode_to(&mut data);
+                let result = self.sandbox.call_contract(
+                    self.contract.clone(),
+                    value,
+                    data,
+                    AccountId32::new(caller),
+                    DefaultSandbox::default_gas_limit(),
+                    None,
+                    ink_sandbox::pallet_contracts::Determinism::Enforced,
+                );
+                let output = result.result.expect("call failed");
+                <Result<R, ink::LangError> as scale::Decode>::decode(&mut &output.data[..])
+                    .expect("undecodable return value")
+                    .expect("dispatch failed")
+            }
+
+            fn block_number(&mut self) -> BlockNumber {
+                self.sandbox.block_number()
+            }
+
+            fn advance_blocks(&mut self, blocks: u32) {
+                self.sandbox.build_blocks(blocks);
+            }
+
+            fn balance(&mut self, account: [u8; 32]) -> Balance {
+                self.sandbox.free_balance(&AccountId32::new(account))
+            }
+
+            /// Django creates a match starting in 10 blocks and lasting 10 blocks.
+            fn create_match(&mut self) -> AccountId {
+                let start = self.block_number() + 10;
+                let result: Result<(), Error> = self.call(
+                    DJANGO,
+                    ink::selector_bytes!("create_match_to_bet"),
+                    (
+                        "team1".as_bytes().to_vec(),
+                        "team2".as_bytes().to_vec(),
+                        start,
+                        10u32,
+                        true,
+                        false,
+                        None::<Vec<AccountId>>,
+                    ),
+                    DEFAULT_MIN_DEPOSIT,
+                );
+                assert_eq!(result, Ok(()));
+                AccountId::from(DJANGO)
+            }
+
+            fn bet(
+                &mut self,
+                bettor: [u8; 32],
+                match_id: AccountId,
+                result: MatchResult,
+                amount: Balance,
+            ) -> Result<(), Error> {
+                self.call(
+                    bettor,
+                    ink::selector_bytes!("bet"),
+                    (match_id, result),
+                    amount,
+                )
+            }
+
+            /// Alice sets the result once the match is over, then Django distributes
+            /// the winnings.
+            fn settle(&mut self, match_id: AccountId, result: MatchResult) {
+                self.advance_blocks(21);
+                let set_result: Result<(), Error> = self.call(
+                    ALICE,
+                    ink::selector_bytes!("set_result"),
+                    (match_id, result, None::<Vec<u8>>),
+                    0,
+                );
+                assert_eq!(set_result, Ok(()));
+                let distributed: Result<(), Error> =
+                    self.call(DJANGO, ink::selector_bytes!("distribute_winnings"), (), 0);
+                assert_eq!(distributed, Ok(()));
+                let escrow: Balance =
+                    self.call(ALICE, ink::selector_bytes!("get_escrow"), match_id, 0);
+                assert_eq!(escrow, 0);
+                let solvent: bool = self.call(ALICE, ink::selector_bytes!("solvency_check"), (), 0);
+                assert!(solvent);
+            }
+        }
+
+        #[test]
+        fn bets_close_when_the_match_starts() {
+            let mut harness = Harness::new();
+            let match_id = harness.create_match();
+
+            assert_eq!(
+                harness.bet(BOB, match_id, MatchResult::Team1Victory, 10000000000000),
+                Ok(())
+            );
+            harness.advance_blocks(11);
+            assert_eq!(
+                harness.bet(CHARLIE, match_id, MatchResult::Team2Victory, 10000000000000),
+                Err(Error::MatchHasStarted)
+            );
+            let status: Option<MatchStatus> =
+                harness.call(ALICE, ink::selector_bytes!("get_status"), match_id, 0);
+            assert_eq!(status, Some(MatchStatus::BettingClosed));
+        }
+
+        #[test]
+        fn no_winners_rolls_the_pot_over_to_the_jackpot() {
+            let mut harness = Harness::new();
+            let match_id = harness.create_match();
+            for bettor in [BOB, CHARLIE] {
+                assert_eq!(
+                    harness.bet(bettor, match_id, MatchResult::Team1Victory, 10000000000000),
+                    Ok(())
+                );
+            }
+            let django_balance = harness.balance(DJANGO);
+
+            harness.settle(match_id, MatchResult::Draw);
+
+            let jackpot: Balance = harness.call(ALICE, ink::selector_bytes!("get_jackpot"), (), 0);
+            assert_eq!(jackpot, 20000000000000);
+            let treasury: Balance =
+                harness.call(ALICE, ink::selector_bytes!("get_treasury"), (), 0);
+            assert_eq!(treasury, 0);
+            assert_eq!(
+                harness.balance(DJANGO),
+                django_balance + DEFAULT_MIN_DEPOSIT
+            );
+        }
+
+        #[test]
+        fn single_bettor_gets_the_stake_back() {
+            let mut harness = Harness::new();
+            let match_id = harness.create_match();
+            assert_eq!(
+                harness.bet(BOB, match_id, MatchResult::Team2Victory, 10000000000000),
+                Ok(())
+            );
+            let bob_balance = harness.balance(BOB);
+
+            harness.settle(match_id, MatchResult::Team2Victory);
+
+            assert_eq!(harness.balance(BOB), bob_balance + 10000000000000);
+        }
+
+        #[test]
+        fn huge_pools_are_paid_out() {
+            let mut harness = Harness::new();
+            let match_id = harness.create_match();
+            let stake = INITIAL_BALANCE / 10;
+            assert_eq!(
+                harness.bet(BOB, match_id, MatchResult::Team1Victory, stake),
+                Ok(())
+            );
+            assert_eq!(
+                harness.bet(CHARLIE, match_id, MatchResult::Team1Victory, 3 * stake),
+                Ok(())
+            );
+            assert_eq!(
+                harness.bet(EVE, match_id, MatchResult::Team2Victory, 4 * stake),
+                Ok(())
+            );
+            let bob_balance = harness.balance(BOB);
+            let charlie_balance = harness.balance(CHARLIE);
+
+            harness.settle(match_id, MatchResult::Team1Victory);
+
+            // The winners share the pot, weighted by their stakes
+            assert_eq!(harness.balance(BOB), bob_balance + 2 * stake);
+            assert_eq!(harness.balance(CHARLIE), charlie_balance + 6 * stake);
+        }
+    }
 }
@@ -0,0 +1,59 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::malicious_receiver::{MaliciousReceiver, MaliciousReceiverRef};
+
+/// Fee router of the end-to-end tests of the betting contract which calls back into it
+/// when it receives the fees, to check the contract doesn't allow reentry.
+#[ink::contract]
+mod malicious_receiver {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::DefaultEnvironment;
+
+    #[ink(storage)]
+    pub struct MaliciousReceiver {
+        /// The betting contract called back.
+        target: AccountId,
+        /// Calls back made.
+        attempts: u32,
+        /// Whether a call back reached the betting contract.
+        reentered: bool,
+    }
+
+    impl MaliciousReceiver {
+        #[ink(constructor, selector = 0x9BAE9D5E)]
+        pub fn new(target: AccountId) -> Self {
+            Self {
+                target,
+                attempts: 0,
+                reentered: false,
+            }
+        }
+
+        /// Receives the fees as `FeeRouter::receive_fees` of the betting contract and
+        /// tries to distribute the winnings again in the middle of the settlement.
+        #[ink(message, payable, selector = 0xC2D332DA)]
+        pub fn receive_fees(&mut self) {
+            self.attempts = self.attempts.saturating_add(1);
+            let result = build_call::<DefaultEnvironment>()
+                .call(self.target)
+                .exec_input(ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                    "distribute_winnings"
+                ))))
+                .returns::<Result<(), u8>>()
+                .try_invoke();
+            if result.is_ok() {
+                self.reentered = true;
+            }
+        }
+
+        #[ink(message, selector = 0xD20A659E)]
+        pub fn attempts(&self) -> u32 {
+            self.attempts
+        }
+
+        #[ink(message, selector = 0xD01D891D)]
+        pub fn reentered(&self) -> bool {
+            self.reentered
+        }
+    }
+}
@@ -0,0 +1,36 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_oracle::{MockOracle, MockOracleRef};
+
+/// Result oracle of the end-to-end tests of the betting contract, returning the results
+/// its deployer sets for each fixture.
+#[ink::contract]
+mod mock_oracle {
+    use ink::storage::Mapping;
+
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct MockOracle {
+        /// Encoded `MatchResult` of each fixture.
+        results: Mapping<u64, u8>,
+    }
+
+    impl MockOracle {
+        #[ink(constructor, selector = 0x9BAE9D5E)]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns the result of a fixture as `ResultOracle::get_result` of the betting
+        /// contract, the `MatchResult` being encoded as its id.
+        #[ink(message, selector = 0xE6CE0B50)]
+        pub fn get_result(&self, fixture_id: u64) -> Option<u8> {
+            self.results.get(fixture_id)
+        }
+
+        #[ink(message, selector = 0xFCB1EF09)]
+        pub fn set_result(&mut self, fixture_id: u64, result: u8) {
+            self.results.insert(fixture_id, &result);
+        }
+    }
+}